@@ -0,0 +1,116 @@
+//! Latency percentiles of single cache operations.
+//!
+//! Run with `cargo bench --bench latency`. For each capacity and value size a full `LRUCache` is
+//! timed one operation at a time: inserts of new values (each evicting the least recently used
+//! one), lookups that hit a uniformly random entry, and lookups that miss. Lookups scan the
+//! entries, so a miss is always the slowest case and its latency grows with `N` and with the
+//! stride of the scan, the size of a value. The percentiles show where that crosses a latency
+//! budget.
+//!
+//! Each operation is timed with its own pair of `Instant::now` calls. Their cost, measured up
+//! front, is taken off every sample, so results under a few tens of nanoseconds are noise.
+
+use std::{hint::black_box, time::Instant};
+
+use lru::{workload::Uniform, LRUCache};
+
+const SAMPLES: usize = 20_000;
+
+// A value of `W` words, the first one its key
+#[derive(Clone)]
+struct Value<const W: usize>([u64; W]);
+
+impl<const W: usize> Value<W> {
+    fn new(key: u64) -> Self {
+        let mut words = [0; W];
+        words[0] = key;
+        Value(words)
+    }
+}
+
+// Median cost of timing nothing, in nanoseconds
+fn overhead() -> u64 {
+    let mut samples: Vec<u64> = (0..SAMPLES)
+        .map(|_| {
+            let start = Instant::now();
+            black_box(());
+            start.elapsed().as_nanos() as u64
+        })
+        .collect();
+    samples.sort_unstable();
+    samples[SAMPLES / 2]
+}
+
+// Print the percentiles of `samples`, in nanoseconds
+fn report(op: &str, samples: &mut [u64]) {
+    samples.sort_unstable();
+    let at = |p: f64| samples[((samples.len() - 1) as f64 * p) as usize];
+    println!(
+        "    {op:<7} p50 {:>7}  p90 {:>7}  p99 {:>7}  p99.9 {:>7}  max {:>8}",
+        at(0.5),
+        at(0.9),
+        at(0.99),
+        at(0.999),
+        at(1.0)
+    );
+}
+
+fn bench<const N: usize, const W: usize>(overhead: u64) {
+    println!("  N = {N}, {} byte values:", W * 8);
+    let mut cache = Box::new(LRUCache::<Value<W>, N>::new());
+    for key in 0..N as u64 {
+        cache.insert(Value::new(key));
+    }
+    let time = |op: &mut dyn FnMut()| {
+        let start = Instant::now();
+        op();
+        (start.elapsed().as_nanos() as u64).saturating_sub(overhead)
+    };
+
+    // Keys `0..N` are in the cache, keys from `N` on never are
+    let mut keys = Uniform::new(N as u64, N as u64);
+    let mut hits: Vec<u64> = (0..SAMPLES)
+        .map(|_| {
+            let key = keys.next().expect("workloads never end");
+            time(&mut || {
+                black_box(cache.find(|v| v.0[0] == black_box(key)));
+            })
+        })
+        .collect();
+    let mut misses: Vec<u64> = (0..SAMPLES)
+        .map(|_| {
+            time(&mut || {
+                black_box(cache.find(|v| v.0[0] == black_box(u64::MAX)));
+            })
+        })
+        .collect();
+    // Values inserted here take the place of the ones evicted, so the cache stays full
+    let mut inserts: Vec<u64> = (0..SAMPLES as u64)
+        .map(|i| {
+            let val = Value::new(N as u64 + i);
+            time(&mut || {
+                black_box(cache.insert(val.clone()));
+            })
+        })
+        .collect();
+
+    report("hit", &mut hits);
+    report("miss", &mut misses);
+    report("insert", &mut inserts);
+}
+
+fn sizes<const N: usize>(overhead: u64) {
+    bench::<N, 1>(overhead);
+    bench::<N, 8>(overhead);
+    bench::<N, 32>(overhead);
+}
+
+fn main() {
+    let overhead = overhead();
+    println!("latency in ns per operation, less {overhead} ns of timing overhead:");
+    sizes::<16>(overhead);
+    sizes::<64>(overhead);
+    sizes::<256>(overhead);
+    sizes::<1024>(overhead);
+    sizes::<4096>(overhead);
+}
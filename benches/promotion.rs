@@ -0,0 +1,98 @@
+//! Promotion on every hit against batched promotion.
+//!
+//! Run with `cargo bench --bench promotion`. Each workload runs the same deterministic stream of
+//! lookups against an `LRUCache` and against `BatchedLRUCache`s with a few batch sizes, and prints
+//! the time per lookup and the hit ratio. Batching saves link writes, most of all when a few hot
+//! entries take most of the hits, but between batches the list is stale, so recently hit entries
+//! can sit further from the front and take longer to find. Which effect wins depends on the
+//! workload and on how cheap a comparison is, which is what this measures.
+
+use std::{hint::black_box, time::Instant};
+
+use lru::{batch::BatchedLRUCache, LRUCache};
+
+const N: usize = 256;
+const OPS: u32 = 2_000_000;
+
+// Keys of `OPS` lookups over `0..span`, most of them in the first `hot` keys
+fn stream(span: u32, hot: u32) -> Vec<u32> {
+    let mut x = 0x9E37_79B9u32;
+    (0..OPS)
+        .map(|_| {
+            x ^= x << 13;
+            x ^= x >> 17;
+            x ^= x << 5;
+            if x % 10 < 9 {
+                x % hot
+            } else {
+                x % span
+            }
+        })
+        .collect()
+}
+
+// Time `lookup` over `keys`, inserting missed keys with `insert`
+fn run<C>(
+    name: &str,
+    keys: &[u32],
+    cache: &mut C,
+    mut lookup: impl FnMut(&mut C, u32) -> bool,
+    mut insert: impl FnMut(&mut C, u32),
+) {
+    let start = Instant::now();
+    let mut hits = 0u32;
+    for &key in keys {
+        if lookup(cache, key) {
+            hits += 1;
+        } else {
+            insert(cache, key);
+        }
+    }
+    let elapsed = start.elapsed();
+    println!(
+        "  {name:<12} {:>7.1} ns/op  hit ratio {:.3}",
+        elapsed.as_nanos() as f64 / keys.len() as f64,
+        hits as f64 / keys.len() as f64
+    );
+}
+
+fn batched<const K: usize>(name: &str, keys: &[u32]) {
+    let mut cache = BatchedLRUCache::<u32, N, K>::new();
+    run(
+        name,
+        keys,
+        &mut cache,
+        |c, key| c.touch(|&v| v == black_box(key)),
+        |c, key| {
+            c.insert(key);
+        },
+    );
+}
+
+fn main() {
+    for (label, span, hot) in [
+        ("skewed (90% of lookups on 8 keys)", 4 * N as u32, 8),
+        (
+            "uniform over twice the capacity",
+            2 * N as u32,
+            2 * N as u32,
+        ),
+    ] {
+        println!("{label}:");
+        let keys = stream(span, hot);
+
+        let mut cache = LRUCache::<u32, N>::new();
+        run(
+            "every hit",
+            &keys,
+            &mut cache,
+            |c, key| c.touch(|&v| v == black_box(key)),
+            |c, key| {
+                c.insert(key);
+            },
+        );
+        batched::<4>("batch of 4", &keys);
+        batched::<16>("batch of 16", &keys);
+        batched::<64>("batch of 64", &keys);
+    }
+}
@@ -0,0 +1,13 @@
+use std::{env, fs, path::PathBuf};
+
+// Hand the linker script to the linker when building for a target without an OS
+fn main() {
+    println!("cargo:rerun-if-changed=link.x");
+    if env::var("CARGO_CFG_TARGET_OS").as_deref() != Ok("none") {
+        return;
+    }
+    let out = PathBuf::from(env::var_os("OUT_DIR").expect("cargo sets OUT_DIR"));
+    fs::copy("link.x", out.join("link.x")).expect("link.x can be copied");
+    println!("cargo:rustc-link-search={}", out.display());
+    println!("cargo:rustc-link-arg-bins=-Tlink.x");
+}
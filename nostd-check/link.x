@@ -0,0 +1,54 @@
+/* Memory of the MPS2-AN386 board QEMU emulates: code in SSRAM1, data in SSRAM2 and 3 */
+MEMORY
+{
+  FLASH : ORIGIN = 0x00000000, LENGTH = 4M
+  RAM : ORIGIN = 0x20000000, LENGTH = 4M
+}
+
+ENTRY(reset);
+
+_stack_top = ORIGIN(RAM) + LENGTH(RAM);
+
+SECTIONS
+{
+  .vector_table ORIGIN(FLASH) :
+  {
+    LONG(_stack_top);
+    KEEP(*(.vector_table.reset_vector));
+    KEEP(*(.vector_table.exceptions));
+  } > FLASH
+
+  .text :
+  {
+    *(.text .text.*);
+  } > FLASH
+
+  .rodata : ALIGN(4)
+  {
+    *(.rodata .rodata.*);
+    . = ALIGN(4);
+  } > FLASH
+
+  .data : ALIGN(4)
+  {
+    __sdata = .;
+    *(.data .data.*);
+    . = ALIGN(4);
+    __edata = .;
+  } > RAM AT > FLASH
+
+  __sidata = LOADADDR(.data);
+
+  .bss (NOLOAD) : ALIGN(4)
+  {
+    __sbss = .;
+    *(.bss .bss.*);
+    . = ALIGN(4);
+    __ebss = .;
+  } > RAM
+
+  /DISCARD/ :
+  {
+    *(.ARM.exidx .ARM.exidx.*);
+  }
+}
@@ -0,0 +1,82 @@
+//! The checks run by `lru-nostd-check`, on a Cortex-M target or on the host.
+//!
+//! This crate is `no_std` and only uses `core`, so building it for `thumbv7em-none-eabihf` shows
+//! the cache builds there, and running it shows the insert, lookup and eviction paths work
+//! there. Progress goes to `out`, which is semihosting on the target and a `String` on the host.
+
+#![no_std]
+
+use core::fmt::Write;
+
+use lru::{map::LRUMap, LRUCache};
+
+// Fail with `what` unless `ok`
+fn check(ok: bool, what: &'static str) -> Result<(), &'static str> {
+    if ok {
+        Ok(())
+    } else {
+        Err(what)
+    }
+}
+
+fn cache(out: &mut impl Write) -> Result<(), &'static str> {
+    let mut cache: LRUCache<u32, 4> = LRUCache::new();
+    for val in 0..4 {
+        check(
+            cache.insert(val).is_none(),
+            "insert into a cache with room evicted",
+        )?;
+    }
+    check(cache.len() == 4, "cache does not hold 4 values")?;
+    check(cache.find(|v| *v == 0).is_some(), "find missed a value")?;
+    check(
+        cache.find(|v| *v == 9).is_none(),
+        "find hit a value never inserted",
+    )?;
+    // 0 was just used, so 1 is the least recently used
+    check(
+        cache.insert(4) == Some(1),
+        "insert did not evict the LRU value",
+    )?;
+    check(
+        cache.peek_lru() == Some(&2),
+        "wrong LRU value after an eviction",
+    )?;
+    check(
+        cache.iter().copied().eq([4, 0, 3, 2]),
+        "wrong recency order",
+    )?;
+    check(
+        cache.pop_lru() == Some(2),
+        "pop_lru did not take the LRU value",
+    )?;
+    let stats = cache.stats();
+    check(
+        (stats.hits, stats.misses, stats.inserts, stats.evictions) == (1, 1, 5, 1),
+        "wrong stats",
+    )?;
+    let _ = writeln!(out, "cache: ok");
+    Ok(())
+}
+
+fn map(out: &mut impl Write) -> Result<(), &'static str> {
+    let mut map: LRUMap<&str, u32, 2> = LRUMap::new();
+    map.insert("a", 1);
+    map.insert("b", 2);
+    check(map.get(&"a") == Some(&mut 1), "get missed a key")?;
+    check(
+        map.insert("c", 3) == Some(("b", 2)),
+        "insert did not evict the LRU key",
+    )?;
+    check(map.peek(&"b").is_none(), "evicted key still found")?;
+    check(map.remove(&"a") == Some(1), "remove missed a key")?;
+    check(map.len() == 1, "map does not hold 1 entry")?;
+    let _ = writeln!(out, "map: ok");
+    Ok(())
+}
+
+// Run every check, stopping at the first failure
+pub fn run(out: &mut impl Write) -> Result<(), &'static str> {
+    cache(out)?;
+    map(out)
+}
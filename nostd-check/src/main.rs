@@ -0,0 +1,27 @@
+//! Runs the checks of `lru_nostd_check`.
+//!
+//! On a target without an OS this is a bare-metal program for QEMU: `rt` holds the vector table
+//! and startup code, and progress and the exit status go through semihosting. Run it with
+//!
+//! ```text
+//! cargo run -p lru-nostd-check --target thumbv7em-none-eabihf
+//! ```
+//!
+//! which uses the QEMU runner set up in `.cargo/config.toml`. On the host it is a plain program,
+//! so `cargo test --workspace` and `cargo run -p lru-nostd-check` work without the target.
+
+#![cfg_attr(target_os = "none", no_std, no_main)]
+
+#[cfg(target_os = "none")]
+mod rt;
+
+#[cfg(not(target_os = "none"))]
+fn main() {
+    let mut out = String::new();
+    let result = lru_nostd_check::run(&mut out);
+    print!("{out}");
+    if let Err(what) = result {
+        eprintln!("failed: {what}");
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,111 @@
+//! Startup code and semihosting for a Cortex-M4F, standing in for `cortex-m-rt` and
+//! `cortex-m-semihosting`.
+
+use core::{
+    arch::asm,
+    fmt::{self, Write},
+    panic::PanicInfo,
+    ptr::{addr_of, addr_of_mut},
+};
+
+// Bounds of the sections the startup code initializes, from `link.x`
+extern "C" {
+    static mut __sbss: u32;
+    static mut __ebss: u32;
+    static mut __sdata: u32;
+    static mut __edata: u32;
+    static __sidata: u32;
+}
+
+// The reset vector, placed by `link.x` right after the initial stack pointer
+#[link_section = ".vector_table.reset_vector"]
+#[used]
+static RESET_VECTOR: unsafe extern "C" fn() -> ! = reset;
+
+// Every other exception means something went wrong, from NMI to SysTick
+#[link_section = ".vector_table.exceptions"]
+#[used]
+static EXCEPTIONS: [unsafe extern "C" fn() -> !; 14] = [fault; 14];
+
+// Coprocessor access control register, enabling the FPU the `eabihf` target compiles for
+const CPACR: *mut u32 = 0xE000_ED88 as *mut u32;
+
+// Entry point out of reset: initializes memory and the FPU, then runs the checks
+#[no_mangle]
+unsafe extern "C" fn reset() -> ! {
+    let mut dst = addr_of_mut!(__sbss);
+    while dst < addr_of_mut!(__ebss) {
+        dst.write_volatile(0);
+        dst = dst.add(1);
+    }
+    let (mut dst, mut src) = (addr_of_mut!(__sdata), addr_of!(__sidata));
+    while dst < addr_of_mut!(__edata) {
+        dst.write_volatile(src.read());
+        dst = dst.add(1);
+        src = src.add(1);
+    }
+    CPACR.write_volatile(CPACR.read_volatile() | (0xf << 20));
+    asm!("dsb", "isb", options(nostack));
+
+    match lru_nostd_check::run(&mut Semihosting) {
+        Ok(()) => exit(true),
+        Err(what) => {
+            let _ = writeln!(Semihosting, "failed: {what}");
+            exit(false)
+        }
+    }
+}
+
+#[no_mangle]
+unsafe extern "C" fn fault() -> ! {
+    let _ = writeln!(Semihosting, "failed: unexpected exception");
+    exit(false)
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo<'_>) -> ! {
+    let _ = writeln!(Semihosting, "failed: {info}");
+    exit(false)
+}
+
+// Semihosting operations, see the Arm semihosting specification
+const SYS_WRITEC: usize = 0x03;
+const SYS_EXIT: usize = 0x18;
+// Exit reasons, passed as is by 32-bit targets
+const ADP_STOPPED_APPLICATION_EXIT: usize = 0x20026;
+const ADP_STOPPED_RUN_TIME_ERROR: usize = 0x20023;
+
+fn semihost(op: usize, arg: usize) -> usize {
+    let ret;
+    // SAFETY: with a debugger or QEMU attached the breakpoint is a call into the host, which
+    // only reads `arg` as the operation specifies
+    unsafe {
+        asm!("bkpt #0xab", inout("r0") op => ret, in("r1") arg, options(nostack));
+    }
+    ret
+}
+
+// Console of the host
+struct Semihosting;
+
+impl Write for Semihosting {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for byte in s.bytes() {
+            semihost(SYS_WRITEC, addr_of!(byte) as usize);
+        }
+        Ok(())
+    }
+}
+
+// Stop QEMU with a status of 0 if `ok`, of 1 otherwise
+fn exit(ok: bool) -> ! {
+    let reason = if ok {
+        ADP_STOPPED_APPLICATION_EXIT
+    } else {
+        ADP_STOPPED_RUN_TIME_ERROR
+    };
+    semihost(SYS_EXIT, reason);
+    loop {
+        core::hint::spin_loop();
+    }
+}
@@ -0,0 +1,8 @@
+//! The checks the target runs, run on the host.
+
+#[test]
+fn checks_pass_on_the_host() {
+    let mut out = String::new();
+    assert_eq!(lru_nostd_check::run(&mut out), Ok(()), "{out}");
+    assert_eq!(out, "cache: ok\nmap: ok\n");
+}
@@ -0,0 +1,364 @@
+//! A keyed LRU map for async code.
+//!
+//! `AsyncLRUCache` wraps an `LRUMap` in an async lock: a task that finds the map busy is parked
+//! and woken when it is its turn, instead of blocking its executor thread the way a contended std
+//! `Mutex` would. The lock only relies on `Waker`, so it works with any executor.
+//!
+//! `get_or_load` also makes sure that a missing value is loaded by one task at a time. Tasks
+//! asking for a key that is being loaded wait for that load instead of starting their own. If
+//! the task driving the load is cancelled (its future dropped) or panics, the load is simply
+//! abandoned: the waiters wake up, find the key still missing, and the first of them runs its own
+//! loader. No waiter ever sees an error because another task went away.
+
+use core::{
+    borrow::Borrow,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll, Waker},
+};
+use std::{
+    collections::VecDeque,
+    sync::{Mutex, MutexGuard, PoisonError},
+    vec::Vec,
+};
+
+use crate::{map::LRUMap, CacheStats};
+
+// A keyed LRU map of capacity `N` shared between async tasks
+#[derive(Debug)]
+pub struct AsyncLRUCache<K, V, const N: usize> {
+    gate: Gate,
+    // Only locked by the holder of `gate`, so it is never contended
+    map: Mutex<LRUMap<K, V, N>>,
+    // Keys being loaded by `get_or_load`
+    loads: Mutex<Loads<K>>,
+}
+
+impl<K, V, const N: usize> Default for AsyncLRUCache<K, V, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V, const N: usize> AsyncLRUCache<K, V, N> {
+    // create a empty cache
+    pub const fn new() -> Self {
+        AsyncLRUCache {
+            gate: Gate::new(),
+            map: Mutex::new(LRUMap::new()),
+            loads: Mutex::new(Loads {
+                pending: Vec::new(),
+                next_id: 0,
+            }),
+        }
+    }
+
+    // Wait for the map, then run `f` on it. The map is released when `f` returns, and since `f`
+    // is not async it cannot be held across an await.
+    async fn locked<R>(&self, f: impl FnOnce(&mut LRUMap<K, V, N>) -> R) -> R {
+        let _permit = self.gate.acquire().await;
+        let mut map = self.map.lock().unwrap_or_else(PoisonError::into_inner);
+        f(&mut map)
+    }
+
+    // Returns the number of entries in the cache
+    pub async fn len(&self) -> usize {
+        self.locked(|map| map.len()).await
+    }
+
+    // Returns if cache is empty or not
+    pub async fn is_empty(&self) -> bool {
+        self.locked(|map| map.is_empty()).await
+    }
+
+    // Clears all the entries in cache
+    pub async fn clear(&self) {
+        self.locked(|map| map.clear()).await
+    }
+
+    // Returns a copy of the usage counters
+    pub async fn stats(&self) -> CacheStats {
+        self.locked(|map| *map.stats()).await
+    }
+}
+
+impl<K: Eq, V, const N: usize> AsyncLRUCache<K, V, N> {
+    // Insert `val` under `key`, see `LRUMap::insert`
+    pub async fn insert(&self, key: K, val: V) -> Option<(K, V)> {
+        self.locked(|map| map.insert(key, val)).await
+    }
+
+    // Returns a copy of the value stored under `key`, making it the most recently used entry
+    pub async fn get<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        V: Clone,
+    {
+        self.locked(|map| map.get(key).cloned()).await
+    }
+
+    // Returns a copy of the value stored under `key`, first inserting the one made by `f` if
+    // there is none. The map stays locked while `f` runs, so it is called at most once per miss.
+    pub async fn get_or_insert_with(&self, key: K, f: impl FnOnce() -> V) -> V
+    where
+        V: Clone,
+    {
+        self.locked(|map| {
+            if let Some(val) = map.get(&key) {
+                return val.clone();
+            }
+            let val = f();
+            map.insert(key, val.clone());
+            val
+        })
+        .await
+    }
+
+    // Returns a copy of the value stored under `key`, loading it with `load` if there is none.
+    // Concurrent calls for the same key share one load: the others wait for it, and if it is
+    // cancelled or panics one of them runs its own `load` instead. The map is not locked while
+    // loading.
+    pub async fn get_or_load<F, Fut>(&self, key: K, load: F) -> V
+    where
+        K: Clone,
+        V: Clone,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = V>,
+    {
+        loop {
+            let claim = self
+                .locked(|map| {
+                    if let Some(val) = map.get(&key) {
+                        return Claim::Found(val.clone());
+                    }
+                    let mut loads = self.loads();
+                    match loads.pending.iter().find(|l| l.key == key) {
+                        Some(pending) => Claim::Wait(pending.id),
+                        None => Claim::Load(loads.start(key.clone())),
+                    }
+                })
+                .await;
+
+            match claim {
+                Claim::Found(val) => return val,
+                Claim::Wait(id) => WaitLoad { cache: self, id }.await,
+                Claim::Load(id) => {
+                    // Dropped however the load ends, waking the waiters
+                    let _pending = PendingLoad { cache: self, id };
+                    let val = load().await;
+                    self.insert(key, val.clone()).await;
+                    return val;
+                }
+            }
+        }
+    }
+
+    // Remove the entry stored under `key`, returning its value
+    pub async fn remove<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.locked(|map| map.remove(key)).await
+    }
+}
+
+impl<K, V, const N: usize> AsyncLRUCache<K, V, N> {
+    // Only changed in small steps that cannot panic halfway, see `Gate::state`
+    fn loads(&self) -> MutexGuard<'_, Loads<K>> {
+        self.loads.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+// Outcome of looking a key up in `get_or_load`
+enum Claim<V> {
+    Found(V),
+    // Another task is loading it under this id
+    Wait(u64),
+    // This task must load it, under this id
+    Load(u64),
+}
+
+#[derive(Debug)]
+struct Loads<K> {
+    pending: Vec<Load<K>>,
+    next_id: u64,
+}
+
+#[derive(Debug)]
+struct Load<K> {
+    key: K,
+    id: u64,
+    // Tasks waiting for this load to end
+    waiters: Vec<Waker>,
+}
+
+impl<K> Loads<K> {
+    fn start(&mut self, key: K) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.pending.push(Load {
+            key,
+            id,
+            waiters: Vec::new(),
+        });
+        id
+    }
+}
+
+// Load `id` in progress, ended when dropped: after the loaded value was inserted, or without it
+// if the loading task was cancelled or panicked
+struct PendingLoad<'a, K, V, const N: usize> {
+    cache: &'a AsyncLRUCache<K, V, N>,
+    id: u64,
+}
+
+impl<K, V, const N: usize> Drop for PendingLoad<'_, K, V, N> {
+    fn drop(&mut self) {
+        let mut loads = self.cache.loads();
+        if let Some(i) = loads.pending.iter().position(|l| l.id == self.id) {
+            for waker in loads.pending.swap_remove(i).waiters {
+                waker.wake();
+            }
+        }
+    }
+}
+
+// Waits for load `id` to end, however it ends
+struct WaitLoad<'a, K, V, const N: usize> {
+    cache: &'a AsyncLRUCache<K, V, N>,
+    id: u64,
+}
+
+impl<K, V, const N: usize> Future for WaitLoad<'_, K, V, N> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let mut loads = self.cache.loads();
+        match loads.pending.iter_mut().find(|l| l.id == self.id) {
+            Some(load) => {
+                if !load.waiters.iter().any(|w| w.will_wake(cx.waker())) {
+                    load.waiters.push(cx.waker().clone());
+                }
+                Poll::Pending
+            }
+            None => Poll::Ready(()),
+        }
+    }
+}
+
+// An async lock with no data, handed to one task at a time in the order they asked
+#[derive(Debug)]
+struct Gate {
+    state: Mutex<GateState>,
+}
+
+#[derive(Debug)]
+struct GateState {
+    locked: bool,
+    // Tasks waiting for the gate, each under the id of its `Acquire`
+    waiters: VecDeque<(u64, Waker)>,
+    next_id: u64,
+}
+
+impl Gate {
+    const fn new() -> Self {
+        Gate {
+            state: Mutex::new(GateState {
+                locked: false,
+                waiters: VecDeque::new(),
+                next_id: 0,
+            }),
+        }
+    }
+
+    fn acquire(&self) -> Acquire<'_> {
+        Acquire {
+            gate: self,
+            id: None,
+        }
+    }
+
+    // The state is only changed in small steps that cannot panic halfway, so it is fine to keep
+    // using it after a panic elsewhere
+    fn state(&self) -> MutexGuard<'_, GateState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl GateState {
+    // Wake the first waiter, which then competes for the gate when polled
+    fn wake_next(&mut self) {
+        if let Some((_, waker)) = self.waiters.pop_front() {
+            waker.wake();
+        }
+    }
+}
+
+// Future of `Gate::acquire`
+struct Acquire<'a> {
+    gate: &'a Gate,
+    // Set once this future is or was in the queue
+    id: Option<u64>,
+}
+
+impl<'a> Future for Acquire<'a> {
+    type Output = Permit<'a>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Permit<'a>> {
+        let gate = self.gate;
+        let mut state = gate.state();
+        if !state.locked {
+            state.locked = true;
+            if let Some(id) = self.id.take() {
+                state.waiters.retain(|(waiter, _)| *waiter != id);
+            }
+            return Poll::Ready(Permit { gate });
+        }
+
+        let id = match self.id {
+            Some(id) => id,
+            None => {
+                let id = state.next_id;
+                state.next_id += 1;
+                self.id = Some(id);
+                id
+            }
+        };
+        match state.waiters.iter_mut().find(|(waiter, _)| *waiter == id) {
+            Some((_, waker)) => waker.clone_from(cx.waker()),
+            // Woken but beaten to the gate: wait again
+            None => state.waiters.push_back((id, cx.waker().clone())),
+        }
+        Poll::Pending
+    }
+}
+
+// An `Acquire` dropped after being woken must pass its turn on, or the next waiter would sleep
+// forever
+impl Drop for Acquire<'_> {
+    fn drop(&mut self) {
+        if let Some(id) = self.id {
+            let mut state = self.gate.state();
+            let queued = state.waiters.len();
+            state.waiters.retain(|(waiter, _)| *waiter != id);
+            if state.waiters.len() == queued && !state.locked {
+                state.wake_next();
+            }
+        }
+    }
+}
+
+// Holding the gate, released on drop
+struct Permit<'a> {
+    gate: &'a Gate,
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        let mut state = self.gate.state();
+        state.locked = false;
+        state.wake_next();
+    }
+}
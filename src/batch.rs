@@ -0,0 +1,157 @@
+//! Batched promotion.
+//!
+//! Promoting an entry to the front of the list on every hit costs a handful of link writes,
+//! even when the same few entries are hit over and over. `BatchedLRUCache` instead notes the
+//! entries hit, up to `K` of them, and moves them to the front in one pass when the batch is full
+//! or before anything that depends on the exact order, like picking the entry to evict. An entry
+//! hit several times in a batch is moved once.
+//!
+//! Until a batch is applied, lookups see the order as it was before it, so with several matching
+//! entries `find` may return a different one than `LRUCache::find` would.
+
+use arrayvec::ArrayVec;
+
+use crate::{CacheStats, LRUCache, Listener};
+
+// An LRU cache of capacity `N` applying its promotions in batches of up to `K`
+#[derive(Debug, Clone)]
+pub struct BatchedLRUCache<T, const N: usize, const K: usize, L = ()> {
+    cache: LRUCache<T, N, L>,
+    // Entries hit since the last batch was applied, oldest first
+    pending: ArrayVec<u16, K>,
+}
+
+impl<T, L: Listener<T> + Default, const N: usize, const K: usize> Default
+    for BatchedLRUCache<T, N, K, L>
+{
+    fn default() -> Self {
+        Self::with_listener(L::default())
+    }
+}
+
+impl<T, const N: usize, const K: usize> BatchedLRUCache<T, N, K> {
+    // create a empty cache
+    pub const fn new() -> Self {
+        Self::with_listener(())
+    }
+}
+
+impl<T, L: Listener<T>, const N: usize, const K: usize> BatchedLRUCache<T, N, K, L> {
+    // create a empty cache reporting its events to `listener`
+    pub const fn with_listener(listener: L) -> Self {
+        BatchedLRUCache {
+            cache: LRUCache::with_listener(listener),
+            pending: ArrayVec::new_const(),
+        }
+    }
+
+    // Insert given value in cache, after applying the pending promotions so that the right entry
+    // is evicted
+    pub fn insert(&mut self, val: T) -> Option<T> {
+        self.flush();
+        self.cache.insert(val)
+    }
+
+    // Returns the first item in the cache that matches the predicate
+    // Its promotion is queued for the next batch
+    pub fn find<F>(&mut self, pred: F) -> Option<&mut T>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let i = self.lookup(pred)?;
+        Some(&mut self.cache.entry(i).val)
+    }
+
+    // Touch the first item in the cache that matches the given predicate, queuing its promotion,
+    // Returns true or false
+    pub fn touch<F>(&mut self, pred: F) -> bool
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.lookup(pred).is_some()
+    }
+
+    // Apply the pending promotions now, oldest first. Each entry is only moved for its last
+    // hit, which leaves the list as if all of them had been applied one by one.
+    pub fn flush(&mut self) {
+        for (n, &i) in self.pending.iter().enumerate() {
+            if !self.pending[n + 1..].contains(&i) {
+                self.cache.touch_index(i);
+            }
+        }
+        self.pending.clear();
+    }
+
+    // Do at most `budget` units of deferred work: applying a pending promotion, oldest first, then
+    // the work of `LRUCache::maintain` once none are left. Returns the units done.
+    pub fn maintain(&mut self, budget: usize) -> usize {
+        let n = budget.min(self.pending.len());
+        for &i in &self.pending[..n] {
+            self.cache.touch_index(i);
+        }
+        self.pending.drain(..n);
+        if self.pending.is_empty() {
+            n + self.cache.maintain(budget - n)
+        } else {
+            n
+        }
+    }
+
+    // Returns the number of promotions waiting for the next batch
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    // Returns the number of elements in the cache
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    // Returns if cache is empty or not
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+
+    // Clears all the elements in cache
+    pub fn clear(&mut self) {
+        self.pending.clear();
+        self.cache.clear();
+    }
+
+    // Returns the usage counters collected so far
+    pub fn stats(&self) -> &CacheStats {
+        self.cache.stats()
+    }
+
+    // Applies the pending promotions and returns the cache, with its exact LRU order
+    pub fn into_inner(mut self) -> LRUCache<T, N, L> {
+        self.flush();
+        self.cache
+    }
+
+    // Count a lookup and queue the promotion of the entry it found
+    fn lookup<F>(&mut self, pred: F) -> Option<u16>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let weight = self.cache.next_tick();
+        let Some(i) = self.cache.position(pred) else {
+            self.cache.record_miss(weight);
+            return None;
+        };
+        self.cache.record_hit(i, weight);
+
+        // Already at the front or about to be moved there: nothing more to do
+        let queued = self.pending.last() == Some(&i);
+        if !queued && (!self.pending.is_empty() || i != self.cache.head()) {
+            if self.pending.is_full() {
+                self.flush();
+            }
+            // A batch of capacity 0 promotes right away
+            if self.pending.try_push(i).is_err() {
+                self.cache.touch_index(i);
+            }
+        }
+        Some(i)
+    }
+}
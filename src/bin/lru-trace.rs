@@ -0,0 +1,270 @@
+//! Replay a block trace against the caches of the crate and report how each one does.
+//!
+//! Run with `cargo run --release --features std --bin lru-trace -- [options] TRACE`, `-` reading
+//! the trace from stdin. Two trace formats are understood, one request per line:
+//!
+//! - ARC (`--format arc`): `start count ignored request`, a request for the `count` blocks from
+//!   `start` on, as in the traces published with the ARC paper.
+//! - LIRS (`--format lirs`): one block number per line, as in the traces published with the LIRS
+//!   paper. Lines that are not a number, like the `*` ending some of them, are skipped.
+//!
+//! Without `--format` each line is read by its number of fields. For every policy and capacity
+//! the report gives the hit ratio and the hits, misses and evictions. Reuse distances, the number
+//! of distinct blocks requested between two requests for the same block, are a property of the
+//! trace rather than of a policy, and are reported once: an LRU of capacity `c` hits exactly the
+//! requests with a reuse distance under `c`, so the percentiles tell which capacities are worth
+//! trying.
+//!
+//! Options:
+//!
+//! - `--format arc|lirs`: the trace format.
+//! - `--capacity C`, repeatable: only run capacity `C`, one of `CAPACITIES`.
+//! - `--policy P`, repeatable: only run policy `P`, one of `POLICIES`.
+//!
+//! The policies are a plain LRU (`IntLruMap`), an LRU promoting hits in batches of 8
+//! (`BatchedLRUCache`) and 4 LRUs each holding a quarter of the capacity, picked by the hash of
+//! the block (`ShardedLRUMap`). The last two scan their entries on every lookup, so they are slow
+//! at the largest capacities.
+
+use std::{
+    collections::HashMap,
+    env,
+    fs::File,
+    io::{self, BufRead, BufReader},
+    process,
+};
+
+use lru::{batch::BatchedLRUCache, intmap::IntLruMap, sharded::ShardedLRUMap, CacheStats};
+
+// Capacities a cache can be run at, capacities being fixed at compile time. The second one is
+// the capacity of each of the shards of `sharded-4`.
+const CAPACITIES: [(usize, Runner); 6] = [
+    (16, run::<16, 4>),
+    (64, run::<64, 16>),
+    (256, run::<256, 64>),
+    (1024, run::<1024, 256>),
+    (4096, run::<4096, 1024>),
+    (16384, run::<16384, 4096>),
+];
+
+const POLICIES: [&str; 3] = ["lru", "batched-8", "sharded-4"];
+
+type Runner = fn(&[u64], &str) -> CacheStats;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Arc,
+    Lirs,
+}
+
+struct Args {
+    format: Option<Format>,
+    capacities: Vec<usize>,
+    policies: Vec<String>,
+    path: String,
+}
+
+fn usage() -> ! {
+    eprintln!(
+        "usage: lru-trace [--format arc|lirs] [--capacity C]... [--policy P]... TRACE\n\
+         capacities: {:?}\npolicies: {:?}",
+        CAPACITIES.map(|(c, _)| c),
+        POLICIES
+    );
+    process::exit(2)
+}
+
+fn parse_args() -> Args {
+    let mut args = env::args().skip(1);
+    let mut format = None;
+    let mut capacities = Vec::new();
+    let mut policies = Vec::new();
+    let mut path = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => {
+                format = match args.next().as_deref() {
+                    Some("arc") => Some(Format::Arc),
+                    Some("lirs") => Some(Format::Lirs),
+                    _ => usage(),
+                }
+            }
+            "--capacity" => match args.next().and_then(|c| c.parse().ok()) {
+                Some(c) if CAPACITIES.iter().any(|(cap, _)| *cap == c) => capacities.push(c),
+                _ => usage(),
+            },
+            "--policy" => match args.next() {
+                Some(p) if POLICIES.contains(&p.as_str()) => policies.push(p),
+                _ => usage(),
+            },
+            "-h" | "--help" => usage(),
+            _ if path.is_none() => path = Some(arg),
+            _ => usage(),
+        }
+    }
+    if capacities.is_empty() {
+        capacities = CAPACITIES.map(|(c, _)| c).to_vec();
+    }
+    if policies.is_empty() {
+        policies = POLICIES.map(String::from).to_vec();
+    }
+    Args {
+        format,
+        capacities,
+        policies,
+        path: path.unwrap_or_else(|| usage()),
+    }
+}
+
+// Append the blocks requested by `line` to `keys`, returns false if it is not a request
+fn parse_line(line: &str, format: Option<Format>, keys: &mut Vec<u64>) -> bool {
+    let mut fields = line.split_whitespace();
+    let Some(Ok(first)) = fields.next().map(str::parse::<u64>) else {
+        return false;
+    };
+    let count = fields.next().map(str::parse::<u64>);
+    match (format, count) {
+        (Some(Format::Arc), Some(Ok(count))) | (None, Some(Ok(count))) => {
+            keys.extend(first..first.saturating_add(count));
+            true
+        }
+        (Some(Format::Arc), _) | (None, Some(Err(_))) => false,
+        (Some(Format::Lirs), _) | (None, None) => {
+            keys.push(first);
+            true
+        }
+    }
+}
+
+fn read_trace(args: &Args) -> io::Result<(Vec<u64>, usize)> {
+    let reader: Box<dyn BufRead> = if args.path == "-" {
+        Box::new(io::stdin().lock())
+    } else {
+        Box::new(BufReader::new(File::open(&args.path)?))
+    };
+    let mut keys = Vec::new();
+    let mut skipped = 0;
+    for line in reader.lines() {
+        if !parse_line(&line?, args.format, &mut keys) {
+            skipped += 1;
+        }
+    }
+    Ok((keys, skipped))
+}
+
+fn run<const N: usize, const SHARD: usize>(keys: &[u64], policy: &str) -> CacheStats {
+    match policy {
+        "lru" => {
+            let mut cache = Box::new(IntLruMap::<u64, (), N>::new());
+            for &key in keys {
+                if cache.get(key).is_none() {
+                    cache.insert(key, ());
+                }
+            }
+            *cache.stats()
+        }
+        "batched-8" => {
+            let mut cache = Box::new(BatchedLRUCache::<u64, N, 8>::new());
+            for &key in keys {
+                if !cache.touch(|v| *v == key) {
+                    cache.insert(key);
+                }
+            }
+            *cache.stats()
+        }
+        "sharded-4" => {
+            let cache = ShardedLRUMap::<u64, (), SHARD>::new(4);
+            for &key in keys {
+                if cache.get(&key).is_none() {
+                    cache.insert(key, ());
+                }
+            }
+            cache.stats()
+        }
+        _ => unreachable!("policies are checked when parsing arguments"),
+    }
+}
+
+// Reuse distance of every request that is not the first for its block, in request order, and
+// the number of distinct blocks
+fn reuse_distances(keys: &[u64]) -> (Vec<usize>, usize) {
+    // A Fenwick tree over request times, holding a 1 at the last request of every block seen so
+    // far: the distinct blocks requested since time `t` are the ones counted after `t`
+    let mut tree = vec![0u32; keys.len() + 1];
+    let add = |tree: &mut [u32], mut i: usize, d: i32| {
+        i += 1;
+        while i < tree.len() {
+            tree[i] = tree[i].wrapping_add_signed(d);
+            i += i & i.wrapping_neg();
+        }
+    };
+    let prefix = |tree: &[u32], mut i: usize| {
+        let mut sum = 0;
+        while i > 0 {
+            sum += tree[i] as usize;
+            i -= i & i.wrapping_neg();
+        }
+        sum
+    };
+
+    let mut last = HashMap::new();
+    let mut distances = Vec::new();
+    for (t, &key) in keys.iter().enumerate() {
+        if let Some(prev) = last.insert(key, t) {
+            distances.push(prefix(&tree, t) - prefix(&tree, prev + 1));
+            add(&mut tree, prev, -1);
+        }
+        add(&mut tree, t, 1);
+    }
+    (distances, last.len())
+}
+
+fn main() {
+    let args = parse_args();
+    let (keys, skipped) = read_trace(&args).unwrap_or_else(|err| {
+        eprintln!("lru-trace: {}: {err}", args.path);
+        process::exit(1)
+    });
+
+    let (mut distances, distinct) = reuse_distances(&keys);
+    println!(
+        "{}: {} requests for {distinct} distinct blocks, {skipped} lines skipped",
+        args.path,
+        keys.len()
+    );
+    distances.sort_unstable();
+    if distances.is_empty() {
+        println!("reuse distance: no block requested twice");
+    } else {
+        let at = |p: usize| distances[(distances.len() - 1) * p / 100];
+        println!(
+            "reuse distance: p50 {}  p90 {}  p99 {}  max {}",
+            at(50),
+            at(90),
+            at(99),
+            at(100)
+        );
+    }
+
+    println!(
+        "\n{:<12} {:>8} {:>9} {:>12} {:>12} {:>12}",
+        "policy", "capacity", "hit ratio", "hits", "misses", "evictions"
+    );
+    for policy in &args.policies {
+        for &capacity in &args.capacities {
+            let (_, runner) = CAPACITIES
+                .iter()
+                .find(|(c, _)| *c == capacity)
+                .expect("capacities are checked when parsing arguments");
+            let stats = runner(&keys, policy);
+            let lookups = (stats.hits + stats.misses).max(1);
+            println!(
+                "{policy:<12} {capacity:>8} {:>9.4} {:>12} {:>12} {:>12}",
+                stats.hits as f64 / lookups as f64,
+                stats.hits,
+                stats.misses,
+                stats.evictions
+            );
+        }
+    }
+}
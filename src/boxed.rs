@@ -0,0 +1,37 @@
+//! Caches of boxed values.
+//!
+//! Entries are stored inline, so a cache of large values is as large as `N` of them even when
+//! empty. `BoxedLRUCache` keeps each value on the heap instead, leaving a pointer per entry in the
+//! array, and its helpers box and unbox values at the edges so callers deal in plain `T`.
+
+use alloc::boxed::Box;
+
+use crate::{LRUCache, Listener};
+
+// An LRU cache of capacity `N` keeping its values on the heap
+pub type BoxedLRUCache<T, const N: usize, L = ()> = LRUCache<Box<T>, N, L>;
+
+impl<T, L: Listener<Box<T>>, const N: usize> LRUCache<Box<T>, N, L> {
+    // Box given value and insert it in cache. Returns the value it evicted, unboxed.
+    pub fn insert_boxed(&mut self, val: T) -> Option<T> {
+        self.insert(Box::new(val)).map(|old| *old)
+    }
+
+    // Returns the first item in the cache that matches the predicate
+    // Make it most recently used on hit
+    pub fn find_boxed<F>(&mut self, mut pred: F) -> Option<&mut T>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.find(|val| pred(val)).map(|val| &mut **val)
+    }
+
+    // Touch the first item in the cache that matches the given predicate and marks it as recently
+    // used, Returns true or false
+    pub fn touch_boxed<F>(&mut self, mut pred: F) -> bool
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.touch(|val| pred(val))
+    }
+}
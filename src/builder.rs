@@ -0,0 +1,180 @@
+//! One place to configure a cache.
+//!
+//! `CacheBuilder` collects the options the cache types take (listener, stats mode, TTL, memory
+//! budget) and assembles the type they add up to. The builder tracks in its type which kind of
+//! cache it is building, so options only exist where they mean something and `build` cannot
+//! fail:
+//!
+//! - a new builder makes a plain `LRUCache`
+//! - `clock` turns it into a builder for a `TtlCache`, and only then are `default_ttl` and
+//!   `sliding_expiry` available
+//! - `memory_limit`, for values implementing `MemSize`, turns it into a builder for a
+//!   `MemBoundedCache`, and only then is `oversize_policy` available
+//!
+//! A cache cannot be bounded by both time and memory, so a builder given a clock has no
+//! `memory_limit` and the other way around.
+
+use core::{marker::PhantomData, num::NonZero};
+
+use crate::{
+    mem::{MemBoundedCache, MemSize, OversizePolicy},
+    ttl::{Clock, TtlCache},
+    LRUCache, Listener, StatsMode,
+};
+
+// Options for a cache of capacity `N` holding values of type `T`, building the kind of cache
+// `S` stands for
+#[derive(Debug, Clone)]
+pub struct CacheBuilder<T, const N: usize, L = (), S = Plain> {
+    listener: L,
+    stats_mode: StatsMode,
+    state: S,
+    values: PhantomData<fn() -> T>,
+}
+
+// Builder state for a plain `LRUCache`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Plain;
+
+// Builder state for a `TtlCache` reading the time from `C`
+#[derive(Debug, Clone)]
+pub struct Expiring<C> {
+    clock: C,
+    default_ttl: Option<NonZero<u64>>,
+    sliding: bool,
+}
+
+// Builder state for a `MemBoundedCache`
+#[derive(Debug, Clone, Copy)]
+pub struct Bounded {
+    limit: usize,
+    policy: Option<OversizePolicy>,
+}
+
+impl<T, const N: usize> Default for CacheBuilder<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> CacheBuilder<T, N> {
+    // create a builder with every option left at the default of the cache types
+    pub const fn new() -> Self {
+        CacheBuilder {
+            listener: (),
+            stats_mode: StatsMode::Full,
+            state: Plain,
+            values: PhantomData,
+        }
+    }
+}
+
+impl<T, L, S, const N: usize> CacheBuilder<T, N, L, S> {
+    // Report the events of the cache to `listener`
+    pub fn listener<M: Listener<T>>(self, listener: M) -> CacheBuilder<T, N, M, S> {
+        CacheBuilder {
+            listener,
+            stats_mode: self.stats_mode,
+            state: self.state,
+            values: PhantomData,
+        }
+    }
+
+    // Choose which operations are counted in the stats, see `StatsMode`
+    pub const fn stats_mode(mut self, mode: StatsMode) -> Self {
+        self.stats_mode = mode;
+        self
+    }
+
+    fn with_state<R>(self, state: R) -> CacheBuilder<T, N, L, R> {
+        CacheBuilder {
+            listener: self.listener,
+            stats_mode: self.stats_mode,
+            state,
+            values: PhantomData,
+        }
+    }
+}
+
+impl<T, L, const N: usize> CacheBuilder<T, N, L> {
+    // Build a `TtlCache` reading the time from `clock`
+    pub fn clock<C: Clock>(self, clock: C) -> CacheBuilder<T, N, L, Expiring<C>> {
+        self.with_state(Expiring {
+            clock,
+            default_ttl: None,
+            sliding: false,
+        })
+    }
+
+    // Build a `MemBoundedCache` keeping the heap memory owned by the values under `limit` bytes
+    pub fn memory_limit(self, limit: usize) -> CacheBuilder<T, N, L, Bounded>
+    where
+        T: MemSize,
+    {
+        self.with_state(Bounded {
+            limit,
+            policy: None,
+        })
+    }
+}
+
+impl<T, L: Listener<T>, const N: usize> CacheBuilder<T, N, L> {
+    // Build the plain `LRUCache`
+    pub fn build(self) -> LRUCache<T, N, L> {
+        LRUCache::with_listener(self.listener).with_stats_mode(self.stats_mode)
+    }
+}
+
+impl<T, L, C: Clock, const N: usize> CacheBuilder<T, N, L, Expiring<C>> {
+    // Make entries expire `ttl` ticks after insertion unless given their own deadline, see
+    // `TtlCache::with_default_ttl`
+    pub const fn default_ttl(mut self, ttl: NonZero<u64>) -> Self {
+        self.state.default_ttl = Some(ttl);
+        self
+    }
+
+    // Move the deadline of an entry forward on every hit, see `TtlCache::with_sliding_expiry`
+    pub const fn sliding_expiry(mut self) -> Self {
+        self.state.sliding = true;
+        self
+    }
+}
+
+impl<T, L: Listener<T>, C: Clock, const N: usize> CacheBuilder<T, N, L, Expiring<C>> {
+    // Build the `TtlCache`
+    pub fn build(self) -> TtlCache<T, C, N, L> {
+        let Expiring {
+            clock,
+            default_ttl,
+            sliding,
+        } = self.state;
+        let mut cache = TtlCache::with_listener(clock, self.listener);
+        if let Some(ttl) = default_ttl {
+            cache = cache.with_default_ttl(ttl.get());
+        }
+        if sliding {
+            cache = cache.with_sliding_expiry();
+        }
+        cache.with_stats_mode(self.stats_mode)
+    }
+}
+
+impl<T, L, const N: usize> CacheBuilder<T, N, L, Bounded> {
+    // Choose what happens to values over the memory budget, see `OversizePolicy`
+    pub const fn oversize_policy(mut self, policy: OversizePolicy) -> Self {
+        self.state.policy = Some(policy);
+        self
+    }
+}
+
+impl<T: MemSize, L: Listener<T>, const N: usize> CacheBuilder<T, N, L, Bounded> {
+    // Build the `MemBoundedCache`
+    pub fn build(self) -> MemBoundedCache<T, N, L> {
+        let Bounded { limit, policy } = self.state;
+        let mut cache = MemBoundedCache::with_listener(limit, self.listener);
+        if let Some(policy) = policy {
+            cache = cache.with_oversize_policy(policy);
+        }
+        cache.with_stats_mode(self.stats_mode)
+    }
+}
@@ -0,0 +1,131 @@
+//! Detection of pathological churn, the usual sign of an undersized cache.
+//!
+//! A `ChurnMonitor` is a listener that looks at fixed windows of operations. When nearly every
+//! insert in a window evicted something and nearly no lookup hit, it reports the window to a
+//! callback, which typically forwards it to `log::warn!` or a similar sink. Reports are rate
+//! limited so a cache stuck in that state does not flood the log.
+
+use crate::{Event, Listener, RemovalCause};
+
+// What the cache did during one window that looked like churn
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChurnReport {
+    pub inserts: u32,
+    pub evictions: u32,
+    pub hits: u32,
+    pub misses: u32,
+}
+
+impl ChurnReport {
+    // Fraction of inserts in the window that pushed something out
+    pub fn eviction_ratio(&self) -> f64 {
+        ratio(self.evictions, self.inserts)
+    }
+
+    // Fraction of lookups in the window that were hits
+    pub fn hit_ratio(&self) -> f64 {
+        ratio(self.hits, self.hits + self.misses)
+    }
+}
+
+fn ratio(part: u32, total: u32) -> f64 {
+    match total {
+        0 => 0.0,
+        n => part as f64 / n as f64,
+    }
+}
+
+// Listener calling `on_churn` for windows where the cache evicts about as fast as it inserts
+// while almost never hitting
+#[derive(Debug, Clone)]
+pub struct ChurnMonitor<F> {
+    on_churn: F,
+    // Operations (inserts and lookups) per window
+    window: u32,
+    min_eviction_ratio: f64,
+    max_hit_ratio: f64,
+    // Windows to stay quiet for after a report
+    cooldown: u32,
+    quiet_for: u32,
+    current: ChurnReport,
+}
+
+impl<F: FnMut(&ChurnReport)> ChurnMonitor<F> {
+    // Watches windows of `window` operations, reporting when at least 90% of inserts evict and
+    // at most 5% of lookups hit, and at most once every 10 windows
+    pub fn new(window: u32, on_churn: F) -> Self {
+        ChurnMonitor {
+            on_churn,
+            window: window.max(1),
+            min_eviction_ratio: 0.9,
+            max_hit_ratio: 0.05,
+            cooldown: 10,
+            quiet_for: 0,
+            current: ChurnReport {
+                inserts: 0,
+                evictions: 0,
+                hits: 0,
+                misses: 0,
+            },
+        }
+    }
+
+    // Changes what counts as churn
+    pub fn with_thresholds(mut self, min_eviction_ratio: f64, max_hit_ratio: f64) -> Self {
+        self.min_eviction_ratio = min_eviction_ratio;
+        self.max_hit_ratio = max_hit_ratio;
+        self
+    }
+
+    // Changes how many windows are skipped after each report
+    pub fn with_cooldown(mut self, windows: u32) -> Self {
+        self.cooldown = windows;
+        self
+    }
+
+    fn end_window(&mut self) {
+        let report = self.current;
+        self.current = ChurnReport {
+            inserts: 0,
+            evictions: 0,
+            hits: 0,
+            misses: 0,
+        };
+
+        if self.quiet_for > 0 {
+            self.quiet_for -= 1;
+            return;
+        }
+
+        if report.inserts > 0
+            && report.eviction_ratio() >= self.min_eviction_ratio
+            && report.hit_ratio() <= self.max_hit_ratio
+        {
+            (self.on_churn)(&report);
+            self.quiet_for = self.cooldown;
+        }
+    }
+}
+
+impl<T, F: FnMut(&ChurnReport)> Listener<T> for ChurnMonitor<F> {
+    fn on_event(&mut self, event: Event<'_, T>) {
+        match event {
+            Event::Insert(_) => self.current.inserts += 1,
+            Event::Hit(_) => self.current.hits += 1,
+            Event::Miss => self.current.misses += 1,
+            // Evictions come with the insert that caused them, which is what gets counted as
+            // an operation
+            Event::Evict(_, RemovalCause::Capacity) => {
+                self.current.evictions += 1;
+                return;
+            }
+            // Entries leaving for any other reason say nothing about the capacity
+            Event::Evict(..) => return,
+        }
+
+        let c = &self.current;
+        if c.inserts + c.hits + c.misses >= self.window {
+            self.end_window();
+        }
+    }
+}
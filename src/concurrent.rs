@@ -0,0 +1,164 @@
+//! A keyed LRU map tuned for read-heavy sharing between threads.
+//!
+//! Lookups in `ConcurrentLRUMap` only take a shared read lock, so any number of them run at once
+//! and never wait for each other. Moving the entry they found to the front of the list needs
+//! exclusive access, so instead they record its slot in a small fixed-size promotion buffer that
+//! any thread may append to. The next write (an insert or a removal), which holds the lock
+//! exclusively anyway, applies the recorded promotions before doing its own work.
+//!
+//! The price is exact LRU order: promotions are applied late, a burst of reads bigger than the
+//! buffer overwrites some of them, and a promotion may land on an entry that moved into the slot
+//! in the meantime. The entry evicted is then only roughly the least recently used one.
+
+use core::{
+    borrow::Borrow,
+    sync::atomic::{AtomicU16, AtomicU64, AtomicUsize, Ordering},
+};
+use std::sync::{PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use crate::{map::LRUMap, CacheStats};
+
+// A thread-safe LRU map of capacity `N` whose lookups share the lock, with room for `B` deferred
+// promotions
+#[derive(Debug)]
+pub struct ConcurrentLRUMap<K, V, const N: usize, const B: usize = 32> {
+    map: RwLock<LRUMap<K, V, N>>,
+    // Slots found by lookups since the last write, plus one so that 0 is an empty cell
+    promotions: [AtomicU16; B],
+    // Total number of promotions recorded, the next one goes to `promotions[recorded % B]`
+    recorded: AtomicUsize,
+    // Lookups counted outside of the map, which is only borrowed immutably by them
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl<K, V, const N: usize, const B: usize> Default for ConcurrentLRUMap<K, V, N, B> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V, const N: usize, const B: usize> ConcurrentLRUMap<K, V, N, B> {
+    // create a empty map
+    pub const fn new() -> Self {
+        assert!(B > 0, "the promotion buffer needs at least one cell");
+        ConcurrentLRUMap {
+            map: RwLock::new(LRUMap::new()),
+            promotions: [const { AtomicU16::new(0) }; B],
+            recorded: AtomicUsize::new(0),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    // Returns the number of entries in the map
+    pub fn len(&self) -> usize {
+        self.read().len()
+    }
+
+    // Returns if map is empty or not
+    pub fn is_empty(&self) -> bool {
+        self.read().is_empty()
+    }
+
+    // Clears all the entries in map, along with the promotions waiting to be applied
+    pub fn clear(&self) {
+        let mut map = self.write();
+        for cell in &self.promotions {
+            cell.store(0, Ordering::Relaxed);
+        }
+        map.clear();
+    }
+
+    // Returns a copy of the usage counters
+    pub fn stats(&self) -> CacheStats {
+        let mut stats = *self.read().stats();
+        stats.hits += self.hits.load(Ordering::Relaxed);
+        stats.misses += self.misses.load(Ordering::Relaxed);
+        stats
+    }
+
+    // Apply the promotions recorded by lookups so far, which writes otherwise do on their own
+    pub fn flush_promotions(&self) {
+        drop(self.write());
+    }
+
+    // Record that the entry in slot `i` was used
+    fn record_promotion(&self, i: u16) {
+        let at = self.recorded.fetch_add(1, Ordering::Relaxed) % B;
+        self.promotions[at].store(i + 1, Ordering::Relaxed);
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, LRUMap<K, V, N>> {
+        self.map.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    // Lock the map for writing, first applying the recorded promotions, oldest first
+    fn write(&self) -> RwLockWriteGuard<'_, LRUMap<K, V, N>> {
+        let mut map = self.map.write().unwrap_or_else(PoisonError::into_inner);
+        let start = self.recorded.load(Ordering::Relaxed);
+        for n in 0..B {
+            let slot = self.promotions[(start + n) % B].swap(0, Ordering::Relaxed);
+            if slot != 0 {
+                map.promote(slot - 1);
+            }
+        }
+        map
+    }
+}
+
+impl<K: Eq, V, const N: usize, const B: usize> ConcurrentLRUMap<K, V, N, B> {
+    // Insert `val` under `key`, see `LRUMap::insert`
+    pub fn insert(&self, key: K, val: V) -> Option<(K, V)> {
+        self.write().insert(key, val)
+    }
+
+    // Returns a copy of the value stored under `key`, to be made the most recently used entry by
+    // the next write
+    pub fn get<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+        V: Clone,
+    {
+        self.get_with(key, V::clone)
+    }
+
+    // Runs `f` on the value stored under `key` while the map is locked for reading, see `get`
+    pub fn get_with<Q, R>(&self, key: &Q, f: impl FnOnce(&V) -> R) -> Option<R>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        let map = self.read();
+        match map.index_of(key) {
+            Some(i) => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                self.record_promotion(i);
+                Some(f(map.value(i)))
+            }
+            None => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+
+    // Returns true if a value is stored under `key`, without changing the recency order
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.read().contains_key(key)
+    }
+
+    // Remove the entry stored under `key`, returning its value
+    pub fn remove<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.write().remove(key)
+    }
+}
@@ -0,0 +1,279 @@
+//! Walking the recency list and changing it on the way.
+//!
+//! A `CursorMut` points at one entry of an `LRUCache`, or past both ends of the list, and moves
+//! from entry to entry in recency order. Through it the caller reads or changes the entry under
+//! it, removes it, or inserts new values on either side, so logic like "drop every expired
+//! session older than this one" runs in one pass instead of draining and refilling the cache.
+//! Walking does not count as a lookup: it neither promotes entries nor touches the hit and miss
+//! counters.
+//!
+//! `iter_mut` wraps a cursor for the common case of one pass from the most to the least recently
+//! used entry, where each entry handed out can be removed on the spot. `extract_if` does the same
+//! pass removing the entries a predicate picks, yielding their values.
+//!
+//! As in std's `LinkedList` cursors, the position past the ends is a "ghost" between the tail
+//! and the head. Moving forward from the tail lands on it, and moving forward again wraps around
+//! to the head. Backwards is the same in reverse.
+
+use crate::{Entry, Event, LRUCache, Link, Listener, RemovalCause, Slot, Stamp};
+
+// A cursor over the entries of a cache, see the module docs
+#[derive(Debug)]
+pub struct CursorMut<'a, T, const N: usize, L = ()> {
+    cache: &'a mut LRUCache<T, N, L>,
+    // Entry under the cursor, `None` for the ghost position
+    pos: Option<u16>,
+}
+
+impl<T, L: Listener<T>, const N: usize> LRUCache<T, N, L> {
+    // Returns a cursor on the most recently used entry, or the ghost position if the cache is
+    // empty
+    pub fn cursor_front_mut(&mut self) -> CursorMut<'_, T, N, L> {
+        self.drop_spent();
+        let pos = (!self.is_empty()).then(|| self.head());
+        CursorMut { cache: self, pos }
+    }
+
+    // Returns a cursor on the least recently used entry, or the ghost position if the cache is
+    // empty
+    pub fn cursor_back_mut(&mut self) -> CursorMut<'_, T, N, L> {
+        self.drop_spent();
+        let pos = (!self.is_empty()).then(|| self.tail());
+        CursorMut { cache: self, pos }
+    }
+
+    // Returns an iterator over the entries from the most to the least recently used, each of which
+    // can be changed or removed, e.g. to purge values whose backing resource went away. Like a
+    // cursor it does not promote anything or count lookups.
+    pub fn iter_mut(&mut self) -> IterMut<'_, T, N, L> {
+        IterMut {
+            cursor: self.cursor_front_mut(),
+            advance: false,
+        }
+    }
+
+    // Returns an iterator removing the entries matching `pred`, from the most to the least
+    // recently used, and yielding their values, e.g. to write dirty entries back as they are
+    // found. The other entries are left as they were. Dropping the iterator stops the removals,
+    // keeping the matches it did not reach. Like a cursor it does not count lookups.
+    pub fn extract_if<F>(&mut self, pred: F) -> ExtractIf<'_, T, N, L, F>
+    where
+        F: FnMut(&mut T) -> bool,
+    {
+        ExtractIf {
+            cursor: self.cursor_front_mut(),
+            pred,
+        }
+    }
+
+    // Store `val` in a new entry, linked in by `link` once it is in the array, and count it as
+    // an insert. The caller checked that the cache has room.
+    fn insert_linked(&mut self, val: T, link: impl FnOnce(&mut Self, u16)) {
+        let born = self.tick;
+        self.stats.inserts += self.next_tick();
+        let i = self.entries.len() as u16;
+        self.entries.push(Entry {
+            val,
+            link: Link::new(),
+            stamp: Stamp::new(born),
+        });
+        link(self, i);
+        self.listener
+            .on_event(Event::Insert(&self.entries[i as usize].val));
+    }
+
+    // Link entry `i`, which is not in the list, right after entry `at`
+    pub(crate) fn link_after(&mut self, i: u16, at: u16) {
+        self.entry(i).link.set_prev(at);
+        if at == self.tail() {
+            self.tail = Slot::new(i);
+        } else {
+            let next = self.entries[at as usize].link.next();
+            self.entry(i).link.set_next(next);
+            self.entry(next).link.set_prev(i);
+        }
+        self.entry(at).link.set_next(i);
+    }
+
+    // Link entry `i`, which is not in the list, right before entry `at`
+    pub(crate) fn link_before(&mut self, i: u16, at: u16) {
+        if at == self.head() {
+            self.push_front(i);
+        } else {
+            let prev = self.entries[at as usize].link.prev();
+            self.link_after(i, prev);
+        }
+    }
+}
+
+impl<T, L: Listener<T>, const N: usize> CursorMut<'_, T, N, L> {
+    // Returns the entry under the cursor, `None` at the ghost position
+    pub fn current(&mut self) -> Option<&mut T> {
+        let i = self.pos?;
+        Some(&mut self.cache.entry(i).val)
+    }
+
+    // Move to the next, less recently used entry. From the tail this is the ghost position, and
+    // from there the head.
+    pub fn move_next(&mut self) {
+        let cache = &*self.cache;
+        self.pos = match self.pos {
+            None if cache.is_empty() => None,
+            None => Some(cache.head()),
+            Some(i) if i == cache.tail() => None,
+            Some(i) => Some(cache.entries[i as usize].link.next()),
+        };
+    }
+
+    // Move to the previous, more recently used entry. From the head this is the ghost position,
+    // and from there the tail.
+    pub fn move_prev(&mut self) {
+        let cache = &*self.cache;
+        self.pos = match self.pos {
+            None if cache.is_empty() => None,
+            None => Some(cache.tail()),
+            Some(i) if i == cache.head() => None,
+            Some(i) => Some(cache.entries[i as usize].link.prev()),
+        };
+    }
+
+    // Remove the entry under the cursor and return its value, moving the cursor to the next
+    // entry. Does nothing at the ghost position.
+    pub fn remove_current(&mut self) -> Option<T> {
+        let i = self.pos?;
+        let cache = &mut *self.cache;
+        let next = (i != cache.tail()).then(|| cache.entries[i as usize].link.next());
+        cache.listener.on_event(Event::Evict(
+            &cache.entries[i as usize].val,
+            RemovalCause::Explicit,
+        ));
+        // The last entry of the array moves into the slot removed
+        let last = cache.len() as u16 - 1;
+        let val = cache.remove_index(i);
+        self.pos = next.map(|n| if n == last { i } else { n });
+        Some(val)
+    }
+
+    // Insert `val` right before the cursor, as more recently used than the entry under it, or
+    // as the least recently used entry at the ghost position. The cursor does not move. Hands
+    // `val` back if the cache is full, or at its high watermark: making room would evict an entry
+    // the walk may not have reached yet.
+    pub fn insert_before(&mut self, val: T) -> Result<(), T> {
+        if self.cache.len() == self.cache.limit() {
+            return Err(val);
+        }
+        match self.pos {
+            Some(at) => self
+                .cache
+                .insert_linked(val, |cache, i| cache.link_before(i, at)),
+            None if self.cache.is_empty() => self.cache.insert_linked(val, LRUCache::push_front),
+            None => {
+                let tail = self.cache.tail();
+                self.cache
+                    .insert_linked(val, |cache, i| cache.link_after(i, tail))
+            }
+        }
+        Ok(())
+    }
+
+    // Insert `val` right after the cursor, as less recently used than the entry under it, or as
+    // the most recently used entry at the ghost position. The cursor does not move. Hands `val`
+    // back if the cache is full, see `insert_before`.
+    pub fn insert_after(&mut self, val: T) -> Result<(), T> {
+        if self.cache.len() == self.cache.limit() {
+            return Err(val);
+        }
+        match self.pos {
+            Some(at) => self
+                .cache
+                .insert_linked(val, |cache, i| cache.link_after(i, at)),
+            None => self.cache.insert_linked(val, LRUCache::push_front),
+        }
+        Ok(())
+    }
+}
+
+// Iterator returned by `LRUCache::iter_mut`. Entries borrow the iterator, so it is driven with
+// `while let Some(entry) = iter.next()` rather than a `for` loop.
+#[derive(Debug)]
+pub struct IterMut<'a, T, const N: usize, L = ()> {
+    cursor: CursorMut<'a, T, N, L>,
+    // Whether the cursor is still on the entry handed out last. A removal moves it on already.
+    advance: bool,
+}
+
+impl<'a, T, L: Listener<T>, const N: usize> IterMut<'a, T, N, L> {
+    // Returns the next entry, less recently used than the previous one
+    #[allow(clippy::should_implement_trait)] // entries borrow the iterator, `Iterator` can't
+    pub fn next(&mut self) -> Option<EntryMut<'_, 'a, T, N, L>> {
+        // At the ghost position the walk is over, moving on would wrap around to the head
+        self.cursor.pos?;
+        if self.advance {
+            self.cursor.move_next();
+            self.cursor.pos?;
+        }
+        self.advance = true;
+        Some(EntryMut { iter: self })
+    }
+}
+
+// An entry handed out by `IterMut`
+#[derive(Debug)]
+pub struct EntryMut<'i, 'a, T, const N: usize, L = ()> {
+    iter: &'i mut IterMut<'a, T, N, L>,
+}
+
+impl<T, L: Listener<T>, const N: usize> EntryMut<'_, '_, T, N, L> {
+    // Returns the value of the entry
+    pub fn get(&self) -> &T {
+        let i = self
+            .iter
+            .cursor
+            .pos
+            .expect("an entry is handed out at an entry");
+        &self.iter.cursor.cache.entries[i as usize].val
+    }
+
+    // Returns the value of the entry, to change it in place
+    pub fn get_mut(&mut self) -> &mut T {
+        self.iter
+            .cursor
+            .current()
+            .expect("an entry is handed out at an entry")
+    }
+
+    // Remove the entry from the cache and return its value
+    pub fn remove(self) -> T {
+        self.iter.advance = false;
+        self.iter
+            .cursor
+            .remove_current()
+            .expect("an entry is handed out at an entry")
+    }
+}
+
+// Iterator returned by `LRUCache::extract_if`
+#[derive(Debug)]
+pub struct ExtractIf<'a, T, const N: usize, L, F> {
+    cursor: CursorMut<'a, T, N, L>,
+    pred: F,
+}
+
+impl<T, L: Listener<T>, const N: usize, F> Iterator for ExtractIf<'_, T, N, L, F>
+where
+    F: FnMut(&mut T) -> bool,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        // Removing an entry moves the cursor on, so only a kept entry needs a move. The ghost
+        // position ends the walk.
+        while let Some(val) = self.cursor.current() {
+            if (self.pred)(val) {
+                return self.cursor.remove_current();
+            }
+            self.cursor.move_next();
+        }
+        None
+    }
+}
@@ -0,0 +1,77 @@
+//! A digest of what a cache holds, for telling whether two copies diverged.
+//!
+//! A `DigestListener` keeps a 64-bit digest of the values of the cache it is attached to,
+//! updated on every insert and removal, so reading it is free. Two caches holding the same values
+//! have the same digest whatever order they were inserted in and whatever their recency order:
+//! the digest is the wrapping sum of a hash of each value. Nodes mirroring a cache compare
+//! digests first and only compare contents when they differ.
+//!
+//! Both sides must hash the same way, so the hasher must not be seeded per process: the default
+//! `FxBuildHasher` is fine, `RandomState` is not. A keyed map only reports its values to its
+//! listener, so its digest covers the values and not the keys. Values put in without an insert,
+//! by `from_array` or by restoring a snapshot, are not seen either.
+
+use core::hash::{BuildHasher, Hash};
+
+use crate::{hash::FxBuildHasher, map::LRUMap, Event, LRUCache, Listener};
+
+// Listener keeping a digest of the values in its cache
+#[derive(Debug, Clone, Default)]
+pub struct DigestListener<S = FxBuildHasher> {
+    digest: u64,
+    hasher: S,
+}
+
+impl DigestListener {
+    // create a listener for an empty cache
+    pub const fn new() -> Self {
+        Self::with_hasher(FxBuildHasher)
+    }
+}
+
+impl<S> DigestListener<S> {
+    // create a listener for an empty cache hashing values with `hasher`
+    pub const fn with_hasher(hasher: S) -> Self {
+        DigestListener { digest: 0, hasher }
+    }
+
+    // Returns the digest of the values in the cache, 0 when it is empty
+    pub fn digest(&self) -> u64 {
+        self.digest
+    }
+}
+
+impl<S: BuildHasher> DigestListener<S> {
+    // Hash of `val`, mixed further so that sums of hashes of similar values do not collide
+    fn hash<T: Hash>(&self, val: &T) -> u64 {
+        // The finalizer of SplitMix64
+        let mut h = self.hasher.hash_one(val);
+        h = (h ^ (h >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        h = (h ^ (h >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        h ^ (h >> 31)
+    }
+}
+
+impl<T: Hash, S: BuildHasher> Listener<T> for DigestListener<S> {
+    fn on_event(&mut self, event: Event<'_, T>) {
+        match event {
+            Event::Insert(val) => self.digest = self.digest.wrapping_add(self.hash(val)),
+            Event::Evict(val, _) => self.digest = self.digest.wrapping_sub(self.hash(val)),
+            Event::Hit(_) | Event::Miss => {}
+        }
+    }
+}
+
+impl<T: Hash, S: BuildHasher, const N: usize> LRUCache<T, N, DigestListener<S>> {
+    // Returns the digest of the values in the cache, see `DigestListener`
+    pub fn digest(&self) -> u64 {
+        self.listener().digest()
+    }
+}
+
+impl<K, V: Hash, S: BuildHasher, const N: usize> LRUMap<K, V, N, DigestListener<S>> {
+    // Returns the digest of the values in the map, see `DigestListener`
+    pub fn digest(&self) -> u64 {
+        self.listener().digest()
+    }
+}
@@ -0,0 +1,154 @@
+//! A cache of DNS records honoring their TTLs.
+//!
+//! `DnsCache` is a keyed LRU cache whose entries expire after the TTL of the record they hold,
+//! clamped to the bounds the resolver configured: a floor keeps absurdly short TTLs from turning
+//! the cache into a pass-through, a ceiling keeps a record from outliving a change upstream for
+//! days. Lookups answer with the TTL left, which is what a resolver passes on to its clients.
+//!
+//! TTLs are in the unit of the `Clock`, seconds for a clock counting seconds. An expired record is
+//! dropped when looked up, and otherwise keeps its slot until it is the least recently used.
+//!
+//! Keys are compared by `Eq` unless the cache is given another `Equivalence`, e.g. `DomainName`
+//! for name keys so that `Example.COM.` finds what was cached for `example.com`.
+
+use core::borrow::Borrow;
+
+use crate::{
+    equiv::{Equivalence, Exact},
+    map::LRUMap,
+    ttl::Clock,
+    CacheStats,
+};
+
+// A cache of capacity `N` from keys `K`, e.g. a name and record type, to records `V`
+#[derive(Debug, Clone)]
+pub struct DnsCache<K, V, C, const N: usize, E = Exact> {
+    map: LRUMap<K, Record<V>, N>,
+    clock: C,
+    min_ttl: u64,
+    max_ttl: u64,
+    equivalence: E,
+}
+
+#[derive(Debug, Clone)]
+struct Record<V> {
+    value: V,
+    // Time the record stops being served at
+    expires: u64,
+}
+
+impl<K: Eq, V, C: Clock, const N: usize> DnsCache<K, V, C, N> {
+    // create a empty cache reading the time from `clock` and keeping records between `min_ttl`
+    // and `max_ttl` ticks
+    pub fn new(clock: C, min_ttl: u64, max_ttl: u64) -> Self {
+        Self::with_equivalence(clock, min_ttl, max_ttl, Exact)
+    }
+}
+
+impl<K, V, C: Clock, E: Equivalence<K>, const N: usize> DnsCache<K, V, C, N, E> {
+    // create a empty cache like `new` does, matching keys by `equivalence`
+    pub fn with_equivalence(clock: C, min_ttl: u64, max_ttl: u64, equivalence: E) -> Self {
+        assert!(min_ttl <= max_ttl, "the minimum TTL is above the maximum");
+        DnsCache {
+            map: LRUMap::new(),
+            clock,
+            min_ttl,
+            max_ttl,
+            equivalence,
+        }
+    }
+
+    // Insert `record` under `key`, to be served for its `ttl` clamped to the configured bounds.
+    // Returns the TTL it was given, and the entry it replaced, like `LRUMap::insert`.
+    pub fn insert(&mut self, key: K, record: V, ttl: u64) -> (u64, Option<(K, V)>) {
+        let ttl = ttl.clamp(self.min_ttl, self.max_ttl);
+        let expires = self.clock.now().saturating_add(ttl);
+        let record = Record {
+            value: record,
+            expires,
+        };
+        let found = self
+            .map
+            .position_by(|k| self.equivalence.equivalent(k, &key));
+        let replaced = match found {
+            Some(i) => Some(self.map.overwrite(i, key, record)),
+            None => self.map.insert_new(key, record),
+        };
+        (ttl, replaced.map(|(key, old)| (key, old.value)))
+    }
+
+    // Returns the record stored under `key` and the TTL it has left, making it the most recently
+    // used entry. An expired record is removed and reported as a miss.
+    pub fn get<Q>(&mut self, key: &Q) -> Option<(&V, u64)>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        E: Equivalence<Q>,
+    {
+        let now = self.clock.now();
+        let equivalence = &self.equivalence;
+        let record = self.map.get_live_by(
+            |k| equivalence.equivalent(k.borrow(), key),
+            |record| record.expires > now,
+        )?;
+        Some((&record.value, record.expires - now))
+    }
+
+    // Returns the TTL left to the record stored under `key`, `None` if there is none or it
+    // expired, without changing the recency order or the stats
+    pub fn remaining_ttl<Q>(&self, key: &Q) -> Option<u64>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        E: Equivalence<Q>,
+    {
+        let now = self.clock.now();
+        let record = self
+            .map
+            .peek_by(|k| self.equivalence.equivalent(k.borrow(), key))?;
+        (record.expires > now).then(|| record.expires - now)
+    }
+
+    // Remove the record stored under `key`, e.g. on a NOTIFY, returning it even if it expired
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        E: Equivalence<Q>,
+    {
+        let equivalence = &self.equivalence;
+        self.map
+            .remove_by(|k| equivalence.equivalent(k.borrow(), key))
+            .map(|(_, record)| record.value)
+    }
+
+    // Returns the configured TTL bounds, minimum first
+    pub fn ttl_bounds(&self) -> (u64, u64) {
+        (self.min_ttl, self.max_ttl)
+    }
+
+    // Returns the number of records in the cache, expired ones included
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    // Returns if cache is empty or not
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    // Clears all the records in cache
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+
+    // Returns the usage counters collected so far
+    pub fn stats(&self) -> &CacheStats {
+        self.map.stats()
+    }
+
+    // Returns the clock the cache reads the time from
+    pub fn clock(&self) -> &C {
+        &self.clock
+    }
+}
@@ -0,0 +1,176 @@
+//! Keys that are equal by a rule of the caller's choosing.
+//!
+//! Some keys have more than one spelling: host names compare without regard to ASCII case, and
+//! `example.com.` is `example.com`. An `Equivalence` states the rule once, and the keyed types
+//! taking one (`EquivMap`, `DnsCache`) apply it to every lookup, so callers need not normalize
+//! keys at every call site. A closure `Fn(&Q, &Q) -> bool` is an equivalence too.
+//!
+//! The rule must be an equivalence relation (reflexive, symmetric, transitive), or a key may be
+//! stored twice under two of its spellings.
+
+use core::borrow::Borrow;
+
+use crate::{map::LRUMap, CacheStats, Listener};
+
+// Decides whether two keys are the same key
+pub trait Equivalence<Q: ?Sized> {
+    fn equivalent(&self, a: &Q, b: &Q) -> bool;
+}
+
+// The `Eq` equality of the keys
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Exact;
+
+impl<Q: Eq + ?Sized> Equivalence<Q> for Exact {
+    #[inline]
+    fn equivalent(&self, a: &Q, b: &Q) -> bool {
+        a == b
+    }
+}
+
+// Equality of the bytes of the keys, ignoring ASCII case
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AsciiCaseInsensitive;
+
+impl<Q: AsRef<[u8]> + ?Sized> Equivalence<Q> for AsciiCaseInsensitive {
+    #[inline]
+    fn equivalent(&self, a: &Q, b: &Q) -> bool {
+        a.as_ref().eq_ignore_ascii_case(b.as_ref())
+    }
+}
+
+// Equality of domain names: ASCII case is ignored, and so is a trailing dot
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DomainName;
+
+impl<Q: AsRef<[u8]> + ?Sized> Equivalence<Q> for DomainName {
+    #[inline]
+    fn equivalent(&self, a: &Q, b: &Q) -> bool {
+        without_root(a.as_ref()).eq_ignore_ascii_case(without_root(b.as_ref()))
+    }
+}
+
+// A name with the dot standing for the root label, if any, taken off
+#[inline]
+fn without_root(name: &[u8]) -> &[u8] {
+    name.strip_suffix(b".").unwrap_or(name)
+}
+
+impl<Q: ?Sized, F: Fn(&Q, &Q) -> bool> Equivalence<Q> for F {
+    #[inline]
+    fn equivalent(&self, a: &Q, b: &Q) -> bool {
+        self(a, b)
+    }
+}
+
+// An LRU map of capacity `N` from keys `K` to values `V`, matching keys by `E`
+#[derive(Debug, Clone)]
+pub struct EquivMap<K, V, E, const N: usize, L = ()> {
+    map: LRUMap<K, V, N, L>,
+    equivalence: E,
+}
+
+impl<K, V, E, const N: usize> EquivMap<K, V, E, N> {
+    // create a empty map matching keys by `equivalence`
+    pub const fn new(equivalence: E) -> Self {
+        Self::with_listener(equivalence, ())
+    }
+}
+
+impl<K, V, E, L: Listener<V>, const N: usize> EquivMap<K, V, E, N, L> {
+    // create a empty map matching keys by `equivalence` and reporting its events to `listener`
+    pub const fn with_listener(equivalence: E, listener: L) -> Self {
+        EquivMap {
+            map: LRUMap::with_listener(listener),
+            equivalence,
+        }
+    }
+
+    // Returns the number of entries in the map
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    // Returns if map is empty or not
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    // Clears all the entries in map
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+
+    // Returns the usage counters collected so far
+    pub fn stats(&self) -> &CacheStats {
+        self.map.stats()
+    }
+
+    // Returns the rule keys are matched by
+    pub fn equivalence(&self) -> &E {
+        &self.equivalence
+    }
+}
+
+impl<K, V, E: Equivalence<K>, L: Listener<V>, const N: usize> EquivMap<K, V, E, N, L> {
+    // Insert `val` under `key`, replacing the entry of an equivalent key if there is one, see
+    // `LRUMap::insert`. The key is stored in the spelling given last.
+    pub fn insert(&mut self, key: K, val: V) -> Option<(K, V)> {
+        let found = self
+            .map
+            .position_by(|k| self.equivalence.equivalent(k, &key));
+        match found {
+            Some(i) => Some(self.map.overwrite(i, key, val)),
+            None => self.map.insert_new(key, val),
+        }
+    }
+}
+
+impl<K, V, E, L: Listener<V>, const N: usize> EquivMap<K, V, E, N, L> {
+    // Returns the value stored under a key equivalent to `key`, making it the most recently used
+    // entry
+    pub fn get<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        E: Equivalence<Q>,
+    {
+        let equivalence = &self.equivalence;
+        self.map.get_by(|k| equivalence.equivalent(k.borrow(), key))
+    }
+
+    // Returns the value stored under a key equivalent to `key` without changing the recency
+    // order or the stats
+    pub fn peek<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        E: Equivalence<Q>,
+    {
+        self.map
+            .peek_by(|k| self.equivalence.equivalent(k.borrow(), key))
+    }
+
+    // Returns true if a value is stored under a key equivalent to `key`, without changing the
+    // recency order
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        E: Equivalence<Q>,
+    {
+        self.peek(key).is_some()
+    }
+
+    // Remove the entry stored under a key equivalent to `key`, returning its key and value
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized,
+        E: Equivalence<Q>,
+    {
+        let equivalence = &self.equivalence;
+        self.map
+            .remove_by(|k| equivalence.equivalent(k.borrow(), key))
+    }
+}
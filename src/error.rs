@@ -0,0 +1,41 @@
+//! Errors returned by the fallible operations of the crate.
+
+use core::fmt;
+
+// Why an operation on a cache failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CacheError {
+    // More values were given than the cache has room for
+    CapacityExceeded { len: usize, capacity: usize },
+    // A value or key is bigger than the cache accepts, in bytes
+    TooLarge { size: usize, limit: usize },
+    // Saved cache contents could not be restored
+    SnapshotCorrupt,
+    // Saved cache contents do not match their checksum, e.g. after a torn write
+    SnapshotChecksum,
+    // Saved cache contents are in a layout that is not read, e.g. of an older version of the
+    // values
+    SnapshotIncompatible,
+}
+
+impl fmt::Display for CacheError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            CacheError::CapacityExceeded { len, capacity } => write!(
+                f,
+                "{len} values do not fit in a cache of capacity {capacity}"
+            ),
+            CacheError::TooLarge { size, limit } => {
+                write!(f, "{size} bytes is over the limit of {limit} bytes")
+            }
+            CacheError::SnapshotCorrupt => f.write_str("cache snapshot is corrupt"),
+            CacheError::SnapshotChecksum => f.write_str("cache snapshot fails its checksum"),
+            CacheError::SnapshotIncompatible => {
+                f.write_str("cache snapshot is in an incompatible format")
+            }
+        }
+    }
+}
+
+impl core::error::Error for CacheError {}
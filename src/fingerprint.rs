@@ -0,0 +1,171 @@
+//! A keyed LRU map that compares key hashes before keys.
+//!
+//! Scanning an `LRUMap` compares the key looked up with every stored key until one matches. For
+//! keys like strings each comparison chases a pointer and compares bytes. `FingerprintMap` keeps
+//! a 16-bit fingerprint of the hash of each key in a dense array of its own and scans that first,
+//! so a full key comparison only happens for the one stored key that matches, plus whichever keys
+//! collide on the fingerprint (one in 65536 on average).
+//!
+//! Keys are hashed with `FxHasher` unless another hasher is given. Keys chosen to collide only
+//! make lookups slower.
+
+use core::{
+    borrow::Borrow,
+    hash::{BuildHasher, Hash},
+};
+
+use crate::{hash::FxBuildHasher, map::LRUMap, CacheStats, Listener, RemovalCause};
+
+// An LRU map of capacity `N` from keys `K` to values `V`, scanning key fingerprints first
+#[derive(Debug, Clone)]
+pub struct FingerprintMap<K, V, const N: usize, L = (), S = FxBuildHasher> {
+    map: LRUMap<K, V, N, L>,
+    // Fingerprint of the key in each slot of `map`
+    fingerprints: [u16; N],
+    hasher: S,
+}
+
+impl<K, V, L: Listener<V> + Default, S: Default, const N: usize> Default
+    for FingerprintMap<K, V, N, L, S>
+{
+    fn default() -> Self {
+        Self::with_listener_and_hasher(L::default(), S::default())
+    }
+}
+
+impl<K, V, const N: usize> FingerprintMap<K, V, N> {
+    // create a empty map
+    pub const fn new() -> Self {
+        Self::with_listener(())
+    }
+}
+
+impl<K, V, S, const N: usize> FingerprintMap<K, V, N, (), S> {
+    // create a empty map hashing keys with `hasher`
+    pub const fn with_hasher(hasher: S) -> Self {
+        Self::with_listener_and_hasher((), hasher)
+    }
+}
+
+impl<K, V, L: Listener<V>, const N: usize> FingerprintMap<K, V, N, L> {
+    // create a empty map reporting its events to `listener`
+    pub const fn with_listener(listener: L) -> Self {
+        Self::with_listener_and_hasher(listener, FxBuildHasher)
+    }
+}
+
+impl<K, V, L: Listener<V>, S, const N: usize> FingerprintMap<K, V, N, L, S> {
+    // create a empty map reporting its events to `listener` and hashing keys with `hasher`
+    pub const fn with_listener_and_hasher(listener: L, hasher: S) -> Self {
+        FingerprintMap {
+            map: LRUMap::with_listener(listener),
+            fingerprints: [0; N],
+            hasher,
+        }
+    }
+
+    // Returns the number of entries in the map
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    // Returns if map is empty or not
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    // Clears all the entries in map
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+
+    // Returns the usage counters collected so far
+    pub fn stats(&self) -> &CacheStats {
+        self.map.stats()
+    }
+
+    // Returns the hasher keys are hashed with
+    pub fn hasher(&self) -> &S {
+        &self.hasher
+    }
+}
+
+impl<K: Hash + Eq, V, L: Listener<V>, S: BuildHasher, const N: usize>
+    FingerprintMap<K, V, N, L, S>
+{
+    // Insert `val` under `key`, see `LRUMap::insert`
+    pub fn insert(&mut self, key: K, val: V) -> Option<(K, V)> {
+        let fingerprint = self.fingerprint(&key);
+        if let Some(i) = self.index_of(&key, fingerprint) {
+            return Some(self.map.overwrite(i, key, val));
+        }
+        if N == 0 {
+            return Some((key, val));
+        }
+        let replaced = self.map.insert_new(key, val);
+        self.fingerprints[self.map.front_slot() as usize] = fingerprint;
+        replaced
+    }
+
+    // Returns the value stored under `key`, making it the most recently used entry
+    pub fn get<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let found = self.index_of(key, self.fingerprint(key));
+        self.map.lookup(found)
+    }
+
+    // Returns the value stored under `key` without changing the recency order or the stats
+    pub fn peek<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let i = self.index_of(key, self.fingerprint(key))?;
+        Some(self.map.value(i))
+    }
+
+    // Returns true if a value is stored under `key`, without changing the recency order
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.index_of(key, self.fingerprint(key)).is_some()
+    }
+
+    // Remove the entry stored under `key`, returning its value
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let i = self.index_of(key, self.fingerprint(key))?;
+        // The last slot is moved into the one removed, in both arrays
+        self.fingerprints[i as usize] = self.fingerprints[self.map.len() - 1];
+        Some(self.map.remove_index(i, RemovalCause::Explicit).1)
+    }
+
+    // The top bits of the hash of `key`, which multiplicative hashes leave best mixed
+    #[inline]
+    fn fingerprint<Q: Hash + ?Sized>(&self, key: &Q) -> u16 {
+        (self.hasher.hash_one(key) >> 48) as u16
+    }
+
+    // Slot holding `key`, comparing keys only where the fingerprint matches
+    #[inline]
+    fn index_of<Q>(&self, key: &Q, fingerprint: u16) -> Option<u16>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.fingerprints[..self.map.len()]
+            .iter()
+            .enumerate()
+            .filter(|&(_, &f)| f == fingerprint)
+            .map(|(i, _)| i as u16)
+            .find(|&i| self.map.key(i).borrow() == key)
+    }
+}
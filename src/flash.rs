@@ -0,0 +1,220 @@
+//! Keeping cache snapshots in NOR flash across resets.
+//!
+//! `FlashStore` owns two equal regions of a flash device and writes full snapshots to them in
+//! turn, so each region is erased every other save and a save torn by a reset leaves the previous
+//! snapshot in the other region intact. Each region starts with a small header, written only
+//! after the snapshot behind it, holding a sequence number and the snapshot length. On boot
+//! `load` restores the newest snapshot that reads back, falling back to the older one.
+//!
+//! Devices are reached through `NorFlash`, which has the shape of the trait of the same name in
+//! `embedded-storage`: a HAL implementing that one implements this one with a few lines
+//! forwarding to it.
+
+use core::fmt;
+
+use crate::{snapshot::Plain, CacheError, LRUCache, Listener};
+
+// A NOR flash device: bits are cleared by writes and set again, a block at a time, by erases
+pub trait NorFlash {
+    type Error;
+
+    // Alignment and granularity of reads, in bytes
+    const READ_SIZE: usize;
+    // Alignment and granularity of writes, in bytes
+    const WRITE_SIZE: usize;
+    // Size of the blocks erased at once, in bytes
+    const ERASE_SIZE: usize;
+
+    // Read `bytes.len()` bytes starting at `offset`
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error>;
+    // Size of the device, in bytes
+    fn capacity(&self) -> usize;
+    // Erase the blocks from `from` to before `to`
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error>;
+    // Write `bytes` starting at `offset`, which must be erased
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error>;
+}
+
+// Why saving or loading a snapshot failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StoreError<E> {
+    // The device reported an error
+    Flash(E),
+    // The snapshot could not be written or restored, e.g. it does not fit in a region
+    Cache(CacheError),
+}
+
+impl<E: fmt::Display> fmt::Display for StoreError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreError::Flash(err) => write!(f, "flash error: {err}"),
+            StoreError::Cache(err) => err.fmt(f),
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> core::error::Error for StoreError<E> {}
+
+impl<E> From<CacheError> for StoreError<E> {
+    fn from(err: CacheError) -> Self {
+        StoreError::Cache(err)
+    }
+}
+
+// Sequence number and snapshot length (u32 each) at the start of a region
+const REGION_HEADER: usize = 8;
+// Sequence number of a region that was erased and not written yet
+const ERASED: u32 = u32::MAX;
+
+// Two regions of `region_size` bytes of `flash`, from `offset` on, holding cache snapshots
+#[derive(Debug)]
+pub struct FlashStore<F> {
+    flash: F,
+    offset: u32,
+    region_size: u32,
+}
+
+impl<F: NorFlash> FlashStore<F> {
+    // Panics unless `offset` and `region_size` are multiples of the erase size and both regions
+    // fit in the device
+    pub fn new(flash: F, offset: u32, region_size: u32) -> Self {
+        let erase = F::ERASE_SIZE as u32;
+        assert!(
+            offset.is_multiple_of(erase) && region_size.is_multiple_of(erase),
+            "flash regions must be aligned to erase blocks"
+        );
+        assert!(
+            offset as usize + 2 * region_size as usize <= flash.capacity(),
+            "flash regions do not fit in the device"
+        );
+        FlashStore {
+            flash,
+            offset,
+            region_size,
+        }
+    }
+
+    // Returns the flash device, giving up the regions
+    pub fn into_inner(self) -> F {
+        self.flash
+    }
+
+    // Write a full snapshot of `cache` over the older of the two snapshots, using `buf` as
+    // scratch space. `buf` must hold `LRUCache::FULL_SNAPSHOT_SIZE` bytes rounded up to the
+    // flash alignment.
+    pub fn save<T: Plain, L: Listener<T>, const N: usize>(
+        &mut self,
+        cache: &LRUCache<T, N, L>,
+        buf: &mut [u8],
+    ) -> Result<(), StoreError<F::Error>> {
+        let headers = [self.read_header(0, buf)?, self.read_header(1, buf)?];
+        // The new snapshot is numbered after the newest. The count would only get to the value
+        // of erased flash long after the blocks wore out.
+        let (region, seq) = match headers {
+            [Some((a, _)), Some((b, _))] if a > b => (1, a + 1),
+            [Some(_), Some((b, _))] => (0, b + 1),
+            [Some((a, _)), None] => (1, a + 1),
+            [None, Some((b, _))] => (0, b + 1),
+            [None, None] => (0, 0),
+        };
+
+        let len = cache.as_full_bytes_snapshot(buf)?;
+        let padded = aligned::<F>(len);
+        let size = padded + header_size::<F>();
+        if padded > buf.len() || size > self.region_size as usize {
+            return Err(CacheError::TooLarge {
+                size,
+                limit: buf.len().min(self.region_size as usize),
+            }
+            .into());
+        }
+        buf[len..padded].fill(0xff);
+
+        let start = self.region_start(region);
+        self.flash
+            .erase(start, start + self.region_size)
+            .map_err(StoreError::Flash)?;
+        self.flash
+            .write(start + header_size::<F>() as u32, &buf[..padded])
+            .map_err(StoreError::Flash)?;
+        // The header goes last: until it is written the region reads as erased
+        let header = &mut buf[..header_size::<F>()];
+        header.fill(0xff);
+        header[..4].copy_from_slice(&seq.to_le_bytes());
+        header[4..8].copy_from_slice(&(len as u32).to_le_bytes());
+        self.flash.write(start, header).map_err(StoreError::Flash)
+    }
+
+    // Restore the newest snapshot that reads back, using `buf` as scratch space like `save`.
+    // Returns `None` if neither region holds one. A snapshot for another cache type fails with
+    // `SnapshotIncompatible` rather than falling back to an older one.
+    pub fn load<T: Plain, const N: usize>(
+        &mut self,
+        buf: &mut [u8],
+    ) -> Result<Option<LRUCache<T, N>>, StoreError<F::Error>> {
+        let headers = [self.read_header(0, buf)?, self.read_header(1, buf)?];
+        let mut regions = [(0, headers[0]), (1, headers[1])];
+        if let [(_, Some((a, _))), (_, Some((b, _)))] = regions {
+            if b > a {
+                regions.swap(0, 1);
+            }
+        }
+        for (region, header) in regions {
+            let Some((_, len)) = header else {
+                continue;
+            };
+            let padded = aligned::<F>(len);
+            if padded > buf.len() || padded + header_size::<F>() > self.region_size as usize {
+                continue;
+            }
+            let start = self.region_start(region) + header_size::<F>() as u32;
+            self.flash
+                .read(start, &mut buf[..padded])
+                .map_err(StoreError::Flash)?;
+            match LRUCache::from_bytes(&buf[..len]) {
+                Ok(cache) => return Ok(Some(cache)),
+                Err(CacheError::SnapshotCorrupt | CacheError::SnapshotChecksum) => continue,
+                Err(err) => return Err(err.into()),
+            }
+        }
+        Ok(None)
+    }
+
+    // Sequence number and snapshot length in the header of `region`, `None` if it is erased
+    fn read_header(
+        &mut self,
+        region: u32,
+        buf: &mut [u8],
+    ) -> Result<Option<(u32, usize)>, StoreError<F::Error>> {
+        let size = header_size::<F>();
+        if buf.len() < size {
+            return Err(CacheError::TooLarge {
+                size,
+                limit: buf.len(),
+            }
+            .into());
+        }
+        let start = self.region_start(region);
+        self.flash
+            .read(start, &mut buf[..size])
+            .map_err(StoreError::Flash)?;
+        let seq = u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]);
+        let len = u32::from_le_bytes([buf[4], buf[5], buf[6], buf[7]]);
+        Ok((seq != ERASED).then_some((seq, len as usize)))
+    }
+
+    fn region_start(&self, region: u32) -> u32 {
+        self.offset + region * self.region_size
+    }
+}
+
+// Bytes taken by the header of a region
+fn header_size<F: NorFlash>() -> usize {
+    aligned::<F>(REGION_HEADER)
+}
+
+// `len` rounded up to the read and write granularity of `F`
+fn aligned<F: NorFlash>(len: usize) -> usize {
+    let to = F::READ_SIZE.max(F::WRITE_SIZE);
+    len.div_ceil(to) * to
+}
@@ -0,0 +1,215 @@
+//! GreedyDual-Size, a cost-aware replacement policy.
+//!
+//! When misses have very different penalties, e.g. a network fetch next to a local recompute,
+//! the least recently used entry is not always the one to lose. `GdsCache` gives every entry a
+//! credit of `L + cost / size` when it is inserted or hit, where `L` is an inflation value, and
+//! evicts the entry with the lowest credit, the least recently used one among equals. `L` then
+//! rises to the credit of the entry evicted, so entries that are not hit lose ground to fresher
+//! ones over time whatever their cost, and cheap, large entries go first.
+//!
+//! With the same cost and size for every entry all credits tie and this is plain LRU. Credits are
+//! kept in 16.16 fixed point next to the entries, like the tags of a `TaggedCache`, and finding
+//! the entry to evict scans them all.
+
+use arrayvec::ArrayVec;
+
+use crate::{CacheStats, Event, LRUCache, Listener, RemovalCause};
+
+// An LRU cache of capacity `N` of values `T` weighted by their miss cost and size
+#[derive(Debug, Clone)]
+pub struct GdsCache<T, const N: usize, L = ()> {
+    cache: LRUCache<T, N, L>,
+    // Cost, size and credit of each slot of `cache`
+    credits: ArrayVec<Credit, N>,
+    // Credit of the last entry evicted, the `L` of the module docs
+    inflation: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Credit {
+    // Value of `cost / size` in 16.16 fixed point
+    ratio: u64,
+    credit: u64,
+}
+
+impl Credit {
+    fn new(cost: u32, size: u32) -> Self {
+        Credit {
+            ratio: ((cost as u64) << 16) / size.max(1) as u64,
+            credit: 0,
+        }
+    }
+
+    // Renew the credit after an insert or hit. It saturates at `u64::MAX`, past which cost is
+    // ignored and the order is LRU.
+    fn renew(&mut self, inflation: u64) {
+        self.credit = inflation.saturating_add(self.ratio);
+    }
+}
+
+impl<T, L: Listener<T> + Default, const N: usize> Default for GdsCache<T, N, L> {
+    fn default() -> Self {
+        Self::with_listener(L::default())
+    }
+}
+
+impl<T, const N: usize> GdsCache<T, N> {
+    // create a empty cache
+    pub const fn new() -> Self {
+        Self::with_listener(())
+    }
+}
+
+impl<T, L: Listener<T>, const N: usize> GdsCache<T, N, L> {
+    // create a empty cache reporting its events to `listener`
+    pub const fn with_listener(listener: L) -> Self {
+        GdsCache {
+            cache: LRUCache::with_listener(listener),
+            credits: ArrayVec::new_const(),
+            inflation: 0,
+        }
+    }
+
+    // Insert `val`, whose miss costs `cost` in any unit and which takes `size` in any other, 0
+    // counting as 1. If the cache is full the entry with the lowest credit is evicted first and
+    // returned, or `val` for a cache of capacity 0.
+    pub fn insert(&mut self, val: T, cost: u32, size: u32) -> Option<T> {
+        let evicted = match self.victim() {
+            Some(i) if self.len() == N => {
+                self.inflation = self.credits[i as usize].credit;
+                self.credits.swap_remove(i as usize);
+                Some(self.cache.evict_index(i))
+            }
+            _ => None,
+        };
+        if let Some(val) = self.cache.insert(val) {
+            // Only a cache of capacity 0 hands the value back
+            return Some(val);
+        }
+        let mut credit = Credit::new(cost, size);
+        credit.renew(self.inflation);
+        self.credits.push(credit);
+        evicted
+    }
+
+    // Returns the first item in the cache that matches the predicate
+    // Renews its credit and makes it most recently used on hit
+    pub fn find<F>(&mut self, pred: F) -> Option<&mut T>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let i = self.lookup(pred)?;
+        Some(&mut self.cache.entry(i).val)
+    }
+
+    // Touch the first item in the cache that matches the given predicate, renewing its credit,
+    // Returns true or false
+    pub fn touch<F>(&mut self, pred: F) -> bool
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.lookup(pred).is_some()
+    }
+
+    // Returns the credit of the first item in recency order that matches the predicate, in 16.16
+    // fixed point, without touching it
+    pub fn credit_of<F>(&self, mut pred: F) -> Option<u64>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.entries()
+            .find(|(val, _)| pred(val))
+            .map(|(_, credit)| credit)
+    }
+
+    // Returns the credit of the last entry evicted, which new entries start from, in 16.16 fixed
+    // point
+    pub fn inflation(&self) -> u64 {
+        self.inflation
+    }
+
+    // Returns the value the next eviction would take, without touching it
+    pub fn peek_victim(&self) -> Option<&T> {
+        let i = self.victim()?;
+        Some(&self.cache.entries[i as usize].val)
+    }
+
+    // Removes the value the next eviction would take and returns it, without raising the
+    // inflation
+    pub fn pop_victim(&mut self) -> Option<T> {
+        let i = self.victim()?;
+        self.cache.listener.on_event(Event::Evict(
+            &self.cache.entries[i as usize].val,
+            RemovalCause::Explicit,
+        ));
+        self.credits.swap_remove(i as usize);
+        Some(self.cache.remove_index(i))
+    }
+
+    // Returns the values with their credits from most to least recently used, without changing
+    // the recency order or the stats
+    pub fn entries(&self) -> impl ExactSizeIterator<Item = (&T, u64)> + '_ {
+        let mut i = self.cache.head();
+        (0..self.len()).map(move |_| {
+            let slot = i;
+            i = self.cache.entries[slot as usize].link.next();
+            (
+                &self.cache.entries[slot as usize].val,
+                self.credits[slot as usize].credit,
+            )
+        })
+    }
+
+    // Returns the number of elements in the cache
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    // Returns if cache is empty or not
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+
+    // Clears all the elements in cache, and the inflation with them
+    pub fn clear(&mut self) {
+        self.credits.clear();
+        self.inflation = 0;
+        self.cache.clear();
+    }
+
+    // Returns the usage counters collected so far
+    pub fn stats(&self) -> &CacheStats {
+        self.cache.stats()
+    }
+
+    // Returns the listener
+    pub fn listener(&self) -> &L {
+        self.cache.listener()
+    }
+
+    // Count a lookup and renew the credit of the entry it found
+    fn lookup<F>(&mut self, pred: F) -> Option<u16>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let i = self.cache.lookup(pred)?;
+        self.credits[i as usize].renew(self.inflation);
+        Some(i)
+    }
+
+    // Slot of the entry with the lowest credit, the least recently used one among equals
+    fn victim(&self) -> Option<u16> {
+        if self.is_empty() {
+            return None;
+        }
+        let mut i = self.cache.tail();
+        let mut victim = i;
+        for _ in 1..self.len() {
+            i = self.cache.entries[i as usize].link.prev();
+            if self.credits[i as usize].credit < self.credits[victim as usize].credit {
+                victim = i;
+            }
+        }
+        Some(victim)
+    }
+}
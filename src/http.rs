@@ -0,0 +1,232 @@
+//! A cache of HTTP responses following their freshness metadata.
+//!
+//! `HttpCache` stores responses by method and URL, next to the freshness the server gave them,
+//! so a client only has to hand over the `Cache-Control` and `Age` of each response and ask for
+//! a `Lookup` before sending a request. The freshness math follows RFC 9111 for a private cache,
+//! with the `stale-while-revalidate` extension of RFC 5861:
+//!
+//! - the age of a response is its `Age` when stored plus the time spent in the cache
+//! - it is fresh while its age is below `max-age`, and never without one
+//! - past that it may still be served for `stale-while-revalidate` seconds while the client
+//!   revalidates it in the background, unless it came with `must-revalidate`
+//! - `no-cache` responses are stored but always need revalidating, `no-store` ones are refused
+//!
+//! The cache does not parse responses or talk to the network: what the response type `V` holds
+//! (body, headers, validators for a conditional request) is up to the client. Times are read in
+//! milliseconds, from a clock like `InstantClock`.
+
+use std::string::String;
+
+use crate::{ttl::Clock, CacheStats, LRUCache};
+
+// The directives of a `Cache-Control` header that decide how long a response may be used
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheControl {
+    // `max-age`, in seconds
+    pub max_age: Option<u64>,
+    // `stale-while-revalidate`, in seconds
+    pub stale_while_revalidate: u64,
+    pub no_store: bool,
+    pub no_cache: bool,
+    pub must_revalidate: bool,
+}
+
+impl CacheControl {
+    // Read the directives from the value of a `Cache-Control` header. Unknown or malformed
+    // directives are ignored, as RFC 9111 asks.
+    pub fn parse(header: &str) -> Self {
+        let mut cc = CacheControl::default();
+        for directive in header.split(',') {
+            let (name, arg) = match directive.split_once('=') {
+                Some((name, arg)) => (name.trim(), Some(arg.trim().trim_matches('"'))),
+                None => (directive.trim(), None),
+            };
+            let seconds = arg.and_then(|arg| arg.parse::<u64>().ok());
+            if name.eq_ignore_ascii_case("max-age") {
+                cc.max_age = seconds.or(cc.max_age);
+            } else if name.eq_ignore_ascii_case("stale-while-revalidate") {
+                cc.stale_while_revalidate = seconds.unwrap_or(cc.stale_while_revalidate);
+            } else if name.eq_ignore_ascii_case("no-store") {
+                cc.no_store = true;
+            } else if name.eq_ignore_ascii_case("no-cache") {
+                cc.no_cache = true;
+            } else if name.eq_ignore_ascii_case("must-revalidate") {
+                cc.must_revalidate = true;
+            }
+        }
+        cc
+    }
+}
+
+// What the cache holds for a request
+#[derive(Debug, PartialEq, Eq)]
+pub enum Lookup<'a, V> {
+    // A fresh response, to be used as is
+    Fresh(&'a V),
+    // A stale response within its `stale-while-revalidate` window, to be used while it is
+    // revalidated in the background. `revalidate` is only true for the first lookup that found
+    // it stale, so that one client revalidates it instead of all of them.
+    Stale { response: &'a V, revalidate: bool },
+    // A response that must be revalidated before it is used, e.g. with a conditional request
+    Revalidate(&'a V),
+    // Nothing is stored for the request
+    Miss,
+}
+
+// A cache of capacity `N` of HTTP responses `V`
+#[derive(Debug, Clone)]
+pub struct HttpCache<V, C, const N: usize> {
+    cache: LRUCache<Stored<V>, N>,
+    clock: C,
+}
+
+#[derive(Debug, Clone)]
+struct Stored<V> {
+    method: String,
+    url: String,
+    response: V,
+    // Time the response was stored or last revalidated at, and the age it had then
+    stored_at: u64,
+    initial_age: u64,
+    // How long it is fresh, and served stale after that, in milliseconds
+    lifetime: u64,
+    stale_window: u64,
+    no_cache: bool,
+    // Whether a lookup already asked for a background revalidation
+    revalidating: bool,
+}
+
+impl<V> Stored<V> {
+    fn is(&self, method: &str, url: &str) -> bool {
+        self.method == method && self.url == url
+    }
+
+    fn refresh(&mut self, now: u64, cc: &CacheControl, age: u64) {
+        self.stored_at = now;
+        self.initial_age = age.saturating_mul(1000);
+        self.lifetime = cc.max_age.unwrap_or(0).saturating_mul(1000);
+        self.stale_window = if cc.must_revalidate {
+            0
+        } else {
+            cc.stale_while_revalidate.saturating_mul(1000)
+        };
+        self.no_cache = cc.no_cache;
+        self.revalidating = false;
+    }
+}
+
+impl<V, C: Clock, const N: usize> HttpCache<V, C, N> {
+    // create a empty cache reading the time from `clock`, in milliseconds
+    pub fn new(clock: C) -> Self {
+        HttpCache {
+            cache: LRUCache::new(),
+            clock,
+        }
+    }
+
+    // Store `response` for `method` and `url`, given the `Cache-Control` it came with and its
+    // `Age` in seconds (0 without the header). Returns the response it replaced or evicted, or
+    // gives `response` back if `no-store` forbids storing it.
+    pub fn insert(
+        &mut self,
+        method: &str,
+        url: &str,
+        response: V,
+        cache_control: &CacheControl,
+        age: u64,
+    ) -> Result<Option<V>, V> {
+        if cache_control.no_store {
+            return Err(response);
+        }
+        let mut stored = Stored {
+            method: method.into(),
+            url: url.into(),
+            response,
+            stored_at: 0,
+            initial_age: 0,
+            lifetime: 0,
+            stale_window: 0,
+            no_cache: false,
+            revalidating: false,
+        };
+        stored.refresh(self.clock.now(), cache_control, age);
+
+        let old = match self.cache.position(|s| s.is(method, url)) {
+            Some(i) => Some(self.cache.overwrite(i, stored)),
+            None => self.cache.insert(stored),
+        };
+        Ok(old.map(|old| old.response))
+    }
+
+    // Returns what the cache holds for `method` and `url`, making it the most recently used
+    // entry
+    pub fn get(&mut self, method: &str, url: &str) -> Lookup<'_, V> {
+        let now = self.clock.now();
+        let Some(stored) = self.cache.find(|s| s.is(method, url)) else {
+            return Lookup::Miss;
+        };
+        let age = stored
+            .initial_age
+            .saturating_add(now.saturating_sub(stored.stored_at));
+
+        if stored.no_cache {
+            Lookup::Revalidate(&stored.response)
+        } else if age < stored.lifetime {
+            Lookup::Fresh(&stored.response)
+        } else if age < stored.lifetime.saturating_add(stored.stale_window) {
+            let revalidate = !stored.revalidating;
+            stored.revalidating = true;
+            Lookup::Stale {
+                response: &stored.response,
+                revalidate,
+            }
+        } else {
+            Lookup::Revalidate(&stored.response)
+        }
+    }
+
+    // Record that the response stored for `method` and `url` was revalidated, e.g. by a
+    // `304 Not Modified`, with the `Cache-Control` and `Age` of that answer. Returns false if
+    // nothing is stored for the request.
+    pub fn revalidated(
+        &mut self,
+        method: &str,
+        url: &str,
+        cache_control: &CacheControl,
+        age: u64,
+    ) -> bool {
+        let now = self.clock.now();
+        match self.cache.find(|s| s.is(method, url)) {
+            Some(stored) => {
+                stored.refresh(now, cache_control, age);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Returns the number of responses in the cache
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    // Returns if cache is empty or not
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+
+    // Clears all the responses in cache
+    pub fn clear(&mut self) {
+        self.cache.clear();
+    }
+
+    // Returns the usage counters collected so far
+    pub fn stats(&self) -> &CacheStats {
+        self.cache.stats()
+    }
+
+    // Returns the clock the cache reads the time from
+    pub fn clock(&self) -> &C {
+        &self.clock
+    }
+}
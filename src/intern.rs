@@ -0,0 +1,114 @@
+//! Interning of recently seen strings.
+//!
+//! `Interner` keeps up to `N` strings of up to `M` bytes each inline, in an `LRUMap`, and hands
+//! out a small `Handle` for each. Interning a string seen recently returns the handle it already
+//! has, so a parser or logger fed the same identifiers over and over compares and stores handles
+//! instead of strings. Strings that stop coming up are evicted to make room for new ones.
+//!
+//! A handle carries the generation of its slot, bumped whenever the slot is given to another
+//! string, so resolving the handle of an evicted string returns `None` rather than the string
+//! that replaced it.
+
+use arrayvec::ArrayString;
+
+use crate::{map::LRUMap, CacheError, CacheStats};
+
+// Stands for an interned string, see `Interner::resolve`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Handle {
+    slot: u16,
+    generation: u32,
+}
+
+// An interner of up to `N` strings of up to `M` bytes
+#[derive(Debug, Clone)]
+pub struct Interner<const N: usize, const M: usize> {
+    map: LRUMap<ArrayString<M>, (), N>,
+    // Generation of the string in each slot
+    generations: [u32; N],
+}
+
+impl<const N: usize, const M: usize> Default for Interner<N, M> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize, const M: usize> Interner<N, M> {
+    // create a empty interner
+    pub const fn new() -> Self {
+        Interner {
+            map: LRUMap::new(),
+            generations: [0; N],
+        }
+    }
+
+    // Returns the handle of `s`, interning it if it is not already, which may evict the least
+    // recently interned string. Fails for strings longer than `M` bytes.
+    pub fn intern(&mut self, s: &str) -> Result<Handle, CacheError> {
+        if let Some(slot) = self.map.get_slot(s) {
+            return Ok(self.handle(slot));
+        }
+        let key = ArrayString::from(s).map_err(|_| CacheError::TooLarge {
+            size: s.len(),
+            limit: M,
+        })?;
+        if N == 0 {
+            return Err(CacheError::CapacityExceeded {
+                len: 1,
+                capacity: 0,
+            });
+        }
+        let evicted = self.map.insert(key, ());
+        let slot = self.map.front_slot();
+        if evicted.is_some() {
+            let generation = &mut self.generations[slot as usize];
+            *generation = generation.wrapping_add(1);
+        }
+        Ok(self.handle(slot))
+    }
+
+    // Returns the handle of `s` if it is interned, making it the most recently used
+    pub fn get(&mut self, s: &str) -> Option<Handle> {
+        let slot = self.map.get_slot(s)?;
+        Some(self.handle(slot))
+    }
+
+    // Returns the string `handle` stands for, `None` if it was evicted since. Resolving does not
+    // count as a use of the string.
+    pub fn resolve(&self, handle: Handle) -> Option<&str> {
+        let live = (handle.slot as usize) < self.map.len()
+            && self.generations[handle.slot as usize] == handle.generation;
+        live.then(|| self.map.key(handle.slot).as_str())
+    }
+
+    // Returns the number of interned strings
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    // Returns if interner is empty or not
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    // Forgets all the interned strings, making every handle handed out so far stale
+    pub fn clear(&mut self) {
+        for generation in &mut self.generations[..self.map.len()] {
+            *generation = generation.wrapping_add(1);
+        }
+        self.map.clear();
+    }
+
+    // Returns the usage counters collected so far, a hit being a string that was already interned
+    pub fn stats(&self) -> &CacheStats {
+        self.map.stats()
+    }
+
+    fn handle(&self, slot: u16) -> Handle {
+        Handle {
+            slot,
+            generation: self.generations[slot as usize],
+        }
+    }
+}
@@ -0,0 +1,205 @@
+//! A keyed LRU map for integer keys with O(1) lookups.
+//!
+//! `IntLruMap` is an `LRUMap` plus a small open-addressed table of `2N` buckets, each holding
+//! the slot of one key, so finding a key takes a hash and a probe or two instead of a scan. The
+//! table is kept exact on every insert, eviction and removal (linear probing with backward-shift
+//! deletion, so there are no tombstones), which makes misses as cheap as hits. Being at most
+//! half full, its probe sequences stay short.
+//!
+//! The table costs 4 bytes per entry and needs no hash map or allocator. Keys are hashed with
+//! `FxHasher` unless another hasher is given; the table only uses the top 32 bits of the hash.
+
+use core::hash::{BuildHasher, Hash};
+
+use crate::{hash::FxBuildHasher, map::LRUMap, scan::ScanKey, CacheStats, Listener, RemovalCause};
+
+// An integer key of an `IntLruMap`
+pub trait IntKey: ScanKey + Hash {}
+
+impl IntKey for u32 {}
+impl IntKey for u64 {}
+impl IntKey for usize {}
+
+// An LRU map of capacity `N` from integer keys `K` to values `V`
+#[derive(Debug, Clone)]
+pub struct IntLruMap<K, V, const N: usize, L = (), S = FxBuildHasher> {
+    map: LRUMap<K, V, N, L>,
+    // The `2N` buckets of the table, each holding a slot of `map` plus one, 0 if empty
+    buckets: [[u16; 2]; N],
+    hasher: S,
+}
+
+impl<K, V, L: Listener<V> + Default, S: Default, const N: usize> Default
+    for IntLruMap<K, V, N, L, S>
+{
+    fn default() -> Self {
+        Self::with_listener_and_hasher(L::default(), S::default())
+    }
+}
+
+impl<K, V, const N: usize> IntLruMap<K, V, N> {
+    // create a empty map
+    pub const fn new() -> Self {
+        Self::with_listener(())
+    }
+}
+
+impl<K, V, S, const N: usize> IntLruMap<K, V, N, (), S> {
+    // create a empty map hashing keys with `hasher`
+    pub const fn with_hasher(hasher: S) -> Self {
+        Self::with_listener_and_hasher((), hasher)
+    }
+}
+
+impl<K, V, L: Listener<V>, const N: usize> IntLruMap<K, V, N, L> {
+    // create a empty map reporting its events to `listener`
+    pub const fn with_listener(listener: L) -> Self {
+        Self::with_listener_and_hasher(listener, FxBuildHasher)
+    }
+}
+
+impl<K, V, L: Listener<V>, S, const N: usize> IntLruMap<K, V, N, L, S> {
+    // create a empty map reporting its events to `listener` and hashing keys with `hasher`
+    pub const fn with_listener_and_hasher(listener: L, hasher: S) -> Self {
+        IntLruMap {
+            map: LRUMap::with_listener(listener),
+            buckets: [[0; 2]; N],
+            hasher,
+        }
+    }
+
+    // Returns the hasher keys are hashed with
+    pub fn hasher(&self) -> &S {
+        &self.hasher
+    }
+}
+
+impl<K: IntKey, V, L: Listener<V>, S: BuildHasher, const N: usize> IntLruMap<K, V, N, L, S> {
+    // Insert `val` under `key`, see `LRUMap::insert`
+    pub fn insert(&mut self, key: K, val: V) -> Option<(K, V)> {
+        if N == 0 {
+            return Some((key, val));
+        }
+        if let Some((_, i)) = self.find(key) {
+            return Some(self.map.overwrite(i, key, val));
+        }
+        if self.map.len() == N {
+            let tail = self.map.back_slot();
+            let (b, _) = self
+                .find(*self.map.key(tail))
+                .expect("every key is in the table");
+            self.unlink(b);
+        }
+        let replaced = self.map.insert_new(key, val);
+        self.link(key, self.map.front_slot());
+        replaced
+    }
+
+    // Returns the value stored under `key`, making it the most recently used entry
+    pub fn get(&mut self, key: K) -> Option<&mut V> {
+        let found = self.find(key).map(|(_, i)| i);
+        self.map.lookup(found)
+    }
+
+    // Returns the value stored under `key` without changing the recency order or the stats
+    pub fn peek(&self, key: K) -> Option<&V> {
+        let (_, i) = self.find(key)?;
+        Some(self.map.value(i))
+    }
+
+    // Returns true if a value is stored under `key`, without changing the recency order
+    pub fn contains_key(&self, key: K) -> bool {
+        self.find(key).is_some()
+    }
+
+    // Remove the entry stored under `key`, returning its value
+    pub fn remove(&mut self, key: K) -> Option<V> {
+        let (b, i) = self.find(key)?;
+        self.unlink(b);
+        // The last slot is moved into the one removed
+        let last = self.map.len() as u16 - 1;
+        if i != last {
+            let (b, _) = self
+                .find(*self.map.key(last))
+                .expect("every key is in the table");
+            self.buckets.as_flattened_mut()[b] = i + 1;
+        }
+        Some(self.map.remove_index(i, RemovalCause::Explicit).1)
+    }
+
+    // Returns the number of entries in the map
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    // Returns if map is empty or not
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    // Clears all the entries in map
+    pub fn clear(&mut self) {
+        self.buckets = [[0; 2]; N];
+        self.map.clear();
+    }
+
+    // Returns the usage counters collected so far
+    pub fn stats(&self) -> &CacheStats {
+        self.map.stats()
+    }
+
+    // Bucket a probe for `key` starts at
+    #[inline]
+    fn home(&self, key: K) -> usize {
+        let hash = self.hasher.hash_one(key) >> 32;
+        ((hash * (2 * N) as u64) >> 32) as usize
+    }
+
+    // Bucket and slot of `key`
+    #[inline]
+    fn find(&self, key: K) -> Option<(usize, u16)> {
+        if N == 0 {
+            return None;
+        }
+        let buckets = self.buckets.as_flattened();
+        let mut b = self.home(key);
+        loop {
+            let i = buckets[b].checked_sub(1)?;
+            if *self.map.key(i) == key {
+                return Some((b, i));
+            }
+            b = (b + 1) % buckets.len();
+        }
+    }
+
+    // Put slot `i`, holding `key`, in the table
+    fn link(&mut self, key: K, i: u16) {
+        let mut b = self.home(key);
+        let buckets = self.buckets.as_flattened_mut();
+        while buckets[b] != 0 {
+            b = (b + 1) % buckets.len();
+        }
+        buckets[b] = i + 1;
+    }
+
+    // Empty bucket `b`, shifting back the entries after it whose probe sequence went through it
+    fn unlink(&mut self, b: usize) {
+        let len = 2 * N;
+        let mut hole = b;
+        let mut j = b;
+        loop {
+            j = (j + 1) % len;
+            let slot = self.buckets.as_flattened()[j];
+            if slot == 0 {
+                break;
+            }
+            let home = self.home(*self.map.key(slot - 1));
+            // The entry may move to the hole unless its home lies between the hole and it
+            if (j + len - home) % len >= (j + len - hole) % len {
+                self.buckets.as_flattened_mut()[hole] = slot;
+                hole = j;
+            }
+        }
+        self.buckets.as_flattened_mut()[hole] = 0;
+    }
+}
@@ -0,0 +1,154 @@
+//! An LRU cache shared between interrupt handlers and thread mode on bare metal.
+//!
+//! `IrqCache` masks interrupts for the duration of each operation, and only that: the caller's
+//! own work before and after runs with interrupts enabled. The time interrupts stay masked is
+//! bounded by the operation:
+//!
+//! - `len`, `is_empty`, `stats`: constant, a few dozen instructions
+//! - `insert`: a few dozen instructions plus moving the value for a plain cache. A single
+//!   evicted value is handed back and dropped outside. Settings reached through `with` add to
+//!   that: a transient entry spent by a lookup is dropped inside, a probation window of `w`
+//!   walks `w` links, and with watermarks `(low, high)` a batch eviction drops up to
+//!   `high - low` values inside, so at most `N - low`, each reported to the listener first
+//! - `get`, `touch`: one predicate call per entry in the worst case, so up to `N` of them, and
+//!   for `touch` dropping the transient entry it consumes
+//! - `clear`: dropping every value
+//! - `with`: however long the closure runs
+//! - once every 2^27 inserts and lookups, the one it falls on also walks all `N` entries, see
+//!   `LRUCache::bump_epoch`
+//!
+//! Masking interrupts is target specific, so it is left to an `InterruptMask` implementation.
+//! On Cortex-M, with the `cortex-m` crate, it reads PRIMASK and disables interrupts in `mask`,
+//! and re-enables them in `unmask` only if PRIMASK said they were enabled before.
+
+// Sharing a `RefCell` with interrupt handlers rests on the guarantee of `InterruptMask`, which
+// the compiler cannot check
+#![allow(unsafe_code)]
+
+use core::cell::RefCell;
+
+use crate::{CacheStats, LRUCache, Listener};
+
+/// Turns interrupts off and back on
+///
+/// # Safety
+///
+/// Between `mask` and the matching `unmask`, no other code may run that could reach the cache:
+/// no interrupt handler, and no other core. Disabling interrupts gives that on a single-core
+/// microcontroller, not on a multi-core one.
+pub unsafe trait InterruptMask {
+    // Whatever `unmask` needs to restore the state `mask` found, e.g. whether interrupts were
+    // enabled
+    type State;
+
+    fn mask(&self) -> Self::State;
+    fn unmask(&self, state: Self::State);
+}
+
+// An LRU cache of capacity `N` shared with interrupt handlers, see the module docs
+#[derive(Debug)]
+pub struct IrqCache<T, M, const N: usize, L = ()> {
+    cache: RefCell<LRUCache<T, N, L>>,
+    mask: M,
+}
+
+// SAFETY: the cache is only reached while `mask` keeps every other context out, see
+// `InterruptMask`, so shared references never touch it concurrently. Values move between the
+// contexts, which requires `T: Send` like for a mutex.
+unsafe impl<T: Send, M: InterruptMask + Sync, L: Send, const N: usize> Sync
+    for IrqCache<T, M, N, L>
+{
+}
+
+impl<T, M: InterruptMask, const N: usize> IrqCache<T, M, N> {
+    // create a empty cache masking interrupts with `mask`
+    pub const fn new(mask: M) -> Self {
+        Self::with_listener(mask, ())
+    }
+}
+
+impl<T, M: InterruptMask, L: Listener<T>, const N: usize> IrqCache<T, M, N, L> {
+    // create a empty cache masking interrupts with `mask` and reporting its events to `listener`,
+    // which then runs with interrupts masked
+    pub const fn with_listener(mask: M, listener: L) -> Self {
+        IrqCache {
+            cache: RefCell::new(LRUCache::with_listener(listener)),
+            mask,
+        }
+    }
+
+    // Runs `f` on the cache with interrupts masked, so that several operations happen atomically.
+    // Calling the cache again from `f` panics.
+    pub fn with<R>(&self, f: impl FnOnce(&mut LRUCache<T, N, L>) -> R) -> R {
+        let masked = Masked::new(&self.mask);
+        // The borrow must end before interrupts are unmasked
+        let result = f(&mut self.cache.borrow_mut());
+        drop(masked);
+        result
+    }
+
+    // Insert given value in cache, see `LRUCache::insert`
+    pub fn insert(&self, val: T) -> Option<T> {
+        self.with(|cache| cache.insert(val))
+    }
+
+    // Returns a copy of the first item in the cache that matches the predicate
+    // Make it most recently used on hit
+    pub fn get<F>(&self, pred: F) -> Option<T>
+    where
+        F: FnMut(&T) -> bool,
+        T: Clone,
+    {
+        self.with(|cache| cache.find(pred).cloned())
+    }
+
+    // Touch the first item in the cache that matches the given predicate and marks it as recently
+    // used, Returns true or false
+    pub fn touch<F>(&self, pred: F) -> bool
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.with(|cache| cache.touch(pred))
+    }
+
+    // Returns the number of elements in the cache
+    pub fn len(&self) -> usize {
+        self.with(|cache| cache.len())
+    }
+
+    // Returns if cache is empty or not
+    pub fn is_empty(&self) -> bool {
+        self.with(|cache| cache.is_empty())
+    }
+
+    // Clears all the elements in cache
+    pub fn clear(&self) {
+        self.with(|cache| cache.clear())
+    }
+
+    // Returns a copy of the usage counters
+    pub fn stats(&self) -> CacheStats {
+        self.with(|cache| *cache.stats())
+    }
+}
+
+// Interrupts masked until dropped, also when unwinding out of a panicking closure
+struct Masked<'a, M: InterruptMask> {
+    mask: &'a M,
+    state: Option<M::State>,
+}
+
+impl<'a, M: InterruptMask> Masked<'a, M> {
+    fn new(mask: &'a M) -> Self {
+        let state = Some(mask.mask());
+        Masked { mask, state }
+    }
+}
+
+impl<M: InterruptMask> Drop for Masked<'_, M> {
+    fn drop(&mut self) {
+        if let Some(state) = self.state.take() {
+            self.mask.unmask(state);
+        }
+    }
+}
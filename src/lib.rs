@@ -0,0 +1,1309 @@
+//! A simple fast LRU cache. It will use fixed capacity array size It provides `O(1)` insertion, and `O(n)`
+//! lookup.  It does not require an allocator and can be used in `no_std` crates.
+
+// This will cause to not load the standard library which we try in bare metal environments, more
+// knowledge here https://docs.rust-embedded.org/book/intro/no-std.html
+#![no_std]
+#![deny(unsafe_code)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+#[cfg(feature = "async")]
+pub mod async_cache;
+pub mod batch;
+#[cfg(feature = "alloc")]
+pub mod boxed;
+pub mod builder;
+pub mod churn;
+#[cfg(feature = "std")]
+pub mod concurrent;
+pub mod cursor;
+pub mod digest;
+#[cfg(feature = "dns")]
+pub mod dns;
+pub mod equiv;
+mod error;
+pub mod fingerprint;
+#[cfg(feature = "flash")]
+pub mod flash;
+pub mod gds;
+pub mod hash;
+#[cfg(feature = "std")]
+pub mod http;
+pub mod intern;
+pub mod intmap;
+#[cfg(feature = "bare_metal")]
+pub mod irq;
+mod listener;
+pub mod map;
+pub mod mem;
+#[cfg(feature = "std")]
+pub mod metrics;
+pub mod minmax;
+#[cfg(feature = "ordered")]
+pub mod ordered;
+#[cfg(feature = "std")]
+pub mod percore;
+pub mod priority;
+#[cfg(any(kani, test))]
+mod proofs;
+pub mod scan;
+#[cfg(feature = "std")]
+pub mod sharded;
+pub mod smallkey;
+pub mod snapshot;
+mod stats;
+#[cfg(feature = "std")]
+pub mod sync;
+pub mod tagged;
+#[cfg(test)]
+mod tests;
+#[cfg(feature = "trace")]
+pub mod trace;
+pub mod ttl;
+pub mod versioned;
+pub mod wal;
+mod wheel;
+pub mod workload;
+
+pub use error::CacheError;
+pub use listener::{Event, Listener, RemovalCause};
+pub use stats::{CacheStats, HitWindow, LifetimeHistogram, StatsMode, LIFETIME_BUCKETS};
+
+use arrayvec::ArrayVec;
+use core::{fmt, mem::replace, num::NonZero}; // Replaces the previous with the new with its reference to the old memory
+
+#[derive(Debug, Clone)]
+
+pub struct LRUCache<T, const N: usize, L = ()> {
+    // Recent entry is at index head
+    entries: ArrayVec<Entry<T>, N>,
+    // Index of the first entry
+    head: Slot,
+    // Index of the last entry
+    tail: Slot,
+    // Hit, miss and eviction counters
+    stats: CacheStats,
+    // Which operations update `stats`
+    stats_mode: StatsMode,
+    // Which uses of an entry make it the most recently used
+    promotion: Promotion,
+    // Low and high watermarks set with `with_watermarks`
+    watermarks: Option<(u16, u16)>,
+    // Size of the probation window set with `with_probation`, 0 without one
+    probation: u16,
+    // Outcomes of the most recent counted lookups
+    recent: HitWindow,
+    // Operation counter used to age entries, bumped on every insert and lookup
+    tick: u32,
+    // Number of calls to `bump_epoch`
+    epoch: u32,
+    // Value of `tick` at the last `bump_epoch`, entries inserted before are stale. Cleared by
+    // `sweep`, which flags them instead.
+    epoch_tick: Option<u32>,
+    // Told about every insert, hit, miss and eviction
+    listener: L,
+}
+
+#[derive(Debug, Clone)]
+struct Entry<T> {
+    val: T,
+    link: Link,
+    // The cache's tick when this entry was inserted, and the entry's flags
+    stamp: Stamp,
+}
+
+// Largest supported capacity. Indices are `u16`, and `Slot` cannot hold `u16::MAX`.
+pub const MAX_CAPACITY: usize = u16::MAX as usize;
+
+// Bits of a `Stamp` keeping the tick, below the flags
+const TICK_BITS: u32 = 29;
+const TICK_MASK: u32 = (1 << TICK_BITS) - 1;
+
+// Flags of an entry, in the bits of its `Stamp` above the tick
+const FLAGS_MASK: u32 = !TICK_MASK;
+// Removed by the first lookup that finds it, see `insert_transient`
+const FLAG_TRANSIENT: u32 = 1 << TICK_BITS;
+// Found once already by a lookup that handed out a reference to it. The entry waits at the tail
+// until the next operation removes it.
+const FLAG_SPENT: u32 = 1 << (TICK_BITS + 1);
+// Inserted before the last `bump_epoch`. Set by the `sweep` after a bump, and in snapshots,
+// which do not keep the epoch.
+const FLAG_STALE: u32 = 1 << (TICK_BITS + 2);
+
+// Index of an entry, stored inverted so that it is never zero. The zero is then free for
+// `Option<LRUCache<..>>` to encode `None` in, so wrapping a cache in `Option` costs nothing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Slot(NonZero<u16>);
+
+impl Slot {
+    #[inline]
+    const fn new(i: u16) -> Self {
+        // Indices are below `MAX_CAPACITY`, so `!i` is never zero
+        match NonZero::new(!i) {
+            Some(inverted) => Slot(inverted),
+            None => panic!("entry index out of range"),
+        }
+    }
+
+    #[inline]
+    const fn get(self) -> u16 {
+        !self.0.get()
+    }
+}
+
+// Ticks between two runs of `sweep`. Ages stay below twice that, which a `Stamp` holds.
+const SWEEP_PERIOD: u32 = 1 << (TICK_BITS - 2);
+
+const _: () = assert!(
+    core::mem::size_of::<Option<LRUCache<u8, 4>>>() == core::mem::size_of::<LRUCache<u8, 4>>()
+);
+
+// Both links of an entry in one word: the index of the previous (more recently used) entry in
+// the low 16 bits, the index of the next one in the high 16 bits
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Link(u32);
+
+impl Link {
+    const fn new() -> Self {
+        Link(0)
+    }
+
+    #[inline]
+    fn prev(self) -> u16 {
+        self.0 as u16
+    }
+
+    #[inline]
+    fn next(self) -> u16 {
+        (self.0 >> 16) as u16
+    }
+
+    #[inline]
+    fn set_prev(&mut self, i: u16) {
+        self.0 = (self.0 & !0xffff) | i as u32;
+    }
+
+    #[inline]
+    fn set_next(&mut self, i: u16) {
+        self.0 = (self.0 & 0xffff) | (i as u32) << 16;
+    }
+}
+
+// The tick an entry was inserted at, in the low 29 bits, and its flags in the top 3. With the
+// `Link`, an entry costs 8 bytes on top of its value, plus whatever padding the alignment of `T`
+// requires: as much as the separate `prev: u16`, `next: u16` and `born: u32` did before the
+// flags.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Stamp(u32);
+
+impl Stamp {
+    // Stamp for an entry inserted at `tick`, without flags
+    #[inline]
+    const fn new(tick: u32) -> Self {
+        Stamp(tick & TICK_MASK)
+    }
+
+    // Ticks from the insert to `tick`, modulo 2^29
+    #[inline]
+    fn age(self, tick: u32) -> u32 {
+        tick.wrapping_sub(self.0) & TICK_MASK
+    }
+
+    // Move the insert to `tick`, keeping the flags
+    #[inline]
+    fn set_tick(&mut self, tick: u32) {
+        self.0 = (self.0 & FLAGS_MASK) | (tick & TICK_MASK);
+    }
+
+    #[inline]
+    fn flags(self) -> u32 {
+        self.0 & FLAGS_MASK
+    }
+
+    #[inline]
+    fn set_flags(&mut self, flags: u32) {
+        self.0 = (self.0 & !FLAGS_MASK) | flags;
+    }
+}
+
+// Which uses of an entry make it the most recently used, for workloads where only reads or only
+// writes say that a value is still wanted. Inserting a new value always puts it at the front.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Promotion {
+    // Lookups that hit, but not replacing the value of an entry in place
+    OnRead,
+    // Replacing the value of an entry in place, e.g. `LRUMap::insert` of a key already in the
+    // map, but not lookups. Entries then leave in the order they were last written.
+    OnWrite,
+    // Both, as in a classic LRU
+    #[default]
+    OnReadAndWrite,
+}
+
+impl Promotion {
+    fn on_read(self) -> bool {
+        self != Promotion::OnWrite
+    }
+
+    fn on_write(self) -> bool {
+        self != Promotion::OnRead
+    }
+}
+
+impl<T, L: Listener<T> + Default, const N: usize> Default for LRUCache<T, N, L> {
+    fn default() -> Self {
+        Self::with_listener(L::default())
+    }
+}
+
+impl<T, const N: usize> LRUCache<T, N> {
+    // create a empty cache
+    pub const fn new() -> Self {
+        Self::with_listener(())
+    }
+}
+
+// Fill a cache, the first value being the most recently used
+impl<T, const N: usize> From<[T; N]> for LRUCache<T, N> {
+    fn from(values: [T; N]) -> Self {
+        Self::from_array(values)
+    }
+}
+
+// Fill a cache from a slice, the first value being the most recently used. Fails if there are
+// more values than the cache has room for, see `from_slice_truncated` to keep the first ones.
+impl<T: Clone, const N: usize> TryFrom<&[T]> for LRUCache<T, N> {
+    type Error = CacheError;
+
+    fn try_from(values: &[T]) -> Result<Self, CacheError> {
+        if values.len() > N {
+            return Err(CacheError::CapacityExceeded {
+                len: values.len(),
+                capacity: N,
+            });
+        }
+        Ok(Self::from_slice_truncated(values))
+    }
+}
+
+impl<T, const N: usize> LRUCache<T, N> {
+    // create a cache holding the first `N` values of `values`, the first one being the most
+    // recently used, e.g. to load a persisted recency list. Nothing is counted in the stats.
+    pub fn from_slice_truncated(values: &[T]) -> Self
+    where
+        T: Clone,
+    {
+        let mut cache = Self::new();
+        for val in values.iter().take(N) {
+            cache.push_back_value(val.clone());
+        }
+        cache
+    }
+
+    // create a full cache holding `values`, the first one being the most recently used and the
+    // last one the next to be evicted. Nothing is counted in the stats.
+    //
+    // This is not a `const fn`: `ArrayVec` offers no way to fill itself in a const context
+    // without unsafe code, which the crate denies. A cache in a `static` starts empty.
+    pub fn from_array(values: [T; N]) -> Self {
+        Self::from_array_with_listener(values, ())
+    }
+}
+
+impl<T, L: Listener<T>, const N: usize> LRUCache<T, N, L> {
+    // Fails the build for capacities whose indices do not fit in a `Link`
+    const CAPACITY_FITS: () = assert!(N <= MAX_CAPACITY, "capacity exceeds MAX_CAPACITY");
+
+    // create a empty cache reporting its events to `listener`
+    pub const fn with_listener(listener: L) -> Self {
+        let () = Self::CAPACITY_FITS;
+        LRUCache {
+            entries: ArrayVec::new_const(),
+            head: Slot::new(0),
+            tail: Slot::new(0),
+            stats: CacheStats::new(),
+            stats_mode: StatsMode::Full,
+            promotion: Promotion::OnReadAndWrite,
+            watermarks: None,
+            probation: 0,
+            recent: HitWindow::new(),
+            tick: 0,
+            epoch: 0,
+            epoch_tick: None,
+            listener,
+        }
+    }
+
+    // create a full cache holding `values` like `from_array` does, reporting its events to
+    // `listener`. No events are reported for the initial values.
+    pub fn from_array_with_listener(values: [T; N], listener: L) -> Self {
+        let mut cache = Self::with_listener(listener);
+        let mut i = 0;
+        cache.entries = ArrayVec::from(values.map(|val| {
+            let mut link = Link::new();
+            link.set_prev(i.max(1) - 1);
+            // The tail has no next entry, and `N` itself may not fit in a link
+            if (i as usize) + 1 < N {
+                link.set_next(i + 1);
+            }
+            i += 1;
+            Entry {
+                val,
+                link,
+                stamp: Stamp::new(0),
+            }
+        }));
+        cache.tail = Slot::new(N.max(1) as u16 - 1);
+        cache
+    }
+
+    // Append `val` as the least recently used entry of a cache that is not full, without counting
+    // or reporting an insert
+    fn push_back_value(&mut self, val: T) {
+        let i = self.entries.len() as u16;
+        let mut link = Link::new();
+        if i != 0 {
+            let tail = self.tail();
+            link.set_prev(tail);
+            self.entry(tail).link.set_next(i);
+        }
+        self.entries.push(Entry {
+            val,
+            link,
+            stamp: Stamp::new(0),
+        });
+        self.tail = Slot::new(i);
+    }
+
+    // Choose which operations are counted in the stats, see `StatsMode`
+    pub const fn with_stats_mode(mut self, mode: StatsMode) -> Self {
+        self.stats_mode = mode;
+        self
+    }
+
+    // Choose which uses of an entry make it the most recently used, see `Promotion`
+    pub const fn with_promotion(mut self, promotion: Promotion) -> Self {
+        self.promotion = promotion;
+        self
+    }
+
+    // Hold at most `high` entries, and when an insert finds that many evict the least recently
+    // used down to `low` in one batch, so eviction work and the listener's writebacks come in
+    // fewer, bigger runs. The batch counts as evictions for capacity, and `insert` returns `None`
+    // after one: the values evicted are only reported to the listener, as `Event::Evict` with
+    // `RemovalCause::Capacity`, and then dropped. With the default `()` listener nothing sees
+    // them leave, so a cache whose evictions must be written back needs a listener doing it.
+    // Panics unless `low < high <= N`, or if the cache holds more than `high` entries already.
+    pub const fn with_watermarks(mut self, low: usize, high: usize) -> Self {
+        assert!(low < high && high <= N, "watermarks need low < high <= N");
+        assert!(self.entries.len() <= high, "cache above the high watermark");
+        self.watermarks = Some((low as u16, high as u16));
+        self
+    }
+
+    // Insert new entries as the `window`-th least recently used rather than the most recently
+    // used, so they have to be hit once to join the rest of the cache. A scan of keys used once
+    // then only cycles through the `window` entries at the tail, and the hot entries above stay.
+    // An entry hit in the window moves to the front like any other, pushing the entry above the
+    // window into it. Entries go to the front while the cache holds fewer than `window`, and a
+    // window of 0, the default, or of `N` is plain LRU. Panics if `window` is over `N`.
+    pub const fn with_probation(mut self, window: usize) -> Self {
+        assert!(window <= N, "probation window larger than the cache");
+        self.probation = window as u16;
+        self
+    }
+
+    // Number of entries the cache holds at most, the high watermark if set
+    fn limit(&self) -> usize {
+        match self.watermarks {
+            Some((_, high)) => high as usize,
+            None => N,
+        }
+    }
+
+    // Advance the tick for a new operation. Returns how much the operation weighs in the stats, 0
+    // if it is not sampled.
+    #[inline]
+    fn next_tick(&mut self) -> u64 {
+        let weight = self.stats_mode.weight(self.tick);
+        self.tick = self.tick.wrapping_add(1);
+        if self.tick.is_multiple_of(SWEEP_PERIOD) {
+            self.sweep();
+        }
+        weight
+    }
+
+    // Ticks since entry `i` was inserted, at most `2 * SWEEP_PERIOD`
+    #[inline]
+    fn age(&self, i: u16) -> u32 {
+        self.entries[i as usize].stamp.age(self.tick)
+    }
+
+    // Clamp every age to `SWEEP_PERIOD`, and flag the entries inserted before a pending
+    // `bump_epoch` as stale. Run every `SWEEP_PERIOD` ticks, so that no age wraps the tick and
+    // no bump stays pending long enough for its tick to be wrapped.
+    #[cold]
+    #[inline(never)]
+    fn sweep(&mut self) {
+        for i in 0..self.len() as u16 {
+            let stale = self.is_stale(i);
+            let age = self.age(i);
+            let tick = self.tick;
+            let stamp = &mut self.entry(i).stamp;
+            if stale {
+                stamp.set_flags(stamp.flags() | FLAG_STALE);
+            }
+            if age > SWEEP_PERIOD {
+                stamp.set_tick(tick.wrapping_sub(SWEEP_PERIOD));
+            }
+        }
+        self.epoch_tick = None;
+    }
+
+    // Move the tick to `tick`, for tests crossing the wrap or a sweep without running 2^27
+    // operations. Does not sweep.
+    #[cfg(test)]
+    pub(crate) fn set_tick(&mut self, tick: u32) {
+        self.tick = tick;
+    }
+
+    // Insert given key in cache. Returns the value evicted to make room, if any, except after a
+    // batch evicted down to the low watermark, see `with_watermarks`.
+    pub fn insert(&mut self, val: T) -> Option<T> {
+        match self.insert_slot(val) {
+            Ok((_, evicted)) => evicted,
+            Err(val) => Some(val),
+        }
+    }
+
+    // Insert `val` like `insert` does, returning its slot with the value evicted, or handing
+    // `val` back for a cache of capacity 0
+    fn insert_slot(&mut self, val: T) -> Result<(u16, Option<T>), T> {
+        self.drop_spent();
+        if let Some((low, high)) = self.watermarks {
+            if self.len() >= high as usize {
+                drop(self.evict(self.len() - low as usize));
+            }
+        }
+        let new_entry = Entry {
+            val,
+            link: Link::new(),
+            stamp: Stamp::new(self.tick),
+        };
+
+        let weight = self.next_tick();
+        self.stats.inserts += weight;
+
+        // If cache is full, replace the oldest entry. A cache of capacity 0 has none and hands
+        // the value back.
+        if self.entries.is_full() {
+            let Some(i) = self.try_pop_back() else {
+                return Err(new_entry.val);
+            };
+            self.record_eviction(i, weight);
+            let old_entry = replace(self.entry(i), new_entry);
+            self.link_new(i);
+            self.listener
+                .on_event(Event::Insert(&self.entries[i as usize].val));
+            Ok((i, Some(old_entry.val)))
+        } else {
+            let i = self.entries.len() as u16;
+            self.entries.push(new_entry);
+            self.link_new(i);
+            self.listener
+                .on_event(Event::Insert(&self.entries[i as usize].val));
+            Ok((i, None))
+        }
+    }
+
+    // Link new entry `i` at the front of the list, or of the probation window if there is one
+    fn link_new(&mut self, i: u16) {
+        let window = self.probation as usize;
+        // Not counting entry `i`
+        let linked = self.len() - 1;
+        if window == 0 || linked < window {
+            self.push_front(i);
+            return;
+        }
+        // Leave `window - 1` entries behind it
+        let mut at = self.tail();
+        if window == 1 {
+            self.link_after(i, at);
+            return;
+        }
+        for _ in 2..window {
+            at = self.entries[at as usize].link.prev();
+        }
+        self.link_before(i, at);
+    }
+
+    // Insert a value that is removed by the first lookup finding it, e.g. a nonce or the
+    // correlation record of a request awaiting its reply. `touch` and `find_map` remove it on the
+    // spot. `find` and `get_or_insert_with` hand out a reference to it, so it moves to the tail
+    // instead and the next operation changing the cache removes it. Until then it still counts in
+    // `len` and shows up when iterating. Removals are reported as `RemovalCause::Consumed`.
+    pub fn insert_transient(&mut self, val: T) -> Option<T> {
+        match self.insert_slot(val) {
+            Ok((i, evicted)) => {
+                self.entry(i).stamp.set_flags(FLAG_TRANSIENT);
+                evicted
+            }
+            Err(val) => Some(val),
+        }
+    }
+
+    // Count a hit on entry `i` and promote it, or spend it if it is transient
+    fn take_hit(&mut self, i: u16, weight: u64) {
+        if self.entries[i as usize].stamp.flags() & FLAG_TRANSIENT != 0 {
+            self.spend(i);
+        } else {
+            self.promote(i);
+        }
+        self.record_hit(i, weight);
+    }
+
+    // Mark transient entry `i` as found, making it the least recently used
+    fn spend(&mut self, i: u16) {
+        let tail = self.tail();
+        if i != tail {
+            self.remove(i);
+            self.entry(tail).link.set_next(i);
+            self.entry(i).link.set_prev(tail);
+            self.tail = Slot::new(i);
+        }
+        self.entry(i).stamp.set_flags(FLAG_SPENT);
+    }
+
+    // Remove the spent entry, if any, which is always the tail
+    fn drop_spent(&mut self) {
+        let tail = self.tail();
+        match self.entries.get(tail as usize) {
+            Some(entry) if entry.stamp.flags() & FLAG_SPENT != 0 => {
+                self.listener
+                    .on_event(Event::Evict(&entry.val, RemovalCause::Consumed));
+                self.remove_index(tail);
+            }
+            _ => {}
+        }
+    }
+
+    // Replace the value of entry `i` with `val`, counted as an insert, and make it most recently
+    // used if writes promote. Returns the old value.
+    fn overwrite(&mut self, i: u16, val: T) -> T {
+        let born = self.tick;
+        self.stats.inserts += self.next_tick();
+        self.listener.on_event(Event::Evict(
+            &self.entries[i as usize].val,
+            RemovalCause::Replaced,
+        ));
+        let entry = self.entry(i);
+        entry.stamp = Stamp::new(born);
+        let old = replace(&mut entry.val, val);
+        if self.promotion.on_write() {
+            self.touch_index(i);
+        }
+        self.listener
+            .on_event(Event::Insert(&self.entries[i as usize].val));
+        old
+    }
+
+    // Returns the first item in the cache that matches the predicate
+    // Make it most recently used on hit
+    pub fn find<F>(&mut self, pred: F) -> Option<&mut T>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let i = self.lookup(pred)?;
+        Some(&mut self.entry(i).val)
+    }
+
+    // Returns what `pred_map` makes of the first entry it returns `Some` for, making that entry
+    // most recently used. Unlike `find`, the result does not borrow the cache.
+    pub fn find_map<R>(&mut self, mut pred_map: impl FnMut(&mut T) -> Option<R>) -> Option<R> {
+        self.drop_spent();
+        let weight = self.next_tick();
+        let mut i = self.head();
+        for _ in 0..self.len() {
+            // Stale for the tail, which is visited last
+            let next = self.entries[i as usize].link.next();
+            // Stale entries are removed rather than shown to `pred_map`
+            if self.is_stale(i) {
+                // The last entry of the array moves into the slot removed
+                let last = self.len() as u16 - 1;
+                self.expire_index(i);
+                if next != last {
+                    i = next;
+                }
+                continue;
+            }
+            if let Some(found) = pred_map(&mut self.entries[i as usize].val) {
+                self.take_hit(i, weight);
+                self.drop_spent();
+                return Some(found);
+            }
+            i = next;
+        }
+        self.record_miss(weight);
+        None
+    }
+
+    // Returns the first item in the cache that matches the predicate like `find` does, or else
+    // the value made by `f`, inserted like `insert` does. Panics for a cache of capacity 0, which
+    // has nowhere to keep the value.
+    pub fn get_or_insert_with<F>(&mut self, pred: F, f: impl FnOnce() -> T) -> &mut T
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let i = match self.lookup(pred) {
+            Some(i) => i,
+            None => match self.insert_slot(f()) {
+                Ok((i, _)) => i,
+                Err(_) => panic!("no room for a value in a cache of capacity 0"),
+            },
+        };
+        &mut self.entry(i).val
+    }
+
+    // Returns the first item in the cache that matches the predicate, or else a new
+    // `T::default()`, e.g. for a cache of counters or per-connection state
+    pub fn get_or_default<F>(&mut self, pred: F) -> &mut T
+    where
+        F: FnMut(&T) -> bool,
+        T: Default,
+    {
+        self.get_or_insert_with(pred, T::default)
+    }
+
+    fn entry(&mut self, i: u16) -> &mut Entry<T> {
+        &mut self.entries[i as usize]
+    }
+
+    // Unlink the last entry, which must exist, and return its index
+    fn pop_back(&mut self) -> u16 {
+        let new_tail = self.entry(self.tail.get()).link.prev();
+        replace(&mut self.tail, Slot::new(new_tail)).get()
+    }
+
+    // Like `pop_back`, but returns `None` instead of panicking when the cache is empty
+    fn try_pop_back(&mut self) -> Option<u16> {
+        if self.is_empty() {
+            return None;
+        }
+        Some(self.pop_back())
+    }
+
+    // Link entry `i`, which must not be in the list, at its front
+    fn push_front(&mut self, i: u16) {
+        if self.entries.len() == 1 {
+            self.tail = Slot::new(i);
+        } else {
+            let head = self.head.get();
+            self.entry(i).link.set_next(head);
+            self.entry(head).link.set_prev(i);
+        }
+        self.head = Slot::new(i);
+    }
+
+    // Returns the number of elements in the cache
+    #[inline] // https://nnethercote.github.io/perf-book/inlining.html
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    // Returns if cache is empty or not
+    #[inline] // https://nnethercote.github.io/perf-book/inlining.html
+    pub fn is_empty(&self) -> bool {
+        if self.len() == 0 {
+            return true;
+        }
+        false
+    }
+
+    // Clears all the elements in cache
+    #[inline] // https://nnethercote.github.io/perf-book/inlining.html
+    pub fn clear(&mut self) {
+        for entry in &self.entries {
+            self.listener
+                .on_event(Event::Evict(&entry.val, RemovalCause::Explicit));
+        }
+        self.entries.clear()
+    }
+
+    // Returns the memory taken by the cache itself, in bytes. The entry array is stored inline,
+    // so this is the same whether the cache is empty or full, and does not count heap memory
+    // owned by the values (see `memory_usage_with_heap`).
+    pub const fn memory_usage(&self) -> usize {
+        core::mem::size_of::<Self>()
+    }
+
+    // Remove the least recently used value and return it, e.g. to shed load ahead of inserts
+    pub fn pop_lru(&mut self) -> Option<T> {
+        self.drop_spent();
+        if self.is_empty() {
+            return None;
+        }
+        let tail = self.tail();
+        self.listener.on_event(Event::Evict(
+            &self.entries[tail as usize].val,
+            RemovalCause::Explicit,
+        ));
+        Some(self.remove_index(tail))
+    }
+
+    // Evict the `n` least recently used entries, or all of them if there are fewer, yielding
+    // their values from the least recently used on, e.g. to shed load on a low-memory signal
+    // before inserts would. They count as evictions for capacity. Entries not yielded yet when
+    // the iterator is dropped are evicted all the same.
+    pub fn evict(&mut self, n: usize) -> impl ExactSizeIterator<Item = T> + '_ {
+        self.drop_spent();
+        Evict {
+            left: n.min(self.len()),
+            cache: self,
+        }
+    }
+
+    // Make every entry in the cache stale, in O(1), e.g. after a configuration change they were
+    // derived from. Lookups treat a stale entry as absent, removing it as expired and counting a
+    // miss, while the stats carry on. Until a lookup meets them or they reach the tail, stale
+    // entries still take room and show up in `len` and when iterating, see `purge_stale` to drop
+    // them all at once. Returns the new epoch, the number of calls so far.
+    //
+    // Staleness is told apart by the ticks entries were inserted at until the cache next walks
+    // all its entries, once every 2^27 operations, to flag the stale ones and clamp the ages.
+    // That walk makes the operation it falls on O(N).
+    pub fn bump_epoch(&mut self) -> u32 {
+        self.next_tick();
+        self.epoch_tick = Some(self.tick);
+        self.epoch = self.epoch.wrapping_add(1);
+        self.epoch
+    }
+
+    // Returns the number of calls to `bump_epoch` so far
+    pub fn epoch(&self) -> u32 {
+        self.epoch
+    }
+
+    // Remove every stale entry now, see `bump_epoch`. Returns how many there were.
+    pub fn purge_stale(&mut self) -> usize {
+        self.drop_spent();
+        let len = self.len();
+        // Backwards, so the entry moved into a freed slot was checked already
+        for i in (0..len as u16).rev() {
+            if self.is_stale(i) {
+                self.expire_index(i);
+            }
+        }
+        len - self.len()
+    }
+
+    // Do at most `budget` units of the work the cache otherwise leaves to later operations, so a
+    // main loop can bound what the cache costs it per iteration. A unit is one entry removed from
+    // the tail: a spent transient, a stale entry, or one above the low watermark, evicted for
+    // capacity. Stale entries a lookup moved ahead of fresh ones are left to `purge_stale`.
+    // Returns the units done, fewer than `budget` once there is nothing left to do.
+    pub fn maintain(&mut self, budget: usize) -> usize {
+        let low = self.watermarks.map_or(N, |(low, _)| low as usize);
+        let mut done = 0;
+        while done < budget && !self.is_empty() {
+            let tail = self.tail();
+            if self.entries[tail as usize].stamp.flags() & FLAG_SPENT != 0 {
+                self.drop_spent();
+            } else if self.is_stale(tail) {
+                self.expire_index(tail);
+            } else if self.len() > low {
+                self.evict_index(tail);
+            } else {
+                break;
+            }
+            done += 1;
+        }
+        done
+    }
+
+    // Whether entry `i` was inserted before the last `bump_epoch`
+    fn is_stale(&self, i: u16) -> bool {
+        self.entries[i as usize].stamp.flags() & FLAG_STALE != 0
+            || self
+                .epoch_tick
+                .is_some_and(|at| self.age(i) > self.tick.wrapping_sub(at))
+    }
+
+    // Remove stale entry `i`, see `remove_index`
+    fn expire_index(&mut self, i: u16) {
+        self.listener.on_event(Event::Evict(
+            &self.entries[i as usize].val,
+            RemovalCause::Expired,
+        ));
+        self.remove_index(i);
+    }
+
+    // Returns the least recently used value, the next to be evicted, without touching it
+    pub fn peek_lru(&self) -> Option<&T> {
+        self.entries.get(self.tail() as usize).map(|e| &e.val)
+    }
+
+    // Returns the most recently used value without touching it
+    pub fn peek_mru(&self) -> Option<&T> {
+        self.entries.get(self.head() as usize).map(|e| &e.val)
+    }
+
+    // Returns a mutable reference to the front entry in the list
+    pub fn front_mut(&mut self) -> Option<&mut T> {
+        self.entries
+            .get_mut(self.head.get() as usize)
+            .map(|e| &mut e.val)
+    }
+
+    // Make entry `i` most recently used for a lookup that found it, if reads promote
+    #[inline]
+    fn promote(&mut self, i: u16) {
+        if self.promotion.on_read() {
+            self.touch_index(i);
+        }
+    }
+
+    // Touch a given entry, putting it first in the list.
+    #[inline]
+    fn touch_index(&mut self, i: u16) {
+        if i != self.head.get() {
+            self.remove(i);
+            self.push_front(i);
+        }
+    }
+
+    // Remove an entry from the linked list.
+    fn remove(&mut self, i: u16) {
+        let link = self.entry(i).link;
+
+        if i == self.head.get() {
+            self.head = Slot::new(link.next());
+        } else {
+            self.entry(link.prev()).link.set_next(link.next());
+        }
+
+        if i == self.tail.get() {
+            self.tail = Slot::new(link.prev());
+        } else {
+            self.entry(link.next()).link.set_prev(link.prev());
+        }
+    }
+
+    // Touch the first item in the cache that matches the given predicate and marks it as recently
+    // used, Returns true or false
+    pub fn touch<F>(&mut self, pred: F) -> bool
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let found = self.lookup(pred).is_some();
+        self.drop_spent();
+        found
+    }
+
+    // Make every entry matching the predicate most recently used, in one pass, keeping their
+    // order among themselves, e.g. to mark all the sessions of a client that reconnected hot.
+    // Returns how many were moved. This is not a lookup: nothing is counted or reported, and the
+    // entries move whatever the `Promotion` setting.
+    pub fn touch_all<F>(&mut self, mut pred: F) -> usize
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.touch_all_by(|_, val| pred(val))
+    }
+
+    // `touch_all` with the index of each entry passed along
+    fn touch_all_by(&mut self, mut pred: impl FnMut(u16, &T) -> bool) -> usize {
+        self.drop_spent();
+        // Walking from the tail and moving each match to the front leaves the matches in their
+        // old order. Entries not visited yet are never moved, so the walk stays among them.
+        let mut moved = 0;
+        let mut i = self.tail();
+        for _ in 0..self.len() {
+            // Stale for the head, which is visited last
+            let prev = self.entries[i as usize].link.prev();
+            if pred(i, &self.entries[i as usize].val) {
+                self.touch_index(i);
+                moved += 1;
+            }
+            i = prev;
+        }
+        moved
+    }
+
+    // Count a lookup of the first entry matching the predicate and promote it, returning its
+    // index
+    fn lookup<F>(&mut self, mut pred: F) -> Option<u16>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.drop_spent();
+        let weight = self.next_tick();
+        loop {
+            match self.position(&mut pred) {
+                Some(i) if self.is_stale(i) => self.expire_index(i),
+                Some(i) => {
+                    self.take_hit(i, weight);
+                    return Some(i);
+                }
+                None => {
+                    self.record_miss(weight);
+                    return None;
+                }
+            }
+        }
+    }
+
+    // Index of the first entry in recency order matching the predicate, without touching it
+    fn position<F>(&mut self, mut pred: F) -> Option<u16>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let mut iter = self.scan_mut();
+        while let Some((i, val)) = iter.next() {
+            if pred(val) {
+                return Some(i);
+            }
+        }
+        None
+    }
+
+    // Returns an item matching the predicate without changing the recency order or the stats,
+    // skipping stale entries.
+    // Entries are scanned in array order rather than list order, which reads memory
+    // sequentially instead of chasing links, so with several matches any of them may be returned.
+    pub fn peek_unordered<F>(&self, mut pred: F) -> Option<&T>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let i = (0..self.len() as u16)
+            .find(|&i| !self.is_stale(i) && pred(&self.entries[i as usize].val))?;
+        Some(&self.entries[i as usize].val)
+    }
+
+    // Iterate over the contents of this cache in array order, which is not the recency order
+    pub fn values_unordered(&self) -> impl Iterator<Item = &T> + '_ {
+        self.entries.iter().map(|e| &e.val)
+    }
+
+    // Count a lookup that found entry `i`
+    fn record_hit(&mut self, i: u16, weight: u64) {
+        if weight != 0 {
+            self.stats.hits += weight;
+            self.recent.record(true);
+        }
+        self.listener
+            .on_event(Event::Hit(&self.entries[i as usize].val));
+    }
+
+    // Count a lookup that found nothing
+    fn record_miss(&mut self, weight: u64) {
+        if weight != 0 {
+            self.stats.misses += weight;
+            self.recent.record(false);
+        }
+        self.listener.on_event(Event::Miss);
+    }
+
+    // Count entry `i` as evicted for capacity by an operation weighing `weight`
+    fn record_eviction(&mut self, i: u16, weight: u64) {
+        let entry = &self.entries[i as usize];
+        if weight != 0 {
+            self.stats.evictions += weight;
+            self.stats.lifetimes.record_n(self.age(i), weight);
+        }
+        self.listener
+            .on_event(Event::Evict(&entry.val, RemovalCause::Capacity));
+    }
+
+    // Evict entry `i` to make room for the insert about to happen, see `remove_index`
+    fn evict_index(&mut self, i: u16) -> T {
+        let weight = self.stats_mode.weight(self.tick);
+        self.record_eviction(i, weight);
+        self.remove_index(i)
+    }
+
+    // Index of the most recently used entry, meaningless if the cache is empty
+    #[inline]
+    fn head(&self) -> u16 {
+        self.head.get()
+    }
+
+    // Index of the least recently used entry, meaningless if the cache is empty
+    #[inline]
+    fn tail(&self) -> u16 {
+        self.tail.get()
+    }
+
+    // Unlink entry `i` and take it out of the array. The last entry of the array moves into slot
+    // `i`, so indices held by the caller for that entry must be updated.
+    fn remove_index(&mut self, i: u16) -> T {
+        self.remove(i);
+        let last = (self.entries.len() - 1) as u16;
+        let entry = self.entries.swap_remove(i as usize);
+
+        if i != last {
+            let link = self.entry(i).link;
+
+            if self.head.get() == last {
+                self.head = Slot::new(i);
+            } else {
+                self.entry(link.prev()).link.set_next(i);
+            }
+
+            if self.tail.get() == last {
+                self.tail = Slot::new(i);
+            } else {
+                self.entry(link.next()).link.set_prev(i);
+            }
+        }
+        entry.val
+    }
+
+    // Returns the usage counters collected so far
+    pub fn stats(&self) -> &CacheStats {
+        &self.stats
+    }
+
+    // Returns an owned copy of the usage counters, which can be formatted or shipped elsewhere
+    // after the cache (or the lock around it) has been released
+    pub fn stats_snapshot(&self) -> CacheStats {
+        self.stats
+    }
+
+    // Returns the hit ratio over the last 64 counted lookups. Unlike the lifetime ratio in
+    // `stats()`, it quickly shows a drop after the traffic pattern changes.
+    pub fn recent_hit_ratio(&self) -> f64 {
+        self.recent.hit_ratio()
+    }
+
+    // Returns the outcomes behind `recent_hit_ratio`
+    pub fn recent_lookups(&self) -> &HitWindow {
+        &self.recent
+    }
+
+    // Sets all usage counters back to zero
+    pub fn reset_stats(&mut self) {
+        self.stats = CacheStats::new();
+        self.recent = HitWindow::new();
+    }
+
+    // Returns the listener events are reported to
+    pub fn listener(&self) -> &L {
+        &self.listener
+    }
+
+    // Returns the listener events are reported to, e.g. to drain what it collected
+    pub fn listener_mut(&mut self) -> &mut L {
+        &mut self.listener
+    }
+
+    // Iterate mutably over the contents of this cache in order from most-recently-used to
+    // least-recently-used, along with the index of each entry. Lookups use it, the public
+    // `iter_mut` is built on `CursorMut`.
+    fn scan_mut(&mut self) -> ScanMut<'_, T, N, L> {
+        ScanMut {
+            pos: self.head.get(),
+            cache: self,
+        }
+    }
+
+    // Iterate over the contents of this cache in order from most-recently-used to
+    // least-recently-used, without changing it
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = &T> + '_ {
+        Iter {
+            pos: self.head.get(),
+            left: self.len(),
+            cache: self,
+        }
+    }
+
+    // Combine the values from most to least recently used into one, e.g. to sum their weights,
+    // without changing the recency order or the stats
+    pub fn fold<B>(&self, init: B, mut f: impl FnMut(B, &T) -> B) -> B {
+        let mut acc = init;
+        let mut pos = self.head.get();
+        for _ in 0..self.len() {
+            let entry = &self.entries[pos as usize];
+            acc = f(acc, &entry.val);
+            pos = entry.link.next();
+        }
+        acc
+    }
+
+    // Call `f` on each value from most to least recently used, without changing the recency
+    // order or the stats
+    pub fn for_each(&self, mut f: impl FnMut(&T)) {
+        self.fold((), |(), val| f(val));
+    }
+
+    // Iterate over the contents of this cache from the oldest to the newest insert, whatever
+    // lookups did to the recency order since, e.g. to flush the values written first. A value
+    // replaced in place counts as inserted anew. Values given at construction all count as
+    // inserted first.
+    pub fn iter_insertion_order(&self) -> impl ExactSizeIterator<Item = &T> + '_ {
+        self.insertion_order()
+            .into_iter()
+            .map(|i| &self.entries[i as usize].val)
+    }
+
+    // Indices of the entries from the oldest to the newest insert. Ages are measured in ticks and
+    // clamped to `SWEEP_PERIOD`, so entries older than that sort by index among themselves.
+    fn insertion_order(&self) -> ArrayVec<u16, N> {
+        let mut order: ArrayVec<u16, N> = (0..self.len() as u16).collect();
+        // Prefilled entries have the same age, their indices follow from most to least recently
+        // used
+        order.sort_unstable_by_key(|&i| {
+            let age = self.age(i);
+            (core::cmp::Reverse(age), core::cmp::Reverse(i))
+        });
+        order
+    }
+}
+
+impl<T: fmt::Debug, L: Listener<T>, const N: usize> LRUCache<T, N, L> {
+    // Returns something to format the cache with: its values from most to least recently used,
+    // each with its rank, instead of the raw links of the derived `Debug`. `{:#?}` puts each value
+    // on its own line.
+    pub fn fmt_recency(&self) -> impl fmt::Debug + '_ {
+        Recency(self)
+    }
+}
+
+struct Recency<'a, T, const N: usize, L>(&'a LRUCache<T, N, L>);
+
+impl<T: fmt::Debug, L: Listener<T>, const N: usize> fmt::Debug for Recency<'_, T, N, L> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let cache = self.0;
+        let last = cache.len().saturating_sub(1);
+        write!(f, "LRUCache ({}/{}) [", cache.len(), N)?;
+        for (rank, val) in cache.iter().enumerate() {
+            let role = match rank {
+                0 if last == 0 => " (head and tail)",
+                0 => " (head, most recent)",
+                _ if rank == last => " (tail, next to evict)",
+                _ => "",
+            };
+            if f.alternate() {
+                write!(f, "\n    {rank}: {val:?}{role},")?;
+            } else {
+                let sep = if rank == 0 { "" } else { ", " };
+                write!(f, "{sep}{rank}: {val:?}{role}")?;
+            }
+        }
+        if f.alternate() && !cache.is_empty() {
+            f.write_str("\n")?;
+        }
+        f.write_str("]")
+    }
+}
+
+struct Iter<'a, T, const N: usize, L> {
+    cache: &'a LRUCache<T, N, L>,
+    pos: u16,
+    // Entries not yet returned, the tail's link being meaningless
+    left: usize,
+}
+
+impl<'a, T, const N: usize, L> Iterator for Iter<'a, T, N, L> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        if self.left == 0 {
+            return None;
+        }
+        let entry = self.cache.entries.get(self.pos as usize)?;
+        self.pos = entry.link.next();
+        self.left -= 1;
+        Some(&entry.val)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.left, Some(self.left))
+    }
+}
+
+impl<T, const N: usize, L> ExactSizeIterator for Iter<'_, T, N, L> {}
+
+struct Evict<'a, T, const N: usize, L: Listener<T>> {
+    cache: &'a mut LRUCache<T, N, L>,
+    // Entries still to evict
+    left: usize,
+}
+
+impl<T, const N: usize, L: Listener<T>> Iterator for Evict<'_, T, N, L> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if self.left == 0 {
+            return None;
+        }
+        self.left -= 1;
+        let tail = self.cache.tail();
+        Some(self.cache.evict_index(tail))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.left, Some(self.left))
+    }
+}
+
+impl<T, const N: usize, L: Listener<T>> ExactSizeIterator for Evict<'_, T, N, L> {}
+
+impl<T, const N: usize, L: Listener<T>> Drop for Evict<'_, T, N, L> {
+    fn drop(&mut self) {
+        self.for_each(drop);
+    }
+}
+
+struct ScanMut<'a, T, const N: usize, L> {
+    cache: &'a mut LRUCache<T, N, L>,
+    pos: u16,
+}
+
+impl<'a, T, const N: usize, L> ScanMut<'a, T, N, L> {
+    fn next(&mut self) -> Option<(u16, &mut T)> {
+        let index = self.pos;
+        let link = self.cache.entries.get(index as usize)?.link;
+
+        self.pos = if index == self.cache.tail.get() {
+            N as u16 // Point past the end of the array to signal we are done.
+        } else {
+            link.next()
+        };
+        #[cfg(feature = "prefetch")]
+        if let Some(next) = self.cache.entries.get(self.pos as usize) {
+            prefetch(next);
+        }
+        let entry = self.cache.entries.get_mut(index as usize)?;
+        Some((index, &mut entry.val))
+    }
+}
+
+// Hint the CPU to start loading `entry`, so the link to follow is in cache by the time the
+// iterator gets to it. Does nothing on targets without a stable prefetch instruction.
+#[cfg(feature = "prefetch")]
+#[inline(always)]
+#[allow(unsafe_code)]
+fn prefetch<T>(entry: &Entry<T>) {
+    #[cfg(all(target_arch = "x86", target_feature = "sse"))]
+    use core::arch::x86::{_mm_prefetch, _MM_HINT_T0};
+    #[cfg(target_arch = "x86_64")]
+    use core::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+
+    #[cfg(any(
+        target_arch = "x86_64",
+        all(target_arch = "x86", target_feature = "sse")
+    ))]
+    // SAFETY: SSE is enabled, and a prefetch never faults, whatever the address
+    unsafe {
+        _mm_prefetch::<_MM_HINT_T0>((entry as *const Entry<T>).cast());
+    }
+    #[cfg(not(any(
+        target_arch = "x86_64",
+        all(target_arch = "x86", target_feature = "sse")
+    )))]
+    let _ = entry;
+}
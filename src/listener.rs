@@ -0,0 +1,56 @@
+//! Hooks for observing what the cache does.
+
+// Why an entry left the cache
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemovalCause {
+    // Pushed out by an insert into a full cache
+    Capacity,
+    // Its deadline passed, or it was found stale after `LRUCache::bump_epoch`
+    Expired,
+    // Removed on request, e.g. by `LRUMap::remove`, or by `clear`
+    Explicit,
+    // Overwritten by a new value under the same key
+    Replaced,
+    // Found by a lookup, being a single-use entry, see `LRUCache::insert_transient`
+    Consumed,
+}
+
+// Something the cache just did, handed to its listener
+#[derive(Debug)]
+pub enum Event<'a, T> {
+    // A value was stored
+    Insert(&'a T),
+    // A lookup matched this value, which is now the most recently used
+    Hit(&'a T),
+    // A lookup matched nothing
+    Miss,
+    // A value is leaving the cache
+    Evict(&'a T, RemovalCause),
+}
+
+// Events only borrow the value, so they can be copied whatever `T` is
+impl<T> Clone for Event<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Event<'_, T> {}
+
+// Receives every event of the cache it is attached to. `()` ignores them all and costs nothing.
+pub trait Listener<T> {
+    fn on_event(&mut self, event: Event<'_, T>);
+}
+
+impl<T> Listener<T> for () {
+    #[inline]
+    fn on_event(&mut self, _event: Event<'_, T>) {}
+}
+
+// Lets a cache report to two listeners, e.g. a `TraceListener` and a `ChurnMonitor`
+impl<T, A: Listener<T>, B: Listener<T>> Listener<T> for (A, B) {
+    fn on_event(&mut self, event: Event<'_, T>) {
+        self.0.on_event(event);
+        self.1.on_event(event);
+    }
+}
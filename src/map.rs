@@ -0,0 +1,513 @@
+//! A keyed LRU map.
+//!
+//! `LRUMap` stores its keys in their own dense array, next to the array of values and links
+//! kept by an `LRUCache`, both indexed by the same slot. Lookups only scan the key array, so the
+//! O(n) scan stays fast however large the values are.
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use arrayvec::ArrayVec;
+use core::{borrow::Borrow, mem::replace};
+
+#[cfg(feature = "alloc")]
+use crate::CacheError;
+use crate::{scan::ScanKey, CacheStats, Event, LRUCache, Listener, Promotion, RemovalCause};
+
+// An LRU map of capacity `N` from keys `K` to values `V`
+#[derive(Debug, Clone)]
+pub struct LRUMap<K, V, const N: usize, L = ()> {
+    // Key of each slot of `cache`
+    keys: ArrayVec<K, N>,
+    cache: LRUCache<V, N, L>,
+    // Slot of the most recent hit, checked before scanning. It is never invalidated: the key
+    // stored there is compared like any other, and the slot may be past the end after removals.
+    last_hit: u16,
+}
+
+// An entry found in only one of two maps by `LRUMap::diff`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Difference<'a, K, V> {
+    // The key is in the map `diff` was called on, not in the other
+    OnlyInSelf(&'a K, &'a V),
+    // The key is in the other map, not in the one `diff` was called on
+    OnlyInOther(&'a K, &'a V),
+}
+
+impl<K, V, L: Listener<V> + Default, const N: usize> Default for LRUMap<K, V, N, L> {
+    fn default() -> Self {
+        Self::with_listener(L::default())
+    }
+}
+
+impl<K, V, const N: usize> LRUMap<K, V, N> {
+    // create a empty map
+    pub const fn new() -> Self {
+        Self::with_listener(())
+    }
+}
+
+impl<K, V, L: Listener<V>, const N: usize> LRUMap<K, V, N, L> {
+    // create a empty map reporting its events to `listener`
+    pub const fn with_listener(listener: L) -> Self {
+        LRUMap {
+            keys: ArrayVec::new_const(),
+            cache: LRUCache::with_listener(listener),
+            last_hit: 0,
+        }
+    }
+
+    // Choose which uses of an entry make it the most recently used, see `Promotion`
+    pub const fn with_promotion(mut self, promotion: Promotion) -> Self {
+        self.cache.promotion = promotion;
+        self
+    }
+
+    // Make every entry in the map stale, in O(1), see `LRUCache::bump_epoch`. Returns the new
+    // epoch.
+    pub fn bump_epoch(&mut self) -> u32 {
+        self.cache.bump_epoch()
+    }
+
+    // Returns the number of calls to `bump_epoch` so far
+    pub fn epoch(&self) -> u32 {
+        self.cache.epoch()
+    }
+
+    // Remove every stale entry now. Returns how many there were.
+    pub fn purge_stale(&mut self) -> usize {
+        let len = self.len();
+        // Backwards, so the entry moved into a freed slot was checked already
+        for i in (0..len as u16).rev() {
+            if self.cache.is_stale(i) {
+                self.remove_index(i, RemovalCause::Expired);
+            }
+        }
+        len - self.len()
+    }
+
+    // Remove at most `budget` stale entries from the tail, see `LRUCache::maintain`. Returns how
+    // many there were.
+    pub fn maintain(&mut self, budget: usize) -> usize {
+        let len = self.len();
+        while len - self.len() < budget && !self.is_empty() {
+            let tail = self.cache.tail();
+            if !self.cache.is_stale(tail) {
+                break;
+            }
+            self.remove_index(tail, RemovalCause::Expired);
+        }
+        len - self.len()
+    }
+
+    // Returns the number of entries in the map
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    // Returns if map is empty or not
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    // Clears all the entries in map
+    pub fn clear(&mut self) {
+        self.keys.clear();
+        self.cache.clear();
+    }
+
+    // Returns the usage counters collected so far
+    pub fn stats(&self) -> &CacheStats {
+        self.cache.stats()
+    }
+
+    // Returns the listener events are reported to
+    pub fn listener(&self) -> &L {
+        self.cache.listener()
+    }
+
+    // Returns the listener events are reported to
+    pub fn listener_mut(&mut self) -> &mut L {
+        self.cache.listener_mut()
+    }
+
+    // Value in slot `i`
+    #[inline]
+    pub(crate) fn value(&self, i: u16) -> &V {
+        &self.cache.entries[i as usize].val
+    }
+
+    // Key in slot `i`
+    #[inline]
+    pub(crate) fn key(&self, i: u16) -> &K {
+        &self.keys[i as usize]
+    }
+
+    // Slot of the most recently used entry, where the last insert went
+    #[inline]
+    pub(crate) fn front_slot(&self) -> u16 {
+        self.cache.head()
+    }
+
+    // Slot of the least recently used entry, the next to be evicted
+    #[inline]
+    pub(crate) fn back_slot(&self) -> u16 {
+        self.cache.tail()
+    }
+
+    // Make slot `i` the most recently used, without counting a lookup
+    #[cfg(feature = "std")]
+    pub(crate) fn promote(&mut self, i: u16) {
+        if (i as usize) < self.keys.len() {
+            self.cache.touch_index(i);
+        }
+    }
+
+    // Like `get`, for the first key matching `pred`
+    pub(crate) fn get_by(&mut self, pred: impl FnMut(&K) -> bool) -> Option<&mut V> {
+        let found = self.position_by(pred);
+        self.lookup(found)
+    }
+
+    // Like `get_by`, but an entry for which `live` returns false is removed as expired and
+    // counts as a miss
+    #[cfg(feature = "dns")]
+    pub(crate) fn get_live_by(
+        &mut self,
+        pred: impl FnMut(&K) -> bool,
+        live: impl FnOnce(&V) -> bool,
+    ) -> Option<&mut V> {
+        let mut found = self.position_by(pred);
+        if let Some(i) = found.filter(|&i| !live(self.value(i))) {
+            self.remove_index(i, RemovalCause::Expired);
+            found = None;
+        }
+        self.lookup(found)
+    }
+
+    // Like `peek`, for the first key matching `pred`
+    pub(crate) fn peek_by(&self, pred: impl FnMut(&K) -> bool) -> Option<&V> {
+        let i = self
+            .position_by(pred)
+            .filter(|&i| !self.cache.is_stale(i))?;
+        Some(self.value(i))
+    }
+
+    // Like `remove`, for the first key matching `pred`
+    pub(crate) fn remove_by(&mut self, pred: impl FnMut(&K) -> bool) -> Option<(K, V)> {
+        let i = self.position_by(pred)?;
+        Some(self.remove_index(i, RemovalCause::Explicit))
+    }
+
+    // Like `insert`, replacing the entry whose key matches `pred` if there is one
+    pub(crate) fn insert_by(
+        &mut self,
+        pred: impl FnMut(&K) -> bool,
+        key: K,
+        val: V,
+    ) -> Option<(K, V)> {
+        match self.position_by(pred) {
+            Some(i) => Some(self.overwrite(i, key, val)),
+            None => self.insert_new(key, val),
+        }
+    }
+
+    // Put `key` and `val` in slot `i`, making it the most recently used if writes promote, and
+    // return what was there
+    pub(crate) fn overwrite(&mut self, i: u16, key: K, val: V) -> (K, V) {
+        let old_val = self.cache.overwrite(i, val);
+        let old_key = replace(&mut self.keys[i as usize], key);
+        (old_key, old_val)
+    }
+
+    // Like `insert`, for a key known not to be in the map
+    pub(crate) fn insert_new(&mut self, key: K, val: V) -> Option<(K, V)> {
+        match self.cache.insert_slot(val) {
+            // The evicted slot was reused for the new value
+            Ok((i, Some(evicted))) => {
+                let old_key = replace(&mut self.keys[i as usize], key);
+                Some((old_key, evicted))
+            }
+            // The new value went to the end of the array
+            Ok((_, None)) => {
+                self.keys.push(key);
+                None
+            }
+            // A map of capacity 0 hands the entry back
+            Err(val) => Some((key, val)),
+        }
+    }
+
+    // Slot of the first key matching `pred`, in array order
+    pub(crate) fn position_by(&self, pred: impl FnMut(&K) -> bool) -> Option<u16> {
+        self.keys.iter().position(pred).map(|i| i as u16)
+    }
+
+    // Count a lookup that found slot `found`, making it the most recently used entry if reads
+    // promote. A stale entry is removed and counts as a miss.
+    pub(crate) fn lookup(&mut self, mut found: Option<u16>) -> Option<&mut V> {
+        let weight = self.cache.next_tick();
+        if let Some(i) = found.filter(|&i| self.cache.is_stale(i)) {
+            self.remove_index(i, RemovalCause::Expired);
+            found = None;
+        }
+        match found {
+            Some(i) => {
+                self.last_hit = i;
+                self.cache.promote(i);
+                self.cache.record_hit(i, weight);
+                Some(&mut self.cache.entry(i).val)
+            }
+            None => {
+                self.cache.record_miss(weight);
+                None
+            }
+        }
+    }
+
+    // Make every entry matching the predicate most recently used, keeping their order among
+    // themselves, see `LRUCache::touch_all`. Returns how many were moved.
+    pub fn touch_all(&mut self, mut pred: impl FnMut(&K, &V) -> bool) -> usize {
+        let keys = &self.keys;
+        self.cache
+            .touch_all_by(|i, val| pred(&keys[i as usize], val))
+    }
+
+    // Returns the entries from most to least recently used, without changing the recency order
+    // or the stats
+    pub fn entries(&self) -> impl ExactSizeIterator<Item = (&K, &V)> + '_ {
+        self.slots().map(|slot| (self.key(slot), self.value(slot)))
+    }
+
+    // Slots of the entries from most to least recently used
+    fn slots(&self) -> impl ExactSizeIterator<Item = u16> + '_ {
+        let mut i = self.cache.head();
+        (0..self.len()).map(move |_| {
+            let slot = i;
+            i = self.cache.entries[slot as usize].link.next();
+            slot
+        })
+    }
+
+    // Returns the entries from the oldest to the newest insert, see
+    // `LRUCache::iter_insertion_order`. Inserting a key already in the map counts as a new insert.
+    pub fn iter_insertion_order(&self) -> impl ExactSizeIterator<Item = (&K, &V)> + '_ {
+        self.cache
+            .insertion_order()
+            .into_iter()
+            .map(|slot| (self.key(slot), self.value(slot)))
+    }
+
+    // Take slot `i` out of the map, moving the last slot into its place in both arrays
+    pub(crate) fn remove_index(&mut self, i: u16, cause: RemovalCause) -> (K, V) {
+        self.cache
+            .listener
+            .on_event(Event::Evict(&self.cache.entries[i as usize].val, cause));
+        let val = self.cache.remove_index(i);
+        let key = self.keys.swap_remove(i as usize);
+        (key, val)
+    }
+}
+
+impl<K: Eq, V, L: Listener<V>, const N: usize> LRUMap<K, V, N, L> {
+    // Insert `val` under `key`, making it the most recently used entry. Returns the entry it
+    // replaced, either the old one with the same key or the least recently used one if the map
+    // was full.
+    pub fn insert(&mut self, key: K, val: V) -> Option<(K, V)> {
+        if let Some(i) = self.index_of(&key) {
+            return Some(self.overwrite(i, key, val));
+        }
+
+        self.insert_new(key, val)
+    }
+
+    // Returns the value stored under `key`, making it the most recently used entry
+    pub fn get<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        let found = self.index_of(key);
+        self.lookup(found)
+    }
+
+    // Returns the value stored under `key`, or else the value made by `f`, inserted under it.
+    // Either way it is now the most recently used entry. Panics for a map of capacity 0, which
+    // has nowhere to keep the value.
+    pub fn get_or_insert_with(&mut self, key: K, f: impl FnOnce() -> V) -> &mut V {
+        let found = self.index_of(&key);
+        let i = match self.lookup(found) {
+            Some(_) => found.expect("a hit has a slot"),
+            None => {
+                assert!(N > 0, "no room for a value in a map of capacity 0");
+                self.insert(key, f());
+                self.cache.head()
+            }
+        };
+        &mut self.cache.entry(i).val
+    }
+
+    // Returns the value stored under `key`, or else a new `V::default()`, e.g. for per-key
+    // counters
+    pub fn get_or_default(&mut self, key: K) -> &mut V
+    where
+        V: Default,
+    {
+        self.get_or_insert_with(key, V::default)
+    }
+
+    // Returns the value stored under `key` without changing the recency order or the stats
+    pub fn peek<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        let i = self.live_index_of(key)?;
+        Some(self.value(i))
+    }
+
+    // Returns true if a value is stored under `key`, without changing the recency order
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.live_index_of(key).is_some()
+    }
+
+    // Remove the entry stored under `key`, returning its value. A stale entry is removed as
+    // expired and its value dropped.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        let i = self.index_of(key)?;
+        if self.cache.is_stale(i) {
+            self.remove_index(i, RemovalCause::Expired);
+            return None;
+        }
+        Some(self.remove_index(i, RemovalCause::Explicit).1)
+    }
+
+    // `index_of`, unless the entry is stale
+    fn live_index_of<Q>(&self, key: &Q) -> Option<u16>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.index_of(key).filter(|&i| !self.cache.is_stale(i))
+    }
+
+    // Returns the entries whose key is in only one of `self` and `other`, those of `self` first,
+    // each from most to least recently used, e.g. to seed a standby with what it lacks. Stale
+    // entries count as absent on both sides, as they do for `contains_key`: they are neither
+    // returned nor match a key of the other map. Costs a scan of the other map per entry.
+    pub fn diff<'a, const M: usize, L2: Listener<V>>(
+        &'a self,
+        other: &'a LRUMap<K, V, M, L2>,
+    ) -> impl Iterator<Item = Difference<'a, K, V>> + 'a {
+        let only_in_self = self
+            .live_entries()
+            .filter(|(key, _)| !other.contains_key(*key))
+            .map(|(key, val)| Difference::OnlyInSelf(key, val));
+        let only_in_other = other
+            .live_entries()
+            .filter(|(key, _)| !self.contains_key(*key))
+            .map(|(key, val)| Difference::OnlyInOther(key, val));
+        only_in_self.chain(only_in_other)
+    }
+
+    // Like `entries`, leaving out stale entries
+    fn live_entries(&self) -> impl Iterator<Item = (&K, &V)> + '_ {
+        self.slots()
+            .filter(|&i| !self.cache.is_stale(i))
+            .map(|i| (self.key(i), self.value(i)))
+    }
+
+    // Like `get`, but returns the slot of the entry
+    pub(crate) fn get_slot<Q>(&mut self, key: &Q) -> Option<u16>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        let found = self.index_of(key);
+        self.lookup(found)?;
+        found
+    }
+
+    // Slot holding `key`. Keys are unique, so the key array is scanned in array order, after
+    // checking the slot of the last hit as access streams often repeat the same key.
+    #[inline]
+    pub(crate) fn index_of<Q>(&self, key: &Q) -> Option<u16>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        if let Some(k) = self.keys.get(self.last_hit as usize) {
+            if k.borrow() == key {
+                return Some(self.last_hit);
+            }
+        }
+        self.keys
+            .iter()
+            .position(|k| k.borrow() == key)
+            .map(|i| i as u16)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<K: Clone, V: Clone, L: Listener<V>, const N: usize> LRUMap<K, V, N, L> {
+    // Returns a copy of the entries from most to least recently used, without changing the
+    // recency order or the stats
+    pub fn to_vec(&self) -> Vec<(K, V)> {
+        self.entries()
+            .map(|(key, val)| (key.clone(), val.clone()))
+            .collect()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<K: Eq, V, const N: usize> LRUMap<K, V, N> {
+    // create a map holding `entries`, the first one being the most recently used, e.g. as
+    // returned by `to_vec`. Of entries with the same key the first one is kept. Fails if there
+    // are more entries than the map has room for. Nothing is counted in the stats.
+    pub fn from_entries(entries: Vec<(K, V)>) -> Result<Self, CacheError> {
+        if entries.len() > N {
+            return Err(CacheError::CapacityExceeded {
+                len: entries.len(),
+                capacity: N,
+            });
+        }
+        let mut map = Self::new();
+        for (key, val) in entries {
+            if map.index_of(&key).is_none() {
+                map.keys.push(key);
+                map.cache.push_back_value(val);
+            }
+        }
+        Ok(map)
+    }
+}
+
+impl<K: ScanKey, V, L: Listener<V>, const N: usize> LRUMap<K, V, N, L> {
+    // Like `get`, but finds the key with `ScanKey::position`, which the `simd` feature speeds up
+    pub fn get_fast(&mut self, key: K) -> Option<&mut V> {
+        let found = self.scan(key);
+        self.lookup(found)
+    }
+
+    // Like `peek`, but finds the key with `ScanKey::position`
+    pub fn peek_fast(&self, key: K) -> Option<&V> {
+        let i = self.scan(key).filter(|&i| !self.cache.is_stale(i))?;
+        Some(self.value(i))
+    }
+
+    // `index_of` through `ScanKey::position`
+    #[inline]
+    fn scan(&self, key: K) -> Option<u16> {
+        if self.keys.get(self.last_hit as usize) == Some(&key) {
+            return Some(self.last_hit);
+        }
+        K::position(&self.keys, key).map(|i| i as u16)
+    }
+}
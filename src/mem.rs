@@ -0,0 +1,296 @@
+//! Heap accounting for cached values.
+//!
+//! The entry array of a cache has a fixed size, but values like `String` or `Vec` own heap
+//! memory on top of it. `MemSize` reports that memory, `LRUCache::current_memory` sums it up, and
+//! `MemBoundedCache` evicts entries to keep it under a byte budget.
+
+#[cfg(feature = "alloc")]
+use alloc::{boxed::Box, string::String, vec::Vec};
+use core::mem::size_of;
+
+use crate::{CacheStats, LRUCache, Listener, StatsMode};
+
+// Heap memory owned by a value, not counting the value itself
+pub trait MemSize {
+    fn heap_size(&self) -> usize;
+}
+
+macro_rules! no_heap {
+    ($($t:ty),*) => {
+        $(impl MemSize for $t {
+            #[inline]
+            fn heap_size(&self) -> usize {
+                0
+            }
+        })*
+    };
+}
+
+no_heap!(
+    u8,
+    u16,
+    u32,
+    u64,
+    u128,
+    usize,
+    i8,
+    i16,
+    i32,
+    i64,
+    i128,
+    isize,
+    f32,
+    f64,
+    bool,
+    char,
+    ()
+);
+
+// Borrowed data belongs to someone else
+impl<T: ?Sized> MemSize for &T {
+    #[inline]
+    fn heap_size(&self) -> usize {
+        0
+    }
+}
+
+impl<T: MemSize> MemSize for Option<T> {
+    fn heap_size(&self) -> usize {
+        self.as_ref().map_or(0, T::heap_size)
+    }
+}
+
+impl<T: MemSize, const M: usize> MemSize for [T; M] {
+    fn heap_size(&self) -> usize {
+        self.iter().map(T::heap_size).sum()
+    }
+}
+
+impl<A: MemSize, B: MemSize> MemSize for (A, B) {
+    fn heap_size(&self) -> usize {
+        self.0.heap_size() + self.1.heap_size()
+    }
+}
+
+impl<A: MemSize, B: MemSize, C: MemSize> MemSize for (A, B, C) {
+    fn heap_size(&self) -> usize {
+        self.0.heap_size() + self.1.heap_size() + self.2.heap_size()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl MemSize for String {
+    fn heap_size(&self) -> usize {
+        self.capacity()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T: MemSize> MemSize for Vec<T> {
+    fn heap_size(&self) -> usize {
+        self.capacity() * size_of::<T>() + self.iter().map(T::heap_size).sum::<usize>()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T: MemSize> MemSize for Box<T> {
+    fn heap_size(&self) -> usize {
+        size_of::<T>() + (**self).heap_size()
+    }
+}
+
+impl<T: MemSize, L: Listener<T>, const N: usize> LRUCache<T, N, L> {
+    // Returns the heap memory owned by all cached values, in bytes. This walks every entry.
+    pub fn current_memory(&self) -> usize {
+        self.entries.iter().map(|e| e.val.heap_size()).sum()
+    }
+
+    // Returns the memory taken by the cache and the heap memory owned by its values, in bytes
+    pub fn memory_usage_with_heap(&self) -> usize {
+        self.memory_usage() + self.current_memory()
+    }
+}
+
+// What `MemBoundedCache::insert` does with a value that does not fit in the memory budget
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OversizePolicy {
+    // Evict as many least recently used entries as needed. A value bigger than the whole budget
+    // empties the cache and is stored alone, over budget.
+    EvictAsNeeded,
+    // Like `EvictAsNeeded`, but a value bigger than the whole budget is refused and the cache is
+    // left untouched
+    RejectTooLarge,
+    // Never evict for memory: a value that does not fit in what is left of the budget is refused
+    RejectIfFull,
+}
+
+// An LRU cache of at most `N` entries whose values own at most `limit` bytes of heap memory,
+// least recently used entries being evicted to stay under both bounds
+#[derive(Debug, Clone)]
+pub struct MemBoundedCache<T, const N: usize, L = ()> {
+    cache: LRUCache<T, N, L>,
+    // Heap size of each slot when it was last measured
+    sizes: [usize; N],
+    // Sum of `sizes`
+    used: usize,
+    limit: usize,
+    policy: OversizePolicy,
+}
+
+impl<T: MemSize, const N: usize> MemBoundedCache<T, N> {
+    // create a empty cache holding at most `limit` bytes of heap memory
+    pub const fn new(limit: usize) -> Self {
+        Self::with_listener(limit, ())
+    }
+}
+
+impl<T: MemSize, L: Listener<T>, const N: usize> MemBoundedCache<T, N, L> {
+    // create a empty cache holding at most `limit` bytes of heap memory and reporting its events
+    // to `listener`
+    pub const fn with_listener(limit: usize, listener: L) -> Self {
+        MemBoundedCache {
+            cache: LRUCache::with_listener(listener),
+            sizes: [0; N],
+            used: 0,
+            limit,
+            policy: OversizePolicy::RejectTooLarge,
+        }
+    }
+
+    // Choose what happens to values that do not fit, `OversizePolicy::RejectTooLarge` by default
+    pub const fn with_oversize_policy(mut self, policy: OversizePolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    // Choose which operations are counted in the stats, see `StatsMode`
+    pub const fn with_stats_mode(mut self, mode: StatsMode) -> Self {
+        self.cache.stats_mode = mode;
+        self
+    }
+
+    // Insert given value in cache, evicting least recently used entries until it fits. Returns
+    // how many entries were evicted, or gives the value back if the oversize policy refused it so
+    // that the caller can fall back to something else (store it elsewhere, skip caching, ...).
+    // A cache of capacity 0 gives every value back.
+    pub fn insert(&mut self, val: T) -> Result<usize, T> {
+        self.remeasure_front();
+        let size = val.heap_size();
+        let refused = match self.policy {
+            OversizePolicy::EvictAsNeeded => false,
+            OversizePolicy::RejectTooLarge => size > self.limit,
+            OversizePolicy::RejectIfFull => self.used + size > self.limit,
+        };
+        if refused || N == 0 {
+            return Err(val);
+        }
+
+        let mut evicted = 0;
+
+        while !self.cache.is_empty() && self.used + size > self.limit {
+            self.evict_back();
+            evicted += 1;
+        }
+
+        // Out of slots rather than bytes: the cache reuses the back slot itself
+        if self.cache.entries.is_full() {
+            self.used -= self.sizes[self.cache.tail() as usize];
+            evicted += 1;
+        }
+
+        self.cache.insert(val);
+        self.sizes[self.cache.head() as usize] = size;
+        self.used += size;
+        Ok(evicted)
+    }
+
+    // Returns the first item in the cache that matches the predicate
+    // Make it most recently used on hit. The value may be changed freely, its size is measured
+    // again on the next operation.
+    pub fn find<F>(&mut self, pred: F) -> Option<&mut T>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.remeasure_front();
+        self.cache.find(pred)
+    }
+
+    // Touch the first item in the cache that matches the given predicate and marks it as recently
+    // used, Returns true or false
+    pub fn touch<F>(&mut self, pred: F) -> bool
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.remeasure_front();
+        self.cache.touch(pred)
+    }
+
+    // Returns the heap memory owned by the cached values, in bytes
+    pub fn current_memory(&self) -> usize {
+        match self.cache.entries.get(self.cache.head() as usize) {
+            Some(front) => {
+                self.used - self.sizes[self.cache.head() as usize] + front.val.heap_size()
+            }
+            None => 0,
+        }
+    }
+
+    // Returns the memory taken by the cache itself and the heap memory owned by its values, in
+    // bytes
+    pub fn memory_usage(&self) -> usize {
+        size_of::<Self>() + self.current_memory()
+    }
+
+    // Returns the most heap memory the cached values may own, in bytes
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    // Returns what happens to values that do not fit
+    pub fn oversize_policy(&self) -> OversizePolicy {
+        self.policy
+    }
+
+    // Returns the number of elements in the cache
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    // Returns if cache is empty or not
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+
+    // Clears all the elements in cache
+    pub fn clear(&mut self) {
+        self.cache.clear();
+        self.used = 0;
+    }
+
+    // Returns the usage counters collected so far
+    pub fn stats(&self) -> &CacheStats {
+        self.cache.stats()
+    }
+
+    // Only the front entry can have changed since it was measured, as it is the only one handed
+    // out mutably
+    fn remeasure_front(&mut self) {
+        let head = self.cache.head() as usize;
+        if let Some(front) = self.cache.entries.get(head) {
+            let size = front.val.heap_size();
+            self.used = self.used - self.sizes[head] + size;
+            self.sizes[head] = size;
+        }
+    }
+
+    // Evict the least recently used entry
+    fn evict_back(&mut self) {
+        let tail = self.cache.tail();
+        let last = (self.cache.len() - 1) as u16;
+        self.used -= self.sizes[tail as usize];
+        self.cache.evict_index(tail);
+        if tail != last {
+            self.sizes[tail as usize] = self.sizes[last as usize];
+        }
+    }
+}
@@ -0,0 +1,54 @@
+//! Export of cache stats as counters and gauges.
+//!
+//! `Recorder` has the same shape as the `metrics` facade (absolute counters and gauges keyed by
+//! name), so hooking it up is a matter of forwarding each call to `counter!(name).absolute(v)`
+//! or `gauge!(name).set(v)`.
+
+use std::{format, string::String};
+
+use crate::{LRUCache, Listener};
+
+// Destination for exported values
+pub trait Recorder {
+    // Set the counter `name` to `value`
+    fn absolute_counter(&mut self, name: &str, value: u64);
+    // Set the gauge `name` to `value`
+    fn gauge(&mut self, name: &str, value: f64);
+}
+
+// Publishes one cache's stats under a common prefix, e.g. `dns_cache.hits`
+#[derive(Debug, Clone)]
+pub struct MetricsExporter {
+    hits: String,
+    misses: String,
+    inserts: String,
+    evictions: String,
+    len: String,
+}
+
+impl MetricsExporter {
+    // Metric names are built once here so `export` does not allocate
+    pub fn new(prefix: &str) -> Self {
+        MetricsExporter {
+            hits: format!("{prefix}.hits"),
+            misses: format!("{prefix}.misses"),
+            inserts: format!("{prefix}.inserts"),
+            evictions: format!("{prefix}.evictions"),
+            len: format!("{prefix}.len"),
+        }
+    }
+
+    // Push the current stats and length of `cache` to `recorder`
+    pub fn export<T, L: Listener<T>, R: Recorder, const N: usize>(
+        &self,
+        cache: &LRUCache<T, N, L>,
+        recorder: &mut R,
+    ) {
+        let stats = cache.stats();
+        recorder.absolute_counter(&self.hits, stats.hits);
+        recorder.absolute_counter(&self.misses, stats.misses);
+        recorder.absolute_counter(&self.inserts, stats.inserts);
+        recorder.absolute_counter(&self.evictions, stats.evictions);
+        recorder.gauge(&self.len, cache.len() as f64);
+    }
+}
@@ -0,0 +1,186 @@
+//! An LRU cache that also knows its smallest and largest value.
+//!
+//! `MinMaxLRUCache` keeps the slots of its minimum and maximum next to the recency list, so
+//! `peek_min` and `peek_max` are O(1). Inserting compares the new value against both, also O(1).
+//! Only evicting the current minimum or maximum, or changing a value through `modify`, costs a
+//! rescan of the `N` entries. Values are not handed out mutably otherwise, so the two slots can
+//! never go stale.
+
+use core::cmp::Ordering;
+
+use crate::{CacheStats, LRUCache, Listener};
+
+// Orders the values of a `MinMaxLRUCache`
+pub trait Compare<T> {
+    fn compare(&self, a: &T, b: &T) -> Ordering;
+}
+
+// The `Ord` order of `T`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Natural;
+
+impl<T: Ord> Compare<T> for Natural {
+    #[inline]
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        a.cmp(b)
+    }
+}
+
+impl<T, F: Fn(&T, &T) -> Ordering> Compare<T> for F {
+    #[inline]
+    fn compare(&self, a: &T, b: &T) -> Ordering {
+        self(a, b)
+    }
+}
+
+// An LRU cache of capacity `N` tracking its minimum and maximum value by `C`
+#[derive(Debug, Clone)]
+pub struct MinMaxLRUCache<T, const N: usize, C = Natural, L = ()> {
+    cache: LRUCache<T, N, L>,
+    compare: C,
+    // Slots of the minimum and maximum, meaningless if the cache is empty
+    min: u16,
+    max: u16,
+}
+
+impl<T: Ord, const N: usize> Default for MinMaxLRUCache<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Ord, const N: usize> MinMaxLRUCache<T, N> {
+    // create a empty cache ordering values by `Ord`
+    pub const fn new() -> Self {
+        Self::with_comparator(Natural)
+    }
+}
+
+impl<T, C: Compare<T>, const N: usize> MinMaxLRUCache<T, N, C> {
+    // create a empty cache ordering values by `compare`
+    pub const fn with_comparator(compare: C) -> Self {
+        Self::with_listener(compare, ())
+    }
+}
+
+impl<T, C: Compare<T>, L: Listener<T>, const N: usize> MinMaxLRUCache<T, N, C, L> {
+    // create a empty cache ordering values by `compare` and reporting its events to `listener`
+    pub const fn with_listener(compare: C, listener: L) -> Self {
+        MinMaxLRUCache {
+            cache: LRUCache::with_listener(listener),
+            compare,
+            min: 0,
+            max: 0,
+        }
+    }
+
+    // Insert given value in cache, see `LRUCache::insert`
+    pub fn insert(&mut self, val: T) -> Option<T> {
+        // The slot of the evicted value, if any, is reused for the new one
+        let reused = self.cache.entries.is_full().then(|| self.cache.tail());
+        let evicted = self.cache.insert(val);
+        let i = self.cache.head();
+
+        if self.cache.len() == 1 {
+            self.min = i;
+            self.max = i;
+        } else if reused.is_some_and(|slot| slot == self.min || slot == self.max) {
+            self.rescan();
+        } else {
+            if self.is_less(i, self.min) {
+                self.min = i;
+            }
+            if self.is_less(self.max, i) {
+                self.max = i;
+            }
+        }
+        evicted
+    }
+
+    // Returns the first item in the cache that matches the predicate
+    // Make it most recently used on hit
+    pub fn find<F>(&mut self, pred: F) -> Option<&T>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.cache.find(pred).map(|val| &*val)
+    }
+
+    // Touch the first item in the cache that matches the given predicate and marks it as recently
+    // used, Returns true or false
+    pub fn touch<F>(&mut self, pred: F) -> bool
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.cache.touch(pred)
+    }
+
+    // Run `f` on the first item in the cache that matches the predicate, making it most recently
+    // used, then find the minimum and maximum again. Returns true if an item matched.
+    pub fn modify<F>(&mut self, pred: F, f: impl FnOnce(&mut T)) -> bool
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let Some(val) = self.cache.find(pred) else {
+            return false;
+        };
+        f(val);
+        self.rescan();
+        true
+    }
+
+    // Returns the smallest value in the cache
+    pub fn peek_min(&self) -> Option<&T> {
+        self.cache.entries.get(self.min as usize).map(|e| &e.val)
+    }
+
+    // Returns the largest value in the cache
+    pub fn peek_max(&self) -> Option<&T> {
+        self.cache.entries.get(self.max as usize).map(|e| &e.val)
+    }
+
+    // Returns the number of elements in the cache
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    // Returns if cache is empty or not
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+
+    // Clears all the elements in cache
+    pub fn clear(&mut self) {
+        self.cache.clear();
+    }
+
+    // Returns the usage counters collected so far
+    pub fn stats(&self) -> &CacheStats {
+        self.cache.stats()
+    }
+
+    // Returns the cache, in its LRU order
+    pub fn into_inner(self) -> LRUCache<T, N, L> {
+        self.cache
+    }
+
+    fn is_less(&self, a: u16, b: u16) -> bool {
+        let entries = &self.cache.entries;
+        self.compare
+            .compare(&entries[a as usize].val, &entries[b as usize].val)
+            .is_lt()
+    }
+
+    fn rescan(&mut self) {
+        self.min = 0;
+        self.max = 0;
+        for i in 1..self.cache.len() as u16 {
+            if self.is_less(i, self.min) {
+                self.min = i;
+            }
+            if self.is_less(self.max, i) {
+                self.max = i;
+            }
+        }
+    }
+}
@@ -0,0 +1,233 @@
+//! A keyed LRU map that also keeps its keys in order.
+//!
+//! `OrderedLRUMap` is an `LRUMap` plus a sorted array of its slots, ordered by the key in each.
+//! Next to the eviction order of an LRU it then serves as a small ordered map: the smallest and
+//! largest key, iteration in key order, and lookups by binary search instead of a scan. Keeping
+//! the array sorted costs a shift of up to `N` slot numbers on every insert and removal, which
+//! is cheap at the sizes a fixed-capacity cache has.
+//!
+//! The order also lets a whole range of keys be invalidated at once, e.g. every path under a
+//! directory with `invalidate_prefix`.
+
+use core::{
+    borrow::Borrow,
+    ops::{Bound, RangeBounds},
+};
+
+use arrayvec::ArrayVec;
+
+use crate::{map::LRUMap, CacheStats, Listener, RemovalCause};
+
+// An LRU map of capacity `N` from keys `K` to values `V`, with its keys kept in order
+#[derive(Debug, Clone)]
+pub struct OrderedLRUMap<K, V, const N: usize, L = ()> {
+    map: LRUMap<K, V, N, L>,
+    // Slots of `map`, by increasing key
+    order: ArrayVec<u16, N>,
+}
+
+impl<K, V, L: Listener<V> + Default, const N: usize> Default for OrderedLRUMap<K, V, N, L> {
+    fn default() -> Self {
+        Self::with_listener(L::default())
+    }
+}
+
+impl<K, V, const N: usize> OrderedLRUMap<K, V, N> {
+    // create a empty map
+    pub const fn new() -> Self {
+        Self::with_listener(())
+    }
+}
+
+impl<K, V, L: Listener<V>, const N: usize> OrderedLRUMap<K, V, N, L> {
+    // create a empty map reporting its events to `listener`
+    pub const fn with_listener(listener: L) -> Self {
+        OrderedLRUMap {
+            map: LRUMap::with_listener(listener),
+            order: ArrayVec::new_const(),
+        }
+    }
+
+    // Returns the number of entries in the map
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    // Returns if map is empty or not
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    // Clears all the entries in map
+    pub fn clear(&mut self) {
+        self.order.clear();
+        self.map.clear();
+    }
+
+    // Returns the usage counters collected so far
+    pub fn stats(&self) -> &CacheStats {
+        self.map.stats()
+    }
+
+    // Returns the smallest key, without changing the recency order
+    pub fn first_key(&self) -> Option<&K> {
+        self.order.first().map(|&i| self.map.key(i))
+    }
+
+    // Returns the largest key, without changing the recency order
+    pub fn last_key(&self) -> Option<&K> {
+        self.order.last().map(|&i| self.map.key(i))
+    }
+
+    // Returns the entries by increasing key, without changing the recency order
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&K, &V)> + ExactSizeIterator + '_ {
+        self.order
+            .iter()
+            .map(|&i| (self.map.key(i), self.map.value(i)))
+    }
+}
+
+impl<K: Ord, V, L: Listener<V>, const N: usize> OrderedLRUMap<K, V, N, L> {
+    // Insert `val` under `key`, see `LRUMap::insert`
+    pub fn insert(&mut self, key: K, val: V) -> Option<(K, V)> {
+        let rank = match self.rank(&key) {
+            Ok(rank) => return Some(self.map.overwrite(self.order[rank], key, val)),
+            Err(rank) => rank,
+        };
+        if N == 0 {
+            return Some((key, val));
+        }
+
+        // The least recently used entry leaves the order before its slot is reused
+        let mut rank = rank;
+        if self.map.len() == N {
+            let tail = self.map.back_slot();
+            let evicted = self.rank_of_slot(tail);
+            self.order.remove(evicted);
+            if evicted < rank {
+                rank -= 1;
+            }
+        }
+        let replaced = self.map.insert_new(key, val);
+        self.order.insert(rank, self.map.front_slot());
+        replaced
+    }
+
+    // Returns the value stored under `key`, making it the most recently used entry
+    pub fn get<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let found = self.rank(key).ok().map(|rank| self.order[rank]);
+        self.map.lookup(found)
+    }
+
+    // Returns the value stored under `key` without changing the recency order or the stats
+    pub fn peek<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let rank = self.rank(key).ok()?;
+        Some(self.map.value(self.order[rank]))
+    }
+
+    // Returns true if a value is stored under `key`, without changing the recency order
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.rank(key).is_ok()
+    }
+
+    // Remove the entry stored under `key`, returning its value
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let rank = self.rank(key).ok()?;
+        Some(self.remove_rank(rank).1)
+    }
+
+    // Remove every entry whose key is in `range`, returning how many there were
+    pub fn invalidate_range<Q, R>(&mut self, range: R) -> usize
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        let start = match range.start_bound() {
+            Bound::Included(start) => self.partition(|k| k < start),
+            Bound::Excluded(start) => self.partition(|k| k <= start),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(end) => self.partition(|k| k <= end),
+            Bound::Excluded(end) => self.partition(|k| k < end),
+            Bound::Unbounded => self.order.len(),
+        };
+        self.remove_ranks(start, end)
+    }
+
+    // Remove every entry whose key starts with `prefix`, e.g. `/users/42/`, returning how many
+    // there were
+    pub fn invalidate_prefix(&mut self, prefix: &str) -> usize
+    where
+        K: Borrow<str>,
+    {
+        // Keys starting with the prefix sort right after it, one after the other
+        let start = self.partition(|k: &str| k < prefix);
+        let end = self.partition(|k: &str| k < prefix || k.starts_with(prefix));
+        self.remove_ranks(start, end)
+    }
+
+    // Remove the entries from the `start`-th to before the `end`-th in key order
+    fn remove_ranks(&mut self, start: usize, end: usize) -> usize {
+        // From the last, so that the ranks still to remove stay where they are
+        for rank in (start..end).rev() {
+            self.remove_rank(rank);
+        }
+        end.saturating_sub(start)
+    }
+
+    // Number of leading keys in the order for which `before` holds
+    fn partition<Q: ?Sized>(&self, mut before: impl FnMut(&Q) -> bool) -> usize
+    where
+        K: Borrow<Q>,
+    {
+        self.order
+            .partition_point(|&i| before(self.map.key(i).borrow()))
+    }
+
+    // Take the entry `rank`-th in key order out of the map
+    fn remove_rank(&mut self, rank: usize) -> (K, V) {
+        let i = self.order.remove(rank);
+        // The last slot is moved into the one removed
+        let last = self.map.len() as u16 - 1;
+        if i != last {
+            let moved = self.rank_of_slot(last);
+            self.order[moved] = i;
+        }
+        self.map.remove_index(i, RemovalCause::Explicit)
+    }
+
+    // Position of `key` in the order, or where it would go
+    #[inline]
+    fn rank<Q>(&self, key: &Q) -> Result<usize, usize>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.order
+            .binary_search_by(|&i| self.map.key(i).borrow().cmp(key))
+    }
+
+    // Position of slot `i` in the order
+    fn rank_of_slot(&self, i: u16) -> usize {
+        self.rank(self.map.key(i))
+            .expect("every slot is in the order")
+    }
+}
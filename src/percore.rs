@@ -0,0 +1,129 @@
+//! Per-core caches sharing a victim cache.
+//!
+//! `PerCoreCache` gives every core (or thread) its own small LRU cache, so the common case of a
+//! core finding what it cached itself only ever takes that core's lock, which nobody else takes.
+//! Entries evicted from a core's cache are not lost but spill into one shared victim cache. A
+//! core that misses locally looks there next, and on a hit takes the entry back into its own
+//! cache. No operation holds more than one lock at a time.
+
+use std::{
+    sync::{Mutex, MutexGuard, PoisonError},
+    vec::Vec,
+};
+
+use crate::{CacheStats, Event, LRUCache, Listener, RemovalCause};
+
+// `cores` LRU caches of capacity `N` spilling into a shared victim cache of capacity `V`
+#[derive(Debug)]
+pub struct PerCoreCache<T, const N: usize, const V: usize> {
+    locals: Vec<Mutex<LRUCache<T, N>>>,
+    victims: Mutex<LRUCache<T, V>>,
+}
+
+impl<T, const N: usize, const V: usize> PerCoreCache<T, N, V> {
+    // create empty caches for `cores` cores
+    pub fn new(cores: usize) -> Self {
+        assert!(cores > 0, "a per-core cache needs at least one core");
+        PerCoreCache {
+            locals: (0..cores).map(|_| Mutex::new(LRUCache::new())).collect(),
+            victims: Mutex::new(LRUCache::new()),
+        }
+    }
+
+    // Returns the number of cores
+    pub fn cores(&self) -> usize {
+        self.locals.len()
+    }
+
+    // Insert given value in the cache of `core`. Returns the value pushed out of the victim cache
+    // to make room for the one it evicted, if any.
+    pub fn insert(&self, core: usize, val: T) -> Option<T> {
+        let evicted = lock(&self.locals[core]).insert(val)?;
+        lock(&self.victims).insert(evicted)
+    }
+
+    // Returns a copy of the first item matching the predicate in the cache of `core`, or else in
+    // the victim cache, from which it is moved back to the cache of `core`
+    pub fn get<F>(&self, core: usize, pred: F) -> Option<T>
+    where
+        F: FnMut(&T) -> bool,
+        T: Clone,
+    {
+        self.with(core, pred, |val| val.clone())
+    }
+
+    // Runs `f` on the first item matching the predicate, found like `get` does, while the cache
+    // of `core` is locked
+    pub fn with<F, R>(&self, core: usize, mut pred: F, f: impl FnOnce(&mut T) -> R) -> Option<R>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        if let Some(val) = lock(&self.locals[core]).find(&mut pred) {
+            return Some(f(val));
+        }
+
+        let val = lock(&self.victims).take(pred)?;
+        let mut local = lock(&self.locals[core]);
+        let evicted = local.insert(val);
+        let result = local.front_mut().map(f);
+        drop(local);
+        if let Some(evicted) = evicted {
+            lock(&self.victims).insert(evicted);
+        }
+        result
+    }
+
+    // Returns the number of elements in the cache of `core`
+    pub fn local_len(&self, core: usize) -> usize {
+        lock(&self.locals[core]).len()
+    }
+
+    // Returns the number of elements in the victim cache
+    pub fn victim_len(&self) -> usize {
+        lock(&self.victims).len()
+    }
+
+    // Clears all the elements in every cache, one after the other
+    pub fn clear(&self) {
+        for local in &self.locals {
+            lock(local).clear();
+        }
+        lock(&self.victims).clear();
+    }
+
+    // Returns a copy of the usage counters of the cache of `core`. A miss there that was found in
+    // the victim cache counts as a miss, followed by an insert.
+    pub fn local_stats(&self, core: usize) -> CacheStats {
+        *lock(&self.locals[core]).stats()
+    }
+
+    // Returns a copy of the usage counters of the victim cache
+    pub fn victim_stats(&self) -> CacheStats {
+        *lock(&self.victims).stats()
+    }
+}
+
+impl<T, L: Listener<T>, const N: usize> LRUCache<T, N, L> {
+    // Remove the first item in the cache that matches the predicate and return it
+    fn take<F>(&mut self, pred: F) -> Option<T>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let weight = self.next_tick();
+        let Some(i) = self.position(pred) else {
+            self.record_miss(weight);
+            return None;
+        };
+        self.record_hit(i, weight);
+        self.listener.on_event(Event::Evict(
+            &self.entries[i as usize].val,
+            RemovalCause::Explicit,
+        ));
+        Some(self.remove_index(i))
+    }
+}
+
+// Caches are left consistent by a panicking predicate, so a poisoned one is still used
+fn lock<C>(cache: &Mutex<C>) -> MutexGuard<'_, C> {
+    cache.lock().unwrap_or_else(PoisonError::into_inner)
+}
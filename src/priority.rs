@@ -0,0 +1,210 @@
+//! Priority classes.
+//!
+//! `PriorityCache` is an `LRUCache` where every entry has one of `LEVELS` priorities, 0 being the
+//! lowest. Eviction takes the least recently used entry of the lowest priority present, so
+//! entries that are expensive to recompute can be protected from a stream of cheap ones without
+//! pinning them: once only high priority entries are left, they are evicted in LRU order too.
+//! Priorities live in a dense array next to the entries, like the tags of a `TaggedCache`, along
+//! with a count per priority, and finding the entry to evict walks up from the tail until it
+//! meets one of the lowest priority present.
+
+use core::mem::replace;
+
+use arrayvec::ArrayVec;
+
+use crate::{CacheStats, Event, LRUCache, Listener, RemovalCause};
+
+// Number of priorities, `0..LEVELS`
+pub const LEVELS: usize = 4;
+
+// An LRU cache of capacity `N` of values `T`, each with a priority
+#[derive(Debug, Clone)]
+pub struct PriorityCache<T, const N: usize, L = ()> {
+    cache: LRUCache<T, N, L>,
+    // Priority of each slot of `cache`
+    priorities: ArrayVec<u8, N>,
+    // Number of entries at each priority
+    counts: [u16; LEVELS],
+}
+
+impl<T, L: Listener<T> + Default, const N: usize> Default for PriorityCache<T, N, L> {
+    fn default() -> Self {
+        Self::with_listener(L::default())
+    }
+}
+
+impl<T, const N: usize> PriorityCache<T, N> {
+    // create a empty cache
+    pub const fn new() -> Self {
+        Self::with_listener(())
+    }
+}
+
+impl<T, L: Listener<T>, const N: usize> PriorityCache<T, N, L> {
+    // create a empty cache reporting its events to `listener`
+    pub const fn with_listener(listener: L) -> Self {
+        PriorityCache {
+            cache: LRUCache::with_listener(listener),
+            priorities: ArrayVec::new_const(),
+            counts: [0; LEVELS],
+        }
+    }
+
+    // Insert given value at the lowest priority
+    pub fn insert(&mut self, val: T) -> Option<T> {
+        self.insert_with_priority(val, 0)
+    }
+
+    // Insert `val` at `priority`, evicting the least recently used entry of the lowest priority
+    // present if the cache is full. Returns the evicted value, or `val` for a cache of capacity
+    // 0. Panics if `priority` is not below `LEVELS`.
+    pub fn insert_with_priority(&mut self, val: T, priority: u8) -> Option<T> {
+        assert!((priority as usize) < LEVELS, "priority out of range");
+        let evicted = match self.victim() {
+            Some(i) if self.len() == N => {
+                let evicted = self.cache.evict_index(i);
+                self.take_priority(i);
+                Some(evicted)
+            }
+            _ => None,
+        };
+        if let Some(val) = self.cache.insert(val) {
+            // Only a cache of capacity 0 hands the value back
+            return Some(val);
+        }
+        self.priorities.push(priority);
+        self.counts[priority as usize] += 1;
+        evicted
+    }
+
+    // Returns the first item in the cache that matches the predicate
+    // Make it most recently used on hit
+    pub fn find<F>(&mut self, pred: F) -> Option<&mut T>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.cache.find(pred)
+    }
+
+    // Touch the first item in the cache that matches the given predicate and marks it as recently
+    // used, Returns true or false
+    pub fn touch<F>(&mut self, pred: F) -> bool
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.cache.touch(pred)
+    }
+
+    // Returns the priority of the first item in recency order that matches the predicate, without
+    // touching it
+    pub fn priority_of<F>(&self, mut pred: F) -> Option<u8>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.entries()
+            .find(|(val, _)| pred(val))
+            .map(|(_, priority)| priority)
+    }
+
+    // Move the first item in recency order that matches the predicate to `priority`, without
+    // touching it. Returns true or false. Panics if `priority` is not below `LEVELS`.
+    pub fn set_priority<F>(&mut self, pred: F, priority: u8) -> bool
+    where
+        F: FnMut(&T) -> bool,
+    {
+        assert!((priority as usize) < LEVELS, "priority out of range");
+        let Some(i) = self.cache.position(pred) else {
+            return false;
+        };
+        let old = replace(&mut self.priorities[i as usize], priority);
+        self.counts[old as usize] -= 1;
+        self.counts[priority as usize] += 1;
+        true
+    }
+
+    // Returns the number of entries at `priority`
+    pub fn count_priority(&self, priority: u8) -> usize {
+        self.counts
+            .get(priority as usize)
+            .map_or(0, |&n| n as usize)
+    }
+
+    // Returns the value the next eviction would take, with its priority, without touching it
+    pub fn peek_victim(&self) -> Option<(&T, u8)> {
+        let i = self.victim()?;
+        Some((
+            &self.cache.entries[i as usize].val,
+            self.priorities[i as usize],
+        ))
+    }
+
+    // Removes the value the next eviction would take and returns it with its priority
+    pub fn pop_victim(&mut self) -> Option<(T, u8)> {
+        let i = self.victim()?;
+        self.cache.listener.on_event(Event::Evict(
+            &self.cache.entries[i as usize].val,
+            RemovalCause::Explicit,
+        ));
+        let val = self.cache.remove_index(i);
+        Some((val, self.take_priority(i)))
+    }
+
+    // Returns the values with their priorities from most to least recently used, without
+    // changing the recency order or the stats
+    pub fn entries(&self) -> impl ExactSizeIterator<Item = (&T, u8)> + '_ {
+        let mut i = self.cache.head();
+        (0..self.len()).map(move |_| {
+            let slot = i;
+            i = self.cache.entries[slot as usize].link.next();
+            (
+                &self.cache.entries[slot as usize].val,
+                self.priorities[slot as usize],
+            )
+        })
+    }
+
+    // Returns the number of elements in the cache
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    // Returns if cache is empty or not
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+
+    // Clears all the elements in cache
+    pub fn clear(&mut self) {
+        self.priorities.clear();
+        self.counts = [0; LEVELS];
+        self.cache.clear();
+    }
+
+    // Returns the usage counters collected so far
+    pub fn stats(&self) -> &CacheStats {
+        self.cache.stats()
+    }
+
+    // Returns the listener
+    pub fn listener(&self) -> &L {
+        self.cache.listener()
+    }
+
+    // Slot of the least recently used entry of the lowest priority present
+    fn victim(&self) -> Option<u16> {
+        let lowest = self.counts.iter().position(|&n| n != 0)? as u8;
+        let mut i = self.cache.tail();
+        while self.priorities[i as usize] != lowest {
+            i = self.cache.entries[i as usize].link.prev();
+        }
+        Some(i)
+    }
+
+    // Forget the priority of slot `i`, just removed from `cache`, moving the last slot into its
+    // place like `cache` did. Returns it.
+    fn take_priority(&mut self, i: u16) -> u8 {
+        let priority = self.priorities.swap_remove(i as usize);
+        self.counts[priority as usize] -= 1;
+        priority
+    }
+}
@@ -0,0 +1,414 @@
+//! Kani harnesses for the recency list of `LRUCache`.
+//!
+//! Run with `cargo kani`. Each harness starts from an arbitrary valid cache of capacity `N`: any
+//! number of entries holding any values, linked in any order. It then applies one operation with
+//! arbitrary arguments and proves the list is still valid (see `assert_links`): walking from the
+//! head reaches every entry exactly once and ends at the tail, each entry's `prev` is the entry
+//! before it, and no index reaches `len`. The proofs cover every cache of capacity 4, which already
+//! has every shape the list code tells apart: empty, a single entry, head and tail adjacent, and
+//! entries in between.
+//!
+//! Under `cargo test` the same harnesses run as ordinary tests on random inputs instead, `RUNS`
+//! times each: the `kani` module below stands in for Kani with a random generator. That proves
+//! nothing, but it keeps the harnesses building and checks them between Kani runs.
+
+use crate::{Entry, LRUCache, Link, Slot, Stamp, SWEEP_PERIOD};
+
+const N: usize = 4;
+
+// Runs of each harness under `cargo test`
+#[cfg(not(kani))]
+const RUNS: usize = 2000;
+
+type Cache = LRUCache<u8, N>;
+
+impl<T, L, const M: usize> LRUCache<T, M, L> {
+    // Check that the links form one list over all the entries, see the module docs
+    fn assert_links(&self) {
+        let len = self.entries.len();
+        assert!(len <= M);
+        if len == 0 {
+            return;
+        }
+        let (head, tail) = (self.head.get() as usize, self.tail.get() as usize);
+        assert!(head < len && tail < len);
+        let mut seen = [false; M];
+        let mut at = head;
+        for step in 0..len {
+            assert!(at < len && !seen[at]);
+            seen[at] = true;
+            if step + 1 == len {
+                assert!(at == tail);
+            } else {
+                let next = self.entries[at].link.next() as usize;
+                assert!(next < len && self.entries[next].link.prev() as usize == at);
+                at = next;
+            }
+        }
+    }
+}
+
+// An arbitrary number below `n`, which is not 0
+#[cfg(kani)]
+fn any_below(n: usize) -> usize {
+    let i: usize = kani::any();
+    kani::assume(i < n);
+    i
+}
+
+#[cfg(not(kani))]
+fn any_below(n: usize) -> usize {
+    kani::below(n)
+}
+
+// An arbitrary index of an entry of `cache`, `None` if it is empty
+fn any_index(cache: &Cache) -> Option<u16> {
+    (!cache.is_empty()).then(|| any_below(cache.len()) as u16)
+}
+
+// A valid cache of up to `N` arbitrary values, linked in an arbitrary order, at an arbitrary tick
+// and inserted at arbitrary ticks before it, as far back as `sweep` lets ages go
+fn any_cache() -> Cache {
+    let len = any_below(N + 1);
+    // `order[k]` is the index of the `k`th most recently used entry: any permutation of `0..len`,
+    // shuffled by Fisher-Yates
+    let mut order: [u16; N] = core::array::from_fn(|i| i as u16);
+    for k in (1..len).rev() {
+        order.swap(k, any_below(k + 1));
+    }
+
+    let mut cache = Cache::new();
+    cache.tick = kani::any();
+    for _ in 0..len {
+        let age = any_below(2 * SWEEP_PERIOD as usize + 1) as u32;
+        cache.entries.push(Entry {
+            val: kani::any(),
+            link: Link::new(),
+            stamp: Stamp::new(cache.tick.wrapping_sub(age)),
+        });
+    }
+    for k in 0..len {
+        // The head's `prev` and the tail's `next` are never read, so they keep arbitrary bits
+        let mut link = Link(kani::any());
+        if k > 0 {
+            link.set_prev(order[k - 1]);
+        }
+        if k + 1 < len {
+            link.set_next(order[k + 1]);
+        }
+        cache.entries[order[k] as usize].link = link;
+    }
+    if len > 0 {
+        cache.head = Slot::new(order[0]);
+        cache.tail = Slot::new(order[len - 1]);
+    }
+    cache.assert_links();
+    cache
+}
+
+#[cfg_attr(kani, kani::proof, kani::unwind(6))]
+fn insert_keeps_the_list_valid() {
+    let mut cache = any_cache();
+    let len = cache.len();
+    let evicted = cache.insert(kani::any());
+    assert!(evicted.is_some() == (len == N));
+    assert!(cache.len() == (len + 1).min(N));
+    cache.assert_links();
+}
+
+#[cfg_attr(kani, kani::proof, kani::unwind(6))]
+fn find_keeps_the_list_valid() {
+    let mut cache = any_cache();
+    let len = cache.len();
+    let wanted: u8 = kani::any();
+    let found = cache.find(|v| *v == wanted).is_some();
+    assert!(cache.len() == len);
+    if found {
+        assert!(cache.peek_mru() == Some(&wanted));
+    }
+    cache.assert_links();
+}
+
+#[cfg_attr(kani, kani::proof, kani::unwind(6))]
+fn touch_index_keeps_the_list_valid() {
+    let mut cache = any_cache();
+    let Some(i) = any_index(&cache) else { return };
+    cache.touch_index(i);
+    assert!(cache.head() == i);
+    cache.assert_links();
+}
+
+#[cfg_attr(kani, kani::proof, kani::unwind(6))]
+fn pop_lru_keeps_the_list_valid() {
+    let mut cache = any_cache();
+    let len = cache.len();
+    let lru = cache.peek_lru().copied();
+    assert!(cache.pop_lru() == lru);
+    assert!(cache.len() == len.saturating_sub(1));
+    cache.assert_links();
+}
+
+#[cfg_attr(kani, kani::proof, kani::unwind(6))]
+fn remove_index_keeps_the_list_valid() {
+    let mut cache = any_cache();
+    let len = cache.len();
+    let Some(i) = any_index(&cache) else { return };
+    let val = cache.entries[i as usize].val;
+    assert!(cache.remove_index(i) == val);
+    assert!(cache.len() == len - 1);
+    cache.assert_links();
+}
+
+#[cfg_attr(kani, kani::proof, kani::unwind(6))]
+fn overwrite_keeps_the_list_valid() {
+    let mut cache = any_cache();
+    let Some(i) = any_index(&cache) else { return };
+    let val: u8 = kani::any();
+    cache.overwrite(i, val);
+    assert!(cache.peek_mru() == Some(&val));
+    cache.assert_links();
+}
+
+#[cfg_attr(kani, kani::proof, kani::unwind(6))]
+fn clear_keeps_the_list_valid() {
+    let mut cache = any_cache();
+    cache.clear();
+    assert!(cache.is_empty());
+    cache.assert_links();
+    // A cleared cache is as good as a new one
+    cache.insert(kani::any());
+    cache.assert_links();
+}
+
+#[cfg_attr(kani, kani::proof, kani::unwind(6))]
+fn a_transient_entry_goes_with_its_first_hit() {
+    let mut cache = any_cache();
+    let val: u8 = kani::any();
+    cache.insert_transient(val);
+    cache.assert_links();
+    let len = cache.len();
+    if kani::any() {
+        // Removed on the spot
+        assert!(cache.touch(|v| *v == val));
+        assert!(cache.len() == len - 1);
+    } else {
+        // Handed out, so spent and moved to the tail until the next operation
+        assert!(cache.find(|v| *v == val).is_some());
+        assert!(cache.len() == len && cache.peek_lru() == Some(&val));
+        cache.assert_links();
+        assert!(cache.find(|_| false).is_none());
+        assert!(cache.len() == len - 1);
+    }
+    cache.assert_links();
+}
+
+#[cfg_attr(kani, kani::proof, kani::unwind(6))]
+fn probation_inserts_land_at_the_window() {
+    let window = any_below(N + 1);
+    let mut cache = any_cache().with_probation(window);
+    let val: u8 = kani::any();
+    cache.insert(val);
+    cache.assert_links();
+    // The new entry is `window`-th from the tail, unless there were fewer entries than that
+    let len = cache.len();
+    let rank = if window == 0 || len - 1 < window {
+        0
+    } else {
+        len - window
+    };
+    assert!(cache.iter().nth(rank) == Some(&val));
+}
+
+#[cfg_attr(kani, kani::proof, kani::unwind(6))]
+fn watermarks_evict_down_to_the_low_one() {
+    let low = any_below(N);
+    let high = low + 1 + any_below(N - low);
+    let cache = any_cache();
+    if cache.len() > high {
+        return;
+    }
+    let mut cache = cache.with_watermarks(low, high);
+    let len = cache.len();
+    let evicted = cache.insert(kani::any());
+    cache.assert_links();
+    if len == high {
+        // The batch is dropped, not handed back
+        assert!(evicted.is_none() && cache.len() == low + 1);
+    } else {
+        assert!(evicted.is_none() && cache.len() == len + 1);
+    }
+    // And `maintain` takes the cache the rest of the way
+    let len = cache.len();
+    let budget = any_below(N + 2);
+    let done = cache.maintain(budget);
+    assert!(done == budget.min(len.saturating_sub(low)) && cache.len() == len - done);
+    cache.assert_links();
+}
+
+#[cfg_attr(kani, kani::proof, kani::unwind(6))]
+fn stale_entries_go_before_fresh_ones() {
+    let mut cache = any_cache();
+    let old = cache.len();
+    cache.bump_epoch();
+    // Not so many that an old entry is evicted
+    let fresh = any_below(N - old + 1);
+    for _ in 0..fresh {
+        assert!(cache.insert(kani::any()).is_none());
+    }
+    cache.assert_links();
+    if kani::any() {
+        assert!(cache.purge_stale() == old);
+        assert!(cache.len() == fresh);
+    } else {
+        // The old entries are all behind the fresh ones
+        let budget = any_below(N + 2);
+        assert!(cache.maintain(budget) == budget.min(old));
+        assert!(cache.len() == old + fresh - budget.min(old));
+    }
+    cache.assert_links();
+}
+
+#[cfg_attr(kani, kani::proof, kani::unwind(6))]
+fn cursor_inserts_and_removals_keep_the_list_valid() {
+    let mut cache = any_cache();
+    let len = cache.len();
+    let mut cursor = if kani::any() {
+        cache.cursor_front_mut()
+    } else {
+        cache.cursor_back_mut()
+    };
+    for _ in 0..any_below(N + 1) {
+        if kani::any() {
+            cursor.move_next();
+        } else {
+            cursor.move_prev();
+        }
+    }
+    let at_ghost = cursor.current().is_none();
+    let val: u8 = kani::any();
+    match any_below(3) {
+        0 => {
+            assert!(cursor.insert_before(val).is_ok() == (len < N));
+            if len < N {
+                assert!(cache.len() == len + 1);
+                if at_ghost {
+                    assert!(cache.peek_lru() == Some(&val));
+                }
+            }
+        }
+        1 => {
+            assert!(cursor.insert_after(val).is_ok() == (len < N));
+            if len < N {
+                assert!(cache.len() == len + 1);
+                if at_ghost {
+                    assert!(cache.peek_mru() == Some(&val));
+                }
+            }
+        }
+        _ => {
+            assert!(cursor.remove_current().is_some() != at_ghost);
+            assert!(cache.len() == len - !at_ghost as usize);
+        }
+    }
+    cache.assert_links();
+}
+
+#[cfg_attr(kani, kani::proof, kani::unwind(6))]
+fn extract_if_takes_exactly_the_matches() {
+    let mut cache = any_cache();
+    let len = cache.len();
+    let wanted: u8 = kani::any();
+    let matches = cache.iter().filter(|v| **v == wanted).count();
+    assert!(cache.extract_if(|v| *v == wanted).count() == matches);
+    assert!(cache.len() == len - matches);
+    assert!(cache.iter().all(|v| *v != wanted));
+    cache.assert_links();
+}
+
+// Stands in for Kani under `cargo test`: arbitrary values are random, drawn for `u8` from a few
+// so that lookups hit as often as they miss
+#[cfg(not(kani))]
+mod kani {
+    extern crate std;
+
+    use core::cell::Cell;
+
+    std::thread_local! {
+        static STATE: Cell<u64> = const { Cell::new(178) };
+    }
+
+    // SplitMix64
+    fn next() -> u64 {
+        STATE.with(|state| {
+            let x = state.get().wrapping_add(0x9e37_79b9_7f4a_7c15);
+            state.set(x);
+            let x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            let x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            x ^ (x >> 31)
+        })
+    }
+
+    pub trait Arbitrary {
+        fn any() -> Self;
+    }
+
+    impl Arbitrary for u8 {
+        fn any() -> Self {
+            (next() % 6) as u8
+        }
+    }
+
+    impl Arbitrary for u32 {
+        fn any() -> Self {
+            next() as u32
+        }
+    }
+
+    impl Arbitrary for bool {
+        fn any() -> Self {
+            next() & 1 != 0
+        }
+    }
+
+    pub fn any<T: Arbitrary>() -> T {
+        T::any()
+    }
+
+    pub fn below(n: usize) -> usize {
+        (next() % n as u64) as usize
+    }
+}
+
+// Runs each harness `RUNS` times on random inputs, as a test of the same name
+#[cfg(not(kani))]
+macro_rules! random_tests {
+    ($($harness:ident),* $(,)?) => {
+        mod random {
+            $(
+                #[test]
+                fn $harness() {
+                    for _ in 0..super::RUNS {
+                        super::$harness();
+                    }
+                }
+            )*
+        }
+    };
+}
+
+#[cfg(not(kani))]
+random_tests!(
+    insert_keeps_the_list_valid,
+    find_keeps_the_list_valid,
+    touch_index_keeps_the_list_valid,
+    pop_lru_keeps_the_list_valid,
+    remove_index_keeps_the_list_valid,
+    overwrite_keeps_the_list_valid,
+    clear_keeps_the_list_valid,
+    a_transient_entry_goes_with_its_first_hit,
+    probation_inserts_land_at_the_window,
+    watermarks_evict_down_to_the_low_one,
+    stale_entries_go_before_fresh_ones,
+    cursor_inserts_and_removals_keep_the_list_valid,
+    extract_if_takes_exactly_the_matches,
+);
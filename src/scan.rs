@@ -0,0 +1,64 @@
+//! Fast key scans for `LRUMap`.
+//!
+//! Integer and small byte-array keys can be compared many at a time. With the `simd` feature the
+//! key array is scanned in fixed chunks of `LANES` keys, each turned into a bit mask of matches
+//! without branching, a shape the compiler lowers to vector compares on targets that have them.
+//! Without it the scan is a plain scalar loop. Both work without `std`.
+
+// Keys compared per chunk
+#[cfg(feature = "simd")]
+const LANES: usize = 16;
+
+// A key that can be found in a key array faster than by comparing one key at a time
+pub trait ScanKey: Copy + Eq {
+    // Index of the first key equal to `key`
+    fn position(keys: &[Self], key: Self) -> Option<usize>;
+}
+
+#[cfg(feature = "simd")]
+#[inline]
+fn chunked_position<K: Copy + Eq>(keys: &[K], key: K) -> Option<usize> {
+    let mut chunks = keys.chunks_exact(LANES);
+    let mut base = 0;
+    for chunk in &mut chunks {
+        let mut mask = 0u32;
+        for (j, k) in chunk.iter().enumerate() {
+            mask |= ((*k == key) as u32) << j;
+        }
+        if mask != 0 {
+            return Some(base + mask.trailing_zeros() as usize);
+        }
+        base += LANES;
+    }
+    chunks
+        .remainder()
+        .iter()
+        .position(|k| *k == key)
+        .map(|j| base + j)
+}
+
+#[cfg(not(feature = "simd"))]
+#[inline]
+fn chunked_position<K: Copy + Eq>(keys: &[K], key: K) -> Option<usize> {
+    keys.iter().position(|k| *k == key)
+}
+
+macro_rules! scan_key {
+    ($($t:ty),*) => {
+        $(impl ScanKey for $t {
+            #[inline]
+            fn position(keys: &[Self], key: Self) -> Option<usize> {
+                chunked_position(keys, key)
+            }
+        })*
+    };
+}
+
+scan_key!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, char);
+
+impl<const M: usize> ScanKey for [u8; M] {
+    #[inline]
+    fn position(keys: &[Self], key: Self) -> Option<usize> {
+        chunked_position(keys, key)
+    }
+}
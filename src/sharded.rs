@@ -0,0 +1,163 @@
+//! A keyed LRU map shared between threads.
+//!
+//! `ShardedLRUMap` splits its keys over several `LRUMap`s, each behind its own mutex, so threads
+//! working on different keys rarely wait for each other. Which shard a key goes to is picked by
+//! hashing it. Each shard is an independent LRU of capacity `N`, so the least recently used entry
+//! is evicted per shard rather than over the whole map.
+//!
+//! Keys are hashed with `FxHasher` unless another hasher is given. Keys chosen to collide can
+//! pile onto one shard, so a map fed untrusted keys should pass std's `RandomState`.
+
+use core::{
+    borrow::Borrow,
+    hash::{BuildHasher, Hash},
+};
+use std::{
+    sync::{Mutex, MutexGuard, PoisonError},
+    vec::Vec,
+};
+
+use crate::{hash::FxBuildHasher, map::LRUMap, CacheStats};
+
+// A thread-safe LRU map of `shards` shards of capacity `N` each
+#[derive(Debug)]
+pub struct ShardedLRUMap<K, V, const N: usize, S = FxBuildHasher> {
+    shards: Vec<Mutex<LRUMap<K, V, N>>>,
+    hasher: S,
+}
+
+impl<K: Hash + Eq, V, const N: usize> ShardedLRUMap<K, V, N> {
+    // create a empty map of `shards` shards, hashing keys with `FxHasher`
+    pub fn new(shards: usize) -> Self {
+        Self::with_hasher(shards, FxBuildHasher)
+    }
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher, const N: usize> ShardedLRUMap<K, V, N, S> {
+    // create a empty map of `shards` shards, picking the shard of a key with `hasher`
+    pub fn with_hasher(shards: usize, hasher: S) -> Self {
+        assert!(shards > 0, "a sharded map needs at least one shard");
+        ShardedLRUMap {
+            shards: (0..shards).map(|_| Mutex::new(LRUMap::new())).collect(),
+            hasher,
+        }
+    }
+
+    // Insert `val` under `key` in its shard, see `LRUMap::insert`
+    pub fn insert(&self, key: K, val: V) -> Option<(K, V)> {
+        self.shard(&key).insert(key, val)
+    }
+
+    // Returns a copy of the value stored under `key`, making it the most recently used entry of
+    // its shard
+    pub fn get<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+        V: Clone,
+    {
+        self.shard(key).get(key).cloned()
+    }
+
+    // Runs `f` on the value stored under `key` while its shard is locked, making it the most
+    // recently used entry of its shard
+    pub fn get_with<Q, R>(&self, key: &Q, f: impl FnOnce(&mut V) -> R) -> Option<R>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.shard(key).get(key).map(f)
+    }
+
+    // Returns true if a value is stored under `key`, without changing the recency order
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.shard(key).contains_key(key)
+    }
+
+    // Remove the entry stored under `key`, returning its value
+    pub fn remove<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.shard(key).remove(key)
+    }
+
+    // Returns the number of entries in the map. Shards are counted one after the other, so
+    // concurrent writes may or may not be included.
+    pub fn len(&self) -> usize {
+        self.shard_occupancy().sum()
+    }
+
+    // Returns if map is empty or not
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Clears all the entries in map, one shard after the other
+    pub fn clear(&self) {
+        for i in 0..self.shards.len() {
+            self.lock(i).clear();
+        }
+    }
+
+    // Returns the number of shards
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    // Returns the number of entries in each shard. A few shards much fuller than the others
+    // point at keys the hasher does not spread well.
+    pub fn shard_occupancy(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.shards.len()).map(|i| self.lock(i).len())
+    }
+
+    // Returns the usage counters of all shards added up. Shards are locked one after the other,
+    // each only long enough to copy its counters, so scraping never holds up more than one shard.
+    pub fn stats(&self) -> CacheStats {
+        let mut total = CacheStats::new();
+        for stats in self.shard_stats() {
+            total.merge(&stats);
+        }
+        total
+    }
+
+    // Returns the usage counters of each shard, locking them one after the other
+    pub fn shard_stats(&self) -> impl Iterator<Item = CacheStats> + '_ {
+        (0..self.shards.len()).map(|i| *self.lock(i).stats())
+    }
+
+    // Returns which shard `key` belongs to
+    pub fn shard_of<Q>(&self, key: &Q) -> usize
+    where
+        K: Borrow<Q>,
+        Q: Hash + ?Sized,
+    {
+        (self.hasher.hash_one(key) % self.shards.len() as u64) as usize
+    }
+
+    // Returns the hasher picking the shard of a key
+    pub fn hasher(&self) -> &S {
+        &self.hasher
+    }
+
+    fn shard<Q>(&self, key: &Q) -> MutexGuard<'_, LRUMap<K, V, N>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + ?Sized,
+    {
+        self.lock(self.shard_of(key))
+    }
+
+    // Keep using a shard poisoned by a panic in a `K` or `V` impl, rather than turning every later
+    // call on its keys into a panic too
+    fn lock(&self, i: usize) -> MutexGuard<'_, LRUMap<K, V, N>> {
+        self.shards[i]
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
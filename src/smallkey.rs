@@ -0,0 +1,169 @@
+//! A keyed LRU map storing short byte keys inline.
+//!
+//! Most keys a cache sees in practice (names, short paths, identifiers) are only a few bytes
+//! long, yet a map of owned keys makes every one of them a heap allocation. `SmallKeyMap` stores
+//! keys of up to `K` bytes inline in its key array, and only keys longer than that go through a
+//! `LongKey` type of the caller's choosing: a `Box<[u8]>` with `alloc`, or e.g. an offset into an
+//! arena kept by the caller on targets without a heap.
+//!
+//! Inline keys are compared by length first, then bytes, so the scan rarely touches more than the
+//! length of a key that does not match. `K` is at most 255.
+
+#[cfg(feature = "alloc")]
+use alloc::{boxed::Box, vec::Vec};
+
+use crate::{map::LRUMap, CacheStats, Listener};
+
+// Storage for the keys longer than a `SmallKeyMap` stores inline
+pub trait LongKey {
+    // Store `bytes`, which is longer than any inline key
+    fn from_bytes(bytes: &[u8]) -> Self;
+    // Returns true if this is the key `bytes`
+    fn eq_bytes(&self, bytes: &[u8]) -> bool;
+}
+
+#[cfg(feature = "alloc")]
+impl LongKey for Box<[u8]> {
+    fn from_bytes(bytes: &[u8]) -> Self {
+        bytes.into()
+    }
+
+    #[inline]
+    fn eq_bytes(&self, bytes: &[u8]) -> bool {
+        **self == *bytes
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl LongKey for Vec<u8> {
+    fn from_bytes(bytes: &[u8]) -> Self {
+        bytes.into()
+    }
+
+    #[inline]
+    fn eq_bytes(&self, bytes: &[u8]) -> bool {
+        **self == *bytes
+    }
+}
+
+// A key of a `SmallKeyMap`, inline if it is up to `K` bytes long
+#[derive(Debug, Clone)]
+pub enum SmallKey<const K: usize, X> {
+    Inline { len: u8, bytes: [u8; K] },
+    Long(X),
+}
+
+impl<const K: usize, X: LongKey> SmallKey<K, X> {
+    // Fails the build for inline keys whose length does not fit in a `u8`
+    const LEN_FITS: () = assert!(K <= u8::MAX as usize, "inline keys are at most 255 bytes");
+
+    // Store `key`, inline if it fits
+    pub fn new(key: &[u8]) -> Self {
+        let () = Self::LEN_FITS;
+        if key.len() <= K {
+            let mut bytes = [0; K];
+            bytes[..key.len()].copy_from_slice(key);
+            SmallKey::Inline {
+                len: key.len() as u8,
+                bytes,
+            }
+        } else {
+            SmallKey::Long(X::from_bytes(key))
+        }
+    }
+
+    // Returns true if this is the key `key`
+    #[inline]
+    pub fn matches(&self, key: &[u8]) -> bool {
+        match self {
+            SmallKey::Inline { len, bytes } => {
+                *len as usize == key.len() && bytes[..key.len()] == *key
+            }
+            SmallKey::Long(long) => key.len() > K && long.eq_bytes(key),
+        }
+    }
+
+    // Returns true if the key is stored inline
+    pub fn is_inline(&self) -> bool {
+        matches!(self, SmallKey::Inline { .. })
+    }
+}
+
+// An LRU map of capacity `N` from byte keys to values `V`, storing keys of up to `K` bytes
+// inline and longer ones as an `X`
+#[derive(Debug, Clone)]
+pub struct SmallKeyMap<X, V, const N: usize, const K: usize, L = ()> {
+    map: LRUMap<SmallKey<K, X>, V, N, L>,
+}
+
+impl<X, V, L: Listener<V> + Default, const N: usize, const K: usize> Default
+    for SmallKeyMap<X, V, N, K, L>
+{
+    fn default() -> Self {
+        Self::with_listener(L::default())
+    }
+}
+
+impl<X, V, const N: usize, const K: usize> SmallKeyMap<X, V, N, K> {
+    // create a empty map
+    pub const fn new() -> Self {
+        Self::with_listener(())
+    }
+}
+
+impl<X, V, L: Listener<V>, const N: usize, const K: usize> SmallKeyMap<X, V, N, K, L> {
+    // create a empty map reporting its events to `listener`
+    pub const fn with_listener(listener: L) -> Self {
+        SmallKeyMap {
+            map: LRUMap::with_listener(listener),
+        }
+    }
+}
+
+impl<X: LongKey, V, L: Listener<V>, const N: usize, const K: usize> SmallKeyMap<X, V, N, K, L> {
+    // Insert `val` under `key`, see `LRUMap::insert`
+    pub fn insert(&mut self, key: &[u8], val: V) -> Option<(SmallKey<K, X>, V)> {
+        self.map
+            .insert_by(|k| k.matches(key), SmallKey::new(key), val)
+    }
+
+    // Returns the value stored under `key`, making it the most recently used entry
+    pub fn get(&mut self, key: &[u8]) -> Option<&mut V> {
+        self.map.get_by(|k| k.matches(key))
+    }
+
+    // Returns the value stored under `key` without changing the recency order or the stats
+    pub fn peek(&self, key: &[u8]) -> Option<&V> {
+        self.map.peek_by(|k| k.matches(key))
+    }
+
+    // Returns true if a value is stored under `key`, without changing the recency order
+    pub fn contains_key(&self, key: &[u8]) -> bool {
+        self.peek(key).is_some()
+    }
+
+    // Remove the entry stored under `key`, returning its value
+    pub fn remove(&mut self, key: &[u8]) -> Option<V> {
+        self.map.remove_by(|k| k.matches(key)).map(|(_, val)| val)
+    }
+
+    // Returns the number of entries in the map
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    // Returns if map is empty or not
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    // Clears all the entries in map
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+
+    // Returns the usage counters collected so far
+    pub fn stats(&self) -> &CacheStats {
+        self.map.stats()
+    }
+}
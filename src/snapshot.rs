@@ -0,0 +1,483 @@
+//! Saving a cache as raw bytes and restoring it.
+//!
+//! A cache of `Plain` values can be written to a byte buffer as it is laid out: the head and
+//! tail of the list, then every entry with its links and age. Restoring reads it back without
+//! replaying any insert, so on a microcontroller with battery-backed RAM a warm cache survives a
+//! reset for the price of a copy. The listener is not saved.
+//!
+//! A full snapshot also holds the stats, the stats mode and the window of recent lookups, so the
+//! restored cache reports the same hit ratios and lifetimes as if it had never gone away. Each
+//! entry carries the tick it was inserted at either way, which is what its age is measured from.
+//!
+//! The bytes are little-endian whatever the target, so a snapshot can move between devices.
+//! Restoring checks everything it relies on (the capacity, every index, that the links form one
+//! list through all the entries, every value) and reports `CacheError::SnapshotCorrupt` for
+//! anything else, so a buffer of garbage never becomes a cache that panics later. Before that, a
+//! CRC-32 over the whole snapshot catches torn writes and flipped bits, reported as
+//! `CacheError::SnapshotChecksum`.
+//!
+//! The header records the version of the format, the capacity, and the size and `Plain::VERSION`
+//! of the values. `from_bytes` refuses a snapshot that does not match the cache it restores
+//! with `CacheError::SnapshotIncompatible` instead of misreading it, e.g. after a firmware update
+//! changed `T`. `from_bytes_migrating` loads it anyway through a function converting each old
+//! value, keeping the most recently used entries if the capacity shrank.
+//!
+//! `Plain` is a safe stand-in for `bytemuck::Pod`/`zerocopy`: a value is written and read field
+//! by field rather than transmuted, which costs a copy but no unsafe code, and lets types with
+//! invalid bit patterns (`bool`, `char`) refuse them.
+
+use crate::{
+    CacheError, CacheStats, HitWindow, LRUCache, LifetimeHistogram, Link, Listener, Stamp,
+    StatsMode, FLAG_STALE, LIFETIME_BUCKETS,
+};
+
+// A value with a fixed-size byte representation
+pub trait Plain: Sized {
+    // Bytes taken by a value
+    const SIZE: usize;
+    // Version of the byte representation, to bump when it changes so that snapshots taken
+    // before are migrated rather than misread
+    const VERSION: u16 = 0;
+
+    // Write the value to `out`, which is `SIZE` bytes long
+    fn write_bytes(&self, out: &mut [u8]);
+    // Read a value back from `bytes`, which is `SIZE` bytes long, `None` if it is not one
+    fn read_bytes(bytes: &[u8]) -> Option<Self>;
+}
+
+macro_rules! plain_number {
+    ($($t:ty),*) => {$(
+        impl Plain for $t {
+            const SIZE: usize = core::mem::size_of::<$t>();
+
+            #[inline]
+            fn write_bytes(&self, out: &mut [u8]) {
+                out.copy_from_slice(&self.to_le_bytes());
+            }
+
+            #[inline]
+            fn read_bytes(bytes: &[u8]) -> Option<Self> {
+                Some(<$t>::from_le_bytes(bytes.try_into().ok()?))
+            }
+        }
+    )*};
+}
+
+plain_number!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+
+// Takes no bytes, so a snapshot of a cache of `()` only keeps its order and stats
+impl Plain for () {
+    const SIZE: usize = 0;
+
+    fn write_bytes(&self, _out: &mut [u8]) {}
+
+    fn read_bytes(_bytes: &[u8]) -> Option<Self> {
+        Some(())
+    }
+}
+
+impl Plain for bool {
+    const SIZE: usize = 1;
+
+    fn write_bytes(&self, out: &mut [u8]) {
+        out[0] = *self as u8;
+    }
+
+    fn read_bytes(bytes: &[u8]) -> Option<Self> {
+        match bytes[0] {
+            0 => Some(false),
+            1 => Some(true),
+            _ => None,
+        }
+    }
+}
+
+impl Plain for char {
+    const SIZE: usize = 4;
+
+    fn write_bytes(&self, out: &mut [u8]) {
+        (*self as u32).write_bytes(out);
+    }
+
+    fn read_bytes(bytes: &[u8]) -> Option<Self> {
+        char::from_u32(u32::read_bytes(bytes)?)
+    }
+}
+
+impl<T: Plain, const M: usize> Plain for [T; M] {
+    const SIZE: usize = T::SIZE * M;
+    const VERSION: u16 = T::VERSION;
+
+    fn write_bytes(&self, out: &mut [u8]) {
+        for (val, out) in self.iter().zip(out.chunks_exact_mut(T::SIZE.max(1))) {
+            val.write_bytes(out);
+        }
+    }
+
+    fn read_bytes(bytes: &[u8]) -> Option<Self> {
+        let vals: [Option<T>; M] =
+            core::array::from_fn(|j| T::read_bytes(&bytes[j * T::SIZE..(j + 1) * T::SIZE]));
+        if vals.iter().any(Option::is_none) {
+            return None;
+        }
+        Some(vals.map(|val| val.unwrap()))
+    }
+}
+
+impl<A: Plain, B: Plain> Plain for (A, B) {
+    const SIZE: usize = A::SIZE + B::SIZE;
+    // Changes when either version does
+    const VERSION: u16 = A::VERSION.wrapping_mul(31).wrapping_add(B::VERSION);
+
+    fn write_bytes(&self, out: &mut [u8]) {
+        let (a, b) = out.split_at_mut(A::SIZE);
+        self.0.write_bytes(a);
+        self.1.write_bytes(b);
+    }
+
+    fn read_bytes(bytes: &[u8]) -> Option<Self> {
+        let (a, b) = bytes.split_at(A::SIZE);
+        Some((A::read_bytes(a)?, B::read_bytes(b)?))
+    }
+}
+
+// Marks the start of a snapshot
+const MAGIC: [u8; 4] = *b"LRUS";
+// Marks the start of a full snapshot
+const FULL_MAGIC: [u8; 4] = *b"LRUF";
+// Version of the layout below, to bump when it changes
+pub const FORMAT_VERSION: u16 = 3;
+// Magic, format version, value version and size (u32), capacity, length, head, tail (all u16
+// but the magic and the value size) and the tick (u32)
+const HEADER: usize = 24;
+// Stats mode and window length (u8 each, then two spare bytes), the window bits and the four
+// counters, then the lifetime buckets (all u64), between the header and the entries of a full
+// snapshot
+const STATS: usize = 4 + 8 + 4 * 8 + LIFETIME_BUCKETS * 8;
+// Link and stamp of an entry, before its value
+const ENTRY_HEADER: usize = 8;
+// CRC-32 of everything before it, at the end of a snapshot
+const CHECKSUM: usize = 4;
+
+// What the header of a snapshot says about the cache it was taken of
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SnapshotInfo {
+    // `Plain::VERSION` of the values
+    pub value_version: u16,
+    // `Plain::SIZE` of the values
+    pub value_size: usize,
+    pub capacity: usize,
+    // Number of entries
+    pub len: usize,
+    // Whether the snapshot holds the stats
+    pub full: bool,
+}
+
+// Header fields used to restore the entries
+struct Header {
+    info: SnapshotInfo,
+    head: u16,
+    tail: u16,
+    tick: u32,
+    // Offsets of the entries and of the checksum
+    start: usize,
+    end: usize,
+}
+
+// Read the header of a snapshot, e.g. to decide how to migrate it, and check its checksum. Fails
+// with `SnapshotIncompatible` for a snapshot in another format than `FORMAT_VERSION`.
+pub fn snapshot_info(bytes: &[u8]) -> Result<SnapshotInfo, CacheError> {
+    read_header(bytes).map(|header| header.info)
+}
+
+fn read_header(bytes: &[u8]) -> Result<Header, CacheError> {
+    let corrupt = CacheError::SnapshotCorrupt;
+    let header = bytes.get(..HEADER).ok_or(corrupt)?;
+    let read_u16 = |at: usize| u16::read_bytes(&header[at..at + 2]).ok_or(corrupt);
+    let full = match header[..4].try_into() {
+        Ok(MAGIC) => false,
+        Ok(FULL_MAGIC) => true,
+        _ => return Err(corrupt),
+    };
+    if read_u16(4)? != FORMAT_VERSION {
+        return Err(CacheError::SnapshotIncompatible);
+    }
+    let info = SnapshotInfo {
+        value_version: read_u16(6)?,
+        value_size: u32::read_bytes(&header[8..12]).ok_or(corrupt)? as usize,
+        capacity: read_u16(12)? as usize,
+        len: read_u16(14)? as usize,
+        full,
+    };
+    let (head, tail) = (read_u16(16)?, read_u16(18)?);
+    let tick = u32::read_bytes(&header[20..24]).ok_or(corrupt)?;
+    if info.len > info.capacity
+        || (info.len > 0 && (head as usize >= info.len || tail as usize >= info.len))
+    {
+        return Err(corrupt);
+    }
+
+    let stride = info.value_size.checked_add(ENTRY_HEADER).ok_or(corrupt)?;
+    let start = HEADER + if full { STATS } else { 0 };
+    let end = stride
+        .checked_mul(info.len)
+        .and_then(|size| size.checked_add(start))
+        .ok_or(corrupt)?;
+    let checksum = bytes.get(end..end + CHECKSUM).ok_or(corrupt)?;
+    let checksum = u32::read_bytes(checksum).ok_or(corrupt)?;
+    if checksum != crc32(&bytes[..end]) {
+        return Err(CacheError::SnapshotChecksum);
+    }
+    Ok(Header {
+        info,
+        head,
+        tail,
+        tick,
+        start,
+        end,
+    })
+}
+
+// CRC-32 (IEEE, as in zip and Ethernet) of `bytes`
+fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0, |crc, &b| {
+        CRC_TABLE[((crc ^ b as u32) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+// CRC-32 of every byte value, for reflected polynomial 0xEDB88320
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+impl<T: Plain, L: Listener<T>, const N: usize> LRUCache<T, N, L> {
+    // Bytes taken by the snapshot of a full cache, enough for any snapshot of this type
+    pub const SNAPSHOT_SIZE: usize = HEADER + N * (ENTRY_HEADER + T::SIZE) + CHECKSUM;
+    // Bytes taken by the full snapshot of a full cache
+    pub const FULL_SNAPSHOT_SIZE: usize = Self::SNAPSHOT_SIZE + STATS;
+
+    // Write the cache to the start of `out`, returning how many bytes that took. Fails if `out`
+    // is too short for it, see `SNAPSHOT_SIZE`.
+    pub fn as_bytes_snapshot(&self, out: &mut [u8]) -> Result<usize, CacheError> {
+        self.write_snapshot(out, false)
+    }
+
+    // Like `as_bytes_snapshot`, also writing the stats, see `FULL_SNAPSHOT_SIZE`
+    pub fn as_full_bytes_snapshot(&self, out: &mut [u8]) -> Result<usize, CacheError> {
+        self.write_snapshot(out, true)
+    }
+
+    fn write_snapshot(&self, out: &mut [u8], full: bool) -> Result<usize, CacheError> {
+        let stats = if full { STATS } else { 0 };
+        let end = HEADER + stats + self.entries.len() * (ENTRY_HEADER + T::SIZE);
+        let size = end + CHECKSUM;
+        if out.len() < size {
+            return Err(CacheError::TooLarge {
+                size,
+                limit: out.len(),
+            });
+        }
+
+        let (header, body) = out[..end].split_at_mut(HEADER);
+        header[..4].copy_from_slice(if full { &FULL_MAGIC } else { &MAGIC });
+        FORMAT_VERSION.write_bytes(&mut header[4..6]);
+        T::VERSION.write_bytes(&mut header[6..8]);
+        (T::SIZE as u32).write_bytes(&mut header[8..12]);
+        (N as u16).write_bytes(&mut header[12..14]);
+        (self.entries.len() as u16).write_bytes(&mut header[14..16]);
+        self.head().write_bytes(&mut header[16..18]);
+        self.tail().write_bytes(&mut header[18..20]);
+        self.tick.write_bytes(&mut header[20..24]);
+        let (stats, body) = body.split_at_mut(stats);
+        if full {
+            self.write_stats(stats);
+        }
+        for (i, (entry, out)) in self
+            .entries
+            .iter()
+            .zip(body.chunks_exact_mut(ENTRY_HEADER + T::SIZE))
+            .enumerate()
+        {
+            // The epoch is not saved, a stale entry is flagged instead
+            let mut stamp = entry.stamp;
+            if self.is_stale(i as u16) {
+                stamp.set_flags(stamp.flags() | FLAG_STALE);
+            }
+            entry.link.0.write_bytes(&mut out[0..4]);
+            stamp.0.write_bytes(&mut out[4..8]);
+            entry.val.write_bytes(&mut out[ENTRY_HEADER..]);
+        }
+        let checksum = crc32(&out[..end]);
+        checksum.write_bytes(&mut out[end..size]);
+        Ok(size)
+    }
+
+    // Restore a cache written by `as_bytes_snapshot` or `as_full_bytes_snapshot`, reporting its
+    // events to `listener`. The stats of a cache restored from a snapshot that is not full start
+    // from zero. A snapshot of another capacity or another version or size of `T` is refused
+    // with `SnapshotIncompatible`, see `from_bytes_migrating_with_listener` to load it anyway.
+    pub fn from_bytes_with_listener(bytes: &[u8], listener: L) -> Result<Self, CacheError> {
+        let header = read_header(bytes)?;
+        let info = &header.info;
+        if info.value_version != T::VERSION || info.value_size != T::SIZE || info.capacity != N {
+            return Err(CacheError::SnapshotIncompatible);
+        }
+        // A value of the same layout that does not read back was damaged
+        Self::restore(
+            bytes,
+            &header,
+            listener,
+            CacheError::SnapshotCorrupt,
+            |_, bytes| T::read_bytes(bytes),
+        )
+    }
+
+    // Restore a cache from a snapshot taken under an older layout: each value is read from its
+    // bytes by `migrate`, given what the header says about them. Returning `None` rejects the
+    // snapshot with `SnapshotIncompatible`. A snapshot of a larger capacity keeps its `N` most
+    // recently used entries.
+    pub fn from_bytes_migrating_with_listener<F>(
+        bytes: &[u8],
+        listener: L,
+        migrate: F,
+    ) -> Result<Self, CacheError>
+    where
+        F: FnMut(&SnapshotInfo, &[u8]) -> Option<T>,
+    {
+        let header = read_header(bytes)?;
+        Self::restore(
+            bytes,
+            &header,
+            listener,
+            CacheError::SnapshotIncompatible,
+            migrate,
+        )
+    }
+
+    // Rebuild the entries of a snapshot whose header was read, from the most recently used, in
+    // that order in the array. A value `read` returns `None` for fails it with `unreadable`.
+    fn restore<F>(
+        bytes: &[u8],
+        header: &Header,
+        listener: L,
+        unreadable: CacheError,
+        mut read: F,
+    ) -> Result<Self, CacheError>
+    where
+        F: FnMut(&SnapshotInfo, &[u8]) -> Option<T>,
+    {
+        let corrupt = CacheError::SnapshotCorrupt;
+        let info = &header.info;
+        let stats = &bytes[HEADER..header.start];
+        let stride = info.value_size + ENTRY_HEADER;
+        let body = &bytes[header.start..header.end];
+        let entry = |i: u16| &body[i as usize * stride..(i as usize + 1) * stride];
+        let link = |i: u16| u32::read_bytes(&entry(i)[0..4]).map(Link).ok_or(corrupt);
+
+        let mut cache = Self::with_listener(listener);
+        cache.tick = header.tick;
+        if !stats.is_empty() {
+            cache.read_stats(stats).ok_or(corrupt)?;
+        }
+        // Every entry must be reached once from the head, each pointing back at the one before,
+        // and the last must be the tail. A link back to an entry already seen fails the first
+        // check if it is the head, and otherwise the second, as that entry points back at the
+        // one it was reached from the first time.
+        let mut i = header.head;
+        for rank in 0..info.len {
+            if rank > 0 {
+                let next = link(i)?.next();
+                if next as usize >= info.len || next == header.head || link(next)?.prev() != i {
+                    return Err(corrupt);
+                }
+                i = next;
+            }
+            if rank < N {
+                let bytes = entry(i);
+                // Transient entries stay transient, stale ones stale
+                let stamp = u32::read_bytes(&bytes[4..8]).ok_or(corrupt)?;
+                let val = read(info, &bytes[ENTRY_HEADER..]).ok_or(unreadable)?;
+                cache.push_back_value(val);
+                cache.entries[rank].stamp = Stamp(stamp);
+            }
+        }
+        if info.len > 0 && i != header.tail {
+            return Err(corrupt);
+        }
+        Ok(cache)
+    }
+
+    // Write the stats section of a full snapshot to `out`, `STATS` bytes long
+    fn write_stats(&self, out: &mut [u8]) {
+        let (mode, k) = match self.stats_mode {
+            StatsMode::Off => (0, 0),
+            StatsMode::Full => (1, 0),
+            StatsMode::Sampled(k) => (2, k),
+        };
+        let (bits, len) = self.recent.to_parts();
+        out[..4].copy_from_slice(&[mode, k, len, 0]);
+        bits.write_bytes(&mut out[4..12]);
+        let stats = &self.stats;
+        let counters = [stats.hits, stats.misses, stats.inserts, stats.evictions];
+        let buckets = stats.lifetimes.buckets();
+        for (n, out) in counters
+            .iter()
+            .chain(buckets)
+            .zip(out[12..].chunks_exact_mut(8))
+        {
+            n.write_bytes(out);
+        }
+    }
+
+    // Read back a stats section written by `write_stats`, `None` if it is not one
+    fn read_stats(&mut self, bytes: &[u8]) -> Option<()> {
+        self.stats_mode = match bytes[..2] {
+            [0, 0] => StatsMode::Off,
+            [1, 0] => StatsMode::Full,
+            [2, k] => StatsMode::Sampled(k),
+            _ => return None,
+        };
+        self.recent = HitWindow::from_parts(u64::read_bytes(&bytes[4..12])?, bytes[2])?;
+        let [hits, misses, inserts, evictions, buckets @ ..] =
+            <[u64; 4 + LIFETIME_BUCKETS]>::read_bytes(&bytes[12..])?;
+        self.stats = CacheStats {
+            hits,
+            misses,
+            inserts,
+            evictions,
+            lifetimes: LifetimeHistogram::from_buckets(buckets),
+        };
+        Some(())
+    }
+}
+
+impl<T: Plain, const N: usize> LRUCache<T, N> {
+    // Restore a cache written by `as_bytes_snapshot` or `as_full_bytes_snapshot`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CacheError> {
+        Self::from_bytes_with_listener(bytes, ())
+    }
+
+    // Like `from_bytes_migrating_with_listener`, without a listener
+    pub fn from_bytes_migrating<F>(bytes: &[u8], migrate: F) -> Result<Self, CacheError>
+    where
+        F: FnMut(&SnapshotInfo, &[u8]) -> Option<T>,
+    {
+        Self::from_bytes_migrating_with_listener(bytes, (), migrate)
+    }
+}
@@ -0,0 +1,240 @@
+//! Usage counters kept by the cache.
+
+use core::fmt::{self, Write};
+
+// Which operations a cache counts in its stats
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatsMode {
+    // Nothing is counted
+    Off,
+    // Every operation is counted
+    Full,
+    // One operation in `2^k` is counted, with a weight of `2^k`, so the counters estimate the
+    // real totals at a fraction of the cost. `k` is capped at 31.
+    Sampled(u8),
+}
+
+impl StatsMode {
+    // Weight of the operation numbered `tick` in the stats, 0 if it is not counted
+    #[inline]
+    pub(crate) fn weight(self, tick: u32) -> u64 {
+        match self {
+            StatsMode::Off => 0,
+            StatsMode::Full => 1,
+            StatsMode::Sampled(k) => {
+                let k = k.min(31);
+                if tick & ((1 << k) - 1) == 0 {
+                    1 << k
+                } else {
+                    0
+                }
+            }
+        }
+    }
+}
+
+// Counts of what the cache has done since it was created or its stats were last reset
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    // Lookups that found a matching entry
+    pub hits: u64,
+    // Lookups that found nothing
+    pub misses: u64,
+    // Values handed to `insert`
+    pub inserts: u64,
+    // Entries pushed out to make room for an insert, or by `LRUCache::evict`
+    pub evictions: u64,
+    // How long evicted entries stayed in the cache
+    pub lifetimes: LifetimeHistogram,
+}
+
+impl CacheStats {
+    pub const fn new() -> Self {
+        CacheStats {
+            hits: 0,
+            misses: 0,
+            inserts: 0,
+            evictions: 0,
+            lifetimes: LifetimeHistogram::new(),
+        }
+    }
+
+    // Total number of lookups, hits and misses together
+    pub fn lookups(&self) -> u64 {
+        self.hits + self.misses
+    }
+
+    // Fraction of lookups that were hits, 0.0 if nothing was looked up yet
+    pub fn hit_ratio(&self) -> f64 {
+        match self.lookups() {
+            0 => 0.0,
+            n => self.hits as f64 / n as f64,
+        }
+    }
+
+    // Adds the counters of `other` to these, e.g. to total up the stats of several caches
+    pub fn merge(&mut self, other: &CacheStats) {
+        self.hits += other.hits;
+        self.misses += other.misses;
+        self.inserts += other.inserts;
+        self.evictions += other.evictions;
+        self.lifetimes.merge(&other.lifetimes);
+    }
+
+    // Writes these stats as a JSON object, without allocating, e.g. into a `String` or a
+    // fixed-size buffer
+    pub fn write_json<W: Write>(&self, out: &mut W) -> fmt::Result {
+        write!(
+            out,
+            r#"{{"hits":{},"misses":{},"inserts":{},"evictions":{},"lifetimes":"#,
+            self.hits, self.misses, self.inserts, self.evictions
+        )?;
+        self.lifetimes.write_json(out)?;
+        out.write_char('}')
+    }
+}
+
+// Number of buckets in a `LifetimeHistogram`
+pub const LIFETIME_BUCKETS: usize = 16;
+
+// Histogram of entry lifetimes, measured in cache operations (inserts and lookups) between an
+// entry's insertion and its eviction. Bucket 0 holds lifetimes of 0 and 1, bucket `i` holds
+// lifetimes in `2^i..2^(i+1)`, and the last bucket also holds everything longer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LifetimeHistogram {
+    buckets: [u64; LIFETIME_BUCKETS],
+}
+
+impl LifetimeHistogram {
+    pub const fn new() -> Self {
+        LifetimeHistogram {
+            buckets: [0; LIFETIME_BUCKETS],
+        }
+    }
+
+    // Count one entry that lived for `ops` operations
+    pub fn record(&mut self, ops: u32) {
+        self.record_n(ops, 1);
+    }
+
+    // Count `n` entries that lived for `ops` operations
+    pub fn record_n(&mut self, ops: u32, n: u64) {
+        let bucket = match ops {
+            0 => 0,
+            ops => (ops.ilog2() as usize).min(LIFETIME_BUCKETS - 1),
+        };
+        self.buckets[bucket] += n;
+    }
+
+    // Adds the counts of `other` to these
+    pub fn merge(&mut self, other: &LifetimeHistogram) {
+        for (bucket, n) in self.buckets.iter_mut().zip(other.buckets) {
+            *bucket += n;
+        }
+    }
+
+    // Returns the per-bucket counts
+    pub fn buckets(&self) -> &[u64; LIFETIME_BUCKETS] {
+        &self.buckets
+    }
+
+    // Histogram with the given per-bucket counts, e.g. read back from a snapshot
+    pub(crate) const fn from_buckets(buckets: [u64; LIFETIME_BUCKETS]) -> Self {
+        LifetimeHistogram { buckets }
+    }
+
+    // Returns the smallest lifetime counted in `bucket`, its upper bound being the start of the
+    // next one
+    pub fn bucket_start(bucket: usize) -> u64 {
+        match bucket {
+            0 => 0,
+            i => 1 << i,
+        }
+    }
+
+    // Total number of recorded lifetimes
+    pub fn count(&self) -> u64 {
+        self.buckets.iter().sum()
+    }
+
+    // Returns the start of the bucket holding the `p`-th percentile (0.0 to 1.0) of lifetimes,
+    // or `None` if nothing was recorded
+    pub fn percentile(&self, p: f64) -> Option<u64> {
+        let count = self.count();
+        if count == 0 {
+            return None;
+        }
+        let rank = ((count as f64 * p) as u64).clamp(1, count);
+        let mut seen = 0;
+        for (i, n) in self.buckets.iter().enumerate() {
+            seen += n;
+            if seen >= rank {
+                return Some(Self::bucket_start(i));
+            }
+        }
+        None
+    }
+
+    // Writes the bucket counts as a JSON array
+    pub fn write_json<W: Write>(&self, out: &mut W) -> fmt::Result {
+        out.write_char('[')?;
+        for (i, n) in self.buckets.iter().enumerate() {
+            if i > 0 {
+                out.write_char(',')?;
+            }
+            write!(out, "{n}")?;
+        }
+        out.write_char(']')
+    }
+}
+
+// Outcomes of the last 64 counted lookups, one bit each, newest in the lowest bit
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HitWindow {
+    bits: u64,
+    len: u8,
+}
+
+impl HitWindow {
+    pub const fn new() -> Self {
+        HitWindow { bits: 0, len: 0 }
+    }
+
+    // Push the outcome of one lookup, dropping the oldest once 64 are held
+    #[inline]
+    pub fn record(&mut self, hit: bool) {
+        self.bits = (self.bits << 1) | hit as u64;
+        if self.len < 64 {
+            self.len += 1;
+        }
+    }
+
+    // Number of lookups currently held
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    // Returns true if no lookup was recorded yet
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // Fraction of the held lookups that were hits, 0.0 if there are none
+    pub fn hit_ratio(&self) -> f64 {
+        match self.len {
+            0 => 0.0,
+            n => self.bits.count_ones() as f64 / n as f64,
+        }
+    }
+
+    // The outcome bits and how many of them are held
+    pub(crate) fn to_parts(self) -> (u64, u8) {
+        (self.bits, self.len)
+    }
+
+    // Window from the parts given by `to_parts`, `None` if they are not from one
+    pub(crate) fn from_parts(bits: u64, len: u8) -> Option<Self> {
+        let held = if len < 64 { (1 << len) - 1 } else { u64::MAX };
+        (len <= 64 && bits & !held == 0).then_some(HitWindow { bits, len })
+    }
+}
@@ -0,0 +1,124 @@
+//! An LRU cache shared between threads behind a mutex.
+//!
+//! `SyncLRUCache` is the plain locked wrapper: every operation takes the lock for as long as it
+//! needs the cache. It also keeps a copy of its contents for `read_snapshot`. The copy is only
+//! refreshed by the first snapshot taken after the cache changed, so telemetry polling a cache
+//! that is mostly idle gets its snapshot for the price of an `Arc` clone, and writers are only
+//! ever held up by one copy per change.
+
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use crate::{CacheStats, LRUCache, Listener};
+
+// A thread-safe LRU cache of capacity `N`
+#[derive(Debug)]
+pub struct SyncLRUCache<T, const N: usize, L = ()> {
+    inner: Mutex<Inner<T, N, L>>,
+}
+
+#[derive(Debug)]
+struct Inner<T, const N: usize, L> {
+    cache: LRUCache<T, N, L>,
+    // Contents as of the last snapshot, `None` once the cache changed since
+    snapshot: Option<Arc<[T]>>,
+}
+
+impl<T, L: Listener<T> + Default, const N: usize> Default for SyncLRUCache<T, N, L> {
+    fn default() -> Self {
+        Self::with_listener(L::default())
+    }
+}
+
+impl<T, const N: usize> SyncLRUCache<T, N> {
+    // create a empty cache
+    pub const fn new() -> Self {
+        Self::with_listener(())
+    }
+}
+
+impl<T, L: Listener<T>, const N: usize> SyncLRUCache<T, N, L> {
+    // create a empty cache reporting its events to `listener`
+    pub const fn with_listener(listener: L) -> Self {
+        SyncLRUCache {
+            inner: Mutex::new(Inner {
+                cache: LRUCache::with_listener(listener),
+                snapshot: None,
+            }),
+        }
+    }
+
+    // Insert given value in cache, see `LRUCache::insert`
+    pub fn insert(&self, val: T) -> Option<T> {
+        self.changing().cache.insert(val)
+    }
+
+    // Returns a copy of the first item in the cache that matches the predicate
+    // Make it most recently used on hit
+    pub fn get<F>(&self, pred: F) -> Option<T>
+    where
+        F: FnMut(&T) -> bool,
+        T: Clone,
+    {
+        self.changing().cache.find(pred).cloned()
+    }
+
+    // Touch the first item in the cache that matches the given predicate and marks it as recently
+    // used, Returns true or false
+    pub fn touch<F>(&self, pred: F) -> bool
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.changing().cache.touch(pred)
+    }
+
+    // Runs `f` on the cache while it is locked, so that several operations happen atomically.
+    // The lock is released when `f` returns: it cannot be forgotten, or held across an await
+    // since `f` is not async.
+    pub fn with<R>(&self, f: impl FnOnce(&mut LRUCache<T, N, L>) -> R) -> R {
+        f(&mut self.changing().cache)
+    }
+
+    // Returns the number of elements in the cache
+    pub fn len(&self) -> usize {
+        self.lock().cache.len()
+    }
+
+    // Returns if cache is empty or not
+    pub fn is_empty(&self) -> bool {
+        self.lock().cache.is_empty()
+    }
+
+    // Clears all the elements in cache
+    pub fn clear(&self) {
+        self.changing().cache.clear()
+    }
+
+    // Returns a copy of the usage counters
+    pub fn stats(&self) -> CacheStats {
+        *self.lock().cache.stats()
+    }
+
+    // Returns the contents of the cache, from most to least recently used, as they were at one
+    // instant. The copy is shared with every snapshot taken until the cache changes again.
+    pub fn read_snapshot(&self) -> Arc<[T]>
+    where
+        T: Clone,
+    {
+        let mut inner = self.lock();
+        let Inner { cache, snapshot } = &mut *inner;
+        snapshot
+            .get_or_insert_with(|| cache.iter().cloned().collect())
+            .clone()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Inner<T, N, L>> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    // Lock the cache for an operation that may change it, dropping the outdated snapshot
+    fn changing(&self) -> MutexGuard<'_, Inner<T, N, L>> {
+        let mut inner = self.lock();
+        inner.snapshot = None;
+        inner
+    }
+}
@@ -0,0 +1,165 @@
+//! Entries dropped together by tag.
+//!
+//! `TaggedCache` is an `LRUCache` where every entry carries a small tag `G`, e.g. a tenant id, the
+//! file a block was read from, or an enum of the data sources values derive from. The tags live
+//! in their own dense array next to the entries, indexed by the same slot like the keys of an
+//! `LRUMap`, and `invalidate_tag` drops every entry with a tag at once by scanning only that
+//! array.
+
+use arrayvec::ArrayVec;
+
+use crate::{CacheStats, Event, LRUCache, Listener, RemovalCause};
+
+// An LRU cache of capacity `N` of values `T`, each tagged with a `G`
+#[derive(Debug, Clone)]
+pub struct TaggedCache<T, G, const N: usize, L = ()> {
+    cache: LRUCache<T, N, L>,
+    // Tag of each slot of `cache`
+    tags: ArrayVec<G, N>,
+}
+
+impl<T, G: Copy + Eq, L: Listener<T> + Default, const N: usize> Default
+    for TaggedCache<T, G, N, L>
+{
+    fn default() -> Self {
+        Self::with_listener(L::default())
+    }
+}
+
+impl<T, G: Copy + Eq, const N: usize> TaggedCache<T, G, N> {
+    // create a empty cache
+    pub const fn new() -> Self {
+        Self::with_listener(())
+    }
+}
+
+impl<T, G: Copy + Eq, L: Listener<T>, const N: usize> TaggedCache<T, G, N, L> {
+    // create a empty cache reporting its events to `listener`
+    pub const fn with_listener(listener: L) -> Self {
+        TaggedCache {
+            cache: LRUCache::with_listener(listener),
+            tags: ArrayVec::new_const(),
+        }
+    }
+
+    // Insert `val` tagged with `tag`, see `LRUCache::insert`
+    pub fn insert(&mut self, val: T, tag: G) -> Option<T> {
+        let evicted = self.cache.insert(val);
+        match evicted {
+            // The evicted slot was reused for the new value
+            Some(_) if !self.cache.is_empty() => self.tags[self.cache.head() as usize] = tag,
+            // A cache of capacity 0 handed the value back
+            Some(_) => {}
+            // The new value went to the end of the array
+            None => self.tags.push(tag),
+        }
+        evicted
+    }
+
+    // Returns the first item in the cache that matches the predicate
+    // Make it most recently used on hit
+    pub fn find<F>(&mut self, pred: F) -> Option<&mut T>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.cache.find(pred)
+    }
+
+    // Touch the first item in the cache that matches the given predicate and marks it as recently
+    // used, Returns true or false
+    pub fn touch<F>(&mut self, pred: F) -> bool
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.cache.touch(pred)
+    }
+
+    // Returns the tag of the first item in recency order that matches the predicate, without
+    // touching it
+    pub fn tag_of<F>(&self, mut pred: F) -> Option<G>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.entries()
+            .find(|(val, _)| pred(val))
+            .map(|(_, tag)| tag)
+    }
+
+    // Remove every entry tagged with `tag`, e.g. all the entries of a tenant that logged out.
+    // Returns how many there were. They are reported as removed on request.
+    pub fn invalidate_tag(&mut self, tag: G) -> usize {
+        let len = self.len();
+        // Backwards, so the entry moved into a freed slot was checked already
+        for i in (0..len as u16).rev() {
+            if self.tags[i as usize] == tag {
+                self.remove_index(i);
+            }
+        }
+        len - self.len()
+    }
+
+    // Returns the number of entries tagged with `tag`
+    pub fn count_tag(&self, tag: G) -> usize {
+        self.tags.iter().filter(|t| **t == tag).count()
+    }
+
+    // Removes the least recently used value and returns it with its tag
+    pub fn pop_lru(&mut self) -> Option<(T, G)> {
+        if self.is_empty() {
+            return None;
+        }
+        let tail = self.cache.tail();
+        Some(self.remove_index(tail))
+    }
+
+    // Returns the values with their tags from most to least recently used, without changing the
+    // recency order or the stats
+    pub fn entries(&self) -> impl ExactSizeIterator<Item = (&T, G)> + '_ {
+        let mut i = self.cache.head();
+        (0..self.len()).map(move |_| {
+            let slot = i;
+            i = self.cache.entries[slot as usize].link.next();
+            (
+                &self.cache.entries[slot as usize].val,
+                self.tags[slot as usize],
+            )
+        })
+    }
+
+    // Returns the number of elements in the cache
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    // Returns if cache is empty or not
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+
+    // Clears all the elements in cache
+    pub fn clear(&mut self) {
+        self.tags.clear();
+        self.cache.clear();
+    }
+
+    // Returns the usage counters collected so far
+    pub fn stats(&self) -> &CacheStats {
+        self.cache.stats()
+    }
+
+    // Returns the listener
+    pub fn listener(&self) -> &L {
+        self.cache.listener()
+    }
+
+    // Take slot `i` out of the cache, moving the last slot into its place in both arrays
+    fn remove_index(&mut self, i: u16) -> (T, G) {
+        self.cache.listener.on_event(Event::Evict(
+            &self.cache.entries[i as usize].val,
+            RemovalCause::Explicit,
+        ));
+        let val = self.cache.remove_index(i);
+        let tag = self.tags.swap_remove(i as usize);
+        (val, tag)
+    }
+}
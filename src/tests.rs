@@ -0,0 +1,81 @@
+//! Tests that need the cache's internals, here to move the tick where it would take billions of
+//! operations to get to.
+
+use core::mem::size_of;
+
+use crate::{Entry, LRUCache, LIFETIME_BUCKETS, SWEEP_PERIOD};
+
+// Sweeps it takes the tick to come back round
+const SWEEPS_PER_WRAP: u32 = (1u64 << 32).div_ceil(SWEEP_PERIOD as u64) as u32;
+
+// Move `cache` to the tick before the next sweep, and run `op` to cross it
+fn cross_sweep<const N: usize>(
+    cache: &mut LRUCache<u32, N>,
+    op: impl FnOnce(&mut LRUCache<u32, N>),
+) {
+    let next = (cache.tick / SWEEP_PERIOD + 1).wrapping_mul(SWEEP_PERIOD);
+    cache.set_tick(next.wrapping_sub(1));
+    op(cache);
+    assert_eq!(cache.tick, next);
+}
+
+#[test]
+fn staleness_survives_the_tick_wrapping() {
+    let mut cache: LRUCache<u32, 64> = LRUCache::new();
+    cache.insert(1);
+    cache.bump_epoch();
+    cache.insert(2);
+    // Take the tick once round, back to where the bump left it. Inserts do not look at the
+    // other entries, so no lookup meets the stale one before.
+    let fillers = 10..10 + SWEEPS_PER_WRAP;
+    for val in fillers.clone() {
+        cross_sweep(&mut cache, |cache| assert_eq!(cache.insert(val), None));
+    }
+    cache.insert(3);
+    assert_eq!(cache.find(|&v| v == 1), None);
+    for val in [2, 3].into_iter().chain(fillers) {
+        assert_eq!(cache.find(|&v| v == val).copied(), Some(val), "{val}");
+    }
+    assert_eq!(cache.len(), 2 + SWEEPS_PER_WRAP as usize);
+}
+
+#[test]
+fn a_bump_pending_across_the_wrap_still_tells_old_from_new() {
+    let mut cache: LRUCache<u32, 4> = LRUCache::new();
+    cache.set_tick(u32::MAX - 2);
+    cache.insert(1);
+    cache.bump_epoch();
+    // The tick wraps to 0, a sweep, between the bump and this insert
+    cache.insert(2);
+    assert_eq!(cache.tick, 0);
+    cache.insert(3);
+    assert_eq!(cache.find(|&v| v == 1), None);
+    assert_eq!(cache.find(|&v| v == 2), Some(&mut 2));
+    assert_eq!(cache.find(|&v| v == 3), Some(&mut 3));
+}
+
+#[test]
+fn ages_longer_than_the_tick_go_to_the_last_bucket() {
+    let mut cache: LRUCache<u32, 1> = LRUCache::new();
+    cache.insert(1);
+    for _ in 0..SWEEPS_PER_WRAP {
+        cross_sweep(&mut cache, |cache| assert_eq!(cache.find(|_| false), None));
+    }
+    // Nearly 2^32 ticks after the insert, without the clamp the age would have wrapped to 5
+    cache.set_tick(cache.tick.wrapping_add(4));
+    cache.insert(2);
+    let buckets = cache.stats().lifetimes.buckets();
+    assert_eq!(buckets[LIFETIME_BUCKETS - 1], 1);
+    assert_eq!(buckets.iter().sum::<u64>(), 1);
+}
+
+#[test]
+fn an_entry_costs_8_bytes_on_top_of_its_value() {
+    // Sizes with `prev: u16`, `next: u16` and `born: u32` before the flags, and since: the flags
+    // share a word with the tick of the insert
+    assert_eq!(size_of::<Entry<()>>(), 8);
+    assert_eq!(size_of::<Entry<u8>>(), 12);
+    assert_eq!(size_of::<Entry<[u8; 4]>>(), 12);
+    assert_eq!(size_of::<Entry<u32>>(), 12);
+    assert_eq!(size_of::<Entry<u64>>(), 16);
+}
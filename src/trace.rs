@@ -0,0 +1,45 @@
+//! A listener that writes one line per cache event, for following cache behavior while
+//! debugging.
+//!
+//! Lines look like `lru cache=sessions event=evict cause=Capacity value=42`, which is easy to
+//! grep and to forward into a tracing or logging pipeline.
+
+use core::fmt::Debug;
+use std::io::Write;
+
+use crate::{Event, Listener};
+
+// Writes every event of the cache it is attached to into `out`
+#[derive(Debug, Clone)]
+pub struct TraceListener<W> {
+    name: &'static str,
+    out: W,
+}
+
+impl<W: Write> TraceListener<W> {
+    // `name` tells apart several caches writing to the same output
+    pub fn new(name: &'static str, out: W) -> Self {
+        TraceListener { name, out }
+    }
+
+    // Returns the output the events are written into
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+impl<T: Debug, W: Write> Listener<T> for TraceListener<W> {
+    fn on_event(&mut self, event: Event<'_, T>) {
+        let name = self.name;
+        // A broken trace output must not break the cache, so write errors are dropped
+        let _ = match event {
+            Event::Insert(val) => writeln!(self.out, "lru cache={name} event=insert value={val:?}"),
+            Event::Hit(val) => writeln!(self.out, "lru cache={name} event=hit value={val:?}"),
+            Event::Miss => writeln!(self.out, "lru cache={name} event=miss"),
+            Event::Evict(val, cause) => writeln!(
+                self.out,
+                "lru cache={name} event=evict cause={cause:?} value={val:?}"
+            ),
+        };
+    }
+}
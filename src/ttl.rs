@@ -0,0 +1,489 @@
+//! Entries that expire after a deadline.
+//!
+//! `TtlCache` is an `LRUCache` whose entries can carry a deadline, read from a `Clock`. The
+//! deadlines live in a timer wheel next to the entry array, so expired entries are found without
+//! scanning the cache and are reclaimed before any live entry gets evicted.
+
+#[cfg(not(target_has_atomic = "64"))]
+use core::sync::atomic::AtomicU32 as AtomicTicks;
+#[cfg(target_has_atomic = "64")]
+use core::sync::atomic::AtomicU64 as AtomicTicks;
+use core::sync::atomic::Ordering;
+
+use crate::{wheel::TimerWheel, CacheStats, Event, LRUCache, Listener, RemovalCause, StatsMode};
+
+// Source of the current time. The unit of a tick is up to the clock (milliseconds, timer
+// interrupts, ...) and every deadline given to the cache is in the same unit.
+pub trait Clock {
+    // Returns the current time in ticks. It must never go backwards.
+    fn now(&self) -> u64;
+}
+
+// A shared reference to a clock is a clock, so one `static` clock can drive many caches
+impl<C: Clock + ?Sized> Clock for &C {
+    #[inline]
+    fn now(&self) -> u64 {
+        (**self).now()
+    }
+}
+
+// A clock that only moves when the application calls `advance`, e.g. from a SysTick interrupt,
+// for targets without a timer abstraction. It can live in a `static` shared with the interrupt
+// handler, and makes expiry fully deterministic in tests.
+//
+// On targets without 64-bit atomics the count is 32 bits wide and wraps after `2^32` ticks.
+#[derive(Debug, Default)]
+pub struct TickClock {
+    ticks: AtomicTicks,
+}
+
+impl TickClock {
+    // create a clock starting at tick 0
+    pub const fn new() -> Self {
+        TickClock {
+            ticks: AtomicTicks::new(0),
+        }
+    }
+
+    // Move the clock forward by `ticks`
+    #[inline]
+    #[allow(clippy::useless_conversion)] // the count is 32 bits wide on some targets
+    pub fn advance(&self, ticks: u32) {
+        self.ticks.fetch_add(ticks.into(), Ordering::Relaxed);
+    }
+}
+
+impl Clock for TickClock {
+    #[inline]
+    #[allow(clippy::unnecessary_cast)] // the count is 32 bits wide on some targets
+    fn now(&self) -> u64 {
+        self.ticks.load(Ordering::Relaxed) as u64
+    }
+}
+
+// Monotonic clock counting milliseconds since it was created
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy)]
+pub struct InstantClock {
+    start: std::time::Instant,
+}
+
+#[cfg(feature = "std")]
+impl InstantClock {
+    pub fn new() -> Self {
+        InstantClock {
+            start: std::time::Instant::now(),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Default for InstantClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "std")]
+impl Clock for InstantClock {
+    fn now(&self) -> u64 {
+        self.start.elapsed().as_millis() as u64
+    }
+}
+
+// Wall clock counting milliseconds since the Unix epoch, for deadlines that come from outside
+// the process (e.g. an `Expires` header). The system time can be set backwards, so the clock
+// holds at the latest time it has returned until the system catches up again.
+#[cfg(feature = "std")]
+#[derive(Debug, Default)]
+pub struct SystemClock {
+    latest: std::sync::atomic::AtomicU64,
+}
+
+#[cfg(feature = "std")]
+impl SystemClock {
+    pub const fn new() -> Self {
+        SystemClock {
+            latest: std::sync::atomic::AtomicU64::new(0),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Clock for SystemClock {
+    fn now(&self) -> u64 {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |since| since.as_millis() as u64);
+        self.latest.fetch_max(now, Ordering::Relaxed).max(now)
+    }
+}
+
+// Randomness for TTL jitter. `()` always returns 0, i.e. never shortens a TTL.
+pub trait JitterRng {
+    // Returns a uniformly distributed random number
+    fn next_u32(&mut self) -> u32;
+}
+
+impl JitterRng for () {
+    #[inline]
+    fn next_u32(&mut self) -> u32 {
+        0
+    }
+}
+
+// Small xorshift generator, plenty for spreading out expiry times and available on `no_std`
+#[derive(Debug, Clone)]
+pub struct XorShift32 {
+    state: u32,
+}
+
+impl XorShift32 {
+    // A zero seed would only ever produce zeros, so it is replaced by a fixed one
+    pub const fn new(seed: u32) -> Self {
+        XorShift32 {
+            state: if seed == 0 { 0x9e37_79b9 } else { seed },
+        }
+    }
+}
+
+impl JitterRng for XorShift32 {
+    fn next_u32(&mut self) -> u32 {
+        let mut x = self.state;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.state = x;
+        x
+    }
+}
+
+// Called with every entry that is about to be removed because it expired, and how many ticks
+// it lived since it was inserted. Unlike the listener it only sees expirations, which makes it
+// the place to refresh or log stale data. `()` does nothing.
+pub trait ExpiryHook<T> {
+    fn on_expiry(&mut self, val: &T, lived: u64);
+}
+
+impl<T> ExpiryHook<T> for () {
+    #[inline]
+    fn on_expiry(&mut self, _val: &T, _lived: u64) {}
+}
+
+impl<T, F: FnMut(&T, u64)> ExpiryHook<T> for F {
+    fn on_expiry(&mut self, val: &T, lived: u64) {
+        self(val, lived)
+    }
+}
+
+// An LRU cache of capacity `N` whose entries can expire
+#[derive(Debug, Clone)]
+pub struct TtlCache<T, C, const N: usize, L = (), H = (), R = ()> {
+    cache: LRUCache<T, N, L>,
+    // Deadline of each slot of `cache`
+    wheel: TimerWheel<N>,
+    clock: C,
+    // Lifetime, in ticks, of entries inserted without an explicit deadline
+    default_ttl: Option<u64>,
+    // Whether a hit moves the deadline of an entry forward by its lifetime
+    sliding: bool,
+    // Lifetime each slot was inserted with
+    spans: [u64; N],
+    // Time each slot was inserted at
+    born: [u64; N],
+    on_expiry: H,
+    // Percentage by which relative TTLs may be shortened at random, and the randomness to do so
+    jitter: u8,
+    rng: R,
+}
+
+impl<T, C: Clock, const N: usize> TtlCache<T, C, N> {
+    // create a empty cache reading the time from `clock`
+    pub fn new(clock: C) -> Self {
+        Self::with_listener(clock, ())
+    }
+}
+
+impl<T, C: Clock, L: Listener<T>, const N: usize> TtlCache<T, C, N, L> {
+    // create a empty cache reading the time from `clock` and reporting its events to `listener`
+    pub fn with_listener(clock: C, listener: L) -> Self {
+        TtlCache {
+            cache: LRUCache::with_listener(listener),
+            wheel: TimerWheel::new(clock.now()),
+            clock,
+            default_ttl: None,
+            sliding: false,
+            spans: [0; N],
+            born: [0; N],
+            on_expiry: (),
+            jitter: 0,
+            rng: (),
+        }
+    }
+}
+
+impl<T, C: Clock, L: Listener<T>, R: JitterRng, const N: usize> TtlCache<T, C, N, L, (), R> {
+    // Call `hook` with every entry about to be removed because it expired
+    pub fn with_expiry_hook<H: ExpiryHook<T>>(self, hook: H) -> TtlCache<T, C, N, L, H, R> {
+        TtlCache {
+            cache: self.cache,
+            wheel: self.wheel,
+            clock: self.clock,
+            default_ttl: self.default_ttl,
+            sliding: self.sliding,
+            spans: self.spans,
+            born: self.born,
+            on_expiry: hook,
+            jitter: self.jitter,
+            rng: self.rng,
+        }
+    }
+}
+
+impl<T, C: Clock, L: Listener<T>, H: ExpiryHook<T>, const N: usize> TtlCache<T, C, N, L, H> {
+    // Shorten every relative TTL (the default one and those given to `insert_with_ttl`) by a
+    // random amount of up to `percent`%, drawn from `rng`, so entries inserted together do not
+    // all expire and get reloaded at the same instant. Absolute deadlines are left alone.
+    pub fn with_jitter<R: JitterRng>(self, percent: u8, rng: R) -> TtlCache<T, C, N, L, H, R> {
+        TtlCache {
+            cache: self.cache,
+            wheel: self.wheel,
+            clock: self.clock,
+            default_ttl: self.default_ttl,
+            sliding: self.sliding,
+            spans: self.spans,
+            born: self.born,
+            on_expiry: self.on_expiry,
+            jitter: percent.min(100),
+            rng,
+        }
+    }
+}
+
+impl<T, C: Clock, L: Listener<T>, H: ExpiryHook<T>, R: JitterRng, const N: usize>
+    TtlCache<T, C, N, L, H, R>
+{
+    // Make entries given to `insert` expire `ttl` ticks after insertion, instead of never
+    pub fn with_default_ttl(mut self, ttl: u64) -> Self {
+        self.default_ttl = Some(ttl);
+        self
+    }
+
+    // Make every hit push the deadline of the entry back to a full lifetime from now, so entries
+    // in use never expire while idle ones still age out on their own TTL
+    pub fn with_sliding_expiry(mut self) -> Self {
+        self.sliding = true;
+        self
+    }
+
+    // Choose which operations are counted in the stats, see `StatsMode`
+    pub fn with_stats_mode(mut self, mode: StatsMode) -> Self {
+        self.cache.stats_mode = mode;
+        self
+    }
+
+    // Returns the lifetime given to entries given to `insert`, `None` if they do not expire
+    pub fn default_ttl(&self) -> Option<u64> {
+        self.default_ttl
+    }
+
+    // Insert a value living for the default TTL, or forever if the cache has none. Returns the
+    // entry evicted to make room, if any.
+    pub fn insert(&mut self, val: T) -> Option<T> {
+        match self.default_ttl {
+            Some(ttl) => self.insert_with_ttl(val, ttl),
+            None => match self.insert_entry(val) {
+                Ok((i, evicted)) => {
+                    self.wheel.remove(i);
+                    evicted
+                }
+                Err(val) => Some(val),
+            },
+        }
+    }
+
+    // Insert a value living for `ttl` ticks whatever the default TTL is, e.g. for data that
+    // carries its own freshness. Returns the entry evicted to make room, if any.
+    pub fn insert_with_ttl(&mut self, val: T, ttl: u64) -> Option<T> {
+        let ttl = self.jittered(ttl);
+        let (i, evicted) = match self.insert_entry(val) {
+            Ok(inserted) => inserted,
+            Err(val) => return Some(val),
+        };
+        let now = self.clock.now();
+        self.set_deadline(i, now, now.saturating_add(ttl));
+        evicted
+    }
+
+    // Insert a value that expires once the clock reaches `deadline`. Returns the entry evicted
+    // to make room, if any.
+    pub fn insert_until(&mut self, val: T, deadline: u64) -> Option<T> {
+        let (i, evicted) = match self.insert_entry(val) {
+            Ok(inserted) => inserted,
+            Err(val) => return Some(val),
+        };
+        let now = self.clock.now();
+        self.set_deadline(i, now, deadline);
+        evicted
+    }
+
+    // Returns the first live item in the cache that matches the predicate
+    // Make it most recently used on hit
+    pub fn find<F>(&mut self, pred: F) -> Option<&mut T>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        if self.touch(pred) {
+            self.cache.front_mut()
+        } else {
+            None
+        }
+    }
+
+    // Touch the first live item in the cache that matches the given predicate and marks it as
+    // recently used, Returns true or false. Expired matches are removed on the way and count as
+    // misses, so results never depend on a sweep having run.
+    pub fn touch<F>(&mut self, mut pred: F) -> bool
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let now = self.clock.now();
+        let weight = self.cache.next_tick();
+        loop {
+            match self.cache.position(&mut pred) {
+                Some(i) if self.is_expired(i, now) => {
+                    self.expire_slot(i);
+                }
+                Some(i) => {
+                    if self.sliding && self.wheel.deadline(i).is_some() {
+                        let span = self.spans[i as usize];
+                        self.wheel.insert(i, now.saturating_add(span));
+                    }
+                    self.cache.touch_index(i);
+                    self.cache.record_hit(i, weight);
+                    return true;
+                }
+                None => {
+                    self.cache.record_miss(weight);
+                    return false;
+                }
+            }
+        }
+    }
+
+    // Remove every expired entry, returning how many there were
+    pub fn purge_expired(&mut self) -> usize {
+        self.purge_expired_with(drop)
+    }
+
+    // Remove every expired entry, handing each one to `f`, and return how many there were.
+    // The work done is proportional to the number of expired entries, not the capacity.
+    pub fn purge_expired_with<F>(&mut self, mut f: F) -> usize
+    where
+        F: FnMut(T),
+    {
+        self.wheel.advance(self.clock.now());
+        let mut purged = 0;
+        while let Some(i) = self.wheel.pop_ready() {
+            f(self.expire_slot(i));
+            purged += 1;
+        }
+        purged
+    }
+
+    // Returns the number of elements in the cache
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    // Returns if cache is empty or not
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+
+    // Clears all the elements in cache
+    pub fn clear(&mut self) {
+        self.cache.clear();
+        self.wheel.clear();
+    }
+
+    // Returns the usage counters collected so far
+    pub fn stats(&self) -> &CacheStats {
+        self.cache.stats()
+    }
+
+    // Returns the memory taken by the cache, its timer wheel and its clock, in bytes, not
+    // counting heap memory owned by the values
+    pub const fn memory_usage(&self) -> usize {
+        core::mem::size_of::<Self>()
+    }
+
+    // Returns the clock deadlines are read from
+    pub fn clock(&self) -> &C {
+        &self.clock
+    }
+
+    // Returns the clock deadlines are read from, e.g. to advance a manual clock
+    pub fn clock_mut(&mut self) -> &mut C {
+        &mut self.clock
+    }
+
+    // Store `val` as the most recent entry, first reclaiming expired slots if the cache is full
+    // so that only a live entry gets evicted when nothing has expired. Returns its slot with the
+    // entry evicted, or hands `val` back for a cache of capacity 0.
+    fn insert_entry(&mut self, val: T) -> Result<(u16, Option<T>), T> {
+        if self.cache.entries.is_full() {
+            self.purge_expired();
+        }
+        self.cache.insert_slot(val)
+    }
+
+    // `ttl` shortened by the configured jitter
+    fn jittered(&mut self, ttl: u64) -> u64 {
+        if self.jitter == 0 {
+            return ttl;
+        }
+        let max_cut = ttl as u128 * self.jitter as u128 / 100;
+        let cut = (max_cut * self.rng.next_u32() as u128) >> 32;
+        ttl - cut as u64
+    }
+
+    // Make slot `i`, inserted at `now`, expire at `deadline`
+    fn set_deadline(&mut self, i: u16, now: u64, deadline: u64) {
+        self.wheel.insert(i, deadline);
+        self.spans[i as usize] = deadline.saturating_sub(now);
+        self.born[i as usize] = now;
+    }
+
+    #[inline]
+    fn is_expired(&self, i: u16, now: u64) -> bool {
+        self.wheel
+            .deadline(i)
+            .is_some_and(|deadline| deadline <= now)
+    }
+
+    // Take expired entry `i` out of the cache, telling the expiry hook and the listener about it
+    fn expire_slot(&mut self, i: u16) -> T {
+        let val = &self.cache.entries[i as usize].val;
+        let lived = self.clock.now().saturating_sub(self.born[i as usize]);
+        self.on_expiry.on_expiry(val, lived);
+        self.cache
+            .listener
+            .on_event(Event::Evict(val, RemovalCause::Expired));
+        self.remove_slot(i)
+    }
+
+    // Take entry `i` out of the cache, keeping the wheel in step with the slot that moves into
+    // its place
+    fn remove_slot(&mut self, i: u16) -> T {
+        self.wheel.remove(i);
+        let last = (self.cache.len() - 1) as u16;
+        let val = self.cache.remove_index(i);
+        if i != last {
+            self.wheel.relocate(last, i);
+            self.spans[i as usize] = self.spans[last as usize];
+            self.born[i as usize] = self.born[last as usize];
+        }
+        val
+    }
+}
@@ -0,0 +1,157 @@
+//! A keyed LRU map whose entries carry versions, for fills that race with invalidations.
+//!
+//! A cache filler misses, reads the value from the source of truth, and then stores it. If the
+//! key is invalidated while the read is in flight, storing the value it read brings stale data
+//! back. `VersionedMap` stamps every write with a map-wide version number instead, and
+//! `update_if` only stores a value if nothing happened to the key after the version the filler
+//! started from:
+//!
+//! - after a miss, start from `current_version()`
+//! - to replace a value that was read, start from its version, as returned by `get` or `version`
+//!
+//! A key that is not in the map carries no version of its own, so for it any removal after the
+//! version started from counts, evictions included. A busy full map then turns some fills away
+//! that would have been fine, but never stores one that would not.
+
+use core::borrow::Borrow;
+
+use crate::{map::LRUMap, CacheStats, Listener, RemovalCause};
+
+// An LRU map of capacity `N` from keys `K` to values `V`, with a version per entry
+#[derive(Debug, Clone)]
+pub struct VersionedMap<K, V, const N: usize, L = ()> {
+    map: LRUMap<K, V, N, L>,
+    // Version of the entry in each slot of `map`
+    versions: [u64; N],
+    // Last version given out, 0 before the first write
+    version: u64,
+    // Version of the last write that took an entry out of the map
+    removed: u64,
+}
+
+impl<K, V, L: Listener<V> + Default, const N: usize> Default for VersionedMap<K, V, N, L> {
+    fn default() -> Self {
+        Self::with_listener(L::default())
+    }
+}
+
+impl<K, V, const N: usize> VersionedMap<K, V, N> {
+    // create a empty map
+    pub const fn new() -> Self {
+        Self::with_listener(())
+    }
+}
+
+impl<K, V, L: Listener<V>, const N: usize> VersionedMap<K, V, N, L> {
+    // create a empty map reporting its events to `listener`
+    pub const fn with_listener(listener: L) -> Self {
+        VersionedMap {
+            map: LRUMap::with_listener(listener),
+            versions: [0; N],
+            version: 0,
+            removed: 0,
+        }
+    }
+
+    // Returns the last version given out, where a filler starts from after a miss
+    pub fn current_version(&self) -> u64 {
+        self.version
+    }
+
+    // Returns the number of entries in the map
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    // Returns if map is empty or not
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    // Clears all the entries in map, which counts as a removal of every key
+    pub fn clear(&mut self) {
+        self.map.clear();
+        self.removed = self.next_version();
+    }
+
+    // Returns the usage counters collected so far
+    pub fn stats(&self) -> &CacheStats {
+        self.map.stats()
+    }
+
+    fn next_version(&mut self) -> u64 {
+        self.version += 1;
+        self.version
+    }
+}
+
+impl<K: Eq, V, L: Listener<V>, const N: usize> VersionedMap<K, V, N, L> {
+    // Insert `val` under `key` whatever its version, see `LRUMap::insert`. Returns the new
+    // version with the entry it replaced.
+    pub fn insert(&mut self, key: K, val: V) -> (u64, Option<(K, V)>) {
+        let version = self.next_version();
+        if let Some(i) = self.map.index_of(&key) {
+            self.versions[i as usize] = version;
+            return (version, Some(self.map.overwrite(i, key, val)));
+        }
+        if N == 0 {
+            self.removed = version;
+            return (version, Some((key, val)));
+        }
+        let evicted = self.map.insert_new(key, val);
+        if evicted.is_some() {
+            self.removed = version;
+        }
+        self.versions[self.map.front_slot() as usize] = version;
+        (version, evicted)
+    }
+
+    // Store `val` under `key` if nothing happened to the key after version `expected`, see the
+    // module docs, and return its new version. Hands `val` back otherwise.
+    pub fn update_if(&mut self, key: K, expected: u64, val: V) -> Result<u64, V> {
+        let unchanged = match self.map.index_of(&key) {
+            Some(i) => self.versions[i as usize] <= expected,
+            None => self.removed <= expected,
+        };
+        if !unchanged {
+            return Err(val);
+        }
+        Ok(self.insert(key, val).0)
+    }
+
+    // Returns the value stored under `key` with its version, making it the most recently used
+    // entry
+    pub fn get<Q>(&mut self, key: &Q) -> Option<(&V, u64)>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        let i = self.map.get_slot(key)?;
+        Some((self.map.value(i), self.versions[i as usize]))
+    }
+
+    // Returns the version of the entry stored under `key`, without changing the recency order
+    // or the stats
+    pub fn version<Q>(&self, key: &Q) -> Option<u64>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        let i = self.map.index_of(key)?;
+        Some(self.versions[i as usize])
+    }
+
+    // Remove the entry stored under `key`, returning its value. Fills of the key started
+    // before fail from now on, even if it was not in the map: one may be in flight for it.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.removed = self.next_version();
+        let i = self.map.index_of(key)?;
+        // The last slot is moved into the one removed, in both arrays
+        self.versions[i as usize] = self.versions[self.map.len() - 1];
+        Some(self.map.remove_index(i, RemovalCause::Explicit).1)
+    }
+}
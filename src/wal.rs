@@ -0,0 +1,206 @@
+//! A log of the operations changing a cache, for rebuilding it elsewhere.
+//!
+//! A `WalListener` turns the events of the cache it is attached to into `CacheOp` records and
+//! hands each one to a sink: a closure appending it to flash, a ring buffer drained over a link
+//! to another device, anything implementing `OpSink`. Feeding the records back to
+//! `LRUCache::replay`, in order and into a cache of the same capacity, rebuilds the same entries
+//! in the same recency order, e.g. after a crash or on a mirror.
+//!
+//! A record carries the value it is about, so the sink serializes it however it likes. Misses
+//! change nothing and are not recorded. `clear` is recorded as the removal of every entry.
+//!
+//! A follower receiving records over a link that may repeat them uses `apply` instead of
+//! `replay`. Each record then stands for a state rather than a step: after an insert the value is
+//! in the cache and the most recently used, after a removal it is not in the cache, so a record
+//! applied twice in a row changes nothing the second time. Older records delivered again after
+//! newer ones would still reorder the follower, a link that can do that must number them and
+//! drop the ones already applied. `LRUMap::apply` takes records of `(K, V)` pairs, matching them
+//! by key, as written for a leader caching pairs.
+
+use crate::{map::LRUMap, Event, LRUCache, Listener, RemovalCause};
+
+// One change to a cache
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheOp<T> {
+    // The value was stored as the most recently used entry
+    Insert(T),
+    // A lookup hit the value, which is now the most recently used
+    Touch(T),
+    // The value was pushed out to make room
+    Evict(T),
+    // The value was removed on request, expired or was replaced
+    Remove(T),
+}
+
+impl<T> CacheOp<T> {
+    // Returns the value the operation is about
+    pub fn value(&self) -> &T {
+        match self {
+            CacheOp::Insert(val)
+            | CacheOp::Touch(val)
+            | CacheOp::Evict(val)
+            | CacheOp::Remove(val) => val,
+        }
+    }
+
+    // Returns the same operation applied to the value
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> CacheOp<U> {
+        match self {
+            CacheOp::Insert(val) => CacheOp::Insert(f(val)),
+            CacheOp::Touch(val) => CacheOp::Touch(f(val)),
+            CacheOp::Evict(val) => CacheOp::Evict(f(val)),
+            CacheOp::Remove(val) => CacheOp::Remove(f(val)),
+        }
+    }
+}
+
+impl<T: Clone> CacheOp<&T> {
+    // Returns the operation with its value cloned, e.g. to keep a record past the event
+    pub fn cloned(self) -> CacheOp<T> {
+        self.map(T::clone)
+    }
+}
+
+// Receives the records of a `WalListener`
+pub trait OpSink<T> {
+    fn record(&mut self, op: CacheOp<&T>);
+}
+
+impl<T, F: FnMut(CacheOp<&T>)> OpSink<T> for F {
+    #[inline]
+    fn record(&mut self, op: CacheOp<&T>) {
+        self(op)
+    }
+}
+
+// Listener writing a record of every change of its cache to `sink`
+#[derive(Debug, Clone)]
+pub struct WalListener<S> {
+    sink: S,
+}
+
+impl<S> WalListener<S> {
+    // create a listener handing records to `sink`
+    pub const fn new(sink: S) -> Self {
+        WalListener { sink }
+    }
+
+    // Returns the sink the records are handed to
+    pub fn sink(&self) -> &S {
+        &self.sink
+    }
+
+    // Returns the sink the records are handed to
+    pub fn sink_mut(&mut self) -> &mut S {
+        &mut self.sink
+    }
+
+    // Returns the sink, e.g. to flush it
+    pub fn into_inner(self) -> S {
+        self.sink
+    }
+}
+
+impl<T, S: OpSink<T>> Listener<T> for WalListener<S> {
+    fn on_event(&mut self, event: Event<'_, T>) {
+        let op = match event {
+            Event::Insert(val) => CacheOp::Insert(val),
+            Event::Hit(val) => CacheOp::Touch(val),
+            Event::Miss => return,
+            Event::Evict(val, RemovalCause::Capacity) => CacheOp::Evict(val),
+            Event::Evict(
+                val,
+                RemovalCause::Expired
+                | RemovalCause::Explicit
+                | RemovalCause::Replaced
+                | RemovalCause::Consumed,
+            ) => CacheOp::Remove(val),
+        };
+        self.sink.record(op);
+    }
+}
+
+impl<T: PartialEq, L: Listener<T>, const N: usize> LRUCache<T, N, L> {
+    // Apply the records in `ops`, as written by a `WalListener`, in order. Values are found by
+    // `==`; a touch or removal of a value not in the cache is skipped.
+    pub fn replay<I: IntoIterator<Item = CacheOp<T>>>(&mut self, ops: I) {
+        for op in ops {
+            match op {
+                CacheOp::Insert(val) => {
+                    self.insert(val);
+                }
+                CacheOp::Touch(val) => {
+                    self.touch(|v| *v == val);
+                }
+                CacheOp::Evict(val) => {
+                    if let Some(i) = self.replayed_removal(&val) {
+                        self.evict_index(i);
+                    }
+                }
+                CacheOp::Remove(val) => {
+                    if let Some(i) = self.replayed_removal(&val) {
+                        self.listener.on_event(Event::Evict(
+                            &self.entries[i as usize].val,
+                            RemovalCause::Explicit,
+                        ));
+                        self.remove_index(i);
+                    }
+                }
+            }
+        }
+    }
+
+    // Entry a logged removal of `val` was about: the least recently used one if it holds `val`,
+    // as evictions and `pop_lru` take that one even when the value is stored twice
+    fn replayed_removal(&mut self, val: &T) -> Option<u16> {
+        match self.peek_lru() {
+            Some(lru) if lru == val => Some(self.tail()),
+            _ => self.position(|v| v == val),
+        }
+    }
+}
+
+impl<T: PartialEq, L: Listener<T>, const N: usize> LRUCache<T, N, L> {
+    // Apply one record as a follower, see the module docs. An insert of a value already in the
+    // cache replaces it, so the values of the cache must be distinct for it to mirror a leader.
+    pub fn apply(&mut self, op: CacheOp<T>) {
+        match op {
+            CacheOp::Insert(val) => match self.position(|v| *v == val) {
+                Some(i) => {
+                    self.overwrite(i, val);
+                }
+                None => {
+                    self.insert(val);
+                }
+            },
+            CacheOp::Touch(val) => {
+                self.touch(|v| *v == val);
+            }
+            // A removal of a value not in the cache is already skipped
+            op @ (CacheOp::Evict(_) | CacheOp::Remove(_)) => self.replay([op]),
+        }
+    }
+}
+
+impl<K: Eq, V, L: Listener<V>, const N: usize> LRUMap<K, V, N, L> {
+    // Apply one record of a pair as a follower, see the module docs. Records are matched by key:
+    // an insert replaces the value of its key, a touch or removal ignores the value.
+    pub fn apply(&mut self, op: CacheOp<(K, V)>) {
+        match op {
+            CacheOp::Insert((key, val)) => {
+                self.insert(key, val);
+            }
+            CacheOp::Touch((key, _)) => {
+                self.get(&key);
+            }
+            CacheOp::Evict((key, _)) => {
+                if let Some(i) = self.index_of(&key) {
+                    self.remove_index(i, RemovalCause::Capacity);
+                }
+            }
+            CacheOp::Remove((key, _)) => {
+                self.remove(&key);
+            }
+        }
+    }
+}
@@ -0,0 +1,219 @@
+//! Hierarchical timer wheel recording when cache slots expire.
+//!
+//! Level `l` has 16 buckets of `16^l` ticks each, so 8 levels cover the next `2^32` ticks and
+//! anything further out waits in an overflow list. A slot is filed under the level of the
+//! highest bit where its deadline differs from the wheel's current time, and moves down a level
+//! each time the wheel reaches its bucket. Every slot is therefore touched at most once per
+//! level before it comes due, which makes finding expired slots O(1) amortized instead of a scan
+//! of the whole cache.
+
+const SLOT_BITS: u32 = 4;
+const SLOTS: usize = 1 << SLOT_BITS;
+const LEVELS: usize = 8;
+// Lists after the level buckets: slots that are due, and slots beyond the top level
+const READY: u8 = (LEVELS * SLOTS) as u8;
+const OVERFLOW: u8 = READY + 1;
+const LISTS: usize = LEVELS * SLOTS + 2;
+// Marks a slot that is not in any list, i.e. does not expire
+const UNLISTED: u8 = u8::MAX;
+const NIL: u16 = u16::MAX;
+
+#[derive(Debug, Clone, Copy)]
+struct Node {
+    deadline: u64,
+    prev: u16,
+    next: u16,
+    list: u8,
+}
+
+impl Node {
+    const UNLISTED: Node = Node {
+        deadline: u64::MAX,
+        prev: NIL,
+        next: NIL,
+        list: UNLISTED,
+    };
+}
+
+// Deadlines of the slots of a cache of capacity `N`, indexed like its entries
+#[derive(Debug, Clone)]
+pub(crate) struct TimerWheel<const N: usize> {
+    nodes: [Node; N],
+    heads: [u16; LISTS],
+    // Non-empty buckets of each level, one bit per bucket
+    occupied: [u16; LEVELS],
+    // Time the wheel has been advanced to
+    elapsed: u64,
+}
+
+impl<const N: usize> TimerWheel<N> {
+    pub(crate) const fn new(now: u64) -> Self {
+        TimerWheel {
+            nodes: [Node::UNLISTED; N],
+            heads: [NIL; LISTS],
+            occupied: [0; LEVELS],
+            elapsed: now,
+        }
+    }
+
+    // Deadline of slot `i`, `None` if it does not expire
+    #[inline]
+    pub(crate) fn deadline(&self, i: u16) -> Option<u64> {
+        let node = &self.nodes[i as usize];
+        (node.list != UNLISTED).then_some(node.deadline)
+    }
+
+    // Make slot `i` expire at `deadline`, replacing any earlier deadline
+    pub(crate) fn insert(&mut self, i: u16, deadline: u64) {
+        self.remove(i);
+        self.nodes[i as usize].deadline = deadline;
+        let list = self.list_for(deadline);
+        self.push(i, list);
+    }
+
+    // Make slot `i` not expire
+    pub(crate) fn remove(&mut self, i: u16) {
+        if self.nodes[i as usize].list != UNLISTED {
+            self.unlink(i);
+        }
+    }
+
+    // Move the deadline of slot `from` to slot `to`, which must not expire
+    pub(crate) fn relocate(&mut self, from: u16, to: u16) {
+        let node = self.nodes[from as usize];
+        self.nodes[from as usize] = Node::UNLISTED;
+        self.nodes[to as usize] = node;
+        if node.list == UNLISTED {
+            return;
+        }
+
+        if node.prev == NIL {
+            self.heads[node.list as usize] = to;
+        } else {
+            self.nodes[node.prev as usize].next = to;
+        }
+        if node.next != NIL {
+            self.nodes[node.next as usize].prev = to;
+        }
+    }
+
+    // Forget every deadline
+    pub(crate) fn clear(&mut self) {
+        self.nodes = [Node::UNLISTED; N];
+        self.heads = [NIL; LISTS];
+        self.occupied = [0; LEVELS];
+    }
+
+    // Move the wheel forward to `now`, collecting every slot due by then for `pop_ready`
+    pub(crate) fn advance(&mut self, now: u64) {
+        if now <= self.elapsed {
+            return;
+        }
+        let top = LEVELS as u32 * SLOT_BITS;
+        let crossed_top = (now >> top) != (self.elapsed >> top);
+
+        while let Some((list, start)) = self.next_bucket() {
+            if start > now {
+                break;
+            }
+            self.elapsed = start;
+            self.refile(list);
+        }
+
+        self.elapsed = now;
+        if crossed_top {
+            self.refile(OVERFLOW);
+        }
+    }
+
+    // Take one slot whose deadline has passed, or `None` when there are no more
+    pub(crate) fn pop_ready(&mut self) -> Option<u16> {
+        let i = self.heads[READY as usize];
+        if i == NIL {
+            return None;
+        }
+        self.unlink(i);
+        Some(i)
+    }
+
+    // Earliest non-empty bucket and the time it starts at
+    fn next_bucket(&self) -> Option<(u8, u64)> {
+        for level in 0..LEVELS {
+            let shift = level as u32 * SLOT_BITS;
+            let current = ((self.elapsed >> shift) as usize) & (SLOTS - 1);
+            let pending = self.occupied[level] & (u16::MAX << current);
+            if pending != 0 {
+                let bucket = pending.trailing_zeros() as u64;
+                let block = self.elapsed & !((1u64 << (shift + SLOT_BITS)) - 1);
+                let list = (level * SLOTS) as u8 + bucket as u8;
+                return Some((list, block + (bucket << shift)));
+            }
+        }
+        None
+    }
+
+    // Re-file every slot of `list` against the current time
+    fn refile(&mut self, list: u8) {
+        let mut i = self.heads[list as usize];
+        self.heads[list as usize] = NIL;
+        self.mark_empty(list);
+
+        while i != NIL {
+            let next = self.nodes[i as usize].next;
+            let target = self.list_for(self.nodes[i as usize].deadline);
+            self.push(i, target);
+            i = next;
+        }
+    }
+
+    fn list_for(&self, deadline: u64) -> u8 {
+        if deadline <= self.elapsed {
+            return READY;
+        }
+        let level = (63 - (deadline ^ self.elapsed).leading_zeros()) / SLOT_BITS;
+        if level as usize >= LEVELS {
+            return OVERFLOW;
+        }
+        let bucket = (deadline >> (level * SLOT_BITS)) as usize & (SLOTS - 1);
+        (level as usize * SLOTS + bucket) as u8
+    }
+
+    fn push(&mut self, i: u16, list: u8) {
+        let head = self.heads[list as usize];
+        self.nodes[i as usize] = Node {
+            deadline: self.nodes[i as usize].deadline,
+            prev: NIL,
+            next: head,
+            list,
+        };
+        if head != NIL {
+            self.nodes[head as usize].prev = i;
+        }
+        self.heads[list as usize] = i;
+        if (list as usize) < LEVELS * SLOTS {
+            self.occupied[list as usize / SLOTS] |= 1 << (list as usize % SLOTS);
+        }
+    }
+
+    fn unlink(&mut self, i: u16) {
+        let node = self.nodes[i as usize];
+        if node.prev == NIL {
+            self.heads[node.list as usize] = node.next;
+        } else {
+            self.nodes[node.prev as usize].next = node.next;
+        }
+        if node.next != NIL {
+            self.nodes[node.next as usize].prev = node.prev;
+        }
+        if self.heads[node.list as usize] == NIL {
+            self.mark_empty(node.list);
+        }
+        self.nodes[i as usize].list = UNLISTED;
+    }
+
+    fn mark_empty(&mut self, list: u8) {
+        if (list as usize) < LEVELS * SLOTS {
+            self.occupied[list as usize / SLOTS] &= !(1 << (list as usize % SLOTS));
+        }
+    }
+}
@@ -0,0 +1,236 @@
+//! Key streams for simulating cache workloads.
+//!
+//! Hit ratios only mean something for a given access pattern. The generators here produce the
+//! usual ones as iterators of keys in `0..keys`, from a seed, so a simulation or benchmark sees
+//! the same stream on every run and every target:
+//!
+//! - `Uniform`: every key equally likely, the worst case for any cache.
+//! - `Zipf`: key `k` drawn with probability proportional to `1 / (k + 1)^s`, the classic model
+//!   of web and storage traffic where a few keys take most of the lookups.
+//! - `ScanHeavy`: Zipf traffic over a hot set, interrupted by long sequential scans of cold keys
+//!   as backups or table scans make, which flush the hot set out of a plain LRU.
+//!
+//! Everything works without `std`: the few floating point functions Zipf sampling needs are
+//! approximated here, well enough for workloads but not for numerics.
+
+// SplitMix64, small and good enough to drive simulations
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub const fn new(seed: u64) -> Self {
+        Rng(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    // Uniform in `0.0..1.0`
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    // Uniform in `0..n`, `n` being above 0
+    pub fn below(&mut self, n: u64) -> u64 {
+        ((self.next_u64() as u128 * n as u128) >> 64) as u64
+    }
+}
+
+// Keys in `0..keys`, all equally likely
+#[derive(Debug, Clone)]
+pub struct Uniform {
+    rng: Rng,
+    keys: u64,
+}
+
+impl Uniform {
+    // Panics if `keys` is 0
+    pub fn new(seed: u64, keys: u64) -> Self {
+        assert!(keys > 0, "a workload needs at least one key");
+        Uniform {
+            rng: Rng::new(seed),
+            keys,
+        }
+    }
+}
+
+impl Iterator for Uniform {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        Some(self.rng.below(self.keys))
+    }
+}
+
+// Keys in `0..keys`, key `k` with a probability proportional to `1 / (k + 1)^s`. Sampled by
+// rejection-inversion (Hörmann and Derflinger), in constant time whatever the number of keys.
+#[derive(Debug, Clone)]
+pub struct Zipf {
+    rng: Rng,
+    keys: u64,
+    s: f64,
+    // Integral of the hat function over the keys
+    t: f64,
+    // `1 / (1 - s)`, unused when `s` is 1
+    q: f64,
+}
+
+impl Zipf {
+    // Panics if `keys` is 0 or `s` is negative or not finite. `s` around 1 is typical, 0 gives
+    // uniform keys and larger values concentrate on the first ones.
+    pub fn new(seed: u64, keys: u64, s: f64) -> Self {
+        assert!(keys > 0, "a workload needs at least one key");
+        assert!(
+            s >= 0.0 && s.is_finite(),
+            "the Zipf exponent must be finite and not negative"
+        );
+        let n = keys as f64;
+        let (t, q) = if s == 1.0 {
+            (1.0 + ln(n), 0.0)
+        } else {
+            let q = 1.0 / (1.0 - s);
+            ((powf(n, 1.0 - s) - s) * q, q)
+        };
+        Zipf {
+            rng: Rng::new(seed),
+            keys,
+            s,
+            t,
+            q,
+        }
+    }
+
+    // Inverse of the integral of the hat function
+    fn inv_hat(&self, p: f64) -> f64 {
+        let pt = p * self.t;
+        if pt <= 1.0 {
+            pt
+        } else if self.s == 1.0 {
+            exp(pt - 1.0)
+        } else {
+            powf(pt * (1.0 - self.s) + self.s, self.q)
+        }
+    }
+}
+
+impl Iterator for Zipf {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        loop {
+            let p = self.rng.next_f64();
+            let inv = self.inv_hat(p);
+            // Rank from 1, truncation being the floor of a positive number
+            let x = ((inv + 1.0) as u64).clamp(1, self.keys) as f64;
+            let mut ratio = powf(x, -self.s);
+            if x > 1.0 {
+                ratio *= powf(inv, self.s);
+            }
+            if self.rng.next_f64() < ratio {
+                return Some(x as u64 - 1);
+            }
+        }
+    }
+}
+
+// Zipf keys over a hot set of `hot` keys, interrupted every `every` keys by a scan of `len`
+// keys above the hot set, each scan picking up where the last one stopped
+#[derive(Debug, Clone)]
+pub struct ScanHeavy {
+    hot: Zipf,
+    cold: u64,
+    every: u64,
+    len: u64,
+    // Keys left in the current scan, or to draw before the next one
+    left: u64,
+    scanning: bool,
+    // Next cold key to scan, counted from the top of the hot set
+    cursor: u64,
+}
+
+impl ScanHeavy {
+    // Keys are in `0..hot + cold`. Panics if either is 0, or if `every` or `len` is.
+    pub fn new(seed: u64, hot: u64, cold: u64, every: u64, len: u64) -> Self {
+        assert!(cold > 0, "a workload needs at least one cold key");
+        assert!(
+            every > 0 && len > 0,
+            "scans and the gaps between them need a length"
+        );
+        ScanHeavy {
+            hot: Zipf::new(seed, hot, 1.0),
+            cold,
+            every,
+            len,
+            left: every,
+            scanning: false,
+            cursor: 0,
+        }
+    }
+}
+
+impl Iterator for ScanHeavy {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        if self.left == 0 {
+            self.scanning = !self.scanning;
+            self.left = if self.scanning { self.len } else { self.every };
+        }
+        self.left -= 1;
+        if self.scanning {
+            let key = self.hot.keys + self.cursor;
+            self.cursor = (self.cursor + 1) % self.cold;
+            Some(key)
+        } else {
+            self.hot.next()
+        }
+    }
+}
+
+const LN_2: f64 = core::f64::consts::LN_2;
+
+// Natural logarithm of `x`, which must be positive and normal
+fn ln(x: f64) -> f64 {
+    // `x = m * 2^e` with `m` in `1..2`
+    let bits = x.to_bits();
+    let e = ((bits >> 52) & 0x7ff) as i64 - 1023;
+    let m = f64::from_bits((bits & ((1 << 52) - 1)) | (1023 << 52));
+    // ln(m) = 2 atanh(y), `y` being at most 1/3 here
+    let y = (m - 1.0) / (m + 1.0);
+    let y2 = y * y;
+    let (mut term, mut sum) = (y, 0.0);
+    for k in 0..16 {
+        sum += term / (2 * k + 1) as f64;
+        term *= y2;
+    }
+    e as f64 * LN_2 + 2.0 * sum
+}
+
+// `e^x`, 0 where it underflows and infinity where it overflows
+fn exp(x: f64) -> f64 {
+    if x > 709.0 {
+        return f64::INFINITY;
+    }
+    if x < -708.0 {
+        return 0.0;
+    }
+    // `x = k ln 2 + r` with `r` within half of ln 2 of 0
+    let k = (x / LN_2 + if x < 0.0 { -0.5 } else { 0.5 }) as i64;
+    let r = x - k as f64 * LN_2;
+    let (mut term, mut sum) = (1.0, 1.0);
+    for i in 1..20 {
+        term *= r / i as f64;
+        sum += term;
+    }
+    sum * f64::from_bits(((k + 1023) as u64) << 52)
+}
+
+// `x^y` for a positive `x`
+fn powf(x: f64, y: f64) -> f64 {
+    exp(y * ln(x))
+}
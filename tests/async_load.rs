@@ -0,0 +1,111 @@
+//! Deduplication and cancellation of `AsyncLRUCache::get_or_load`.
+//!
+//! Futures are polled by hand so that each test controls exactly where every task is suspended.
+#![cfg(feature = "async")]
+
+use std::{
+    cell::Cell,
+    future::{poll_fn, Future},
+    panic::{self, AssertUnwindSafe},
+    pin::Pin,
+    task::{Context, Poll, Waker},
+};
+
+use lru::async_cache::AsyncLRUCache;
+
+fn poll<F: Future + ?Sized>(fut: Pin<&mut F>) -> Poll<F::Output> {
+    fut.poll(&mut Context::from_waker(Waker::noop()))
+}
+
+// A load that stays pending until `done` is set, counting how often it was started
+fn load<'a>(
+    started: &'a Cell<u32>,
+    done: &'a Cell<bool>,
+    val: u32,
+) -> impl Future<Output = u32> + 'a {
+    started.set(started.get() + 1);
+    poll_fn(move |_| match done.get() {
+        true => Poll::Ready(val),
+        false => Poll::Pending,
+    })
+}
+
+#[test]
+fn waiters_share_one_load() {
+    let cache: AsyncLRUCache<u32, u32, 4> = AsyncLRUCache::new();
+    let (started, done) = (Cell::new(0), Cell::new(false));
+
+    let mut a = Box::pin(cache.get_or_load(1, || load(&started, &done, 10)));
+    let mut b = Box::pin(cache.get_or_load(1, || load(&started, &done, 20)));
+    assert!(poll(a.as_mut()).is_pending());
+    assert!(poll(b.as_mut()).is_pending());
+    assert_eq!(started.get(), 1);
+
+    done.set(true);
+    assert_eq!(poll(a.as_mut()), Poll::Ready(10));
+    assert_eq!(poll(b.as_mut()), Poll::Ready(10));
+    assert_eq!(started.get(), 1);
+}
+
+#[test]
+fn waiter_takes_over_a_cancelled_load() {
+    let cache: AsyncLRUCache<u32, u32, 4> = AsyncLRUCache::new();
+    let (started, done) = (Cell::new(0), Cell::new(false));
+
+    let mut a = Box::pin(cache.get_or_load(1, || load(&started, &done, 10)));
+    let mut b = Box::pin(cache.get_or_load(1, || load(&started, &done, 20)));
+    assert!(poll(a.as_mut()).is_pending());
+    assert!(poll(b.as_mut()).is_pending());
+
+    // The driving task goes away mid-load: `b` runs its own loader instead of failing
+    drop(a);
+    assert!(poll(b.as_mut()).is_pending());
+    assert_eq!(started.get(), 2);
+
+    done.set(true);
+    assert_eq!(poll(b.as_mut()), Poll::Ready(20));
+}
+
+#[test]
+fn waiter_takes_over_a_panicked_load() {
+    let cache: AsyncLRUCache<u32, u32, 4> = AsyncLRUCache::new();
+    let (started, done, fail) = (Cell::new(0), Cell::new(true), Cell::new(false));
+
+    let failing = || {
+        poll_fn(|_| match fail.get() {
+            true => panic!("load failed"),
+            false => Poll::<u32>::Pending,
+        })
+    };
+    let mut a = Box::pin(cache.get_or_load(1, failing));
+    let mut b = Box::pin(cache.get_or_load(1, || load(&started, &done, 20)));
+    assert!(poll(a.as_mut()).is_pending());
+    assert!(poll(b.as_mut()).is_pending());
+
+    fail.set(true);
+    assert!(panic::catch_unwind(AssertUnwindSafe(|| poll(a.as_mut()))).is_err());
+    drop(a);
+
+    assert_eq!(poll(b.as_mut()), Poll::Ready(20));
+    assert_eq!(started.get(), 1);
+}
+
+#[test]
+fn cancelled_waiter_does_not_affect_the_load() {
+    let cache: AsyncLRUCache<u32, u32, 4> = AsyncLRUCache::new();
+    let (started, done) = (Cell::new(0), Cell::new(false));
+
+    let mut a = Box::pin(cache.get_or_load(1, || load(&started, &done, 10)));
+    let mut b = Box::pin(cache.get_or_load(1, || load(&started, &done, 20)));
+    assert!(poll(a.as_mut()).is_pending());
+    assert!(poll(b.as_mut()).is_pending());
+    drop(b);
+
+    done.set(true);
+    assert_eq!(poll(a.as_mut()), Poll::Ready(10));
+    assert_eq!(started.get(), 1);
+
+    let mut c = Box::pin(cache.get_or_load(1, || load(&started, &done, 30)));
+    assert_eq!(poll(c.as_mut()), Poll::Ready(10));
+    assert_eq!(started.get(), 1);
+}
@@ -0,0 +1,94 @@
+//! `CacheBuilder`: `build` hands each option to the cache it assembles.
+
+mod common;
+
+use core::num::NonZero;
+
+use common::Removals;
+use lru::{
+    builder::CacheBuilder,
+    mem::{MemSize, OversizePolicy},
+    ttl::TickClock,
+    LRUCache, RemovalCause, StatsMode,
+};
+
+// A value owning the given number of bytes of heap memory
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Blob(usize);
+
+impl MemSize for Blob {
+    fn heap_size(&self) -> usize {
+        self.0
+    }
+}
+
+#[test]
+fn builds_a_plain_cache() {
+    let mut cache: LRUCache<u32, 4, Removals> = CacheBuilder::new()
+        .stats_mode(StatsMode::Sampled(2))
+        .listener(Removals::default())
+        .build();
+    for val in 0..5 {
+        cache.insert(val);
+    }
+    assert_eq!(cache.listener().of(RemovalCause::Capacity), [0]);
+    // The first of every 4 operations counts for 4
+    assert_eq!(cache.stats().inserts, 8);
+
+    let mut cache: LRUCache<u32, 4> = CacheBuilder::default().build();
+    cache.insert(1);
+    cache.insert(2);
+    assert_eq!(cache.stats().inserts, 2);
+}
+
+#[test]
+fn builds_a_ttl_cache() {
+    let ttl = NonZero::new(10).unwrap();
+    let expiring = |sliding| {
+        let builder = CacheBuilder::<u32, 4>::new()
+            .stats_mode(StatsMode::Off)
+            .listener(Removals::default())
+            .clock(TickClock::new())
+            .default_ttl(ttl);
+        let mut cache = match sliding {
+            true => builder.sliding_expiry().build(),
+            false => builder.build(),
+        };
+        assert_eq!(cache.default_ttl(), Some(10));
+        cache.insert(1);
+        cache.clock().advance(8);
+        assert!(cache.touch(|&v| v == 1));
+        cache.clock().advance(8);
+        let alive = cache.touch(|&v| v == 1);
+        assert_eq!(cache.stats(), &Default::default());
+        alive
+    };
+    // The hit at 8 moved the deadline to 18
+    assert!(expiring(true));
+    assert!(!expiring(false));
+
+    let cache = CacheBuilder::<u32, 4>::new()
+        .clock(TickClock::new())
+        .build();
+    assert_eq!(cache.default_ttl(), None);
+}
+
+#[test]
+fn builds_a_memory_bounded_cache() {
+    let mut cache = CacheBuilder::<Blob, 4>::new()
+        .memory_limit(100)
+        .oversize_policy(OversizePolicy::EvictAsNeeded)
+        .stats_mode(StatsMode::Off)
+        .build();
+    assert_eq!(
+        (cache.limit(), cache.oversize_policy()),
+        (100, OversizePolicy::EvictAsNeeded)
+    );
+    assert_eq!(cache.insert(Blob(60)), Ok(0));
+    assert_eq!(cache.insert(Blob(150)), Ok(1));
+    assert_eq!(cache.stats().inserts, 0);
+
+    let cache = CacheBuilder::<Blob, 4>::new().memory_limit(100).build();
+    assert_eq!(cache.oversize_policy(), OversizePolicy::RejectTooLarge);
+    assert_eq!(cache.stats().inserts, 0);
+}
@@ -0,0 +1,147 @@
+//! The model the caches are tested against, shared by the test binaries.
+
+// Each test binary uses only part of it
+#![allow(dead_code)]
+
+use std::collections::VecDeque;
+
+use lru::{priority::PriorityCache, Event, LRUCache, Listener, RemovalCause};
+
+// xorshift64*, so that failures replay from their seed without a dependency
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1)
+    }
+
+    pub fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 32
+    }
+
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+}
+
+// What an LRU cache of capacity `cap` does, most recently used first
+#[derive(Debug, Clone)]
+pub struct Model<T> {
+    pub cap: usize,
+    pub values: VecDeque<T>,
+    pub hits: u64,
+    pub misses: u64,
+    pub inserts: u64,
+    pub evictions: u64,
+    // Whether a hit makes the value the most recently used
+    pub promote_hits: bool,
+}
+
+impl<T: Clone> Model<T> {
+    pub fn new(cap: usize) -> Self {
+        Model {
+            cap,
+            values: VecDeque::new(),
+            hits: 0,
+            misses: 0,
+            inserts: 0,
+            evictions: 0,
+            promote_hits: true,
+        }
+    }
+
+    pub fn insert(&mut self, val: T) -> Option<T> {
+        self.inserts += 1;
+        if self.cap == 0 {
+            return Some(val);
+        }
+        let evicted = if self.values.len() == self.cap {
+            self.evictions += 1;
+            self.values.pop_back()
+        } else {
+            None
+        };
+        self.values.push_front(val);
+        evicted
+    }
+
+    pub fn touch(&mut self, pred: impl Fn(&T) -> bool) -> bool {
+        match self.values.iter().position(pred) {
+            Some(i) => {
+                self.hits += 1;
+                if self.promote_hits {
+                    let val = self.values.remove(i).unwrap();
+                    self.values.push_front(val);
+                }
+                true
+            }
+            None => {
+                self.misses += 1;
+                false
+            }
+        }
+    }
+
+    pub fn remove(&mut self, pred: impl Fn(&T) -> bool) -> Option<T> {
+        let i = self.values.iter().position(pred)?;
+        self.values.remove(i)
+    }
+}
+
+// Records the values removed from a cache and why, in order
+#[derive(Debug)]
+pub struct Removals<T = u32>(pub Vec<(T, RemovalCause)>);
+
+impl<T> Default for Removals<T> {
+    fn default() -> Self {
+        Removals(Vec::new())
+    }
+}
+
+impl<T: Clone> Listener<T> for Removals<T> {
+    fn on_event(&mut self, event: Event<'_, T>) {
+        if let Event::Evict(val, cause) = event {
+            self.0.push((val.clone(), cause));
+        }
+    }
+}
+
+impl<T: Clone> Removals<T> {
+    // Returns the values removed for `cause`, in order
+    pub fn of(&self, cause: RemovalCause) -> Vec<T> {
+        self.0
+            .iter()
+            .filter(|(_, c)| *c == cause)
+            .map(|(val, _)| val.clone())
+            .collect()
+    }
+}
+
+// A cache of capacity `N` recording its removals, `values` inserted into it in order
+pub fn filled<const N: usize>(values: impl IntoIterator<Item = u32>) -> LRUCache<u32, N, Removals> {
+    let mut cache = LRUCache::default();
+    for val in values {
+        cache.insert(val);
+    }
+    cache
+}
+
+// 1, then 2 as a transient entry, then 3, in a cache with room for one more
+pub fn with_transient() -> LRUCache<u32, 4, Removals> {
+    let mut cache = filled([1]);
+    cache.insert_transient(2);
+    cache.insert(3);
+    cache
+}
+
+// A full cache of 1 at priority 2, 2 at 0, 3 at 1 and 4 at 0, inserted in that order
+pub fn prioritized() -> PriorityCache<u32, 4, Removals> {
+    let mut cache = PriorityCache::default();
+    for (val, priority) in [(1, 2), (2, 0), (3, 1), (4, 0)] {
+        cache.insert_with_priority(val, priority);
+    }
+    cache
+}
@@ -0,0 +1,45 @@
+//! `LRUMap::diff`: the entries whose key only one of two maps holds.
+
+use lru::map::{Difference, LRUMap};
+
+fn map(keys: &[u32]) -> LRUMap<u32, u32, 8> {
+    let mut map = LRUMap::new();
+    for &key in keys {
+        map.insert(key, key * 10);
+    }
+    map
+}
+
+#[test]
+fn lists_each_side_in_recency_order() {
+    let a = map(&[1, 2, 3, 4]);
+    let b = map(&[2, 5, 4, 6]);
+    let diff: Vec<_> = a.diff(&b).collect();
+    assert_eq!(
+        diff,
+        [
+            Difference::OnlyInSelf(&3, &30),
+            Difference::OnlyInSelf(&1, &10),
+            Difference::OnlyInOther(&6, &60),
+            Difference::OnlyInOther(&5, &50),
+        ]
+    );
+    assert_eq!(a.diff(&a).count(), 0);
+}
+
+#[test]
+fn stale_entries_are_absent_on_both_sides() {
+    let mut a = map(&[1, 2]);
+    a.bump_epoch();
+    a.insert(3, 30);
+    let b = map(&[1, 3]);
+    // 1 is stale in `a`, so `b` has it alone, and 2 is in neither
+    assert_eq!(
+        a.diff(&b).collect::<Vec<_>>(),
+        [Difference::OnlyInOther(&1, &10)]
+    );
+    assert_eq!(
+        b.diff(&a).collect::<Vec<_>>(),
+        [Difference::OnlyInSelf(&1, &10)]
+    );
+}
@@ -0,0 +1,86 @@
+//! `DigestListener`: the digest depends on the values held and nothing else.
+
+mod common;
+
+use common::Rng;
+use lru::{digest::DigestListener, map::LRUMap, LRUCache};
+
+type Cache = LRUCache<u32, 8, DigestListener>;
+
+fn cache_of(values: impl IntoIterator<Item = u32>) -> Cache {
+    let mut cache = Cache::with_listener(DigestListener::new());
+    for val in values {
+        cache.insert(val);
+    }
+    cache
+}
+
+#[test]
+fn the_order_does_not_matter() {
+    let a = cache_of([1, 2, 3, 4]);
+    let mut b = cache_of([4, 2, 1, 3]);
+    assert_eq!(a.digest(), b.digest());
+    b.touch(|&v| v == 1);
+    assert_eq!(a.digest(), b.digest());
+    assert_ne!(a.digest(), cache_of([1, 2, 3, 5]).digest());
+    assert_ne!(a.digest(), cache_of([1, 2, 3]).digest());
+    assert_eq!(cache_of([]).digest(), 0);
+}
+
+#[test]
+fn removing_what_was_inserted_restores_the_digest() {
+    let mut cache = cache_of([2, 3]);
+    let before = cache.digest();
+    cache.insert(1);
+    assert_ne!(cache.digest(), before);
+    assert_eq!(cache.pop_lru(), Some(2));
+    cache.insert(2);
+    assert!(cache.touch(|&v| v == 3));
+    assert!(cache.touch(|&v| v == 2));
+    assert_eq!(cache.pop_lru(), Some(1));
+    assert_eq!(cache.digest(), before);
+    cache.clear();
+    assert_eq!(cache.digest(), 0);
+}
+
+#[test]
+fn matches_the_digest_of_the_values_held() {
+    let mut rng = Rng::new(167);
+    let mut cache = cache_of([]);
+    for step in 0..20_000 {
+        let val = rng.below(24) as u32;
+        match rng.below(4) {
+            0 | 1 => {
+                cache.insert(val);
+            }
+            2 => {
+                cache.touch(|&v| v == val);
+            }
+            _ => {
+                cache.pop_lru();
+            }
+        }
+        // Held in another order
+        let mut values: Vec<u32> = cache.iter().copied().collect();
+        values.sort_unstable();
+        assert_eq!(cache.digest(), cache_of(values).digest(), "step {step}");
+    }
+}
+
+#[test]
+fn a_map_digests_its_values_only() {
+    let mut a: LRUMap<&str, u32, 4, DigestListener> = LRUMap::with_listener(DigestListener::new());
+    let mut b: LRUMap<&str, u32, 4, DigestListener> = LRUMap::with_listener(DigestListener::new());
+    a.insert("x", 1);
+    a.insert("y", 2);
+    b.insert("q", 2);
+    b.insert("r", 1);
+    assert_eq!(a.digest(), b.digest());
+    // Replacing a value swaps its hash for the new one's
+    a.insert("x", 5);
+    b.insert("r", 5);
+    assert_eq!(a.digest(), b.digest());
+    a.remove("x");
+    b.remove("r");
+    assert_eq!(a.digest(), cache_of([2]).digest());
+}
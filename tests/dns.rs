@@ -0,0 +1,81 @@
+//! `DnsCache`: records are served for their TTL clamped to the configured bounds, counting down.
+#![cfg(feature = "dns")]
+
+use lru::{dns::DnsCache, equiv::DomainName, ttl::TickClock};
+
+type Cache<'a> = DnsCache<&'static str, [u8; 4], &'a TickClock, 4>;
+
+const ADDR: [u8; 4] = [192, 0, 2, 1];
+
+#[test]
+fn clamps_the_ttl_to_the_bounds() {
+    let clock = TickClock::new();
+    let mut cache: Cache = DnsCache::new(&clock, 10, 100);
+    assert_eq!(cache.ttl_bounds(), (10, 100));
+    assert_eq!(cache.insert("short", ADDR, 1), (10, None));
+    assert_eq!(cache.insert("long", ADDR, 86_400), (100, None));
+    assert_eq!(cache.insert("fit", ADDR, 50), (50, None));
+    assert_eq!(cache.remaining_ttl("short"), Some(10));
+    assert_eq!(cache.remaining_ttl("long"), Some(100));
+}
+
+#[test]
+fn answers_with_the_ttl_left_until_it_expires() {
+    let clock = TickClock::new();
+    let mut cache: Cache = DnsCache::new(&clock, 0, 100);
+    cache.insert("a", ADDR, 30);
+    clock.advance(12);
+    assert_eq!(cache.get("a"), Some((&ADDR, 18)));
+    clock.advance(18);
+    assert_eq!(cache.remaining_ttl("a"), None);
+    // Still there until a lookup drops it
+    assert_eq!(cache.len(), 1);
+    assert_eq!(cache.get("a"), None);
+    assert!(cache.is_empty());
+    assert_eq!((cache.stats().hits, cache.stats().misses), (1, 1));
+}
+
+#[test]
+fn a_new_record_replaces_the_old_one_and_its_ttl() {
+    let clock = TickClock::new();
+    let mut cache: Cache = DnsCache::new(&clock, 0, 100);
+    cache.insert("a", ADDR, 5);
+    clock.advance(5);
+    let addr = [192, 0, 2, 2];
+    assert_eq!(cache.insert("a", addr, 60), (60, Some(("a", ADDR))));
+    assert_eq!(cache.get("a"), Some((&addr, 60)));
+    // An expired record can still be removed, e.g. on a NOTIFY
+    cache.insert("b", ADDR, 1);
+    clock.advance(1);
+    assert_eq!(cache.remove("b"), Some(ADDR));
+    assert_eq!(cache.remove("b"), None);
+}
+
+#[test]
+#[should_panic(expected = "minimum TTL is above the maximum")]
+fn refuses_bounds_the_wrong_way_round() {
+    let clock = TickClock::new();
+    let _: Cache = DnsCache::new(&clock, 100, 10);
+}
+
+#[test]
+fn domain_names_match_in_any_spelling() {
+    let clock = TickClock::new();
+    let mut cache: DnsCache<&str, [u8; 4], &TickClock, 4, DomainName> =
+        DnsCache::with_equivalence(&clock, 0, 100, DomainName);
+    assert_eq!(cache.insert("Example.COM.", ADDR, 30), (30, None));
+    assert_eq!(cache.get("example.com"), Some((&ADDR, 30)));
+    clock.advance(10);
+    assert_eq!(cache.remaining_ttl("EXAMPLE.com."), Some(20));
+    // Another spelling replaces the record, and stays as the key
+    let addr = [192, 0, 2, 2];
+    assert_eq!(
+        cache.insert("example.com", addr, 60),
+        (60, Some(("Example.COM.", ADDR)))
+    );
+    assert_eq!(cache.len(), 1);
+    assert_eq!(cache.get("example.com."), Some((&addr, 60)));
+    assert_eq!(cache.get("example.org"), None);
+    assert_eq!(cache.remove("EXAMPLE.COM"), Some(addr));
+    assert!(cache.is_empty());
+}
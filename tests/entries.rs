@@ -0,0 +1,64 @@
+//! `LRUMap::to_vec` and `from_entries`: a map copied out and back keeps its recency order.
+#![cfg(feature = "alloc")]
+
+mod common;
+
+use common::Rng;
+use lru::{map::LRUMap, CacheError};
+
+type Map = LRUMap<u32, u64, 8>;
+
+#[test]
+fn round_trips_in_recency_order() {
+    let mut rng = Rng::new(164);
+    let mut map = Map::new();
+    for step in 0..2_000 {
+        let key = rng.below(12) as u32;
+        match rng.below(3) {
+            0 => {
+                map.insert(key, rng.next());
+            }
+            1 => {
+                map.get(&key);
+            }
+            _ => {
+                map.remove(&key);
+            }
+        }
+        let entries = map.to_vec();
+        let copied: Vec<_> = map.entries().map(|(&k, &v)| (k, v)).collect();
+        assert_eq!(entries, copied, "step {step}");
+
+        let mut restored = Map::from_entries(entries.clone()).unwrap();
+        assert_eq!(restored.to_vec(), entries, "step {step}");
+        assert_eq!(restored.stats(), &Default::default(), "step {step}");
+        // And it goes on like the original from there
+        let (mut original, key) = (map.clone(), rng.below(12) as u32);
+        assert_eq!(restored.get(&key), original.get(&key), "step {step}");
+        assert_eq!(
+            restored.insert(99, 0),
+            original.insert(99, 0),
+            "step {step}"
+        );
+        assert_eq!(restored.to_vec(), original.to_vec(), "step {step}");
+    }
+}
+
+#[test]
+fn the_first_of_a_repeated_key_is_kept() {
+    let map = Map::from_entries(vec![(1, 10), (2, 20), (1, 11)]).unwrap();
+    assert_eq!(map.to_vec(), [(1, 10), (2, 20)]);
+}
+
+#[test]
+fn refuses_more_entries_than_fit() {
+    let entries = (0..9).map(|k| (k, 0)).collect();
+    assert_eq!(
+        Map::from_entries(entries).err(),
+        Some(CacheError::CapacityExceeded {
+            len: 9,
+            capacity: 8
+        })
+    );
+    assert!(Map::from_entries(Vec::new()).unwrap().is_empty());
+}
@@ -0,0 +1,82 @@
+//! Mass invalidation with `bump_epoch`: entries inserted before a bump are stale, and lookups
+//! treat them as absent.
+
+mod common;
+
+use common::{filled, Removals};
+use lru::{LRUCache, RemovalCause};
+
+fn cache() -> LRUCache<u32, 8, Removals> {
+    filled(1..=4)
+}
+
+#[test]
+fn lookups_miss_stale_entries_and_remove_them() {
+    let mut cache = cache();
+    assert_eq!(cache.bump_epoch(), 1);
+    cache.insert(5);
+    assert!(!cache.touch(|v| *v == 2));
+    assert!(cache.touch(|v| *v == 5));
+    assert_eq!(cache.find(|v| *v == 3), None);
+    // `find_map` does not show stale entries to its closure, removing those it walks past
+    assert_eq!(cache.find_map(|v| (*v == 4).then_some(*v)), None);
+    assert!(cache.iter().eq(&[5]));
+    assert_eq!(cache.listener().of(RemovalCause::Expired), [2, 3, 4, 1]);
+    assert_eq!((cache.stats().hits, cache.stats().misses), (1, 3));
+}
+
+#[test]
+fn a_stale_match_gives_way_to_a_fresh_one() {
+    let mut cache = cache();
+    cache.bump_epoch();
+    cache.insert(12);
+    // 2 is stale and more recently used than 12 after this
+    assert_eq!(cache.touch_all(|v| *v == 2), 1);
+    assert_eq!(cache.find(|v| v % 10 == 2), Some(&mut 12));
+    assert_eq!(cache.find_map(|v| (*v % 2 == 0).then_some(*v)), Some(12));
+    assert_eq!(cache.listener().of(RemovalCause::Expired), [2]);
+}
+
+#[test]
+fn peeking_skips_stale_entries() {
+    let mut cache = cache();
+    cache.bump_epoch();
+    assert_eq!(cache.peek_unordered(|v| *v == 1), None);
+    cache.insert(1);
+    assert_eq!(cache.peek_unordered(|v| *v == 1), Some(&1));
+}
+
+#[test]
+fn purge_drops_every_stale_entry() {
+    let mut cache = cache();
+    cache.bump_epoch();
+    cache.insert(5);
+    assert_eq!(cache.purge_stale(), 4);
+    assert!(cache.iter().eq(&[5]));
+    assert_eq!(cache.purge_stale(), 0);
+}
+
+#[test]
+fn prefilled_entries_go_stale_too() {
+    let mut cache = LRUCache::from_array([1, 2, 3]);
+    cache.bump_epoch();
+    assert!(!cache.touch(|v| *v == 1));
+    assert_eq!(cache.len(), 2);
+}
+
+#[test]
+fn snapshots_keep_entries_stale() {
+    let mut cache: LRUCache<u32, 8> = LRUCache::new();
+    for val in 1..=4 {
+        cache.insert(val);
+    }
+    cache.bump_epoch();
+    cache.insert(5);
+    let mut buf = [0u8; LRUCache::<u32, 8>::SNAPSHOT_SIZE];
+    let len = cache.as_bytes_snapshot(&mut buf).unwrap();
+    let mut restored: LRUCache<u32, 8> = LRUCache::from_bytes(&buf[..len]).unwrap();
+    assert!(restored.iter().eq(&[5, 4, 3, 2, 1]));
+    assert!(restored.touch(|v| *v == 5));
+    assert!(!restored.touch(|v| *v == 1));
+    assert_eq!(restored.purge_stale(), 3);
+}
@@ -0,0 +1,120 @@
+//! `EquivMap` against the model: any spelling of a key finds the entry stored under another, and
+//! the key is kept in the spelling inserted last.
+
+mod common;
+
+use common::{Model, Rng};
+
+use lru::equiv::{AsciiCaseInsensitive, DomainName, EquivMap, Equivalence};
+
+const NAMES: [&str; 6] = [
+    "example.com",
+    "example.org",
+    "a.example.com",
+    "ns1.example.net",
+    "localhost",
+    "xn--bcher-kva.example",
+];
+
+// `name` with each letter's case flipped at random, and a trailing dot half the time
+fn spelling(rng: &mut Rng, name: &str) -> String {
+    let mut s: String = name
+        .chars()
+        .map(|c| match rng.below(2) {
+            0 => c.to_ascii_uppercase(),
+            _ => c,
+        })
+        .collect();
+    if rng.below(2) == 0 {
+        s.push('.');
+    }
+    s
+}
+
+#[test]
+fn domain_names_match_the_model() {
+    let mut rng = Rng::new(157);
+    let mut map: EquivMap<String, u64, DomainName, 4> = EquivMap::new(DomainName);
+    // The name each entry stands for, its spelling and its value
+    let mut model: Model<(&str, String, u64)> = Model::new(4);
+
+    for step in 0..20_000 {
+        let name = NAMES[rng.below(NAMES.len() as u64) as usize];
+        let key = spelling(&mut rng, name);
+        let val = rng.next();
+        let ctx = format!("step {step} key {key}");
+        match rng.below(10) {
+            0..=4 => {
+                let expected = match model.values.iter().position(|e| e.0 == name) {
+                    Some(i) => {
+                        model.inserts += 1;
+                        let old = model.values.remove(i);
+                        model.values.push_front((name, key.clone(), val));
+                        old
+                    }
+                    None => model.insert((name, key.clone(), val)),
+                };
+                let expected = expected.map(|(_, spelling, val)| (spelling, val));
+                assert_eq!(map.insert(key, val), expected, "{ctx}");
+            }
+            5..=7 => {
+                let found = model.values.iter().find(|e| e.0 == name).map(|e| e.2);
+                model.touch(|e| e.0 == name);
+                assert_eq!(map.get(key.as_str()).copied(), found, "{ctx}");
+            }
+            _ => {
+                let removed = model.remove(|e| e.0 == name).map(|(_, s, v)| (s, v));
+                assert_eq!(map.remove(key.as_str()), removed, "{ctx}");
+            }
+        }
+
+        assert_eq!(map.len(), model.values.len(), "{ctx}");
+        for name in NAMES {
+            let held = model.values.iter().find(|e| e.0 == name).map(|e| e.2);
+            let key = spelling(&mut rng, name);
+            assert_eq!(map.peek(key.as_str()).copied(), held, "{ctx}: {key}");
+        }
+        let stats = map.stats();
+        assert_eq!(
+            (stats.hits, stats.misses, stats.inserts, stats.evictions),
+            (model.hits, model.misses, model.inserts, model.evictions),
+            "{ctx}"
+        );
+    }
+}
+
+#[test]
+fn what_each_rule_treats_as_the_same_key() {
+    let same = |rule: &dyn Equivalence<str>, a: &str, b: &str| rule.equivalent(a, b);
+    assert!(same(&AsciiCaseInsensitive, "Content-Type", "content-TYPE"));
+    assert!(!same(&AsciiCaseInsensitive, "example.com", "example.com."));
+    // Only ASCII letters fold
+    assert!(!same(&AsciiCaseInsensitive, "É", "é"));
+    assert!(same(&DomainName, "Example.COM.", "example.com"));
+    assert!(same(&DomainName, ".", ""));
+    // A single dot stands for the root, not two
+    assert!(!same(&DomainName, "example.com..", "example.com"));
+    assert!(!same(&DomainName, "example.com", "example.co"));
+}
+
+#[test]
+fn headers_match_in_any_case() {
+    let mut map: EquivMap<&str, u32, AsciiCaseInsensitive, 4> = EquivMap::new(AsciiCaseInsensitive);
+    assert_eq!(map.insert("Content-Length", 1), None);
+    assert_eq!(map.insert("content-length", 2), Some(("Content-Length", 1)));
+    assert_eq!(map.len(), 1);
+    assert_eq!(map.peek("CONTENT-LENGTH"), Some(&2));
+    assert!(!map.contains_key("Content-Type"));
+    assert_eq!(map.remove("Content-length"), Some(("content-length", 2)));
+    assert!(map.is_empty());
+}
+
+#[test]
+fn a_closure_is_an_equivalence() {
+    // Keys equal modulo 10
+    let mut map = EquivMap::<u32, &str, _, 4>::new(|a: &u32, b: &u32| a % 10 == b % 10);
+    map.insert(3, "three");
+    assert_eq!(map.get(&13), Some(&mut "three"));
+    assert_eq!(map.insert(23, "twenty-three"), Some((3, "three")));
+    assert_eq!(map.peek(&3), Some(&"twenty-three"));
+}
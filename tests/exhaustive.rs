@@ -0,0 +1,171 @@
+//! Every sequence of operations up to a given length, on caches of capacity 1 to 3.
+//!
+//! Random sequences rarely hit a given short pattern, like evicting a key and inserting it right
+//! back, or touching the tail of a full cache. Small capacities and few keys keep the space small
+//! enough to walk it all: each sequence extends a shorter one, so the walk clones the cache and
+//! its model at each step instead of replaying from scratch, and checks them after every
+//! operation.
+
+mod common;
+
+use common::Model;
+use lru::{map::LRUMap, LRUCache};
+
+#[derive(Debug, Clone, Copy)]
+enum Op {
+    Insert(u32),
+    Touch(u32),
+    PopLru,
+    Remove(u32),
+}
+
+// Every operation of `LRUCache` on keys `0..keys`
+fn cache_ops(keys: u32) -> Vec<Op> {
+    let mut ops = vec![Op::PopLru];
+    for key in 0..keys {
+        ops.extend([Op::Insert(key), Op::Touch(key)]);
+    }
+    ops
+}
+
+// Every operation of `LRUMap` on keys `0..keys`
+fn map_ops(keys: u32) -> Vec<Op> {
+    let mut ops = Vec::new();
+    for key in 0..keys {
+        ops.extend([Op::Insert(key), Op::Touch(key), Op::Remove(key)]);
+    }
+    ops
+}
+
+fn check_cache<const N: usize>(cache: &LRUCache<u32, N>, model: &Model<u32>, ops: &[Op]) {
+    assert!(cache.len() <= N, "{ops:?}");
+    assert_eq!(cache.iter().len(), cache.len(), "{ops:?}");
+    assert!(
+        cache.iter().eq(model.values.iter()),
+        "{ops:?}: {:?}",
+        cache.fmt_recency()
+    );
+    assert_eq!(cache.peek_mru(), model.values.front(), "{ops:?}");
+    assert_eq!(cache.peek_lru(), model.values.back(), "{ops:?}");
+    let stats = cache.stats();
+    assert_eq!(
+        (stats.hits, stats.misses, stats.inserts, stats.evictions),
+        (model.hits, model.misses, model.inserts, model.evictions),
+        "{ops:?}"
+    );
+}
+
+// Apply every extension of `ops` up to `depth` more operations to copies of `cache` and `model`
+fn walk_cache<const N: usize>(
+    cache: &LRUCache<u32, N>,
+    model: &Model<u32>,
+    alphabet: &[Op],
+    ops: &mut Vec<Op>,
+    depth: usize,
+) -> usize {
+    if depth == 0 {
+        return 1;
+    }
+    let mut sequences = 1;
+    for &op in alphabet {
+        let (mut cache, mut model) = (cache.clone(), model.clone());
+        ops.push(op);
+        match op {
+            Op::Insert(key) => assert_eq!(cache.insert(key), model.insert(key), "{ops:?}"),
+            Op::Touch(key) => assert_eq!(
+                cache.touch(|v| *v == key),
+                model.touch(|v| *v == key),
+                "{ops:?}"
+            ),
+            Op::PopLru => assert_eq!(cache.pop_lru(), model.values.pop_back(), "{ops:?}"),
+            Op::Remove(_) => unreachable!("not an operation of LRUCache"),
+        }
+        check_cache(&cache, &model, ops);
+        sequences += walk_cache(&cache, &model, alphabet, ops, depth - 1);
+        ops.pop();
+    }
+    sequences
+}
+
+fn walk_map<const N: usize>(
+    map: &LRUMap<u32, u32, N>,
+    model: &Model<(u32, u32)>,
+    alphabet: &[Op],
+    ops: &mut Vec<Op>,
+    depth: usize,
+) -> usize {
+    if depth == 0 {
+        return 1;
+    }
+    let mut sequences = 1;
+    for &op in alphabet {
+        let (mut map, mut model) = (map.clone(), model.clone());
+        ops.push(op);
+        // Each insert stores a value of its own, to tell a replaced entry from the new one
+        let val = ops.len() as u32;
+        match op {
+            Op::Insert(key) => {
+                let expected = match model.remove(|(k, _)| *k == key) {
+                    Some(old) => {
+                        model.inserts += 1;
+                        model.values.push_front((key, val));
+                        Some(old)
+                    }
+                    None => model.insert((key, val)),
+                };
+                assert_eq!(map.insert(key, val), expected, "{ops:?}");
+            }
+            Op::Touch(key) => {
+                let found = model.touch(|(k, _)| *k == key).then(|| model.values[0].1);
+                assert_eq!(map.get(&key).copied(), found, "{ops:?}");
+            }
+            Op::PopLru => unreachable!("not an operation of LRUMap"),
+            Op::Remove(key) => {
+                let removed = model.remove(|(k, _)| *k == key).map(|e| e.1);
+                assert_eq!(map.remove(&key), removed, "{ops:?}");
+            }
+        }
+        assert!(map.len() <= N, "{ops:?}");
+        assert!(
+            map.entries()
+                .map(|(k, v)| (*k, *v))
+                .eq(model.values.iter().copied()),
+            "{ops:?}"
+        );
+        for key in 0..(N as u32 + 1) {
+            let found = model.values.iter().find(|(k, _)| *k == key).map(|e| e.1);
+            assert_eq!(map.peek(&key).copied(), found, "{ops:?}");
+        }
+        sequences += walk_map(&map, &model, alphabet, ops, depth - 1);
+        ops.pop();
+    }
+    sequences
+}
+
+// One key more than the capacity, so that a full cache still misses, and the longest sequences
+// that keep the walk in the hundreds of thousands
+fn cache_space<const N: usize>(depth: usize) -> usize {
+    let alphabet = cache_ops(N as u32 + 1);
+    let cache = LRUCache::<u32, N>::new();
+    walk_cache(&cache, &Model::new(N), &alphabet, &mut Vec::new(), depth)
+}
+
+fn map_space<const N: usize>(depth: usize) -> usize {
+    let alphabet = map_ops(N as u32 + 1);
+    let map = LRUMap::<u32, u32, N>::new();
+    walk_map(&map, &Model::new(N), &alphabet, &mut Vec::new(), depth)
+}
+
+#[test]
+fn every_cache_sequence_matches_the_model() {
+    assert_eq!(cache_space::<1>(8), (0..=8).map(|i| 5usize.pow(i)).sum());
+    assert_eq!(cache_space::<2>(6), (0..=6).map(|i| 7usize.pow(i)).sum());
+    assert_eq!(cache_space::<3>(5), (0..=5).map(|i| 9usize.pow(i)).sum());
+}
+
+#[test]
+fn every_map_sequence_matches_the_model() {
+    assert_eq!(map_space::<1>(7), (0..=7).map(|i| 6usize.pow(i)).sum());
+    assert_eq!(map_space::<2>(5), (0..=5).map(|i| 9usize.pow(i)).sum());
+    assert_eq!(map_space::<3>(4), (0..=4).map(|i| 12usize.pow(i)).sum());
+}
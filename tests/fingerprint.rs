@@ -0,0 +1,118 @@
+//! `FingerprintMap` against the model: each slot's fingerprint must follow its key when an insert
+//! reuses the slot of an evicted entry, and when a removal moves the last slot into the one freed.
+
+mod common;
+
+use common::{Model, Rng};
+use core::hash::{BuildHasher, Hasher};
+
+use lru::{fingerprint::FingerprintMap, hash::FxBuildHasher};
+
+// Leaves only four fingerprints, so most scans compare keys with a matching fingerprint that are
+// not the one looked up
+#[derive(Default)]
+struct FewFingerprints(u64);
+
+impl Hasher for FewFingerprints {
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 = self.0.wrapping_mul(31).wrapping_add(b as u64);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        (self.0 % 4) << 48
+    }
+}
+
+impl BuildHasher for FewFingerprints {
+    type Hasher = FewFingerprints;
+
+    fn build_hasher(&self) -> FewFingerprints {
+        FewFingerprints(0)
+    }
+}
+
+fn check<S: BuildHasher, const N: usize>(seed: u64, steps: usize, hasher: S) {
+    let mut rng = Rng::new(seed);
+    let mut map: FingerprintMap<u32, u64, N, (), S> = FingerprintMap::with_hasher(hasher);
+    let mut model: Model<(u32, u64)> = Model::new(N);
+    let keys = (N as u32 * 2).max(2);
+
+    for step in 0..steps {
+        let key = rng.below(keys as u64) as u32;
+        let val = rng.next();
+        let op = rng.below(100);
+        let ctx = format!("seed {seed} step {step} op {op} key {key}");
+        match op {
+            0..=44 => {
+                let expected = match model.values.iter().position(|(k, _)| *k == key) {
+                    Some(i) => {
+                        model.inserts += 1;
+                        let old = model.values.remove(i);
+                        model.values.push_front((key, val));
+                        old
+                    }
+                    None => model.insert((key, val)),
+                };
+                assert_eq!(map.insert(key, val), expected, "{ctx}");
+            }
+            45..=69 => {
+                let found = model.values.iter().find(|(k, _)| *k == key).map(|e| e.1);
+                model.touch(|(k, _)| *k == key);
+                assert_eq!(map.get(&key).copied(), found, "{ctx}");
+            }
+            70..=97 => {
+                let removed = model.remove(|(k, _)| *k == key).map(|e| e.1);
+                assert_eq!(map.remove(&key), removed, "{ctx}");
+            }
+            _ => {
+                map.clear();
+                model.values.clear();
+            }
+        }
+
+        assert_eq!(map.len(), model.values.len(), "{ctx}");
+        for k in 0..keys {
+            let held = model.values.iter().find(|(mk, _)| *mk == k).map(|e| e.1);
+            assert_eq!(map.peek(&k).copied(), held, "{ctx}: key {k}");
+            assert_eq!(map.contains_key(&k), held.is_some(), "{ctx}: key {k}");
+        }
+        let stats = map.stats();
+        assert_eq!(
+            (stats.hits, stats.misses, stats.inserts, stats.evictions),
+            (model.hits, model.misses, model.inserts, model.evictions),
+            "{ctx}"
+        );
+    }
+}
+
+#[test]
+fn matches_the_model() {
+    for seed in 0..4 {
+        check::<_, 1>(seed, 2_000, FxBuildHasher);
+        check::<_, 7>(seed, 5_000, FxBuildHasher);
+        check::<_, 32>(seed, 10_000, FxBuildHasher);
+    }
+}
+
+#[test]
+fn matches_the_model_with_colliding_fingerprints() {
+    for seed in 0..4 {
+        check::<_, 1>(seed, 2_000, FewFingerprints::default());
+        check::<_, 7>(seed, 5_000, FewFingerprints::default());
+        check::<_, 32>(seed, 10_000, FewFingerprints::default());
+    }
+}
+
+#[test]
+fn string_keys_are_found_by_str() {
+    let mut map: FingerprintMap<String, u32, 2> = FingerprintMap::new();
+    map.insert("a".to_string(), 1);
+    map.insert("b".to_string(), 2);
+    assert_eq!(map.get("a"), Some(&mut 1));
+    // "b" is evicted, and the slot it leaves takes the fingerprint of "c"
+    assert_eq!(map.insert("c".to_string(), 3), Some(("b".to_string(), 2)));
+    assert!(!map.contains_key("b"));
+    assert_eq!(map.peek("c"), Some(&3));
+}
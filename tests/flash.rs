@@ -0,0 +1,167 @@
+//! `FlashStore`: saves alternate between two regions, so a save cut short by a reset leaves the
+//! previous snapshot to load.
+#![cfg(feature = "flash")]
+
+use lru::{
+    flash::{FlashStore, NorFlash, StoreError},
+    CacheError, LRUCache,
+};
+
+const BLOCK: usize = 256;
+
+// Power went away before the operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct PowerLoss;
+
+// NOR flash in memory: writes can only clear bits. After `ops` erases and writes the power goes.
+struct Flash {
+    bytes: Vec<u8>,
+    ops: usize,
+}
+
+impl Flash {
+    fn new(blocks: usize) -> Self {
+        Flash {
+            bytes: vec![0xff; blocks * BLOCK],
+            ops: usize::MAX,
+        }
+    }
+
+    fn spend(&mut self) -> Result<(), PowerLoss> {
+        self.ops = self.ops.checked_sub(1).ok_or(PowerLoss)?;
+        Ok(())
+    }
+}
+
+impl NorFlash for Flash {
+    type Error = PowerLoss;
+
+    const READ_SIZE: usize = 1;
+    const WRITE_SIZE: usize = 4;
+    const ERASE_SIZE: usize = BLOCK;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), PowerLoss> {
+        let at = offset as usize;
+        bytes.copy_from_slice(&self.bytes[at..at + bytes.len()]);
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        self.bytes.len()
+    }
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), PowerLoss> {
+        assert!((from as usize).is_multiple_of(BLOCK) && (to as usize).is_multiple_of(BLOCK));
+        self.spend()?;
+        self.bytes[from as usize..to as usize].fill(0xff);
+        Ok(())
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), PowerLoss> {
+        let aligned = |n: usize| n.is_multiple_of(Self::WRITE_SIZE);
+        assert!(aligned(offset as usize) && aligned(bytes.len()));
+        self.spend()?;
+        let at = offset as usize;
+        for (old, new) in self.bytes[at..at + bytes.len()].iter_mut().zip(bytes) {
+            *old &= new;
+        }
+        Ok(())
+    }
+}
+
+type Cache = LRUCache<u32, 8>;
+
+fn store() -> FlashStore<Flash> {
+    FlashStore::new(Flash::new(8), BLOCK as u32, 2 * BLOCK as u32)
+}
+
+fn buf() -> Vec<u8> {
+    vec![0; Cache::FULL_SNAPSHOT_SIZE + 8]
+}
+
+fn cache_of(values: impl IntoIterator<Item = u32>) -> Cache {
+    let mut cache = Cache::new();
+    for val in values {
+        cache.insert(val);
+    }
+    cache
+}
+
+fn load(store: &mut FlashStore<Flash>) -> Option<Vec<u32>> {
+    let cache: Option<Cache> = store.load(&mut buf()).unwrap();
+    cache.map(|cache| cache.iter().copied().collect())
+}
+
+#[test]
+fn loads_nothing_from_blank_flash() {
+    assert_eq!(load(&mut store()), None);
+}
+
+#[test]
+fn loads_the_newest_snapshot() {
+    let mut store = store();
+    for n in 1..=5 {
+        let mut cache = cache_of(0..n);
+        cache.touch(|&v| v == 0);
+        store.save(&cache, &mut buf()).unwrap();
+        let loaded: Cache = store.load(&mut buf()).unwrap().unwrap();
+        assert!(loaded.iter().eq(cache.iter()), "save {n}");
+        assert_eq!(loaded.stats(), cache.stats());
+    }
+}
+
+#[test]
+fn a_torn_save_leaves_the_previous_snapshot() {
+    // The erase, the snapshot and the header: cut before each of them
+    for ops in 0..3 {
+        let mut store = store();
+        store.save(&cache_of([1, 2]), &mut buf()).unwrap();
+        store.save(&cache_of([3, 4]), &mut buf()).unwrap();
+        let mut flash = store.into_inner();
+        flash.ops = ops;
+        let mut store = FlashStore::new(flash, BLOCK as u32, 2 * BLOCK as u32);
+        assert_eq!(
+            store.save(&cache_of([5, 6]), &mut buf()),
+            Err(StoreError::Flash(PowerLoss))
+        );
+        let mut flash = store.into_inner();
+        flash.ops = usize::MAX;
+        let mut store = FlashStore::new(flash, BLOCK as u32, 2 * BLOCK as u32);
+        assert_eq!(load(&mut store), Some(vec![4, 3]), "cut after {ops}");
+        // And the next save goes on from there
+        store.save(&cache_of([7]), &mut buf()).unwrap();
+        assert_eq!(load(&mut store), Some(vec![7]));
+    }
+}
+
+#[test]
+fn falls_back_to_the_older_snapshot_if_the_newer_is_damaged() {
+    let mut store = store();
+    store.save(&cache_of([1, 2]), &mut buf()).unwrap();
+    store.save(&cache_of([3, 4]), &mut buf()).unwrap();
+    let mut flash = store.into_inner();
+    // A bit of the second snapshot, in the second region, lost its charge
+    flash.bytes[3 * BLOCK + 40] ^= 0x10;
+    let mut store = FlashStore::new(flash, BLOCK as u32, 2 * BLOCK as u32);
+    assert_eq!(load(&mut store), Some(vec![2, 1]));
+}
+
+#[test]
+fn refuses_a_snapshot_larger_than_a_region() {
+    let mut store = FlashStore::new(Flash::new(2), 0, BLOCK as u32);
+    let cache: LRUCache<u64, 64> = (0..64).fold(LRUCache::new(), |mut cache, val| {
+        cache.insert(val);
+        cache
+    });
+    let mut buf = vec![0; LRUCache::<u64, 64>::FULL_SNAPSHOT_SIZE + 8];
+    assert!(matches!(
+        store.save(&cache, &mut buf),
+        Err(StoreError::Cache(CacheError::TooLarge { .. }))
+    ));
+}
+
+#[test]
+#[should_panic(expected = "aligned to erase blocks")]
+fn refuses_regions_not_aligned_to_erase_blocks() {
+    FlashStore::new(Flash::new(8), 100, 2 * BLOCK as u32);
+}
@@ -0,0 +1,33 @@
+//! Caches built full from an array.
+
+use lru::{LRUCache, MAX_CAPACITY};
+
+#[test]
+fn the_first_value_is_the_most_recently_used() {
+    let mut cache = LRUCache::from_array([1, 2, 3]);
+    assert!(cache.iter().eq(&[1, 2, 3]));
+    assert_eq!(cache.insert(4), Some(3));
+    assert!(cache.iter().eq(&[4, 1, 2]));
+}
+
+#[test]
+fn the_tail_of_the_largest_cache_is_an_ordinary_entry() {
+    // The cache and the array it is built from take a few copies of 768 KiB on the stack
+    let test = || {
+        let mut cache: LRUCache<u16, MAX_CAPACITY> =
+            LRUCache::from_array(core::array::from_fn(|i| i as u16));
+        let last = MAX_CAPACITY as u16 - 1;
+        assert_eq!(cache.peek_lru(), Some(&last));
+        // A transient entry would be gone after the first hit
+        assert!(cache.touch(|v| *v == last));
+        assert!(cache.touch(|v| *v == last));
+        assert_eq!(cache.len(), MAX_CAPACITY);
+        assert_eq!(cache.peek_lru(), Some(&(last - 1)));
+    };
+    std::thread::Builder::new()
+        .stack_size(64 << 20)
+        .spawn(test)
+        .unwrap()
+        .join()
+        .unwrap();
+}
@@ -0,0 +1,73 @@
+//! GreedyDual-Size: eviction weighs recency against the miss cost and size of entries.
+
+use lru::gds::GdsCache;
+
+#[test]
+fn expensive_entries_outlive_cheap_ones() {
+    let mut cache: GdsCache<u32, 3> = GdsCache::new();
+    cache.insert(1, 100, 1);
+    for val in 2..10 {
+        cache.insert(val, 1, 1);
+    }
+    assert!(cache.touch(|v| *v == 1));
+    assert_eq!(cache.len(), 3);
+}
+
+#[test]
+fn large_entries_go_first() {
+    let mut cache: GdsCache<u32, 2> = GdsCache::new();
+    cache.insert(1, 8, 8);
+    cache.insert(2, 8, 1);
+    assert_eq!(cache.peek_victim(), Some(&1));
+    assert_eq!(cache.insert(3, 8, 1), Some(1));
+    assert_eq!(cache.inflation(), 1 << 16);
+}
+
+#[test]
+fn unused_expensive_entries_age_out() {
+    let mut cache: GdsCache<u32, 2> = GdsCache::new();
+    cache.insert(1, 10, 1);
+    // Each eviction of a cheap entry raises the inflation by its credit, until new cheap entries
+    // start above the expensive one
+    let mut evicted = Vec::new();
+    for val in 2..20 {
+        evicted.extend(cache.insert(val, 1, 1));
+    }
+    assert!(evicted.contains(&1));
+    assert_eq!(cache.credit_of(|v| *v == 1), None);
+}
+
+#[test]
+fn hits_renew_the_credit() {
+    let mut cache: GdsCache<u32, 2> = GdsCache::new();
+    cache.insert(1, 1, 1);
+    cache.insert(2, 4, 1);
+    assert_eq!(cache.insert(3, 4, 1), Some(1));
+    let credit = cache.credit_of(|v| *v == 2).unwrap();
+    assert_eq!(cache.find(|v| *v == 2), Some(&mut 2));
+    assert_eq!(
+        cache.credit_of(|v| *v == 2),
+        Some(credit + cache.inflation())
+    );
+    // Same credit as 3 otherwise, but the most recently used
+    assert_eq!(cache.insert(4, 1, 1), Some(3));
+}
+
+#[test]
+fn pop_victim_leaves_the_inflation() {
+    let mut cache: GdsCache<u32, 2> = GdsCache::new();
+    cache.insert(1, 2, 1);
+    cache.insert(2, 1, 1);
+    assert_eq!(cache.pop_victim(), Some(2));
+    assert_eq!(cache.inflation(), 0);
+    assert!(cache.entries().eq([(&1, 2 << 16)].into_iter()));
+    cache.clear();
+    assert_eq!(cache.pop_victim(), None);
+}
+
+#[test]
+fn capacity_0_hands_the_value_back() {
+    let mut cache: GdsCache<u32, 0> = GdsCache::new();
+    assert_eq!(cache.insert(1, 1, 1), Some(1));
+    assert!(cache.is_empty());
+}
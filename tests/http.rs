@@ -0,0 +1,85 @@
+//! `HttpCache`: the freshness of a stored response, from its `Cache-Control` and `Age`.
+#![cfg(feature = "std")]
+
+use lru::{
+    http::{CacheControl, HttpCache, Lookup},
+    ttl::TickClock,
+};
+
+// Seconds of the headers, in the milliseconds of the clock
+const SECOND: u32 = 1000;
+
+fn cache(clock: &TickClock) -> HttpCache<&'static str, &TickClock, 4> {
+    HttpCache::new(clock)
+}
+
+#[test]
+fn parses_the_directives_that_matter() {
+    let cc =
+        CacheControl::parse(r#"public, Max-Age="60", stale-while-revalidate=30, x=1, no-cache"#);
+    assert_eq!(cc.max_age, Some(60));
+    assert_eq!(cc.stale_while_revalidate, 30);
+    assert!(cc.no_cache && !cc.no_store && !cc.must_revalidate);
+    assert_eq!(
+        CacheControl::parse("max-age=soon, max-age=5").max_age,
+        Some(5)
+    );
+    assert_eq!(CacheControl::parse(""), CacheControl::default());
+}
+
+#[test]
+fn fresh_then_stale_then_needs_revalidating() {
+    let clock = TickClock::new();
+    let mut cache = cache(&clock);
+    let cc = CacheControl::parse("max-age=60, stale-while-revalidate=30");
+    // 10 seconds old already when stored
+    assert_eq!(cache.insert("GET", "/a", "a", &cc, 10), Ok(None));
+    clock.advance(49 * SECOND);
+    assert_eq!(cache.get("GET", "/a"), Lookup::Fresh(&"a"));
+    clock.advance(SECOND);
+    let stale = |revalidate| Lookup::Stale {
+        response: &"a",
+        revalidate,
+    };
+    // Only the first lookup finding it stale is asked to revalidate it
+    assert_eq!(cache.get("GET", "/a"), stale(true));
+    assert_eq!(cache.get("GET", "/a"), stale(false));
+    clock.advance(30 * SECOND);
+    assert_eq!(cache.get("GET", "/a"), Lookup::Revalidate(&"a"));
+    assert!(cache.revalidated("GET", "/a", &cc, 0));
+    assert_eq!(cache.get("GET", "/a"), Lookup::Fresh(&"a"));
+    assert_eq!(cache.get("HEAD", "/a"), Lookup::Miss);
+    assert!(!cache.revalidated("GET", "/b", &cc, 0));
+}
+
+#[test]
+fn directives_limit_what_is_served() {
+    let clock = TickClock::new();
+    let mut cache = cache(&clock);
+    let no_store = CacheControl::parse("no-store, max-age=60");
+    assert_eq!(cache.insert("GET", "/s", "s", &no_store, 0), Err("s"));
+    let no_cache = CacheControl::parse("no-cache, max-age=60");
+    cache.insert("GET", "/c", "c", &no_cache, 0).unwrap();
+    assert_eq!(cache.get("GET", "/c"), Lookup::Revalidate(&"c"));
+    let must = CacheControl::parse("max-age=1, stale-while-revalidate=30, must-revalidate");
+    cache.insert("GET", "/m", "m", &must, 0).unwrap();
+    // Without max-age a response is never fresh
+    cache
+        .insert("GET", "/n", "n", &CacheControl::default(), 0)
+        .unwrap();
+    assert_eq!(cache.get("GET", "/n"), Lookup::Revalidate(&"n"));
+    clock.advance(SECOND);
+    assert_eq!(cache.get("GET", "/m"), Lookup::Revalidate(&"m"));
+    assert_eq!(cache.len(), 3);
+}
+
+#[test]
+fn a_new_response_replaces_the_stored_one() {
+    let clock = TickClock::new();
+    let mut cache = cache(&clock);
+    let cc = CacheControl::parse("max-age=60");
+    cache.insert("GET", "/a", "old", &cc, 0).unwrap();
+    assert_eq!(cache.insert("GET", "/a", "new", &cc, 0), Ok(Some("old")));
+    assert_eq!(cache.get("GET", "/a"), Lookup::Fresh(&"new"));
+    assert_eq!(cache.len(), 1);
+}
@@ -0,0 +1,128 @@
+//! `Interner` against the model: a handle resolves to its string until that string is evicted or
+//! the interner cleared, and never to the string that took its slot.
+
+mod common;
+
+use common::{Model, Rng};
+
+use lru::{
+    intern::{Handle, Interner},
+    CacheError,
+};
+
+const NAMES: [&str; 10] = [
+    "id", "name", "user", "host", "path", "port", "query", "method", "status", "length",
+];
+
+// A handle handed out, with the string it stands for and whether it still does
+struct Issued {
+    handle: Handle,
+    name: &'static str,
+    live: bool,
+}
+
+#[test]
+fn handles_resolve_until_their_string_goes() {
+    let mut rng = Rng::new(153);
+    let mut interner: Interner<4, 8> = Interner::new();
+    let mut model: Model<&str> = Model::new(4);
+    let mut issued: Vec<Issued> = Vec::new();
+
+    for step in 0..20_000 {
+        let name = NAMES[rng.below(NAMES.len() as u64) as usize];
+        let ctx = format!("step {step} name {name}");
+        match rng.below(100) {
+            0..=69 => {
+                let handle = interner.intern(name).unwrap();
+                if model.touch(|n| *n == name) {
+                    // The handle it had already
+                    let live = issued.iter().find(|i| i.live && i.name == name);
+                    assert_eq!(live.map(|i| i.handle), Some(handle), "{ctx}");
+                } else {
+                    if let Some(evicted) = model.insert(name) {
+                        for i in issued.iter_mut().filter(|i| i.name == evicted) {
+                            i.live = false;
+                        }
+                    }
+                    issued.push(Issued {
+                        handle,
+                        name,
+                        live: true,
+                    });
+                }
+            }
+            70..=97 => {
+                let found = model.touch(|n| *n == name);
+                let handle = interner.get(name);
+                assert_eq!(handle.is_some(), found, "{ctx}");
+                if let Some(handle) = handle {
+                    assert_eq!(interner.resolve(handle), Some(name), "{ctx}");
+                }
+            }
+            _ => {
+                interner.clear();
+                model.values.clear();
+                for i in &mut issued {
+                    i.live = false;
+                }
+            }
+        }
+
+        assert_eq!(interner.len(), model.values.len(), "{ctx}");
+        for i in &issued {
+            let expected = i.live.then_some(i.name);
+            assert_eq!(
+                interner.resolve(i.handle),
+                expected,
+                "{ctx}: {:?}",
+                i.handle
+            );
+        }
+        let stats = interner.stats();
+        assert_eq!(
+            (stats.hits, stats.misses, stats.inserts, stats.evictions),
+            (model.hits, model.misses, model.inserts, model.evictions),
+            "{ctx}"
+        );
+    }
+    assert!(issued.len() > 100);
+}
+
+#[test]
+fn a_slot_given_to_another_string_bumps_its_generation() {
+    let mut interner: Interner<1, 8> = Interner::new();
+    let a = interner.intern("a").unwrap();
+    let b = interner.intern("b").unwrap();
+    assert_ne!(a, b);
+    assert_eq!(interner.resolve(a), None);
+    assert_eq!(interner.resolve(b), Some("b"));
+    // Back in the same slot, but a generation later than its first handle
+    let a2 = interner.intern("a").unwrap();
+    assert_ne!(a, a2);
+    assert_eq!(interner.resolve(a), None);
+    assert_eq!(interner.resolve(a2), Some("a"));
+    interner.clear();
+    assert_eq!(interner.resolve(a2), None);
+    let c = interner.intern("c").unwrap();
+    assert_eq!(interner.resolve(c), Some("c"));
+    assert_eq!(interner.resolve(a2), None);
+}
+
+#[test]
+fn refuses_strings_it_cannot_hold() {
+    let mut interner: Interner<2, 4> = Interner::new();
+    assert_eq!(
+        interner.intern("toolong"),
+        Err(CacheError::TooLarge { size: 7, limit: 4 })
+    );
+    assert!(interner.is_empty());
+    assert!(interner.intern("four").is_ok());
+    let mut none: Interner<0, 4> = Interner::new();
+    assert_eq!(
+        none.intern("a"),
+        Err(CacheError::CapacityExceeded {
+            len: 1,
+            capacity: 0
+        })
+    );
+}
@@ -0,0 +1,104 @@
+//! `IntLruMap` against the model: its slot table must find every key the map holds, including
+//! after removals have shifted the probe sequences of colliding keys back.
+
+mod common;
+
+use common::{Model, Rng};
+use core::hash::{BuildHasher, Hasher};
+
+use lru::{hash::FxBuildHasher, intmap::IntLruMap};
+
+// Sends every key to one of four home buckets, so probe sequences run long and overlap
+#[derive(Default)]
+struct Clustered(u64);
+
+impl Hasher for Clustered {
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 = self.0.wrapping_mul(31).wrapping_add(b as u64);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        (self.0 % 4) << 62
+    }
+}
+
+impl BuildHasher for Clustered {
+    type Hasher = Clustered;
+
+    fn build_hasher(&self) -> Clustered {
+        Clustered(0)
+    }
+}
+
+fn check<S: BuildHasher, const N: usize>(seed: u64, steps: usize, hasher: S) {
+    let mut rng = Rng::new(seed);
+    let mut map: IntLruMap<u32, u64, N, (), S> = IntLruMap::with_hasher(hasher);
+    let mut model: Model<(u32, u64)> = Model::new(N);
+    let keys = (N as u32 * 2).max(2);
+
+    for step in 0..steps {
+        let key = rng.below(keys as u64) as u32;
+        let val = rng.next();
+        let op = rng.below(100);
+        let ctx = format!("seed {seed} step {step} op {op} key {key}");
+        match op {
+            0..=44 => {
+                let expected = match model.values.iter().position(|(k, _)| *k == key) {
+                    Some(i) => {
+                        model.inserts += 1;
+                        let old = model.values.remove(i);
+                        model.values.push_front((key, val));
+                        old
+                    }
+                    None => model.insert((key, val)),
+                };
+                assert_eq!(map.insert(key, val), expected, "{ctx}");
+            }
+            45..=69 => {
+                let found = model.values.iter().find(|(k, _)| *k == key).map(|e| e.1);
+                model.touch(|(k, _)| *k == key);
+                assert_eq!(map.get(key).copied(), found, "{ctx}");
+            }
+            70..=97 => {
+                let removed = model.remove(|(k, _)| *k == key).map(|e| e.1);
+                assert_eq!(map.remove(key), removed, "{ctx}");
+            }
+            _ => {
+                map.clear();
+                model.values.clear();
+            }
+        }
+
+        assert_eq!(map.len(), model.values.len(), "{ctx}");
+        for k in 0..keys {
+            let held = model.values.iter().find(|(mk, _)| *mk == k).map(|e| e.1);
+            assert_eq!(map.peek(k).copied(), held, "{ctx}: key {k}");
+        }
+        let stats = map.stats();
+        assert_eq!(
+            (stats.hits, stats.misses, stats.inserts, stats.evictions),
+            (model.hits, model.misses, model.inserts, model.evictions),
+            "{ctx}"
+        );
+    }
+}
+
+#[test]
+fn matches_the_model() {
+    for seed in 0..4 {
+        check::<_, 1>(seed, 2_000, FxBuildHasher);
+        check::<_, 7>(seed, 5_000, FxBuildHasher);
+        check::<_, 32>(seed, 10_000, FxBuildHasher);
+    }
+}
+
+#[test]
+fn matches_the_model_with_colliding_keys() {
+    for seed in 0..4 {
+        check::<_, 1>(seed, 2_000, Clustered::default());
+        check::<_, 7>(seed, 5_000, Clustered::default());
+        check::<_, 32>(seed, 10_000, Clustered::default());
+    }
+}
@@ -0,0 +1,86 @@
+//! Deferred work done a bounded amount at a time with `maintain`.
+
+mod common;
+
+use common::{filled, with_transient};
+use lru::{batch::BatchedLRUCache, map::LRUMap, LRUCache, RemovalCause};
+
+#[test]
+fn sheds_entries_above_the_low_watermark() {
+    let mut cache: LRUCache<u32, 8, _> = filled([]).with_watermarks(2, 6);
+    for val in 1..=5 {
+        cache.insert(val);
+    }
+    assert_eq!(cache.maintain(2), 2);
+    assert!(cache.iter().eq(&[5, 4, 3]));
+    assert_eq!(cache.maintain(10), 1);
+    assert_eq!(cache.maintain(10), 0);
+    assert!(cache.iter().eq(&[5, 4]));
+    assert_eq!(cache.stats().evictions, 3);
+    assert!(cache
+        .listener()
+        .0
+        .iter()
+        .all(|(_, cause)| *cause == RemovalCause::Capacity));
+}
+
+#[test]
+fn nothing_to_do_without_watermarks() {
+    let mut cache: LRUCache<u32, 4> = LRUCache::from_array([1, 2, 3, 4]);
+    assert_eq!(cache.maintain(10), 0);
+    assert_eq!(cache.len(), 4);
+}
+
+#[test]
+fn expires_stale_entries_from_the_tail() {
+    let mut cache: LRUCache<u32, 8, _> = filled(1..=4);
+    cache.bump_epoch();
+    cache.insert(5);
+    assert_eq!(cache.maintain(3), 3);
+    assert!(cache.iter().eq(&[5, 4]));
+    assert_eq!(cache.maintain(3), 1);
+    assert!(cache.iter().eq(&[5]));
+    assert_eq!(
+        cache.listener().0,
+        [1, 2, 3, 4].map(|val| (val, RemovalCause::Expired))
+    );
+}
+
+#[test]
+fn drops_a_spent_transient() {
+    let mut cache = with_transient();
+    cache.find(|v| *v == 2);
+    assert_eq!(cache.maintain(1), 1);
+    assert!(cache.iter().eq(&[3, 1]));
+    assert_eq!(cache.listener().0, [(2, RemovalCause::Consumed)]);
+}
+
+#[test]
+fn map_expires_stale_entries_from_the_tail() {
+    let mut map: LRUMap<u32, u32, 8> = LRUMap::new();
+    for key in 1..=4 {
+        map.insert(key, key * 10);
+    }
+    map.bump_epoch();
+    map.insert(5, 50);
+    assert_eq!(map.maintain(2), 2);
+    assert_eq!(map.len(), 3);
+    assert_eq!(map.maintain(8), 2);
+    assert_eq!(map.len(), 1);
+    assert_eq!(map.peek(&5), Some(&50));
+}
+
+#[test]
+fn batched_applies_promotions_first() {
+    let mut cache: BatchedLRUCache<u32, 4, 4> = BatchedLRUCache::new();
+    for val in 1..=4 {
+        cache.insert(val);
+    }
+    assert!(cache.touch(|v| *v == 1));
+    assert!(cache.touch(|v| *v == 2));
+    assert_eq!(cache.maintain(1), 1);
+    assert_eq!(cache.pending(), 1);
+    assert_eq!(cache.maintain(5), 1);
+    assert_eq!(cache.pending(), 0);
+    assert!(cache.into_inner().iter().eq(&[2, 1, 4, 3]));
+}
@@ -0,0 +1,256 @@
+//! `MemBoundedCache`: the heap memory of the values stays under the budget, evicting or refusing
+//! as the oversize policy says, and `MemSize` measures that memory.
+#![cfg(feature = "alloc")]
+
+mod common;
+
+use std::{collections::VecDeque, mem::size_of};
+
+use common::Rng;
+use lru::{
+    mem::{MemBoundedCache, MemSize, OversizePolicy},
+    LRUCache,
+};
+
+const LIMIT: usize = 100;
+
+// A value owning `heap` bytes, which a lookup may change
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Blob {
+    id: u32,
+    heap: usize,
+}
+
+impl MemSize for Blob {
+    fn heap_size(&self) -> usize {
+        self.heap
+    }
+}
+
+fn blob(id: u32, heap: usize) -> Blob {
+    Blob { id, heap }
+}
+
+// What a `MemBoundedCache` of capacity `cap` does: the ids and sizes of its values, most
+// recently used first
+struct Model {
+    cap: usize,
+    policy: OversizePolicy,
+    values: VecDeque<(u32, usize)>,
+    evictions: u64,
+}
+
+impl Model {
+    fn used(&self) -> usize {
+        self.values.iter().map(|&(_, size)| size).sum()
+    }
+
+    fn insert(&mut self, id: u32, size: usize) -> Result<usize, u32> {
+        let refused = match self.policy {
+            OversizePolicy::EvictAsNeeded => false,
+            OversizePolicy::RejectTooLarge => size > LIMIT,
+            OversizePolicy::RejectIfFull => self.used() + size > LIMIT,
+        };
+        if refused {
+            return Err(id);
+        }
+        let mut evicted = 0;
+        while !self.values.is_empty() && self.used() + size > LIMIT {
+            self.values.pop_back();
+            evicted += 1;
+        }
+        if self.values.len() == self.cap {
+            self.values.pop_back();
+            evicted += 1;
+        }
+        self.values.push_front((id, size));
+        self.evictions += evicted as u64;
+        Ok(evicted)
+    }
+
+    // Find `id`, making it most recently used, and set its size to `size`
+    fn resize(&mut self, id: u32, size: usize) -> bool {
+        let Some(i) = self.values.iter().position(|&(v, _)| v == id) else {
+            return false;
+        };
+        self.values.remove(i);
+        self.values.push_front((id, size));
+        true
+    }
+}
+
+fn matches_the_model(policy: OversizePolicy, seed: u64) {
+    let mut rng = Rng::new(seed);
+    let mut cache: MemBoundedCache<Blob, 4> =
+        MemBoundedCache::new(LIMIT).with_oversize_policy(policy);
+    let mut model = Model {
+        cap: 4,
+        policy,
+        values: VecDeque::new(),
+        evictions: 0,
+    };
+    for step in 0..20_000u32 {
+        let ctx = format!("{policy:?} step {step}");
+        // Now and then a value larger than the whole budget
+        let size = match rng.below(16) {
+            0 => LIMIT + 1 + rng.below(50) as usize,
+            _ => rng.below(60) as usize,
+        };
+        if rng.below(2) == 0 {
+            let expected = model.insert(step, size);
+            let got = cache.insert(blob(step, size)).map_err(|b| b.id);
+            assert_eq!(got, expected, "{ctx}");
+        } else {
+            // Grow or shrink a value through the reference a lookup hands out
+            let id = step.saturating_sub(1 + rng.below(8) as u32);
+            let found = cache.find(|b| b.id == id).map(|b| b.heap = size);
+            assert_eq!(found.is_some(), model.resize(id, size), "{ctx}");
+        }
+        assert_eq!(cache.current_memory(), model.used(), "{ctx}");
+        assert_eq!(cache.len(), model.values.len(), "{ctx}");
+        assert_eq!(cache.stats().evictions, model.evictions, "{ctx}");
+    }
+}
+
+#[test]
+fn evict_as_needed_matches_the_model() {
+    matches_the_model(OversizePolicy::EvictAsNeeded, 121);
+}
+
+#[test]
+fn reject_too_large_matches_the_model() {
+    matches_the_model(OversizePolicy::RejectTooLarge, 122);
+}
+
+#[test]
+fn reject_if_full_matches_the_model() {
+    matches_the_model(OversizePolicy::RejectIfFull, 123);
+}
+
+#[test]
+fn each_policy_on_a_value_over_the_budget() {
+    let fill = |policy| {
+        let mut cache: MemBoundedCache<Blob, 4> =
+            MemBoundedCache::new(LIMIT).with_oversize_policy(policy);
+        assert_eq!(cache.insert(blob(1, 40)), Ok(0));
+        assert_eq!(cache.insert(blob(2, 40)), Ok(0));
+        cache
+    };
+
+    let mut cache = fill(OversizePolicy::EvictAsNeeded);
+    assert_eq!(cache.insert(blob(3, 150)), Ok(2));
+    assert_eq!((cache.len(), cache.current_memory()), (1, 150));
+
+    let mut cache = fill(OversizePolicy::RejectTooLarge);
+    assert_eq!(cache.insert(blob(3, 150)), Err(blob(3, 150)));
+    assert_eq!((cache.len(), cache.current_memory()), (2, 80));
+    // Within the budget, it evicts like `EvictAsNeeded`
+    assert_eq!(cache.insert(blob(4, 30)), Ok(1));
+    assert_eq!((cache.len(), cache.current_memory()), (2, 70));
+
+    let mut cache = fill(OversizePolicy::RejectIfFull);
+    assert_eq!(cache.insert(blob(3, 30)), Err(blob(3, 30)));
+    assert_eq!(cache.insert(blob(4, 20)), Ok(0));
+    assert_eq!((cache.len(), cache.current_memory()), (3, 100));
+}
+
+#[test]
+fn a_full_array_evicts_one_entry_whatever_the_bytes() {
+    let mut cache: MemBoundedCache<Blob, 2> = MemBoundedCache::new(1000);
+    assert_eq!(cache.insert(blob(1, 10)), Ok(0));
+    assert_eq!(cache.insert(blob(2, 20)), Ok(0));
+    assert_eq!(cache.insert(blob(3, 30)), Ok(1));
+    assert_eq!(cache.current_memory(), 50);
+    // Out of bytes and of slots: the bytes evict one, which leaves a slot
+    assert_eq!(cache.insert(blob(4, 960)), Ok(1));
+    assert_eq!((cache.len(), cache.current_memory()), (2, 990));
+    assert_eq!(cache.stats().evictions, 2);
+}
+
+#[test]
+fn a_changed_front_value_is_measured_again() {
+    let mut cache: MemBoundedCache<Blob, 4> = MemBoundedCache::new(LIMIT);
+    cache.insert(blob(1, 10)).unwrap();
+    cache.insert(blob(2, 10)).unwrap();
+    cache.find(|b| b.id == 1).unwrap().heap = 50;
+    assert_eq!(cache.current_memory(), 60);
+    // Counting 1 at the 10 bytes it had, 45 more would fit without evicting 2
+    assert_eq!(cache.insert(blob(3, 45)), Ok(1));
+    assert!(cache.find(|b| b.id == 2).is_none());
+    assert_eq!(cache.current_memory(), 95);
+}
+
+#[test]
+fn the_entry_moved_into_an_evicted_slot_keeps_its_size() {
+    let mut cache: MemBoundedCache<Blob, 3> = MemBoundedCache::new(60);
+    for (id, heap) in [(1, 10), (2, 20), (3, 30)] {
+        cache.insert(blob(id, heap)).unwrap();
+    }
+    // 1 goes to the front, leaving 2 at the tail in the middle slot. Evicting it moves 3 from
+    // the last slot into its place, with its 30 bytes.
+    assert!(cache.touch(|b| b.id == 1));
+    assert_eq!(cache.insert(blob(4, 25)), Ok(2));
+    assert_eq!(cache.current_memory(), 35);
+    assert_eq!(cache.len(), 2);
+}
+
+#[test]
+fn clearing_frees_the_budget() {
+    let mut cache: MemBoundedCache<Blob, 4> = MemBoundedCache::new(LIMIT);
+    cache.insert(blob(1, 90)).unwrap();
+    cache.clear();
+    assert_eq!(cache.current_memory(), 0);
+    assert_eq!(cache.insert(blob(2, 100)), Ok(0));
+    assert_eq!(cache.current_memory(), 100);
+}
+
+#[test]
+fn a_cache_of_capacity_zero_gives_every_value_back() {
+    let mut cache: MemBoundedCache<Blob, 0> = MemBoundedCache::new(LIMIT);
+    assert_eq!(cache.insert(blob(1, 0)), Err(blob(1, 0)));
+    assert!(cache.is_empty());
+}
+
+#[test]
+fn memory_usage_adds_the_heap_to_the_cache_itself() {
+    let mut cache: MemBoundedCache<Blob, 4> = MemBoundedCache::new(LIMIT);
+    cache.insert(blob(1, 30)).unwrap();
+    cache.insert(blob(2, 12)).unwrap();
+    assert_eq!(
+        cache.memory_usage(),
+        size_of::<MemBoundedCache<Blob, 4>>() + 42
+    );
+    assert_eq!(
+        (cache.limit(), cache.oversize_policy()),
+        (LIMIT, OversizePolicy::RejectTooLarge)
+    );
+
+    let mut cache: LRUCache<String, 4> = LRUCache::new();
+    let strings = [String::with_capacity(16), String::with_capacity(5)];
+    let heap = strings.iter().map(String::capacity).sum::<usize>();
+    for s in strings {
+        cache.insert(s);
+    }
+    assert_eq!(cache.current_memory(), heap);
+    assert_eq!(cache.memory_usage(), size_of::<LRUCache<String, 4>>());
+    assert_eq!(cache.memory_usage_with_heap(), cache.memory_usage() + heap);
+}
+
+#[test]
+fn mem_size_counts_owned_heap_memory_only() {
+    assert_eq!(7u64.heap_size(), 0);
+    assert_eq!(().heap_size(), 0);
+    // Borrowed data belongs to someone else
+    assert_eq!(<&String>::heap_size(&&String::with_capacity(10)), 0);
+    assert_eq!(Some(blob(0, 3)).heap_size(), 3);
+    assert_eq!(None::<Blob>.heap_size(), 0);
+    assert_eq!([blob(0, 1), blob(1, 2)].heap_size(), 3);
+    assert_eq!((blob(0, 1), 5u8).heap_size(), 1);
+    assert_eq!((blob(0, 1), blob(1, 2), blob(2, 4)).heap_size(), 7);
+    let string = String::with_capacity(9);
+    assert_eq!(string.heap_size(), string.capacity());
+    let mut vec: Vec<Blob> = Vec::with_capacity(3);
+    vec.push(blob(0, 5));
+    assert_eq!(vec.heap_size(), vec.capacity() * size_of::<Blob>() + 5);
+    assert_eq!(Box::new(blob(0, 5)).heap_size(), size_of::<Blob>() + 5);
+}
@@ -0,0 +1,80 @@
+//! `MinMaxLRUCache` against the model: the minimum and maximum stay right as inserts evict them
+//! and `modify` changes values.
+
+mod common;
+
+use common::{Model, Rng};
+
+use lru::minmax::{Compare, MinMaxLRUCache};
+
+fn check<C: Compare<u32>>(seed: u64, cache: &mut MinMaxLRUCache<u32, 5, C>, reversed: bool) {
+    let mut rng = Rng::new(seed);
+    let mut model: Model<u32> = Model::new(5);
+    for step in 0..20_000 {
+        let val = rng.below(16) as u32;
+        let op = rng.below(10);
+        let ctx = format!("seed {seed} step {step} op {op} val {val}");
+        match op {
+            0..=4 => assert_eq!(cache.insert(val), model.insert(val), "{ctx}"),
+            5..=6 => assert_eq!(
+                cache.touch(|&v| v == val),
+                model.touch(|&v| v == val),
+                "{ctx}"
+            ),
+            7..=8 => {
+                let to = rng.below(16) as u32;
+                let found = model.touch(|&v| v == val);
+                if found {
+                    model.values[0] = to;
+                }
+                assert_eq!(cache.modify(|&v| v == val, |v| *v = to), found, "{ctx}");
+            }
+            _ => {
+                if rng.below(20) == 0 {
+                    cache.clear();
+                    model.values.clear();
+                }
+            }
+        }
+        let (min, max) = (model.values.iter().min(), model.values.iter().max());
+        let (min, max) = if reversed { (max, min) } else { (min, max) };
+        assert_eq!(cache.peek_min(), min, "{ctx}");
+        assert_eq!(cache.peek_max(), max, "{ctx}");
+        assert_eq!(cache.len(), model.values.len(), "{ctx}");
+    }
+}
+
+#[test]
+fn matches_the_model() {
+    for seed in 0..4 {
+        check(seed, &mut MinMaxLRUCache::new(), false);
+    }
+}
+
+#[test]
+fn matches_the_model_ordered_by_a_closure() {
+    let reversed = |a: &u32, b: &u32| b.cmp(a);
+    for seed in 0..4 {
+        check(seed, &mut MinMaxLRUCache::with_comparator(reversed), true);
+    }
+}
+
+#[test]
+fn evicting_the_minimum_or_maximum_finds_the_next_one() {
+    let mut cache: MinMaxLRUCache<u32, 3> = MinMaxLRUCache::new();
+    for val in [1, 9, 5] {
+        cache.insert(val);
+    }
+    assert_eq!((cache.peek_min(), cache.peek_max()), (Some(&1), Some(&9)));
+    // 1 is the least recently used, and the minimum
+    assert_eq!(cache.insert(6), Some(1));
+    assert_eq!((cache.peek_min(), cache.peek_max()), (Some(&5), Some(&9)));
+    // Then 9, the maximum
+    assert_eq!(cache.insert(4), Some(9));
+    assert_eq!((cache.peek_min(), cache.peek_max()), (Some(&4), Some(&6)));
+    assert!(cache.modify(|&v| v == 4, |v| *v = 7));
+    assert_eq!((cache.peek_min(), cache.peek_max()), (Some(&5), Some(&7)));
+    assert!(!cache.modify(|&v| v == 4, |v| *v = 0));
+    let order: Vec<u32> = cache.into_inner().iter().copied().collect();
+    assert_eq!(order, [7, 6, 5]);
+}
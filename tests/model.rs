@@ -0,0 +1,364 @@
+//! Differential tests of `LRUCache` and `LRUMap` against a `VecDeque` model.
+//!
+//! The model keeps its values from most to least recently used and does everything the
+//! obvious way. Long random sequences of operations run against both, and after every operation
+//! the results, the contents in recency order and the stats must agree.
+
+mod common;
+
+use common::{Model, Rng};
+use std::{
+    collections::{HashSet, VecDeque},
+    mem::replace,
+};
+
+use lru::{map::LRUMap, LRUCache, Promotion};
+
+fn check_cache<const N: usize>(seed: u64, steps: usize, promotion: Promotion) {
+    let mut rng = Rng::new(seed);
+    let mut cache: LRUCache<u32, N> = LRUCache::new().with_promotion(promotion);
+    let mut model = Model::new(N);
+    model.promote_hits = promotion != Promotion::OnWrite;
+    // A key space somewhat larger than the capacity gives both hits and evictions
+    let keys = (N as u64 * 2).max(2);
+
+    for step in 0..steps {
+        let key = rng.below(keys) as u32;
+        let op = rng.below(100);
+        let ctx = format!("seed {seed} step {step} op {op} key {key} {promotion:?}");
+        match op {
+            0..=34 => assert_eq!(cache.insert(key), model.insert(key), "{ctx}"),
+            35..=59 => assert_eq!(
+                cache.touch(|v| *v == key),
+                model.touch(|v| *v == key),
+                "{ctx}"
+            ),
+            60..=69 => {
+                let found = model.touch(|v| *v == key).then_some(key);
+                assert_eq!(cache.find(|v| *v == key).copied(), found, "{ctx}");
+            }
+            70..=74 => {
+                let found = model.touch(|v| *v == key).then_some(key as u64 * 2);
+                let mapped = cache.find_map(|v| (*v == key).then_some(*v as u64 * 2));
+                assert_eq!(mapped, found, "{ctx}");
+            }
+            // A cache of capacity 0 has no entry to return a reference to
+            75..=84 if N > 0 => {
+                if !model.touch(|v| *v == key) {
+                    model.insert(key);
+                }
+                assert_eq!(
+                    *cache.get_or_insert_with(|v| *v == key, || key),
+                    key,
+                    "{ctx}"
+                );
+            }
+            85..=88 => {
+                let lru = model.values.back().copied();
+                assert_eq!(cache.pop_lru(), lru, "{ctx}");
+                model.values.pop_back();
+            }
+            89..=90 => {
+                // Only some of the values are taken, the rest go when the iterator is dropped
+                let n = rng.below(4) as usize;
+                let taken = rng.below(n as u64 + 1) as usize;
+                let k = n.min(model.values.len());
+                let coldest: Vec<_> = model.values.iter().rev().take(k).copied().collect();
+                let mut evicted = cache.evict(n);
+                assert_eq!(evicted.len(), k, "{ctx}");
+                let yielded: Vec<_> = evicted.by_ref().take(taken).collect();
+                drop(evicted);
+                assert_eq!(yielded, coldest[..taken.min(k)], "{ctx}");
+                model.values.truncate(model.values.len() - k);
+                model.evictions += k as u64;
+            }
+            91..=92 => {
+                let (mut hot, cold): (VecDeque<_>, VecDeque<_>) =
+                    model.values.drain(..).partition(|v| v % 3 == key % 3);
+                assert_eq!(cache.touch_all(|v| v % 3 == key % 3), hot.len(), "{ctx}");
+                hot.extend(cold);
+                model.values = hot;
+            }
+            93..=94 => {
+                let found = model.values.iter().find(|v| **v == key).copied();
+                assert_eq!(cache.peek_unordered(|v| *v == key).copied(), found, "{ctx}");
+            }
+            95 if rng.below(2) == 0 => {
+                // Extract some of the values of one third, keeping those not reached
+                let taken = rng.below(keys) as usize;
+                let matches = |v: &u32| v % 3 == key % 3;
+                let extracted: Vec<_> = cache.extract_if(|v| matches(v)).take(taken).collect();
+                let mut expected = Vec::new();
+                model.values.retain(|v| {
+                    let extract = matches(v) && expected.len() < taken;
+                    if extract {
+                        expected.push(*v);
+                    }
+                    !extract
+                });
+                assert_eq!(extracted, expected, "{ctx}");
+            }
+            95 => {
+                // Purge a third of the values, changing the others in place and back
+                let mut iter = cache.iter_mut();
+                while let Some(mut entry) = iter.next() {
+                    if *entry.get() % 3 == key % 3 {
+                        let val = *entry.get();
+                        assert_eq!(entry.remove(), val, "{ctx}");
+                    } else {
+                        *entry.get_mut() += 1;
+                        *entry.get_mut() -= 1;
+                    }
+                }
+                model.values.retain(|v| v % 3 != key % 3);
+            }
+            96..=98 => {
+                // Walk to entry `at`, or to the ghost position past the ends for `None`
+                let len = model.values.len();
+                let steps = rng.below(len as u64 + 1) as usize;
+                let (mut cursor, at) = if rng.below(2) == 0 {
+                    let mut cursor = cache.cursor_front_mut();
+                    (0..steps).for_each(|_| cursor.move_next());
+                    (cursor, (steps < len).then_some(steps))
+                } else {
+                    let mut cursor = cache.cursor_back_mut();
+                    (0..steps).for_each(|_| cursor.move_prev());
+                    (cursor, (steps < len).then(|| len - 1 - steps))
+                };
+                let current = at.map(|p| model.values[p]);
+                assert_eq!(cursor.current().copied(), current, "{ctx}");
+
+                let full = len == N;
+                match rng.below(3) {
+                    0 => {
+                        let removed = at.and_then(|p| model.values.remove(p));
+                        assert_eq!(cursor.remove_current(), removed, "{ctx}");
+                        // The cursor moved on to the next entry
+                        let next = at.and_then(|p| model.values.get(p)).copied();
+                        assert_eq!(cursor.current().copied(), next, "{ctx}");
+                    }
+                    1 => {
+                        let result = cursor.insert_before(key);
+                        assert_eq!(result, if full { Err(key) } else { Ok(()) }, "{ctx}");
+                        if !full {
+                            model.values.insert(at.unwrap_or(len), key);
+                            model.inserts += 1;
+                        }
+                        assert_eq!(cursor.current().copied(), current, "{ctx}");
+                    }
+                    _ => {
+                        let result = cursor.insert_after(key);
+                        assert_eq!(result, if full { Err(key) } else { Ok(()) }, "{ctx}");
+                        if !full {
+                            model.values.insert(at.map_or(0, |p| p + 1), key);
+                            model.inserts += 1;
+                        }
+                        assert_eq!(cursor.current().copied(), current, "{ctx}");
+                    }
+                }
+            }
+            _ => {
+                cache.clear();
+                model.values.clear();
+            }
+        }
+
+        assert_eq!(cache.len(), model.values.len(), "{ctx}");
+        assert_eq!(cache.is_empty(), model.values.is_empty(), "{ctx}");
+        assert!(
+            cache.iter().eq(model.values.iter()),
+            "{ctx}: {:?} != {:?}",
+            cache.fmt_recency(),
+            model.values
+        );
+        let folded = cache.fold(Vec::new(), |mut acc, v| {
+            acc.push(*v);
+            acc
+        });
+        assert!(folded.iter().eq(model.values.iter()), "{ctx}");
+        let mut count = 0;
+        cache.for_each(|_| count += 1);
+        assert_eq!(count, model.values.len(), "{ctx}");
+        assert_eq!(cache.peek_mru(), model.values.front(), "{ctx}");
+        assert_eq!(cache.peek_lru(), model.values.back(), "{ctx}");
+        let stats = cache.stats();
+        assert_eq!(
+            (stats.hits, stats.misses, stats.inserts, stats.evictions),
+            (model.hits, model.misses, model.inserts, model.evictions),
+            "{ctx}"
+        );
+    }
+}
+
+fn check_map<const N: usize>(seed: u64, steps: usize, promotion: Promotion) {
+    let mut rng = Rng::new(seed);
+    let mut map: LRUMap<u32, u64, N> = LRUMap::new().with_promotion(promotion);
+    // Entries are pairs, keys being unique
+    let mut model: Model<(u32, u64)> = Model::new(N);
+    model.promote_hits = promotion != Promotion::OnWrite;
+    // Keys from the oldest to the newest insert
+    let mut written: Vec<u32> = Vec::new();
+    // Keys inserted before the last epoch bump
+    let mut stale: HashSet<u32> = HashSet::new();
+    let keys = (N as u64 * 2).max(2);
+
+    for step in 0..steps {
+        let key = rng.below(keys) as u32;
+        let val = rng.next();
+        let op = rng.below(100);
+        let ctx = format!("seed {seed} step {step} op {op} key {key} {promotion:?}");
+        match op {
+            0..=39 => {
+                let expected = match model.values.iter().position(|(k, _)| *k == key) {
+                    // The old entry of the key is replaced in place, which counts as an insert
+                    // but never evicts
+                    Some(i) if promotion == Promotion::OnRead => {
+                        model.inserts += 1;
+                        Some(replace(&mut model.values[i], (key, val)))
+                    }
+                    Some(i) => {
+                        model.inserts += 1;
+                        let old = model.values.remove(i);
+                        model.values.push_front((key, val));
+                        old
+                    }
+                    None => model.insert((key, val)),
+                };
+                let evicted = map.insert(key, val);
+                assert_eq!(evicted, expected, "{ctx}");
+                if let Some((old, _)) = evicted {
+                    written.retain(|k| *k != old);
+                    stale.remove(&old);
+                }
+                written.push(key);
+                stale.remove(&key);
+            }
+            // A stale entry is removed by the first lookup meeting it, a miss
+            40..=69 | 80..=95 if stale.remove(&key) => {
+                model.remove(|(k, _)| *k == key);
+                written.retain(|k| *k != key);
+                if op < 70 {
+                    model.misses += 1;
+                    assert_eq!(map.get(&key), None, "{ctx}");
+                } else {
+                    assert_eq!(map.remove(&key), None, "{ctx}");
+                }
+            }
+            70..=79 if stale.contains(&key) => {
+                assert_eq!(map.peek(&key), None, "{ctx}");
+                assert!(!map.contains_key(&key), "{ctx}");
+            }
+            40..=69 => {
+                let found = model.values.iter().find(|(k, _)| *k == key).map(|e| e.1);
+                model.touch(|(k, _)| *k == key);
+                assert_eq!(map.get(&key).copied(), found, "{ctx}");
+            }
+            70..=79 => {
+                let found = model.values.iter().find(|(k, _)| *k == key).map(|e| e.1);
+                assert_eq!(map.peek(&key).copied(), found, "{ctx}");
+                assert_eq!(map.contains_key(&key), found.is_some(), "{ctx}");
+            }
+            80..=95 => {
+                let removed = model.remove(|(k, _)| *k == key).map(|e| e.1);
+                assert_eq!(map.remove(&key), removed, "{ctx}");
+                written.retain(|k| *k != key);
+            }
+            96..=98 => {
+                let is_hot = |k: &u32, v: &u64| (k ^ *v as u32) & 1 == 0;
+                let (mut hot, cold): (VecDeque<_>, VecDeque<_>) =
+                    model.values.drain(..).partition(|(k, v)| is_hot(k, v));
+                let moved = map.touch_all(is_hot);
+                assert_eq!(moved, hot.len(), "{ctx}");
+                hot.extend(cold);
+                model.values = hot;
+            }
+            _ => match rng.below(3) {
+                0 => {
+                    map.bump_epoch();
+                    stale = model.values.iter().map(|(k, _)| *k).collect();
+                }
+                1 => {
+                    assert_eq!(map.purge_stale(), stale.len(), "{ctx}");
+                    model.values.retain(|(k, _)| !stale.contains(k));
+                    written.retain(|k| !stale.contains(k));
+                    stale.clear();
+                }
+                _ => {
+                    map.clear();
+                    model.values.clear();
+                    written.clear();
+                    stale.clear();
+                }
+            },
+        }
+
+        assert_eq!(map.len(), model.values.len(), "{ctx}");
+        assert!(
+            map.entries()
+                .map(|(k, v)| (*k, *v))
+                .eq(model.values.iter().copied()),
+            "{ctx}"
+        );
+        assert!(
+            map.iter_insertion_order()
+                .map(|(k, _)| *k)
+                .eq(written.iter().copied()),
+            "{ctx}"
+        );
+        let stats = map.stats();
+        assert_eq!(
+            (stats.hits, stats.misses, stats.inserts, stats.evictions),
+            (model.hits, model.misses, model.inserts, model.evictions),
+            "{ctx}"
+        );
+    }
+}
+
+const STEPS: usize = 20_000;
+
+const CLASSIC: Promotion = Promotion::OnReadAndWrite;
+
+#[test]
+fn cache_of_capacity_0_hands_values_back() {
+    check_cache::<0>(1, 1_000, CLASSIC);
+}
+
+#[test]
+fn small_caches_match_the_model() {
+    for seed in 0..8 {
+        check_cache::<1>(seed, STEPS, CLASSIC);
+        check_cache::<2>(seed, STEPS, CLASSIC);
+        check_cache::<3>(seed, STEPS, CLASSIC);
+        check_cache::<4>(seed, STEPS, CLASSIC);
+    }
+}
+
+#[test]
+fn larger_caches_match_the_model() {
+    for seed in 0..4 {
+        check_cache::<16>(seed, STEPS, CLASSIC);
+        check_cache::<64>(seed, STEPS, CLASSIC);
+    }
+}
+
+#[test]
+fn maps_match_the_model() {
+    for seed in 0..8 {
+        check_map::<1>(seed, STEPS, CLASSIC);
+        check_map::<3>(seed, STEPS, CLASSIC);
+        check_map::<8>(seed, STEPS, CLASSIC);
+        check_map::<32>(seed, STEPS, CLASSIC);
+    }
+}
+
+#[test]
+fn other_promotions_match_the_model() {
+    for promotion in [Promotion::OnRead, Promotion::OnWrite] {
+        for seed in 0..4 {
+            check_cache::<3>(seed, STEPS, promotion);
+            check_cache::<16>(seed, STEPS, promotion);
+            check_map::<3>(seed, STEPS, promotion);
+            check_map::<16>(seed, STEPS, promotion);
+        }
+    }
+}
@@ -0,0 +1,148 @@
+//! Proof that the fixed-capacity caches never touch the heap.
+//!
+//! A counting global allocator tallies the allocations of the thread it runs on, and each test
+//! runs a workload of the core operations and asserts that tally did not move. Tests run on
+//! threads of their own, so the harness allocating on other threads does not count.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+    fmt::Write,
+};
+
+use lru::{batch::BatchedLRUCache, intmap::IntLruMap, map::LRUMap, LRUCache};
+
+struct Counting;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+#[allow(unsafe_code)]
+// SAFETY: every call is forwarded to `System` unchanged, counting touches a const thread local
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|n| n.set(n.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|n| n.set(n.get() + 1));
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.with(|n| n.set(n.get() + 1));
+        System.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+// Allocations made by `f` on this thread
+fn allocations(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.with(Cell::get);
+    f();
+    ALLOCATIONS.with(Cell::get) - before
+}
+
+// Writes into a fixed buffer, for formatting without a `String`
+struct Buf<const N: usize>([u8; N], usize);
+
+impl<const N: usize> Write for Buf<N> {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        let end = self.1 + s.len();
+        let dst = self.0.get_mut(self.1..end).ok_or(std::fmt::Error)?;
+        dst.copy_from_slice(s.as_bytes());
+        self.1 = end;
+        Ok(())
+    }
+}
+
+#[test]
+fn cache_operations_do_not_allocate() {
+    let n = allocations(|| {
+        let mut cache: LRUCache<u64, 32> = LRUCache::new();
+        for i in 0..1_000u64 {
+            cache.insert(i % 48);
+            cache.find(|v| *v == i % 40);
+            cache.touch(|v| *v == i % 7);
+            *cache.get_or_insert_with(|v| *v == i % 50, || i % 50) += 0;
+            if i % 13 == 0 {
+                cache.pop_lru();
+            }
+        }
+        assert!(cache.peek_unordered(|v| *v == 1).is_some() || cache.len() < 32);
+        assert_eq!(cache.iter().count(), cache.len());
+        assert!(cache.peek_lru().is_some() && cache.peek_mru().is_some());
+        let stats = cache.stats();
+        assert!(stats.hits > 0 && stats.evictions > 0);
+        let mut buf = Buf([0; 512], 0);
+        write!(buf, "{:?}", cache.fmt_recency()).unwrap();
+        cache.clear();
+        assert!(cache.is_empty());
+    });
+    assert_eq!(n, 0);
+}
+
+#[test]
+fn map_operations_do_not_allocate() {
+    let n = allocations(|| {
+        let mut map: LRUMap<u32, [u8; 16], 32> = LRUMap::new();
+        for i in 0..1_000u32 {
+            map.insert(i % 48, [i as u8; 16]);
+            map.get(&(i % 40));
+            map.peek(&(i % 30));
+            map.get_or_insert_with(i % 50, || [0; 16]);
+            if i % 11 == 0 {
+                map.remove(&(i % 48));
+            }
+        }
+        assert!(map.contains_key(&49) || map.len() <= 32);
+        assert_eq!(map.entries().count(), map.len());
+        map.clear();
+    });
+    assert_eq!(n, 0);
+}
+
+#[test]
+fn other_fixed_caches_do_not_allocate() {
+    let n = allocations(|| {
+        let mut ints: IntLruMap<u64, u64, 64> = IntLruMap::new();
+        let mut batched: BatchedLRUCache<u32, 32, 8> = BatchedLRUCache::new();
+        for i in 0..1_000u64 {
+            if ints.get(i % 50).is_none() {
+                ints.insert(i % 50, i);
+            }
+            if i % 9 == 0 {
+                ints.remove(i % 50);
+            }
+            if !batched.touch(|v| *v == (i % 24) as u32) {
+                batched.insert((i % 24) as u32);
+            }
+        }
+        batched.flush();
+        assert!(ints.stats().hits > 0 && batched.stats().hits > 0);
+    });
+    assert_eq!(n, 0);
+}
+
+#[test]
+fn snapshots_do_not_allocate() {
+    let n = allocations(|| {
+        let mut cache: LRUCache<u32, 16> = LRUCache::new();
+        for i in 0..40 {
+            cache.insert(i);
+        }
+        let mut buf = [0u8; LRUCache::<u32, 16>::FULL_SNAPSHOT_SIZE];
+        let len = cache.as_full_bytes_snapshot(&mut buf).unwrap();
+        let restored: LRUCache<u32, 16> = LRUCache::from_bytes(&buf[..len]).unwrap();
+        assert!(restored.iter().eq(cache.iter()));
+    });
+    assert_eq!(n, 0);
+}
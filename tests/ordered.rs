@@ -0,0 +1,109 @@
+//! `OrderedLRUMap` against the model: it evicts like an LRU map while its keys stay sorted, and
+//! drops whole ranges of them at once.
+#![cfg(feature = "ordered")]
+
+mod common;
+
+use common::{Model, Rng};
+
+use lru::ordered::OrderedLRUMap;
+
+fn check<const N: usize>(seed: u64, steps: usize) {
+    let mut rng = Rng::new(seed);
+    let mut map: OrderedLRUMap<u32, u64, N> = OrderedLRUMap::new();
+    let mut model: Model<(u32, u64)> = Model::new(N);
+    let keys = (N as u32 * 2).max(2);
+
+    for step in 0..steps {
+        let key = rng.below(keys as u64) as u32;
+        let val = rng.next();
+        let op = rng.below(100);
+        let ctx = format!("seed {seed} step {step} op {op} key {key}");
+        match op {
+            0..=44 => {
+                let expected = match model.values.iter().position(|(k, _)| *k == key) {
+                    Some(i) => {
+                        model.inserts += 1;
+                        let old = model.values.remove(i);
+                        model.values.push_front((key, val));
+                        old
+                    }
+                    None => model.insert((key, val)),
+                };
+                assert_eq!(map.insert(key, val), expected, "{ctx}");
+            }
+            45..=69 => {
+                let found = model.values.iter().find(|(k, _)| *k == key).map(|e| e.1);
+                model.touch(|(k, _)| *k == key);
+                assert_eq!(map.get(&key).copied(), found, "{ctx}");
+            }
+            70..=94 => {
+                let removed = model.remove(|(k, _)| *k == key).map(|e| e.1);
+                assert_eq!(map.remove(&key), removed, "{ctx}");
+            }
+            95..=98 => {
+                // Also empty and reversed ranges
+                let end = rng.below(keys as u64 + 1) as u32;
+                let inclusive = rng.below(2) == 0;
+                let removed = if inclusive {
+                    map.invalidate_range(key..=end)
+                } else {
+                    map.invalidate_range(key..end)
+                };
+                let before = model.values.len();
+                model
+                    .values
+                    .retain(|(k, _)| *k < key || *k > end || (*k == end && !inclusive));
+                assert_eq!(removed, before - model.values.len(), "{ctx}");
+            }
+            _ => {
+                map.clear();
+                model.values.clear();
+            }
+        }
+
+        let mut sorted: Vec<(u32, u64)> = model.values.iter().copied().collect();
+        sorted.sort_unstable();
+        assert!(
+            map.iter().map(|(k, v)| (*k, *v)).eq(sorted.iter().copied()),
+            "{ctx}"
+        );
+        assert_eq!(map.first_key(), sorted.first().map(|e| &e.0), "{ctx}");
+        assert_eq!(map.last_key(), sorted.last().map(|e| &e.0), "{ctx}");
+        for k in 0..keys {
+            let held = sorted.iter().find(|e| e.0 == k).map(|e| e.1);
+            assert_eq!(map.peek(&k).copied(), held, "{ctx}: key {k}");
+        }
+        let stats = map.stats();
+        assert_eq!(
+            (stats.hits, stats.misses, stats.inserts, stats.evictions),
+            (model.hits, model.misses, model.inserts, model.evictions),
+            "{ctx}"
+        );
+    }
+}
+
+#[test]
+fn matches_the_model() {
+    for seed in 0..4 {
+        check::<1>(seed, 2_000);
+        check::<7>(seed, 5_000);
+        check::<32>(seed, 10_000);
+    }
+}
+
+#[test]
+fn invalidates_every_key_with_a_prefix() {
+    let mut map: OrderedLRUMap<&str, u32, 8> = OrderedLRUMap::new();
+    for (i, key) in ["/u/1", "/u/10/a", "/u", "/u/1/b", "/u/2", "/v/1", "/u/1/"]
+        .into_iter()
+        .enumerate()
+    {
+        map.insert(key, i as u32);
+    }
+    assert_eq!(map.invalidate_prefix("/u/1"), 4);
+    assert!(map.iter().map(|(k, _)| *k).eq(["/u", "/u/2", "/v/1"]));
+    assert_eq!(map.invalidate_prefix("/w"), 0);
+    assert_eq!(map.invalidate_prefix(""), 3);
+    assert!(map.is_empty());
+}
@@ -0,0 +1,108 @@
+//! `PerCoreCache`: entries evicted from a core spill into the victim cache, where any core finds
+//! them and takes them back.
+#![cfg(feature = "std")]
+
+mod common;
+
+use common::{Model, Rng};
+use std::thread;
+
+use lru::percore::PerCoreCache;
+
+const CORES: usize = 3;
+
+// A model per core and one of the victim cache
+struct Models {
+    locals: Vec<Model<u32>>,
+    victims: Model<u32>,
+}
+
+impl Models {
+    fn insert(&mut self, core: usize, val: u32) -> Option<u32> {
+        let evicted = self.locals[core].insert(val)?;
+        self.victims.insert(evicted)
+    }
+
+    fn get(&mut self, core: usize, val: u32) -> Option<u32> {
+        let local = &mut self.locals[core];
+        if local.touch(|&v| v == val) {
+            return Some(val);
+        }
+        if !self.victims.touch(|&v| v == val) {
+            return None;
+        }
+        self.victims.remove(|&v| v == val);
+        if let Some(evicted) = self.locals[core].insert(val) {
+            self.victims.insert(evicted);
+        }
+        Some(val)
+    }
+}
+
+#[test]
+fn matches_a_model_per_core_and_of_the_victims() {
+    let mut rng = Rng::new(136);
+    let cache: PerCoreCache<u32, 4, 8> = PerCoreCache::new(CORES);
+    let mut models = Models {
+        locals: (0..CORES).map(|_| Model::new(4)).collect(),
+        victims: Model::new(8),
+    };
+    // Values stay distinct, so that each is in at most one cache
+    let mut next = 0;
+    for step in 0..20_000 {
+        let core = rng.below(CORES as u64) as usize;
+        let ctx = format!("step {step} core {core}");
+        if rng.below(3) == 0 {
+            next += 1;
+            assert_eq!(cache.insert(core, next), models.insert(core, next), "{ctx}");
+        } else {
+            let val = next.saturating_sub(rng.below(24) as u32);
+            assert_eq!(
+                cache.get(core, |&v| v == val),
+                models.get(core, val),
+                "{ctx}"
+            );
+        }
+        for (core, model) in models.locals.iter().enumerate() {
+            assert_eq!(cache.local_len(core), model.values.len(), "{ctx}");
+        }
+        assert_eq!(cache.victim_len(), models.victims.values.len(), "{ctx}");
+    }
+
+    let counters = |m: &Model<u32>| (m.hits, m.misses, m.inserts, m.evictions);
+    for (core, model) in models.locals.iter().enumerate() {
+        let stats = cache.local_stats(core);
+        assert_eq!(
+            (stats.hits, stats.misses, stats.inserts, stats.evictions),
+            counters(model)
+        );
+    }
+    let stats = cache.victim_stats();
+    assert_eq!(
+        (stats.hits, stats.misses, stats.inserts, stats.evictions),
+        counters(&models.victims)
+    );
+}
+
+#[test]
+fn a_core_takes_back_what_another_spilled() {
+    let cache: PerCoreCache<u32, 2, 4> = PerCoreCache::new(2);
+    thread::scope(|s| {
+        s.spawn(|| {
+            for val in 1..=4 {
+                cache.insert(0, val);
+            }
+        });
+    });
+    assert_eq!((cache.local_len(0), cache.victim_len()), (2, 2));
+    thread::scope(|s| {
+        s.spawn(|| assert_eq!(cache.with(1, |&v| v == 1, |v| *v * 10), Some(10)));
+    });
+    assert_eq!((cache.local_len(1), cache.victim_len()), (1, 1));
+    assert_eq!(cache.get(0, |&v| v == 1), None);
+    cache.clear();
+    assert_eq!(
+        (cache.local_len(0), cache.local_len(1), cache.victim_len()),
+        (0, 0, 0)
+    );
+}
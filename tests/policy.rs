@@ -0,0 +1,245 @@
+//! Properties that define LRU, checked on every cache type that claims to be one.
+//!
+//! The model tests replay a reference list next to the cache. These check the definition
+//! instead, from the outside, so a cache that drifted into another policy fails them even if its
+//! list code agreed with itself:
+//!
+//! - The entry evicted is the resident one whose last access is the oldest. FIFO evicts the
+//!   oldest insert instead and MRU the newest access, both caught here.
+//! - An access hits exactly when fewer than `N` distinct keys were accessed since the last
+//!   access to the same key (its reuse distance), which is what makes LRU a stack algorithm.
+//! - Inclusion: a cache of capacity `N` always holds a subset of what a larger one holds on the
+//!   same trace. FIFO and CLOCK break it (Belady's anomaly), LRU cannot.
+//!
+//! Only LRU is implemented so far, along with GreedyDual-Size, which is LRU when every entry costs
+//! the same and is checked as such. A new policy gets its own defining properties here, and a run
+//! of it against the LRU ones should fail.
+
+use std::collections::{HashMap, HashSet};
+
+use lru::{
+    batch::BatchedLRUCache, gds::GdsCache, intmap::IntLruMap, map::LRUMap, workload::Zipf, LRUCache,
+};
+
+// A cache seen from outside: an access looks `key` up and inserts it on a miss
+trait Cache {
+    const CAPACITY: usize;
+
+    // Returns whether the access hit, and the key it evicted if any
+    fn access(&mut self, key: u32) -> (bool, Option<u32>);
+}
+
+impl<const N: usize> Cache for LRUCache<u32, N> {
+    const CAPACITY: usize = N;
+
+    fn access(&mut self, key: u32) -> (bool, Option<u32>) {
+        if self.touch(|v| *v == key) {
+            (true, None)
+        } else {
+            // A cache of capacity 0 hands the key back rather than evicting anything
+            (false, self.insert(key).filter(|old| *old != key))
+        }
+    }
+}
+
+impl<const N: usize> Cache for LRUMap<u32, (), N> {
+    const CAPACITY: usize = N;
+
+    fn access(&mut self, key: u32) -> (bool, Option<u32>) {
+        if self.get(&key).is_some() {
+            (true, None)
+        } else {
+            (false, self.insert(key, ()).map(|(k, _)| k))
+        }
+    }
+}
+
+impl<const N: usize> Cache for IntLruMap<u32, (), N> {
+    const CAPACITY: usize = N;
+
+    fn access(&mut self, key: u32) -> (bool, Option<u32>) {
+        if self.get(key).is_some() {
+            (true, None)
+        } else {
+            (false, self.insert(key, ()).map(|(k, _)| k))
+        }
+    }
+}
+
+impl<const N: usize, const K: usize> Cache for BatchedLRUCache<u32, N, K> {
+    const CAPACITY: usize = N;
+
+    fn access(&mut self, key: u32) -> (bool, Option<u32>) {
+        if self.touch(|v| *v == key) {
+            (true, None)
+        } else {
+            // A cache of capacity 0 hands the key back rather than evicting anything
+            (false, self.insert(key).filter(|old| *old != key))
+        }
+    }
+}
+
+impl<const N: usize> Cache for GdsCache<u32, N> {
+    const CAPACITY: usize = N;
+
+    fn access(&mut self, key: u32) -> (bool, Option<u32>) {
+        if self.touch(|v| *v == key) {
+            (true, None)
+        } else {
+            // A cache of capacity 0 hands the key back rather than evicting anything
+            (false, self.insert(key, 1, 1).filter(|old| *old != key))
+        }
+    }
+}
+
+// Keys of a skewed trace, with enough distinct keys to keep any of the caches here evicting
+fn trace(seed: u64, len: usize) -> Vec<u32> {
+    Zipf::new(seed, 200, 0.9)
+        .take(len)
+        .map(|k| k as u32)
+        .collect()
+}
+
+// Run `cache` over `trace`, checking the first two properties. Returns the keys resident after
+// each access.
+fn check<C: Cache>(mut cache: C, trace: &[u32], name: &str) -> Vec<HashSet<u32>> {
+    let mut resident = HashSet::new();
+    let mut last_access: HashMap<u32, usize> = HashMap::new();
+    let mut history = Vec::with_capacity(trace.len());
+    // Every key seen so far, most recently accessed first: the reuse distance of a key is its
+    // position, the number of distinct keys accessed after it
+    let mut stack: Vec<u32> = Vec::new();
+
+    for (t, &key) in trace.iter().enumerate() {
+        let ctx = format!("{name}, N {}: access {t} to {key}", C::CAPACITY);
+        let (hit, evicted) = cache.access(key);
+
+        let distance = stack.iter().position(|k| *k == key);
+        if let Some(d) = distance {
+            stack.remove(d);
+        }
+        stack.insert(0, key);
+        let expected = distance.is_some_and(|d| d < C::CAPACITY);
+        assert_eq!(hit, expected, "{ctx}: reuse distance {distance:?}");
+        assert_eq!(hit, resident.contains(&key), "{ctx}");
+
+        if let Some(old) = evicted {
+            assert!(resident.remove(&old), "{ctx}: evicted {old}, not resident");
+            let coldest = resident
+                .iter()
+                .chain([&old])
+                .min_by_key(|k| last_access[k])
+                .copied();
+            assert_eq!(
+                Some(old),
+                coldest,
+                "{ctx}: evicted {old}, not the least recent"
+            );
+        }
+        if !hit && C::CAPACITY > 0 {
+            resident.insert(key);
+        }
+        assert!(resident.len() <= C::CAPACITY, "{ctx}");
+        last_access.insert(key, t);
+        history.push(resident.clone());
+    }
+    history
+}
+
+// Check inclusion between the histories of a smaller and a larger cache
+fn check_inclusion(small: &[HashSet<u32>], large: &[HashSet<u32>], name: &str) {
+    for (t, (s, l)) in small.iter().zip(large).enumerate() {
+        assert!(s.is_subset(l), "{name}: access {t}: {s:?} not in {l:?}");
+    }
+}
+
+fn check_all<C4: Cache, C16: Cache, C64: Cache>(caches: fn() -> (C4, C16, C64), name: &str) {
+    for seed in 0..4 {
+        let trace = trace(seed, 3_000);
+        let (c4, c16, c64) = caches();
+        let h4 = check(c4, &trace, name);
+        let h16 = check(c16, &trace, name);
+        let h64 = check(c64, &trace, name);
+        check_inclusion(&h4, &h16, name);
+        check_inclusion(&h16, &h64, name);
+    }
+}
+
+#[test]
+fn lru_cache_is_lru() {
+    check_all(
+        || {
+            (
+                LRUCache::<u32, 4>::new(),
+                LRUCache::<u32, 16>::new(),
+                LRUCache::<u32, 64>::new(),
+            )
+        },
+        "LRUCache",
+    );
+}
+
+#[test]
+fn lru_map_is_lru() {
+    check_all(
+        || {
+            (
+                LRUMap::<u32, (), 4>::new(),
+                LRUMap::<u32, (), 16>::new(),
+                LRUMap::<u32, (), 64>::new(),
+            )
+        },
+        "LRUMap",
+    );
+}
+
+#[test]
+fn int_lru_map_is_lru() {
+    check_all(
+        || {
+            (
+                IntLruMap::<u32, (), 4>::new(),
+                IntLruMap::<u32, (), 16>::new(),
+                IntLruMap::<u32, (), 64>::new(),
+            )
+        },
+        "IntLruMap",
+    );
+}
+
+// Batching only delays promotions, and they are applied before anything is evicted
+#[test]
+fn batched_lru_cache_is_lru() {
+    check_all(
+        || {
+            (
+                BatchedLRUCache::<u32, 4, 8>::new(),
+                BatchedLRUCache::<u32, 16, 8>::new(),
+                BatchedLRUCache::<u32, 64, 8>::new(),
+            )
+        },
+        "BatchedLRUCache",
+    );
+}
+
+// All credits tie, so the least recently used entry goes
+#[test]
+fn gds_cache_with_uniform_costs_is_lru() {
+    check_all(
+        || {
+            (
+                GdsCache::<u32, 4>::new(),
+                GdsCache::<u32, 16>::new(),
+                GdsCache::<u32, 64>::new(),
+            )
+        },
+        "GdsCache",
+    );
+}
+
+#[test]
+fn capacity_0_never_hits() {
+    let trace = trace(9, 500);
+    let history = check(LRUCache::<u32, 0>::new(), &trace, "LRUCache");
+    assert!(history.iter().all(HashSet::is_empty));
+}
@@ -0,0 +1,77 @@
+//! Priority classes: eviction takes the least recently used entry of the lowest priority present.
+
+mod common;
+
+use common::prioritized as cache;
+use lru::{
+    priority::{PriorityCache, LEVELS},
+    RemovalCause,
+};
+
+#[test]
+fn evicts_the_lowest_priority_first() {
+    let mut cache = cache();
+    assert_eq!(cache.insert(5), Some(2));
+    assert_eq!(cache.insert(6), Some(4));
+    assert_eq!(cache.insert(7), Some(5));
+    assert_eq!(cache.listener().of(RemovalCause::Capacity), [2, 4, 5]);
+    assert_eq!(cache.stats().evictions, 3);
+}
+
+#[test]
+fn falls_back_to_lru_order_among_protected_entries() {
+    let mut cache = cache();
+    assert_eq!(cache.insert_with_priority(5, 3), Some(2));
+    assert_eq!(cache.insert_with_priority(6, 3), Some(4));
+    assert_eq!(cache.insert_with_priority(7, 3), Some(3));
+    assert_eq!(cache.insert_with_priority(8, 3), Some(1));
+    assert_eq!(cache.insert_with_priority(9, 3), Some(5));
+    assert_eq!(cache.count_priority(3), 4);
+}
+
+#[test]
+fn recency_picks_within_a_priority() {
+    let mut cache = cache();
+    assert!(cache.touch(|v| *v == 2));
+    assert_eq!(cache.peek_victim(), Some((&4, 0)));
+    assert_eq!(cache.insert(5), Some(4));
+}
+
+#[test]
+fn priorities_can_change() {
+    let mut cache = cache();
+    assert!(cache.set_priority(|v| *v == 1, 0));
+    assert!(!cache.set_priority(|v| *v == 9, 0));
+    assert_eq!(cache.priority_of(|v| *v == 1), Some(0));
+    assert_eq!(cache.count_priority(0), 3);
+    assert_eq!(cache.count_priority(2), 0);
+    assert_eq!(cache.pop_victim(), Some((1, 0)));
+    assert_eq!(cache.pop_victim(), Some((2, 0)));
+    assert_eq!(cache.pop_victim(), Some((4, 0)));
+    assert_eq!(cache.pop_victim(), Some((3, 1)));
+    assert_eq!(cache.pop_victim(), None);
+    assert!(cache.listener().of(RemovalCause::Capacity).is_empty());
+}
+
+#[test]
+fn entries_keep_their_priority_when_slots_move() {
+    let mut cache = cache();
+    cache.pop_victim();
+    assert!(cache.entries().eq([(&4, 0), (&3, 1), (&1, 2)].into_iter()));
+    cache.clear();
+    assert_eq!(cache.count_priority(0), 0);
+    assert_eq!(cache.peek_victim(), None);
+}
+
+#[test]
+fn capacity_0_hands_the_value_back() {
+    let mut cache: PriorityCache<u32, 0> = PriorityCache::new();
+    assert_eq!(cache.insert_with_priority(1, 3), Some(1));
+    assert!(cache.is_empty());
+}
+
+#[test]
+#[should_panic]
+fn priority_must_be_below_levels() {
+    PriorityCache::<u32, 4>::new().insert_with_priority(1, LEVELS as u8);
+}
@@ -0,0 +1,94 @@
+//! Probation window: new entries start near the tail and only join the rest of the cache once
+//! hit, so a scan cannot flush it.
+
+use lru::LRUCache;
+
+// Hot entries 1 to 4, hit after they were inserted, and cold entries 5 and 6 in the window
+fn cache() -> LRUCache<u32, 6> {
+    let mut cache = LRUCache::new().with_probation(2);
+    for val in 1..=6 {
+        cache.insert(val);
+    }
+    for val in 1..=4 {
+        assert!(cache.touch(|v| *v == val));
+    }
+    cache
+}
+
+#[test]
+fn a_scan_only_cycles_through_the_window() {
+    let mut cache = cache();
+    for val in 100..200 {
+        cache.insert(val);
+    }
+    assert!(cache.iter().eq(&[4, 3, 2, 1, 199, 198]));
+    for val in 1..=4 {
+        assert!(cache.touch(|v| *v == val));
+    }
+}
+
+#[test]
+fn new_entries_start_in_front_of_the_window() {
+    let mut cache = cache();
+    assert!(cache.iter().eq(&[4, 3, 2, 1, 5, 6]));
+    assert_eq!(cache.insert(7), Some(6));
+    assert!(cache.iter().eq(&[4, 3, 2, 1, 7, 5]));
+    assert_eq!(cache.insert(8), Some(5));
+    assert!(cache.iter().eq(&[4, 3, 2, 1, 8, 7]));
+}
+
+#[test]
+fn a_hit_promotes_out_of_the_window() {
+    let mut cache = cache();
+    assert!(cache.touch(|v| *v == 5));
+    // 1 is pushed into the window, and goes before 6
+    assert!(cache.iter().eq(&[5, 4, 3, 2, 1, 6]));
+    assert_eq!(cache.insert(7), Some(6));
+    assert_eq!(cache.insert(8), Some(1));
+}
+
+#[test]
+fn fills_from_the_front_below_the_window() {
+    let mut cache: LRUCache<u32, 4> = LRUCache::new().with_probation(3);
+    cache.insert(1);
+    cache.insert(2);
+    cache.insert(3);
+    cache.insert(4);
+    assert!(cache.iter().eq(&[3, 4, 2, 1]));
+}
+
+#[test]
+fn a_window_of_one_inserts_at_the_tail() {
+    let mut cache: LRUCache<u32, 3> = LRUCache::new().with_probation(1);
+    for val in 1..=5 {
+        cache.insert(val);
+    }
+    assert!(cache.iter().eq(&[1, 2, 5]));
+    assert_eq!(cache.get_or_insert_with(|v| *v == 6, || 6), &mut 6);
+    assert!(cache.iter().eq(&[1, 2, 6]));
+}
+
+#[test]
+fn transient_entries_start_in_the_window_too() {
+    let mut cache = cache();
+    assert_eq!(cache.insert_transient(7), Some(6));
+    assert!(cache.iter().eq(&[4, 3, 2, 1, 7, 5]));
+    assert!(cache.touch(|v| *v == 7));
+    assert!(!cache.touch(|v| *v == 7));
+    assert!(cache.iter().eq(&[4, 3, 2, 1, 5]));
+}
+
+#[test]
+fn a_full_window_is_plain_lru() {
+    let mut cache: LRUCache<u32, 3> = LRUCache::new().with_probation(3);
+    for val in 1..=5 {
+        cache.insert(val);
+    }
+    assert!(cache.iter().eq(&[5, 4, 3]));
+}
+
+#[test]
+#[should_panic]
+fn window_must_fit_the_capacity() {
+    let _ = LRUCache::<u32, 3>::new().with_probation(4);
+}
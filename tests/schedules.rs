@@ -0,0 +1,212 @@
+//! Seeded schedules for the deferred promotions of `ConcurrentLRUMap`.
+//!
+//! Lookups record promotions in a shared buffer that the next write applies, so what a thread
+//! sees depends on how the threads interleave. Two kinds of tests cover that, each driven by a
+//! seed printed with any failure. `LRU_SCHEDULE_SEED=<seed>` runs that one seed only.
+//!
+//! - Scheduled runs hand out turns between threads in an order drawn from the seed, one
+//!   operation per turn. Every interleaving of whole operations is then reproducible, and the
+//!   results must be the ones of the same operations run in that order on one thread.
+//! - Free runs let the threads race, yielding at points drawn from the seed, and check what must
+//!   hold whatever the interleaving: values belong to their key, the map stays within capacity
+//!   and the stats count every operation. Races inside an operation are only reproducible as far
+//!   as the OS scheduler allows.
+#![cfg(feature = "std")]
+
+mod common;
+
+use std::{
+    env,
+    sync::{Condvar, Mutex},
+    thread,
+};
+
+use common::Rng;
+use lru::concurrent::ConcurrentLRUMap;
+
+const THREADS: usize = 4;
+const KEYS: u64 = 24;
+
+#[derive(Debug, Clone, Copy)]
+enum Op {
+    Insert(u32, u64),
+    Get(u32),
+    Remove(u32),
+    Flush,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Out {
+    Inserted(Option<(u32, u64)>),
+    Got(Option<u64>),
+    Removed(Option<u64>),
+    Flushed,
+}
+
+// Operations for thread `t`, mostly lookups. Values hold their key in the high half and are
+// unique in the low half.
+fn ops(rng: &mut Rng, t: usize, len: usize) -> Vec<Op> {
+    (0..len)
+        .map(|i| {
+            let key = rng.below(KEYS) as u32;
+            match rng.below(100) {
+                0..=29 => Op::Insert(key, (key as u64) << 32 | (t * len + i) as u64),
+                30..=84 => Op::Get(key),
+                85..=96 => Op::Remove(key),
+                _ => Op::Flush,
+            }
+        })
+        .collect()
+}
+
+fn apply<const N: usize, const B: usize>(map: &ConcurrentLRUMap<u32, u64, N, B>, op: Op) -> Out {
+    match op {
+        Op::Insert(key, val) => Out::Inserted(map.insert(key, val)),
+        Op::Get(key) => Out::Got(map.get(&key)),
+        Op::Remove(key) => Out::Removed(map.remove(&key)),
+        Op::Flush => {
+            map.flush_promotions();
+            Out::Flushed
+        }
+    }
+}
+
+// The seeds to run, all of `0..count` unless one is picked from the environment
+fn seeds(count: u64) -> Vec<u64> {
+    match env::var("LRU_SCHEDULE_SEED") {
+        Ok(seed) => vec![seed.parse().expect("LRU_SCHEDULE_SEED is a number")],
+        Err(_) => (0..count).collect(),
+    }
+}
+
+// Whose turn it is, as an index into the schedule
+struct Turns {
+    next: Mutex<usize>,
+    changed: Condvar,
+}
+
+fn scheduled<const N: usize, const B: usize>(seed: u64, len: usize) {
+    let mut rng = Rng::new(seed);
+    let per_thread: Vec<Vec<Op>> = (0..THREADS).map(|t| ops(&mut rng, t, len)).collect();
+    // Any thread with operations left may go next
+    let mut left = [len; THREADS];
+    let mut schedule = Vec::with_capacity(THREADS * len);
+    while schedule.len() < THREADS * len {
+        let t = rng.below(THREADS as u64) as usize;
+        if left[t] > 0 {
+            left[t] -= 1;
+            schedule.push(t);
+        }
+    }
+
+    let map = ConcurrentLRUMap::<u32, u64, N, B>::new();
+    let turns = Turns {
+        next: Mutex::new(0),
+        changed: Condvar::new(),
+    };
+    let mut results: Vec<(usize, Out)> = thread::scope(|s| {
+        let handles: Vec<_> = (0..THREADS)
+            .map(|t| {
+                let (map, turns, schedule, ops) = (&map, &turns, &schedule, &per_thread[t]);
+                s.spawn(move || {
+                    let mut out = Vec::new();
+                    for &op in ops {
+                        let mut next = turns.next.lock().unwrap();
+                        while schedule[*next] != t {
+                            next = turns.changed.wait(next).unwrap();
+                        }
+                        out.push((*next, apply(map, op)));
+                        *next += 1;
+                        turns.changed.notify_all();
+                    }
+                    out
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|h| h.join().unwrap())
+            .collect()
+    });
+    results.sort_by_key(|(at, _)| *at);
+
+    let alone = ConcurrentLRUMap::<u32, u64, N, B>::new();
+    let mut pos = [0; THREADS];
+    for (at, &t) in schedule.iter().enumerate() {
+        let expected = apply(&alone, per_thread[t][pos[t]]);
+        pos[t] += 1;
+        assert_eq!(
+            results[at],
+            (at, expected),
+            "seed {seed}, N {N}, B {B}: turn {at} of thread {t}"
+        );
+    }
+    assert_eq!(map.stats(), alone.stats(), "seed {seed}, N {N}, B {B}");
+}
+
+fn free<const N: usize, const B: usize>(seed: u64, len: usize) {
+    let map = ConcurrentLRUMap::<u32, u64, N, B>::new();
+    let counts: Vec<(u64, u64)> = thread::scope(|s| {
+        let handles: Vec<_> = (0..THREADS)
+            .map(|t| {
+                let map = &map;
+                s.spawn(move || {
+                    let mut rng = Rng::new(seed ^ (t as u64) << 48);
+                    let (mut gets, mut inserts) = (0, 0);
+                    for op in ops(&mut rng, t, len) {
+                        if rng.below(4) == 0 {
+                            thread::yield_now();
+                        }
+                        let ctx = format!("seed {seed}, N {N}, B {B}: thread {t} {op:?}");
+                        match apply(map, op) {
+                            Out::Inserted(old) => {
+                                inserts += 1;
+                                if let Some((k, v)) = old {
+                                    assert_eq!(v >> 32, k as u64, "{ctx}");
+                                }
+                            }
+                            Out::Got(found) | Out::Removed(found) => {
+                                gets += matches!(op, Op::Get(_)) as u64;
+                                if let (Some(v), Op::Get(key) | Op::Remove(key)) = (found, op) {
+                                    assert_eq!(v >> 32, key as u64, "{ctx}");
+                                }
+                            }
+                            Out::Flushed => {}
+                        }
+                        assert!(map.len() <= N, "{ctx}");
+                    }
+                    (gets, inserts)
+                })
+            })
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+
+    let stats = map.stats();
+    let gets: u64 = counts.iter().map(|c| c.0).sum();
+    let inserts: u64 = counts.iter().map(|c| c.1).sum();
+    assert_eq!(stats.hits + stats.misses, gets, "seed {seed}, N {N}, B {B}");
+    assert_eq!(stats.inserts, inserts, "seed {seed}, N {N}, B {B}");
+    // The recorded promotions only reorder entries, they never lose or duplicate one
+    map.flush_promotions();
+    let held = (0..KEYS as u32).filter(|k| map.contains_key(k)).count();
+    assert_eq!(held, map.len(), "seed {seed}, N {N}, B {B}");
+}
+
+#[test]
+fn scheduled_runs_match_one_thread() {
+    for seed in seeds(64) {
+        scheduled::<8, 2>(seed, 200);
+        scheduled::<8, 32>(seed, 200);
+        scheduled::<16, 4>(seed, 200);
+    }
+}
+
+#[test]
+fn free_runs_keep_the_map_consistent() {
+    for seed in seeds(32) {
+        free::<8, 2>(seed, 2_000);
+        free::<8, 32>(seed, 2_000);
+        free::<16, 4>(seed, 2_000);
+    }
+}
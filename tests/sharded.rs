@@ -0,0 +1,89 @@
+//! `ShardedLRUMap`: each shard is an LRU map of its own, whichever thread uses it.
+#![cfg(feature = "std")]
+
+mod common;
+
+use common::{Model, Rng};
+use std::thread;
+
+use lru::sharded::ShardedLRUMap;
+
+const SHARDS: usize = 4;
+
+// One model per shard, fed the keys `shard_of` sends there
+#[test]
+fn each_shard_matches_the_model() {
+    let mut rng = Rng::new(130);
+    let map: ShardedLRUMap<u32, u64, 4> = ShardedLRUMap::new(SHARDS);
+    let mut models: Vec<Model<(u32, u64)>> = (0..SHARDS).map(|_| Model::new(4)).collect();
+    for step in 0..20_000 {
+        let key = rng.below(48) as u32;
+        let val = rng.next();
+        let model = &mut models[map.shard_of(&key)];
+        let ctx = format!("step {step} key {key}");
+        match rng.below(10) {
+            0..=4 => {
+                let expected = match model.values.iter().position(|(k, _)| *k == key) {
+                    Some(i) => {
+                        model.inserts += 1;
+                        let old = model.values.remove(i);
+                        model.values.push_front((key, val));
+                        old
+                    }
+                    None => model.insert((key, val)),
+                };
+                assert_eq!(map.insert(key, val), expected, "{ctx}");
+            }
+            5..=7 => {
+                let found = model.values.iter().find(|(k, _)| *k == key).map(|e| e.1);
+                model.touch(|(k, _)| *k == key);
+                assert_eq!(map.get(&key), found, "{ctx}");
+            }
+            _ => {
+                let removed = model.remove(|(k, _)| *k == key).map(|e| e.1);
+                assert_eq!(map.remove(&key), removed, "{ctx}");
+            }
+        }
+    }
+    assert!(map
+        .shard_occupancy()
+        .eq(models.iter().map(|m| m.values.len())));
+    for (stats, model) in map.shard_stats().zip(&models) {
+        assert_eq!(
+            (stats.hits, stats.misses, stats.inserts, stats.evictions),
+            (model.hits, model.misses, model.inserts, model.evictions)
+        );
+    }
+    let total = map.stats();
+    let sum = |f: fn(&Model<(u32, u64)>) -> u64| models.iter().map(f).sum::<u64>();
+    assert_eq!(total.hits, sum(|m| m.hits));
+    assert_eq!(total.misses, sum(|m| m.misses));
+    assert_eq!(total.inserts, sum(|m| m.inserts));
+    assert_eq!(total.evictions, sum(|m| m.evictions));
+}
+
+#[test]
+fn threads_see_each_other_writes() {
+    let map: ShardedLRUMap<u32, u32, 64> = ShardedLRUMap::new(SHARDS);
+    thread::scope(|s| {
+        for t in 0..4 {
+            let map = &map;
+            s.spawn(move || {
+                for key in (t..64).step_by(4) {
+                    map.insert(key, key * 2);
+                    assert_eq!(map.get(&key), Some(key * 2));
+                }
+            });
+        }
+    });
+    assert_eq!(map.len(), 64);
+    assert!((0..64).all(|key| map.get_with(&key, |val| *val == key * 2) == Some(true)));
+    map.clear();
+    assert!(map.is_empty());
+}
+
+#[test]
+#[should_panic(expected = "at least one shard")]
+fn refuses_zero_shards() {
+    ShardedLRUMap::<u32, u32, 4>::new(0);
+}
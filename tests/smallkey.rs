@@ -0,0 +1,116 @@
+//! `SmallKeyMap` against the model: keys of up to `K` bytes are stored inline and longer ones
+//! spill, and either kind is found by its bytes alone.
+
+mod common;
+
+use common::{Model, Rng};
+
+use lru::smallkey::{LongKey, SmallKey, SmallKeyMap};
+
+const K: usize = 4;
+
+// Spilled keys, on the heap of the test
+#[derive(Debug, Clone)]
+struct Spilled(Vec<u8>);
+
+impl LongKey for Spilled {
+    fn from_bytes(bytes: &[u8]) -> Self {
+        Spilled(bytes.to_vec())
+    }
+
+    fn eq_bytes(&self, bytes: &[u8]) -> bool {
+        self.0 == bytes
+    }
+}
+
+type Map = SmallKeyMap<Spilled, u64, 6, K>;
+
+// Zero to `2 * K` bytes out of two, so that keys often share a prefix and lengths straddle `K`
+fn key(rng: &mut Rng) -> Vec<u8> {
+    let len = rng.below(2 * K as u64 + 1) as usize;
+    (0..len).map(|_| b"ab"[rng.below(2) as usize]).collect()
+}
+
+fn check_key(stored: &SmallKey<K, Spilled>, bytes: &[u8]) {
+    assert!(stored.matches(bytes));
+    assert_eq!(stored.is_inline(), bytes.len() <= K, "{bytes:?}");
+}
+
+#[test]
+fn matches_the_model() {
+    let mut rng = Rng::new(152);
+    let mut map = Map::new();
+    let mut model: Model<(Vec<u8>, u64)> = Model::new(6);
+    let mut seen: Vec<Vec<u8>> = Vec::new();
+
+    for step in 0..20_000 {
+        let key = key(&mut rng);
+        let val = rng.next();
+        let ctx = format!("step {step} key {key:?}");
+        if !seen.contains(&key) {
+            seen.push(key.clone());
+        }
+        match rng.below(10) {
+            0..=4 => {
+                let expected = match model.values.iter().position(|(k, _)| *k == key) {
+                    Some(i) => {
+                        model.inserts += 1;
+                        let old = model.values.remove(i);
+                        model.values.push_front((key.clone(), val));
+                        old
+                    }
+                    None => model.insert((key.clone(), val)),
+                };
+                let replaced = map.insert(&key, val);
+                assert_eq!(replaced.is_some(), expected.is_some(), "{ctx}");
+                if let (Some((stored, old)), Some((bytes, expected))) = (replaced, expected) {
+                    check_key(&stored, &bytes);
+                    assert_eq!(old, expected, "{ctx}");
+                }
+            }
+            5..=7 => {
+                let found = model.values.iter().find(|(k, _)| *k == key).map(|e| e.1);
+                model.touch(|(k, _)| *k == key);
+                assert_eq!(map.get(&key).copied(), found, "{ctx}");
+            }
+            _ => {
+                let removed = model.remove(|(k, _)| *k == key).map(|e| e.1);
+                assert_eq!(map.remove(&key), removed, "{ctx}");
+            }
+        }
+
+        assert_eq!(map.len(), model.values.len(), "{ctx}");
+        for k in &seen {
+            let held = model.values.iter().find(|(mk, _)| mk == k).map(|e| e.1);
+            assert_eq!(map.peek(k).copied(), held, "{ctx}: key {k:?}");
+        }
+        let stats = map.stats();
+        assert_eq!(
+            (stats.hits, stats.misses, stats.inserts, stats.evictions),
+            (model.hits, model.misses, model.inserts, model.evictions),
+            "{ctx}"
+        );
+    }
+    // Every length from 0 to 2K came up
+    assert_eq!(seen.iter().map(Vec::len).max(), Some(2 * K));
+}
+
+#[test]
+fn keys_of_exactly_k_bytes_stay_inline() {
+    for len in 0..=K + 1 {
+        let bytes = vec![b'x'; len];
+        check_key(&SmallKey::<K, Spilled>::new(&bytes), &bytes);
+    }
+    let mut map = SmallKeyMap::<Spilled, u32, 4, K>::new();
+    map.insert(b"abcd", 4);
+    map.insert(b"abcde", 5);
+    map.insert(b"ab", 2);
+    // Inline keys are padded with zeros, which the length tells apart from real ones
+    assert_eq!(map.peek(b"ab\0\0"), None);
+    assert_eq!(map.peek(b"abc"), None);
+    assert_eq!(map.peek(b"abcd"), Some(&4));
+    assert_eq!(map.peek(b"abcde"), Some(&5));
+    assert_eq!(map.peek(b"abcdef"), None);
+    assert_eq!(map.remove(b"abcd"), Some(4));
+    assert_eq!(map.peek(b"abcde"), Some(&5));
+}
@@ -0,0 +1,140 @@
+//! Byte snapshots: what restoring accepts, and what it refuses with which error.
+
+use lru::{snapshot::Plain, CacheError, LRUCache};
+
+// A value that only reads back if it is even, like a `bool` only reads back from 0 or 1
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Even(u32);
+
+impl Plain for Even {
+    const SIZE: usize = 4;
+
+    fn write_bytes(&self, out: &mut [u8]) {
+        self.0.write_bytes(out);
+    }
+
+    fn read_bytes(bytes: &[u8]) -> Option<Self> {
+        u32::read_bytes(bytes).filter(|v| v % 2 == 0).map(Even)
+    }
+}
+
+fn snapshot(values: &[u32]) -> Vec<u8> {
+    let mut cache: LRUCache<Even, 4> = LRUCache::new();
+    for &val in values {
+        cache.insert(Even(val));
+    }
+    let mut buf = vec![0u8; LRUCache::<Even, 4>::SNAPSHOT_SIZE];
+    let len = cache.as_bytes_snapshot(&mut buf).unwrap();
+    buf.truncate(len);
+    buf
+}
+
+#[test]
+fn a_value_that_does_not_read_back_is_corrupt() {
+    let bytes = snapshot(&[2, 3]);
+    assert_eq!(
+        LRUCache::<Even, 4>::from_bytes(&bytes).unwrap_err(),
+        CacheError::SnapshotCorrupt
+    );
+    let restored = LRUCache::<Even, 4>::from_bytes(&snapshot(&[2, 4])).unwrap();
+    assert!(restored.iter().eq(&[Even(4), Even(2)]));
+}
+
+#[test]
+fn a_value_migrate_rejects_is_incompatible() {
+    let bytes = snapshot(&[2, 3]);
+    let restored: Result<LRUCache<Even, 4>, _> =
+        LRUCache::from_bytes_migrating(&bytes, |_, bytes| Even::read_bytes(bytes));
+    assert_eq!(restored.unwrap_err(), CacheError::SnapshotIncompatible);
+}
+
+// A cache of 1 to 6 with 3 and 2 hit since, most recently used first: 2, 3, 6, 5, 4
+fn used() -> LRUCache<u32, 5> {
+    let mut cache = LRUCache::new();
+    for val in 1..=6 {
+        cache.insert(val);
+    }
+    cache.touch(|&v| v == 3);
+    cache.touch(|&v| v == 2);
+    cache.touch(|&v| v == 9);
+    cache
+}
+
+fn bytes_of(cache: &LRUCache<u32, 5>, full: bool) -> Vec<u8> {
+    let mut buf = vec![0u8; LRUCache::<u32, 5>::FULL_SNAPSHOT_SIZE];
+    let len = if full {
+        cache.as_full_bytes_snapshot(&mut buf)
+    } else {
+        cache.as_bytes_snapshot(&mut buf)
+    };
+    buf.truncate(len.unwrap());
+    buf
+}
+
+#[test]
+fn restores_the_recency_order() {
+    let cache = used();
+    let mut restored = LRUCache::<u32, 5>::from_bytes(&bytes_of(&cache, false)).unwrap();
+    assert!(restored.iter().eq(cache.iter()));
+    assert!(restored
+        .iter_insertion_order()
+        .eq(cache.iter_insertion_order()));
+    // The stats are not in it
+    assert_eq!(restored.stats().inserts, 0);
+    assert_eq!(restored.insert(7), Some(4));
+    assert_eq!(restored.insert(8), Some(5));
+}
+
+#[test]
+fn refuses_a_buffer_too_short_or_another_capacity() {
+    let cache = used();
+    let mut short = [0u8; 16];
+    assert!(matches!(
+        cache.as_bytes_snapshot(&mut short),
+        Err(CacheError::TooLarge { limit: 16, .. })
+    ));
+    assert_eq!(
+        LRUCache::<u32, 6>::from_bytes(&bytes_of(&cache, false)).unwrap_err(),
+        CacheError::SnapshotIncompatible
+    );
+}
+
+#[test]
+fn a_full_snapshot_carries_the_stats() {
+    let mut cache = used();
+    cache.insert(7);
+    let restored = LRUCache::<u32, 5>::from_bytes(&bytes_of(&cache, true)).unwrap();
+    assert!(restored.iter().eq(cache.iter()));
+    assert_eq!(restored.stats(), cache.stats());
+    assert_eq!(restored.stats().evictions, 2);
+    assert_eq!(restored.recent_lookups(), cache.recent_lookups());
+}
+
+#[test]
+fn a_flipped_bit_fails_the_checksum() {
+    for full in [false, true] {
+        let bytes = bytes_of(&used(), full);
+        for i in 0..bytes.len() {
+            for bit in 0..8 {
+                let mut bytes = bytes.clone();
+                bytes[i] ^= 1 << bit;
+                let restored = LRUCache::<u32, 5>::from_bytes(&bytes);
+                // The header is read before the checksum is found, past it only the checksum fails
+                if i >= 24 {
+                    assert_eq!(
+                        restored.unwrap_err(),
+                        CacheError::SnapshotChecksum,
+                        "byte {i}"
+                    );
+                } else {
+                    assert!(restored.is_err(), "byte {i} bit {bit}");
+                }
+            }
+        }
+        let torn = &bytes[..bytes.len() - 1];
+        assert_eq!(
+            LRUCache::<u32, 5>::from_bytes(torn).unwrap_err(),
+            CacheError::SnapshotCorrupt
+        );
+    }
+}
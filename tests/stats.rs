@@ -0,0 +1,299 @@
+//! The stats: lifetime buckets and percentiles, the JSON they are written as, sampled counting
+//! and the window of recent lookups.
+
+mod common;
+
+use core::fmt::{self, Write};
+
+use common::Rng;
+use lru::{CacheStats, HitWindow, LRUCache, LifetimeHistogram, StatsMode, LIFETIME_BUCKETS};
+
+#[test]
+fn lifetimes_fall_in_power_of_two_buckets() {
+    let bucket_of = |ops| {
+        let mut h = LifetimeHistogram::new();
+        h.record(ops);
+        h.buckets().iter().position(|&n| n == 1).unwrap()
+    };
+    let expected = [
+        (0, 0),
+        (1, 0),
+        (2, 1),
+        (3, 1),
+        (4, 2),
+        (7, 2),
+        (8, 3),
+        ((1 << 14) - 1, 13),
+        (1 << 14, 14),
+        ((1 << 15) - 1, 14),
+        // The last bucket holds everything longer
+        (1 << 15, 15),
+        (u32::MAX, 15),
+    ];
+    for (ops, bucket) in expected {
+        assert_eq!(bucket_of(ops), bucket, "{ops} ops");
+    }
+    for bucket in 0..LIFETIME_BUCKETS {
+        let start = LifetimeHistogram::bucket_start(bucket);
+        assert_eq!(bucket_of(start as u32), bucket, "start of {bucket}");
+        if bucket > 1 {
+            assert_eq!(
+                bucket_of(start as u32 - 1),
+                bucket - 1,
+                "end of {}",
+                bucket - 1
+            );
+        }
+    }
+    assert_eq!(LifetimeHistogram::bucket_start(1), 2);
+}
+
+#[test]
+fn record_n_counts_like_repeated_records() {
+    let mut once = LifetimeHistogram::new();
+    once.record_n(5, 3);
+    once.record_n(100, 0);
+    let mut each = LifetimeHistogram::new();
+    for _ in 0..3 {
+        each.record(5);
+    }
+    assert_eq!(once, each);
+    assert_eq!(once.buckets()[2], 3);
+    assert_eq!(once.count(), 3);
+}
+
+#[test]
+fn percentiles_are_the_start_of_the_bucket_reaching_the_rank() {
+    let mut h = LifetimeHistogram::new();
+    assert_eq!(h.percentile(0.5), None);
+    h.record_n(1, 10);
+    h.record_n(8, 10);
+    h.record_n(1000, 5);
+    // 25 lifetimes: ranks 1 to 10 are in bucket 0, 11 to 20 in bucket 3, the rest in bucket 9
+    assert_eq!(h.percentile(0.0), Some(0));
+    assert_eq!(h.percentile(0.4), Some(0));
+    assert_eq!(h.percentile(0.41), Some(0));
+    assert_eq!(h.percentile(0.44), Some(8));
+    assert_eq!(h.percentile(0.8), Some(8));
+    assert_eq!(h.percentile(0.84), Some(512));
+    assert_eq!(h.percentile(1.0), Some(512));
+    // Ranks past the count are the last one
+    assert_eq!(h.percentile(2.0), Some(512));
+
+    let mut other = LifetimeHistogram::new();
+    other.record_n(u32::MAX, 25);
+    h.merge(&other);
+    assert_eq!(h.count(), 50);
+    assert_eq!(h.percentile(0.4), Some(8));
+    assert_eq!(h.percentile(0.5), Some(512));
+    assert_eq!(h.percentile(0.52), Some(1 << 15));
+}
+
+// Inserts 1, 2 and 3 into a cache of 2, then finds 3 and misses 9
+fn small_run() -> LRUCache<u32, 2> {
+    let mut cache = LRUCache::new();
+    for val in 1..=3 {
+        cache.insert(val);
+    }
+    assert!(cache.touch(|&v| v == 3));
+    assert!(!cache.touch(|&v| v == 9));
+    cache
+}
+
+#[test]
+fn stats_are_written_as_json() {
+    let cache = small_run();
+    let stats = cache.stats_snapshot();
+    assert_eq!(&stats, cache.stats());
+    let mut json = String::new();
+    stats.write_json(&mut json).unwrap();
+    // 1 was inserted by the first operation and evicted by the third
+    assert_eq!(
+        json,
+        r#"{"hits":1,"misses":1,"inserts":3,"evictions":1,"lifetimes":[0,1,0,0,0,0,0,0,0,0,0,0,0,0,0,0]}"#
+    );
+
+    let mut json = String::new();
+    CacheStats::new().write_json(&mut json).unwrap();
+    assert_eq!(
+        json,
+        r#"{"hits":0,"misses":0,"inserts":0,"evictions":0,"lifetimes":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0]}"#
+    );
+}
+
+// Fixed-size buffer, failing the write that would overflow it
+struct Buf {
+    bytes: [u8; 64],
+    len: usize,
+}
+
+impl Write for Buf {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end = self.len + s.len();
+        self.bytes.get_mut(self.len..end).ok_or(fmt::Error)?;
+        self.bytes[self.len..end].copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+#[test]
+fn json_goes_into_a_fixed_buffer_or_fails() {
+    let mut buf = Buf {
+        bytes: [0; 64],
+        len: 0,
+    };
+    let mut h = LifetimeHistogram::new();
+    h.record_n(3, 12);
+    h.write_json(&mut buf).unwrap();
+    assert_eq!(&buf.bytes[..buf.len], b"[0,12,0,0,0,0,0,0,0,0,0,0,0,0,0,0]");
+
+    buf.len = 0;
+    assert_eq!(small_run().stats().write_json(&mut buf), Err(fmt::Error));
+}
+
+#[test]
+fn the_snapshot_is_a_copy() {
+    let mut cache = small_run();
+    let snapshot = cache.stats_snapshot();
+    cache.insert(4);
+    cache.reset_stats();
+    assert_eq!((snapshot.inserts, snapshot.evictions), (3, 1));
+    assert_eq!(cache.stats(), &CacheStats::new());
+}
+
+// What `run` saw each operation do in a cache counting everything
+#[derive(Default)]
+struct Outcome {
+    hit: bool,
+    miss: bool,
+    insert: bool,
+    evicted: bool,
+}
+
+// Random inserts and lookups, each advancing the operation count by one
+fn run(mode: StatsMode, seed: u64) -> (LRUCache<u32, 8>, Vec<Outcome>) {
+    let mut rng = Rng::new(seed);
+    let mut cache: LRUCache<u32, 8> = LRUCache::new().with_stats_mode(mode);
+    let mut outcomes = Vec::new();
+    for _ in 0..20_000 {
+        let val = rng.below(16) as u32;
+        let mut outcome = Outcome::default();
+        if rng.below(3) == 0 {
+            outcome.insert = true;
+            outcome.evicted = cache.insert(val).is_some();
+        } else {
+            outcome.hit = cache.touch(|&v| v == val);
+            outcome.miss = !outcome.hit;
+        }
+        outcomes.push(outcome);
+    }
+    (cache, outcomes)
+}
+
+#[test]
+fn sampled_stats_weigh_one_operation_in_2_to_the_k() {
+    let (full, outcomes) = run(StatsMode::Full, 107);
+    let (sampled, _) = run(StatsMode::Sampled(3), 107);
+    // The same operations, whatever is counted
+    assert!(full.iter().eq(sampled.iter()));
+
+    let totals = |pick: fn(&Outcome) -> bool, every: usize, weight: u64| {
+        outcomes.iter().step_by(every).filter(|o| pick(o)).count() as u64 * weight
+    };
+    let (f, s) = (full.stats(), sampled.stats());
+    assert_eq!(f.hits, totals(|o| o.hit, 1, 1));
+    assert_eq!(f.misses, totals(|o| o.miss, 1, 1));
+    assert_eq!(f.inserts, totals(|o| o.insert, 1, 1));
+    assert_eq!(f.evictions, totals(|o| o.evicted, 1, 1));
+    // Operations 0, 8, 16... each count for 8
+    assert_eq!(s.hits, totals(|o| o.hit, 8, 8));
+    assert_eq!(s.misses, totals(|o| o.miss, 8, 8));
+    assert_eq!(s.inserts, totals(|o| o.insert, 8, 8));
+    assert_eq!(s.evictions, totals(|o| o.evicted, 8, 8));
+    assert_eq!(s.lifetimes.count(), s.evictions);
+
+    // And estimate the full totals
+    let near = |estimate: u64, total: u64| estimate.abs_diff(total) * 20 < total;
+    assert!(near(s.hits, f.hits), "{} hits for {}", s.hits, f.hits);
+    assert!(
+        near(s.misses, f.misses),
+        "{} misses for {}",
+        s.misses,
+        f.misses
+    );
+    assert!(near(s.inserts, f.inserts));
+    assert!(near(s.evictions, f.evictions));
+    assert!(near(s.lookups(), f.lookups()));
+
+    let (off, _) = run(StatsMode::Off, 107);
+    assert_eq!(off.stats(), &CacheStats::new());
+    assert!(off.recent_lookups().is_empty());
+}
+
+#[test]
+fn sampling_is_capped_at_2_to_the_31() {
+    let mut cache: LRUCache<u32, 4> = LRUCache::new().with_stats_mode(StatsMode::Sampled(40));
+    cache.insert(1);
+    cache.insert(2);
+    assert_eq!(cache.stats().inserts, 1 << 31);
+}
+
+#[test]
+fn the_recent_ratio_forgets_all_but_the_last_64_lookups() {
+    let mut cache: LRUCache<u32, 4> = LRUCache::new();
+    cache.insert(1);
+    for _ in 0..64 {
+        cache.touch(|&v| v == 2);
+    }
+    assert_eq!(cache.recent_hit_ratio(), 0.0);
+    for _ in 0..16 {
+        cache.touch(|&v| v == 1);
+    }
+    // 16 hits and the last 48 of the misses
+    assert_eq!(cache.recent_lookups().len(), 64);
+    assert_eq!(cache.recent_hit_ratio(), 0.25);
+    assert_eq!(cache.stats().hit_ratio(), 0.2);
+    // Inserts are not lookups
+    cache.insert(3);
+    assert_eq!(cache.recent_hit_ratio(), 0.25);
+    for _ in 0..64 {
+        cache.touch(|&v| v == 1);
+    }
+    assert_eq!(cache.recent_hit_ratio(), 1.0);
+    cache.reset_stats();
+    assert_eq!(cache.recent_hit_ratio(), 0.0);
+}
+
+#[test]
+fn only_sampled_lookups_enter_the_window() {
+    let mut cache: LRUCache<u32, 4> = LRUCache::new().with_stats_mode(StatsMode::Sampled(2));
+    // Operations 0 to 79: the insert, then lookups, of which 4, 8... are counted
+    cache.insert(1);
+    for _ in 0..79 {
+        cache.touch(|&v| v == 1);
+    }
+    assert_eq!(cache.recent_lookups().len(), 19);
+    assert_eq!(cache.recent_hit_ratio(), 1.0);
+    assert_eq!(cache.stats().hits, 19 * 4);
+}
+
+#[test]
+fn a_hit_window_holds_64_outcomes() {
+    let mut window = HitWindow::new();
+    assert!(window.is_empty());
+    assert_eq!(window.hit_ratio(), 0.0);
+    for i in 0..100 {
+        window.record(i % 4 == 0);
+    }
+    assert_eq!(window.len(), 64);
+    // Lookups 36 to 99, of which every fourth hit
+    assert_eq!(window.hit_ratio(), 16.0 / 64.0);
+    for _ in 0..63 {
+        window.record(false);
+    }
+    // Lookup 99 missed, so none of the held ones hit
+    assert_eq!(window.hit_ratio(), 0.0);
+    window.record(true);
+    assert_eq!(window.hit_ratio(), 1.0 / 64.0);
+}
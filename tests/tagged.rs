@@ -0,0 +1,73 @@
+//! `TaggedCache`: entries carry a tag, and `invalidate_tag` drops every entry with one.
+
+mod common;
+
+use common::Removals;
+use lru::{tagged::TaggedCache, LRUCache, RemovalCause};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Tenant {
+    A,
+    B,
+    C,
+}
+
+#[test]
+fn invalidating_a_tag_drops_its_entries_only() {
+    let mut cache: TaggedCache<u32, Tenant, 8, Removals> = TaggedCache::default();
+    for (val, tenant) in [
+        (1, Tenant::A),
+        (2, Tenant::B),
+        (3, Tenant::A),
+        (4, Tenant::C),
+    ] {
+        cache.insert(val, tenant);
+    }
+    assert_eq!(cache.count_tag(Tenant::A), 2);
+    assert_eq!(cache.invalidate_tag(Tenant::A), 2);
+    assert!(cache
+        .entries()
+        .eq([(&4, Tenant::C), (&2, Tenant::B)].into_iter()));
+    assert_eq!(cache.listener().of(RemovalCause::Explicit), [3, 1]);
+    assert_eq!(cache.invalidate_tag(Tenant::A), 0);
+    assert_eq!(cache.tag_of(|v| *v == 2), Some(Tenant::B));
+}
+
+#[test]
+fn tags_follow_their_entries() {
+    let mut cache: TaggedCache<u32, u16, 3> = TaggedCache::new();
+    let mut plain: LRUCache<u32, 3> = LRUCache::new();
+    for val in 0..50u32 {
+        let tag = (val % 4) as u16;
+        assert_eq!(cache.insert(val, tag), plain.insert(val));
+        if val % 3 == 0 {
+            assert_eq!(
+                cache.touch(|v| *v + 2 == val),
+                plain.touch(|v| *v + 2 == val)
+            );
+        }
+        if val % 7 == 0 {
+            // Tag 1 is the values 1 more than a multiple of 4: rebuild the plain cache without them
+            cache.invalidate_tag(1);
+            let kept: Vec<u32> = plain.iter().copied().filter(|v| v % 4 != 1).collect();
+            plain = LRUCache::new();
+            for v in kept.into_iter().rev() {
+                plain.insert(v);
+            }
+        }
+        assert!(cache.entries().map(|(v, _)| *v).eq(plain.iter().copied()));
+        assert!(cache.entries().all(|(v, tag)| tag as u32 == v % 4));
+    }
+    assert_eq!(
+        cache.pop_lru().map(|(v, tag)| tag as u32 == v % 4),
+        Some(true)
+    );
+}
+
+#[test]
+fn capacity_0_hands_the_value_back() {
+    let mut cache: TaggedCache<u32, u8, 0> = TaggedCache::new();
+    assert_eq!(cache.insert(1, 0), Some(1));
+    assert!(cache.is_empty());
+    assert_eq!(cache.pop_lru(), None);
+}
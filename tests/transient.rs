@@ -0,0 +1,81 @@
+//! Single-use entries: inserted with `insert_transient`, gone after the first lookup finding them.
+
+mod common;
+
+use common::{filled, with_transient as cache};
+use lru::{LRUCache, RemovalCause};
+
+#[test]
+fn touch_consumes_on_the_spot() {
+    let mut cache = cache();
+    assert!(cache.touch(|v| *v == 2));
+    assert!(cache.iter().eq(&[3, 1]));
+    assert_eq!(cache.listener().0, [(2, RemovalCause::Consumed)]);
+    assert!(!cache.touch(|v| *v == 2));
+    assert_eq!((cache.stats().hits, cache.stats().misses), (1, 1));
+}
+
+#[test]
+fn find_map_consumes_on_the_spot() {
+    let mut cache = cache();
+    assert_eq!(cache.find_map(|v| (*v == 2).then_some(*v * 10)), Some(20));
+    assert!(cache.iter().eq(&[3, 1]));
+    assert_eq!(cache.find_map(|v| (*v == 2).then_some(*v)), None);
+}
+
+#[test]
+fn find_consumes_on_the_next_operation() {
+    let mut cache = cache();
+    assert_eq!(cache.find(|v| *v == 2), Some(&mut 2));
+    // Waiting at the tail until something changes the cache
+    assert!(cache.iter().eq(&[3, 1, 2]));
+    assert!(cache.listener().0.is_empty());
+    assert_eq!(cache.find(|v| *v == 2), None);
+    assert!(cache.iter().eq(&[3, 1]));
+    assert_eq!(cache.listener().0, [(2, RemovalCause::Consumed)]);
+}
+
+#[test]
+fn spent_entry_is_never_evicted_for_capacity() {
+    let mut cache: LRUCache<u32, 2, _> = filled([1]);
+    cache.insert_transient(2);
+    cache.find(|v| *v == 2);
+    assert_eq!(cache.insert(3), None);
+    assert!(cache.iter().eq(&[3, 1]));
+    assert_eq!(cache.pop_lru(), Some(1));
+    assert_eq!(
+        cache.listener().0,
+        [(2, RemovalCause::Consumed), (1, RemovalCause::Explicit)]
+    );
+}
+
+#[test]
+fn transient_entries_age_like_any_other() {
+    let mut cache = cache();
+    cache.insert(4);
+    // 2 is the least recently used but one, and goes after 1 without being found
+    cache.insert(5);
+    cache.insert(6);
+    assert!(cache.iter().eq(&[6, 5, 4, 3]));
+    assert_eq!(
+        cache.listener().0,
+        [(1, RemovalCause::Capacity), (2, RemovalCause::Capacity)]
+    );
+}
+
+#[test]
+fn later_inserts_are_not_transient() {
+    let mut cache: LRUCache<u32, 1> = LRUCache::new();
+    cache.insert_transient(1);
+    cache.insert(2);
+    assert!(cache.touch(|v| *v == 2));
+    assert!(cache.touch(|v| *v == 2));
+    assert_eq!(cache.len(), 1);
+}
+
+#[test]
+fn capacity_0_hands_the_value_back() {
+    let mut cache: LRUCache<u32, 0> = LRUCache::new();
+    assert_eq!(cache.insert_transient(1), Some(1));
+    assert!(cache.is_empty());
+}
@@ -0,0 +1,198 @@
+//! `TtlCache`: entries leave once the clock reaches their deadline, whichever level of the timer
+//! wheel holds it.
+
+mod common;
+
+use common::Rng;
+use lru::ttl::{Clock, TickClock, TtlCache, XorShift32};
+
+type Cache<const N: usize> = TtlCache<u32, TickClock, N>;
+
+#[test]
+fn expires_at_the_deadline() {
+    let mut cache: Cache<4> = TtlCache::new(TickClock::new());
+    cache.insert_until(1, 10);
+    cache.insert(2);
+    cache.clock().advance(9);
+    assert_eq!(cache.purge_expired(), 0);
+    cache.clock().advance(1);
+    assert_eq!(cache.purge_expired(), 1);
+    assert!(!cache.touch(|&v| v == 1));
+    assert!(cache.touch(|&v| v == 2));
+}
+
+#[test]
+fn reclaims_expired_entries_before_evicting_live_ones() {
+    let mut cache: Cache<2> = TtlCache::new(TickClock::new());
+    cache.insert(1);
+    cache.insert_until(2, 5);
+    cache.clock().advance(5);
+    // 1 is the least recently used, but 2 has expired
+    assert_eq!(cache.insert(3), None);
+    assert!(cache.touch(|&v| v == 1));
+    assert_eq!(cache.insert(4), Some(3));
+}
+
+// A random number below `2^bits`, as likely to be small as large
+fn spread(rng: &mut Rng, bits: u64) -> u64 {
+    let max = 1 << rng.below(bits);
+    rng.below(max)
+}
+
+// Random deadlines from a tick to past the top level of the wheel, against a list of them
+#[test]
+fn purges_what_a_list_of_deadlines_says() {
+    const N: usize = 64;
+    let mut rng = Rng::new(110);
+    let mut cache: Cache<N> = TtlCache::new(TickClock::new());
+    let mut deadlines: Vec<(u32, u64)> = Vec::new();
+    for val in 0..5000 {
+        if cache.len() < N {
+            let span = 1 + spread(&mut rng, 37);
+            let deadline = cache.clock().now() + span;
+            cache.insert_until(val, deadline);
+            deadlines.push((val, deadline));
+        }
+        if rng.below(4) == 0 {
+            cache.clock().advance(spread(&mut rng, 33) as u32);
+            let now = cache.clock().now();
+            let mut purged = Vec::new();
+            cache.purge_expired_with(|val| purged.push(val));
+            purged.sort_unstable();
+            let mut due: Vec<u32> = deadlines
+                .iter()
+                .filter(|&&(_, deadline)| deadline <= now)
+                .map(|&(val, _)| val)
+                .collect();
+            due.sort_unstable();
+            assert_eq!(purged, due, "value {val} at {now}");
+            deadlines.retain(|&(_, deadline)| deadline > now);
+            assert_eq!(cache.len(), deadlines.len());
+        }
+    }
+}
+
+#[test]
+fn purge_hands_over_each_expired_value() {
+    let mut cache: Cache<8> = TtlCache::new(TickClock::new());
+    for val in 1..=6 {
+        cache.insert_until(val, val as u64 * 10);
+    }
+    cache.insert(7);
+    cache.clock().advance(35);
+    let mut purged = Vec::new();
+    assert_eq!(cache.purge_expired_with(|val| purged.push(val)), 3);
+    purged.sort_unstable();
+    assert_eq!(purged, [1, 2, 3]);
+    assert_eq!(cache.len(), 4);
+    assert_eq!(cache.purge_expired(), 0);
+}
+
+#[test]
+fn lookups_expire_entries_without_a_sweep() {
+    let mut cache: Cache<4> = TtlCache::new(TickClock::new());
+    cache.insert_until(1, 5);
+    cache.insert(2);
+    assert!(cache.find(|&v| v == 1).is_some());
+    cache.clock().advance(5);
+    assert_eq!(cache.len(), 2);
+    assert!(cache.find(|&v| v == 1).is_none());
+    assert_eq!(cache.len(), 1);
+    assert_eq!((cache.stats().hits, cache.stats().misses), (1, 1));
+    // Nothing left for the sweep
+    assert_eq!(cache.purge_expired(), 0);
+}
+
+#[test]
+fn insert_uses_the_default_ttl() {
+    let mut cache: Cache<4> = TtlCache::new(TickClock::new()).with_default_ttl(10);
+    assert_eq!(cache.default_ttl(), Some(10));
+    cache.insert(1);
+    cache.clock().advance(5);
+    cache.insert(2);
+    cache.clock().advance(5);
+    assert!(!cache.touch(|&v| v == 1));
+    assert!(cache.touch(|&v| v == 2));
+    cache.clock().advance(5);
+    assert!(!cache.touch(|&v| v == 2));
+    assert!(TtlCache::<u32, _, 4>::new(TickClock::new())
+        .default_ttl()
+        .is_none());
+}
+
+#[test]
+fn insert_with_ttl_overrides_the_default() {
+    let mut cache: Cache<4> = TtlCache::new(TickClock::new()).with_default_ttl(10);
+    cache.insert_with_ttl(1, 3);
+    cache.insert_with_ttl(2, 100);
+    cache.insert(3);
+    cache.clock().advance(3);
+    assert!(!cache.touch(|&v| v == 1));
+    cache.clock().advance(7);
+    assert!(!cache.touch(|&v| v == 3));
+    assert!(cache.touch(|&v| v == 2));
+    cache.clock().advance(90);
+    assert!(!cache.touch(|&v| v == 2));
+}
+
+#[test]
+fn sliding_expiry_keeps_entries_in_use() {
+    let mut cache: Cache<4> = TtlCache::new(TickClock::new())
+        .with_default_ttl(10)
+        .with_sliding_expiry();
+    cache.insert(1);
+    cache.insert(2);
+    for _ in 0..5 {
+        cache.clock().advance(8);
+        assert!(cache.touch(|&v| v == 1));
+    }
+    assert!(!cache.touch(|&v| v == 2));
+    // A hit renews the lifetime, it does not extend it past a full TTL
+    cache.clock().advance(10);
+    assert!(!cache.touch(|&v| v == 1));
+}
+
+#[test]
+fn expiry_hook_sees_only_expirations() {
+    let mut expired = Vec::new();
+    let mut cache: TtlCache<u32, TickClock, 2, (), _> = TtlCache::new(TickClock::new())
+        .with_expiry_hook(|&val: &u32, lived| expired.push((val, lived)));
+    cache.insert_until(1, 4);
+    cache.clock().advance(2);
+    cache.insert_until(2, 9);
+    cache.clock().advance(4);
+    assert!(!cache.touch(|&v| v == 1));
+    cache.insert(3);
+    // Evicted for room, not expired
+    cache.insert(4);
+    cache.clock().advance(10);
+    assert_eq!(cache.purge_expired(), 0);
+    cache.insert_until(5, 20);
+    cache.clock().advance(10);
+    assert_eq!(cache.purge_expired(), 1);
+    drop(cache);
+    assert_eq!(expired, [(1, 6), (5, 10)]);
+}
+
+#[test]
+fn jitter_spreads_expiry_within_the_percentage() {
+    let mut cache = TtlCache::<u32, _, 65>::new(TickClock::new())
+        .with_default_ttl(100)
+        .with_jitter(20, XorShift32::new(119));
+    for val in 0..64 {
+        cache.insert(val);
+    }
+    // An absolute deadline is left alone
+    cache.insert_until(64, 90);
+    cache.clock().advance(79);
+    assert_eq!(cache.purge_expired(), 0);
+    cache.clock().advance(10);
+    let early = cache.purge_expired();
+    assert!(early > 0 && early < 64, "{early} expired early");
+    assert!(cache.touch(|&v| v == 64));
+    cache.clock().advance(1);
+    assert!(!cache.touch(|&v| v == 64));
+    cache.clock().advance(10);
+    assert_eq!(cache.purge_expired(), 64 - early);
+    assert!(cache.is_empty());
+}
@@ -0,0 +1,70 @@
+//! `VersionedMap`: fills racing with invalidations must not bring stale values back.
+
+use lru::versioned::VersionedMap;
+
+#[test]
+fn fill_after_a_miss_loses_to_an_invalidation() {
+    let mut map: VersionedMap<&str, u32, 4> = VersionedMap::new();
+    assert_eq!(map.get("a"), None);
+    let started = map.current_version();
+    // The source of truth changes while the fill reads it, and the key is invalidated
+    map.remove("a");
+    assert_eq!(map.update_if("a", started, 1), Err(1));
+    assert_eq!(map.get("a"), None);
+
+    // A fill started after the invalidation goes through
+    let started = map.current_version();
+    let version = map.update_if("a", started, 2).unwrap();
+    assert_eq!(map.get("a"), Some((&2, version)));
+}
+
+#[test]
+fn fill_after_a_miss_loses_to_a_newer_write() {
+    let mut map: VersionedMap<&str, u32, 4> = VersionedMap::new();
+    let started = map.current_version();
+    let (written, _) = map.insert("a", 7);
+    assert_eq!(map.update_if("a", started, 1), Err(1));
+    assert_eq!(map.get("a"), Some((&7, written)));
+}
+
+#[test]
+fn compare_and_swap_on_the_version_read() {
+    let mut map: VersionedMap<&str, u32, 4> = VersionedMap::new();
+    let (v1, _) = map.insert("a", 1);
+    let v2 = map.update_if("a", v1, 2).unwrap();
+    assert!(v2 > v1);
+    // A second writer still holding the first version loses
+    assert_eq!(map.update_if("a", v1, 3), Err(3));
+    assert_eq!(map.version("a"), Some(v2));
+    // Writes to other keys do not get in the way
+    map.insert("b", 10);
+    assert!(map.update_if("a", v2, 4).is_ok());
+}
+
+#[test]
+fn evictions_count_as_removals_for_absent_keys() {
+    let mut map: VersionedMap<u32, u32, 2> = VersionedMap::new();
+    map.insert(1, 1);
+    map.insert(2, 2);
+    let started = map.current_version();
+    // Key 1 is written and evicted while a fill of it is in flight
+    map.insert(1, 10);
+    map.insert(3, 3);
+    map.insert(4, 4);
+    assert_eq!(map.version(&1), None);
+    assert_eq!(map.update_if(1, started, 5), Err(5));
+    assert_eq!(map.len(), 2);
+}
+
+#[test]
+fn versions_follow_their_entries() {
+    let mut map: VersionedMap<u32, u32, 4> = VersionedMap::new();
+    let versions: Vec<u64> = (0..4).map(|k| map.insert(k, k).0).collect();
+    map.remove(&0);
+    for k in 1..4 {
+        assert_eq!(map.version(&k), Some(versions[k as usize]));
+    }
+    map.clear();
+    assert!(map.is_empty());
+    assert_eq!(map.update_if(1, versions[3], 1), Err(1));
+}
@@ -0,0 +1,127 @@
+//! Write-ahead log: replaying what a `WalListener` recorded rebuilds the cache it was attached to,
+//! and applying each record twice as a follower changes nothing the second time.
+
+mod common;
+
+use common::Rng;
+use lru::{
+    map::LRUMap,
+    wal::{CacheOp, OpSink, WalListener},
+    LRUCache,
+};
+
+// Keeps every record
+#[derive(Debug, Default)]
+struct Log<T>(Vec<CacheOp<T>>);
+
+impl<T: Clone> OpSink<T> for Log<T> {
+    fn record(&mut self, op: CacheOp<&T>) {
+        self.0.push(op.cloned());
+    }
+}
+
+type Leader<const N: usize> = LRUCache<u32, N, WalListener<Log<u32>>>;
+
+// Random changes of a leader, with values repeated so that replay has to pick the right copy
+fn run<const N: usize>(seed: u64, steps: usize, distinct: bool) -> Leader<N> {
+    let mut rng = Rng::new(seed);
+    let mut cache: Leader<N> = LRUCache::with_listener(WalListener::new(Log(Vec::new())));
+    let mut next = 0;
+    for _ in 0..steps {
+        let val = rng.below(N as u64 * 2) as u32;
+        match rng.below(100) {
+            0..=44 if distinct => {
+                next += 1;
+                cache.insert(next);
+            }
+            0..=44 => {
+                cache.insert(val);
+            }
+            45..=69 => {
+                cache.touch(|&v| v == val);
+            }
+            70..=79 => {
+                cache.find(|&v| v % 3 == val % 3);
+            }
+            80..=89 if distinct => {
+                let live: Vec<u32> = cache.iter().copied().collect();
+                if let Some(&v) = live.get(val as usize % (live.len() + 1)) {
+                    cache.extract_if(|x| *x == v).for_each(drop);
+                }
+            }
+            80..=89 => {
+                cache.extract_if(|v| *v == val).take(1).for_each(drop);
+            }
+            90..=94 => {
+                cache.pop_lru();
+            }
+            95..=98 => {
+                cache.evict(rng.below(3) as usize).for_each(drop);
+            }
+            _ => cache.clear(),
+        }
+    }
+    cache
+}
+
+#[test]
+fn replay_rebuilds_the_cache() {
+    for seed in 0..16 {
+        let leader = run::<8>(seed, 2_000, false);
+        let mut follower: LRUCache<u32, 8> = LRUCache::new();
+        follower.replay(leader.listener().sink().0.iter().copied());
+        assert!(
+            follower.iter().eq(leader.iter()),
+            "seed {seed}: {:?} != {:?}",
+            follower.fmt_recency(),
+            leader.fmt_recency()
+        );
+    }
+}
+
+#[test]
+fn replay_skips_records_of_values_not_in_the_cache() {
+    let mut cache: LRUCache<u32, 4> = LRUCache::new();
+    cache.replay([
+        CacheOp::Insert(1),
+        CacheOp::Touch(9),
+        CacheOp::Remove(9),
+        CacheOp::Evict(9),
+        CacheOp::Insert(2),
+    ]);
+    assert!(cache.iter().eq(&[2, 1]));
+}
+
+#[test]
+fn applying_each_record_twice_changes_nothing() {
+    for seed in 0..16 {
+        let leader = run::<8>(seed, 2_000, true);
+        let mut follower: LRUCache<u32, 8> = LRUCache::new();
+        for &op in &leader.listener().sink().0 {
+            follower.apply(op);
+            let once: Vec<u32> = follower.iter().copied().collect();
+            follower.apply(op);
+            assert!(follower.iter().eq(&once), "seed {seed} {op:?}");
+        }
+        assert!(follower.iter().eq(leader.iter()), "seed {seed}");
+    }
+}
+
+#[test]
+fn a_map_applies_pairs_by_key() {
+    let mut map: LRUMap<u32, &str, 2> = LRUMap::new();
+    for op in [
+        CacheOp::Insert((1, "a")),
+        CacheOp::Insert((2, "b")),
+        CacheOp::Insert((1, "c")),
+        CacheOp::Insert((1, "c")),
+        CacheOp::Touch((2, "")),
+        CacheOp::Remove((3, "")),
+    ] {
+        map.apply(op);
+    }
+    assert!(map.entries().eq([(&2, &"b"), (&1, &"c")]));
+    map.apply(CacheOp::Evict((2, "")));
+    map.apply(CacheOp::Evict((2, "")));
+    assert!(map.entries().eq([(&1, &"c")]));
+}
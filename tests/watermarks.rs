@@ -0,0 +1,79 @@
+//! High and low watermarks: an insert at the high watermark evicts down to the low one at once.
+
+mod common;
+
+use common::{filled, Removals};
+use lru::{LRUCache, RemovalCause};
+
+fn cache() -> LRUCache<u32, 8, Removals> {
+    filled([]).with_watermarks(2, 6)
+}
+
+#[test]
+fn evicts_down_to_the_low_watermark_in_one_batch() {
+    let mut cache = cache();
+    for val in 1..=6 {
+        assert_eq!(cache.insert(val), None);
+    }
+    assert!(cache.listener().of(RemovalCause::Capacity).is_empty());
+    assert_eq!(cache.insert(7), None);
+    assert!(cache.iter().eq(&[7, 6, 5]));
+    assert_eq!(cache.listener().of(RemovalCause::Capacity), [1, 2, 3, 4]);
+    assert_eq!(cache.stats().evictions, 4);
+}
+
+#[test]
+fn never_holds_more_than_the_high_watermark() {
+    let mut cache = cache();
+    for val in 0..100 {
+        cache.insert(val);
+        assert!(cache.len() <= 6);
+    }
+    // Batches of 4 evictions, after the first 6 inserts
+    assert_eq!(cache.listener().of(RemovalCause::Capacity).len(), 96);
+}
+
+#[test]
+fn recency_picks_the_batch() {
+    let mut cache = cache();
+    for val in 1..=6 {
+        cache.insert(val);
+    }
+    assert!(cache.touch(|v| *v == 1));
+    cache.insert(7);
+    assert!(cache.iter().eq(&[7, 1, 6]));
+}
+
+#[test]
+fn cursor_inserts_stop_at_the_high_watermark() {
+    let mut cache = cache();
+    for val in 1..=6 {
+        cache.insert(val);
+    }
+    assert_eq!(cache.cursor_front_mut().insert_before(7), Err(7));
+    assert_eq!(cache.len(), 6);
+}
+
+#[test]
+#[should_panic]
+fn low_must_be_under_high() {
+    let _ = LRUCache::<u32, 8>::new().with_watermarks(4, 4);
+}
+
+#[test]
+#[should_panic]
+fn high_must_fit_the_capacity() {
+    let _ = LRUCache::<u32, 8>::new().with_watermarks(4, 9);
+}
+
+#[test]
+fn insert_returns_none_after_a_batch() {
+    let mut cache: LRUCache<u32, 4> = LRUCache::new().with_watermarks(1, 3);
+    for val in 1..=3 {
+        assert_eq!(cache.insert(val), None);
+    }
+    // 1 and 2 go, and only a listener would have seen them
+    assert_eq!(cache.insert(4), None);
+    assert!(cache.iter().eq(&[4, 3]));
+    assert_eq!(cache.stats().evictions, 2);
+}
@@ -0,0 +1,180 @@
+//! Edge cases of size: caches of capacity 0, and caches of zero-sized values.
+
+mod common;
+
+use common::Removals;
+use lru::{
+    batch::BatchedLRUCache,
+    equiv::EquivMap,
+    fingerprint::FingerprintMap,
+    gds::GdsCache,
+    intmap::IntLruMap,
+    map::LRUMap,
+    mem::MemBoundedCache,
+    minmax::MinMaxLRUCache,
+    priority::PriorityCache,
+    tagged::TaggedCache,
+    ttl::{TickClock, TtlCache},
+    versioned::VersionedMap,
+    LRUCache, RemovalCause,
+};
+
+#[test]
+fn capacity_0_hands_inserts_back() {
+    let mut cache: LRUCache<u32, 0, Removals> = LRUCache::default();
+    assert_eq!(cache.insert(1), Some(1));
+    assert_eq!(cache.insert_transient(2), Some(2));
+    assert!(cache.is_empty());
+    assert_eq!(cache.len(), 0);
+    // Nothing was stored, so nothing was evicted
+    assert!(cache.listener().0.is_empty());
+    assert_eq!(cache.stats().evictions, 0);
+}
+
+#[test]
+fn capacity_0_lookups_miss() {
+    let mut cache: LRUCache<u32, 0> = LRUCache::new();
+    cache.insert(1);
+    assert!(!cache.touch(|v| *v == 1));
+    assert_eq!(cache.find(|v| *v == 1), None);
+    assert_eq!(cache.find_map(|v| Some(*v)), None);
+    assert_eq!(cache.peek_unordered(|_| true), None);
+    assert_eq!(cache.touch_all(|_| true), 0);
+    assert_eq!(cache.stats().misses, 3);
+}
+
+#[test]
+fn capacity_0_has_nothing_to_remove_or_show() {
+    let mut cache: LRUCache<u32, 0> = LRUCache::from_array([]);
+    assert_eq!(cache.pop_lru(), None);
+    assert_eq!(cache.evict(3).len(), 0);
+    assert_eq!(cache.extract_if(|_| true).count(), 0);
+    assert_eq!(cache.purge_stale(), 0);
+    assert_eq!(cache.maintain(4), 0);
+    assert_eq!((cache.peek_lru(), cache.peek_mru()), (None, None));
+    assert_eq!(cache.front_mut(), None);
+    assert_eq!(cache.iter().len(), 0);
+    assert_eq!(cache.iter_insertion_order().len(), 0);
+    assert_eq!(cache.values_unordered().count(), 0);
+    assert_eq!(cache.fold(0, |n, _| n + 1), 0);
+    assert_eq!(format!("{:?}", cache.fmt_recency()), "LRUCache (0/0) []");
+    let mut iter = cache.iter_mut();
+    assert!(iter.next().is_none());
+    cache.clear();
+    cache.bump_epoch();
+    assert!(cache.is_empty());
+}
+
+#[test]
+fn capacity_0_cursors_sit_at_the_ghost() {
+    let mut cache: LRUCache<u32, 0> = LRUCache::new();
+    let mut cursor = cache.cursor_back_mut();
+    assert_eq!(cursor.current(), None);
+    cursor.move_next();
+    cursor.move_prev();
+    assert_eq!(cursor.remove_current(), None);
+    assert_eq!(cursor.insert_before(1), Err(1));
+    assert_eq!(cursor.insert_after(2), Err(2));
+}
+
+#[test]
+fn capacity_0_snapshots_round_trip() {
+    let cache: LRUCache<u32, 0> = LRUCache::new();
+    let mut buf = [0u8; LRUCache::<u32, 0>::SNAPSHOT_SIZE];
+    let len = cache.as_bytes_snapshot(&mut buf).unwrap();
+    let restored: LRUCache<u32, 0> = LRUCache::from_bytes(&buf[..len]).unwrap();
+    assert!(restored.is_empty());
+}
+
+#[test]
+#[should_panic = "capacity 0"]
+fn capacity_0_has_no_room_for_get_or_insert_with() {
+    let mut cache: LRUCache<u32, 0> = LRUCache::new();
+    cache.get_or_insert_with(|v| *v == 1, || 1);
+}
+
+#[test]
+fn capacity_0_maps_hand_inserts_back() {
+    let mut map: LRUMap<u32, u32, 0> = LRUMap::new();
+    assert_eq!(map.insert(1, 10), Some((1, 10)));
+    assert_eq!(map.get(&1), None);
+    assert_eq!(map.remove(&1), None);
+    assert!(map.is_empty());
+}
+
+#[test]
+fn zero_sized_values_take_no_room() {
+    // Only the links and insert ticks of the entries take room
+    assert_eq!(
+        core::mem::size_of::<LRUCache<(), 64>>(),
+        core::mem::size_of::<LRUCache<(), 0>>() + 64 * 8,
+    );
+}
+
+#[test]
+fn zero_sized_values_behave_like_any_other() {
+    let mut cache: LRUCache<(), 3, Removals<()>> = LRUCache::default();
+    assert_eq!(cache.insert(()), None);
+    assert_eq!(cache.insert(()), None);
+    assert_eq!(cache.insert(()), None);
+    assert_eq!(cache.insert(()), Some(()));
+    assert_eq!(cache.len(), 3);
+    assert!(cache.touch(|_| true));
+    assert_eq!(cache.touch_all(|_| true), 3);
+    assert_eq!(cache.pop_lru(), Some(()));
+    assert_eq!(cache.evict(5).count(), 2);
+    assert!(cache.is_empty());
+    assert_eq!(
+        cache.listener().0,
+        [
+            RemovalCause::Capacity,
+            RemovalCause::Explicit,
+            RemovalCause::Capacity,
+            RemovalCause::Capacity,
+        ]
+        .map(|cause| ((), cause))
+    );
+}
+
+#[test]
+fn zero_sized_snapshots_round_trip() {
+    let mut cache: LRUCache<(), 4> = LRUCache::new();
+    cache.insert(());
+    cache.insert(());
+    let mut buf = [0u8; LRUCache::<(), 4>::SNAPSHOT_SIZE];
+    let len = cache.as_bytes_snapshot(&mut buf).unwrap();
+    let restored: LRUCache<(), 4> = LRUCache::from_bytes(&buf[..len]).unwrap();
+    assert_eq!(restored.len(), 2);
+}
+
+#[test]
+fn capacity_0_wrappers_hand_inserts_back() {
+    let mut batched: BatchedLRUCache<u32, 0, 4> = BatchedLRUCache::new();
+    assert_eq!(batched.insert(1), Some(1));
+    assert!(!batched.touch(|v| *v == 1));
+    let mut minmax: MinMaxLRUCache<u32, 0> = MinMaxLRUCache::new();
+    assert_eq!(minmax.insert(1), Some(1));
+    assert_eq!((minmax.peek_min(), minmax.peek_max()), (None, None));
+    let mut mem: MemBoundedCache<u32, 0> = MemBoundedCache::new(64);
+    assert_eq!(mem.insert(1), Err(1));
+    assert!(mem.is_empty());
+    let mut tagged: TaggedCache<u32, u8, 0> = TaggedCache::new();
+    assert_eq!(tagged.insert(1, 0), Some(1));
+    assert_eq!(tagged.pop_lru(), None);
+    let mut ints: IntLruMap<u32, u32, 0> = IntLruMap::new();
+    assert_eq!(ints.insert(1, 10), Some((1, 10)));
+    let mut equiv: EquivMap<u32, u32, _, 0> = EquivMap::new(|a: &u32, b: &u32| a == b);
+    assert_eq!(equiv.insert(1, 10), Some((1, 10)));
+    let mut ttl: TtlCache<u32, TickClock, 0> = TtlCache::new(TickClock::new());
+    assert_eq!(ttl.insert(1), Some(1));
+    assert_eq!(ttl.insert_with_ttl(2, 5), Some(2));
+    assert_eq!(ttl.purge_expired(), 0);
+    let mut fingerprints: FingerprintMap<u32, u32, 0> = FingerprintMap::new();
+    assert_eq!(fingerprints.insert(1, 10), Some((1, 10)));
+    let mut priority: PriorityCache<u32, 0> = PriorityCache::new();
+    assert_eq!(priority.insert(1), Some(1));
+    let mut gds: GdsCache<u32, 0> = GdsCache::new();
+    assert_eq!(gds.insert(1, 1, 1), Some(1));
+    let mut versioned: VersionedMap<u32, u32, 0> = VersionedMap::new();
+    assert_eq!(versioned.insert(1, 10).1, Some((1, 10)));
+}